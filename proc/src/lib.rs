@@ -1,6 +1,9 @@
 use proc_macro::TokenStream;
 use syn::{
-    __private::quote::{format_ident, quote},
+    __private::{
+        quote::{format_ident, quote},
+        TokenStream2,
+    },
     parse_macro_input, DeriveInput,
 };
 
@@ -31,103 +34,327 @@ pub fn all_tuple(input: TokenStream) -> TokenStream {
     result.into()
 }
 
+/// 为结构体实现`Bundle`
+///
+/// 所有字段都必须实现`Bundle`,即是一个`Component`,或者是嵌套的`Bundle`,
+/// 嵌套的`Bundle`会被展开为它的所有`Component`
 #[proc_macro_derive(Bundle)]
 pub fn bundle(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let struct_name = input.ident;
-    match input.data {
-        syn::Data::Struct(struct_) => {
-            // 1 destory
-            let idents = struct_.fields.iter().cloned().map(|field| {
-                let ident = field.ident;
-                quote! {
-                    #ident
-                }
-            });
-            let idents2 = idents.clone();
-
-            let destory = quote! {
-                #[allow(non_snake_case)]
-                fn destory(self) -> ::trecs::bundle::Components{
-                    let #struct_name {#(#idents,)*} = self;
-                    vec![#(Box::new(#idents2))*,]
-                }
-            };
+    let syn::Data::Struct(struct_) = input.data else {
+        panic!("Bundle仅支持为结构体实现");
+    };
+    let tys = struct_
+        .fields
+        .iter()
+        .map(|field| &field.ty)
+        .collect::<Vec<_>>();
 
-            // 2 components_ids
-            let components_ids = struct_.fields.clone().into_iter().map(|field| {
-                let ty = field.ty;
-                quote! {
-                    <#ty as ::trecs::bundle::Component>::type_id_()
-                }
-            });
-            let components_ids = quote! {
-                fn components_ids() -> &'static [::std::any::TypeId]{
-                    static mut COMPONNETS_IDS :
-                        ::std::cell::OnceCell<Vec<::std::any::TypeId>>
-                        = ::std::cell::OnceCell::new();
-                    unsafe{
-                        COMPONNETS_IDS.get_or_init(||{
-                            vec![#(#components_ids,)*]
-                        })
-                    }
-                }
-            };
-            // 3 type_name
-            let type_name = quote! {
-                fn type_name() -> &'static str{
-                    ::std::any::type_name::<Self>()
-                }
-            };
-            // 4 type_id_
-            let type_id_ = quote! {
-                fn type_id_() -> std::any::TypeId{
-                    ::std::any::TypeId::of::<Self>()
-                }
-            };
-
-            // droper
-            let tys = struct_.fields.iter().cloned().map(|field| field.ty);
-
-            let generator = struct_.fields.iter().scan(0, |state, _| {
-                *state += 1;
-                Some(format_ident!("T{}", state.to_string()))
-            });
-            let drop = quote! {
-                fn drop(cs : ::trecs::bundle::Components) {
-                    let mut iter = cs.into_iter().rev();
-                    #(let #generator = iter.next().unwrap().downcast::<#tys>();)*
-                }
-            };
-
-            let result = quote! {
-                // #input
-                impl ::trecs::bundle::Bundle for #struct_name{
-                    #destory
-                    #components_ids
-                    #drop
-                    #type_name
-                    #type_id_
-                }
-            };
+    // 每个字段都必须是Bundle
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+    for ty in &tys {
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(#ty: ::trecs::bundle::Bundle));
+    }
+
+    // 1 destory
+    let bindings = (0..tys.len())
+        .map(|i| format_ident!("__field{i}"))
+        .collect::<Vec<_>>();
+    let pattern = match &struct_.fields {
+        syn::Fields::Named(_) => {
+            let idents = struct_.fields.iter().map(|field| &field.ident);
+            quote! { Self { #(#idents: #bindings,)* } }
+        }
+        syn::Fields::Unnamed(_) => quote! { Self ( #(#bindings,)* ) },
+        syn::Fields::Unit => quote! { Self },
+    };
+    let destory = quote! {
+        fn destory(self) -> ::trecs::bundle::Components {
+            let #pattern = self;
+            let mut components = ::std::vec::Vec::with_capacity(
+                <Self as ::trecs::bundle::Bundle>::components_ids().len(),
+            );
+            #(components.extend(<#tys as ::trecs::bundle::Bundle>::destory(#bindings));)*
+            components
+        }
+    };
+
+    // 1.5 write_to 依次写入,每个字段之后的offset增加它的Component的数量
+    let write_to = quote! {
+        unsafe fn write_to(self, offset: usize, dst: &mut dyn FnMut(usize) -> *mut u8) {
+            let #pattern = self;
+            #(
+                let offset = {
+                    <#tys as ::trecs::bundle::Bundle>::write_to(#bindings, offset, dst);
+                    offset + <#tys as ::trecs::bundle::Bundle>::components_ids().len()
+                };
+            )*
+            let _ = (offset, dst);
+        }
+    };
 
-            result.into()
+    // 2 components_ids, components_names, components_descriptors
+    // 泛型函数中的静态变量对于不同的类型是同一个,所以使用trecs中以TypeId为键的缓存
+    let cached = |method: TokenStream2, ty: TokenStream2| {
+        let cache = format_ident!("cached_{method}");
+        quote! {
+            fn #method() -> &'static [#ty] {
+                ::trecs::bundle::__private::#cache(::std::any::TypeId::of::<Self>(), || {
+                    let mut all = ::std::vec::Vec::new();
+                    #(all.extend_from_slice(<#tys as ::trecs::bundle::Bundle>::#method());)*
+                    all
+                })
+            }
+        }
+    };
+    let components_ids = cached(quote!(components_ids), quote!(::trecs::bundle::ComponentId));
+    let components_names = cached(quote!(components_names), quote!(&'static str));
+    let components_descriptors = cached(
+        quote!(components_descriptors),
+        quote!(::trecs::bundle::ComponentDescriptor),
+    );
+
+    // 3 drop 按照每个字段的Component数量拆分,依次还原并drop
+    let drop = quote! {
+        fn drop(cs: ::trecs::bundle::Components) {
+            let mut cs = cs.into_iter();
+            #(
+                let len = <#tys as ::trecs::bundle::Bundle>::components_ids().len();
+                <#tys as ::trecs::bundle::Bundle>::drop(cs.by_ref().take(len).collect());
+            )*
+        }
+    };
+
+    quote! {
+        impl #impl_generics ::trecs::bundle::Bundle for #struct_name #ty_generics #where_clause {
+            #destory
+            #write_to
+            #components_ids
+            #components_names
+            #components_descriptors
+            #drop
+
+            fn type_name() -> &'static str {
+                ::std::any::type_name::<Self>()
+            }
+
+            fn type_id_() -> ::std::any::TypeId {
+                ::std::any::TypeId::of::<Self>()
+            }
         }
-        _ => panic!("Bundle仅支持为结构体实现"),
     }
+    .into()
 }
 
-#[proc_macro_derive(Component)]
+/// 为类型实现`Component`
+///
+/// 可以通过`#[component(...)]`配置:
+/// + `storage = "table"`或`storage = "sparse"`: 存储方式,默认为`"table"`
+/// + `name = "..."`: 用于调试输出的名字,默认为类型名
+/// + `reflect(default, debug, serde)`: 在`TypeRegistry::register_type`时注册的信息,
+///   分别需要实现`Default`,`Debug`和`Serialize`+`Deserialize`
+///
+/// 结构体的字段可以通过`Component::field`访问
+#[proc_macro_derive(Component, attributes(component))]
 pub fn component(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let type_name = input.ident;
 
+    let mut storage = None;
+    let mut name = None;
+    let mut reflect = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("component"))
+    {
+        let result = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("storage") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                storage = Some(match value.value().as_str() {
+                    "table" => quote!(Table),
+                    "sparse" => quote!(SparseSet),
+                    _ => return Err(meta.error("storage只能是\"table\"或\"sparse\"")),
+                });
+            } else if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<syn::LitStr>()?);
+            } else if meta.path.is_ident("reflect") {
+                let reflect = reflect.get_or_insert_with(Vec::new);
+                meta.parse_nested_meta(|meta| {
+                    let register = if meta.path.is_ident("default") {
+                        quote!(registry.register_default::<Self>();)
+                    } else if meta.path.is_ident("debug") {
+                        quote!(registry.register_debug::<Self>();)
+                    } else if meta.path.is_ident("serde") {
+                        quote!(::trecs::__register_serde!(registry, Self);)
+                    } else {
+                        return Err(meta.error("reflect只能包含default,debug和serde"));
+                    };
+                    reflect.push(register);
+                    Ok(())
+                })?;
+            } else {
+                return Err(meta.error("未知的component属性"));
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            return e.to_compile_error().into();
+        }
+    }
+
+    let storage = storage.map(|storage| {
+        quote! {
+            const STORAGE: ::trecs::bundle::StorageType = ::trecs::bundle::StorageType::#storage;
+        }
+    });
+    let name = name.map(|name| {
+        quote! {
+            fn component_name() -> &'static str {
+                #name
+            }
+        }
+    });
+
+    let reflect = reflect.map(|reflect| {
+        quote! {
+            fn register_type(registry: &mut ::trecs::reflect::TypeRegistry) {
+                registry.register::<Self>();
+                #(#reflect)*
+            }
+        }
+    });
+    let fields = match &input.data {
+        syn::Data::Struct(data) if !data.fields.is_empty() => {
+            let (names, members): (Vec<_>, Vec<_>) = data
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| match &field.ident {
+                    Some(ident) => (ident.to_string(), quote!(#ident)),
+                    None => {
+                        let index = syn::Index::from(index);
+                        (index.index.to_string(), quote!(#index))
+                    }
+                })
+                .unzip();
+            let indexes = 0..names.len();
+            let indexes_mut = indexes.clone();
+            Some(quote! {
+                fn field_names() -> &'static [&'static str] {
+                    &[#(#names),*]
+                }
+
+                fn field(&self, index: usize) -> ::std::option::Option<&dyn ::std::any::Any> {
+                    match index {
+                        #(#indexes => ::std::option::Option::Some(&self.#members),)*
+                        _ => ::std::option::Option::None,
+                    }
+                }
+
+                fn field_mut(
+                    &mut self,
+                    index: usize,
+                ) -> ::std::option::Option<&mut dyn ::std::any::Any> {
+                    match index {
+                        #(#indexes_mut => ::std::option::Option::Some(&mut self.#members),)*
+                        _ => ::std::option::Option::None,
+                    }
+                }
+            })
+        }
+        _ => None,
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
     quote! {
-        // #input
-        impl ::trecs::bundle::Component for #type_name{
+        impl #impl_generics ::trecs::bundle::Component for #type_name #ty_generics #where_clause {
+            #storage
+
             fn type_id_() -> ::std::any::TypeId{
                 ::std::any::TypeId::of::<Self>()
             }
+
+            #name
+
+            #fields
+
+            #reflect
+        }
+    }
+    .into()
+}
+
+/// 为由多个[System]参数组成的结构体实现`SystemParm`
+///
+/// 结构体至多有一个生命周期参数,所有字段都必须实现`SystemParm`
+///
+/// [System]: https://docs.rs/trecs/latest/trecs/system/struct.System.html
+#[proc_macro_derive(SystemParm)]
+pub fn system_parm(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let struct_name = input.ident;
+    let syn::Data::Struct(struct_) = input.data else {
+        panic!("SystemParm仅支持为结构体实现");
+    };
+    if input.generics.lifetimes().count() > 1 {
+        panic!("SystemParm至多有一个生命周期参数");
+    }
+
+    // Item<'__w>中的生命周期参数被替换为'__w
+    let item_generics = input.generics.params.iter().map(|param| match param {
+        syn::GenericParam::Lifetime(_) => quote! { '__w },
+        syn::GenericParam::Type(ty) => {
+            let ident = &ty.ident;
+            quote! { #ident }
+        }
+        syn::GenericParam::Const(c) => {
+            let ident = &c.ident;
+            quote! { #ident }
+        }
+    });
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let tys = struct_
+        .fields
+        .iter()
+        .map(|field| &field.ty)
+        .collect::<Vec<_>>();
+    let build = struct_.fields.iter().map(|field| {
+        let ty = &field.ty;
+        let value = quote! { <#ty as ::trecs::system::SystemParm>::build(world) };
+        match &field.ident {
+            Some(ident) => quote! { #ident: #value },
+            None => value,
+        }
+    });
+    let build = match struct_.fields {
+        syn::Fields::Named(_) => quote! { #struct_name { #(#build,)* } },
+        syn::Fields::Unnamed(_) => quote! { #struct_name ( #(#build,)* ) },
+        syn::Fields::Unit => quote! { #struct_name },
+    };
+
+    quote! {
+        impl #impl_generics ::trecs::system::SystemParm for #struct_name #ty_generics #where_clause {
+            type Item<'__w> = #struct_name<#(#item_generics),*>;
+
+            unsafe fn build<'__w>(world: ::trecs::world::UnsafeWorldCell<'__w>) -> Self::Item<'__w> {
+                #build
+            }
+
+            fn prepare(world: &mut ::trecs::World) {
+                #(<#tys as ::trecs::system::SystemParm>::prepare(world);)*
+            }
+
+            fn init(state: &mut ::trecs::system::SystemState) {
+                #(<#tys as ::trecs::system::SystemParm>::init(state);)*
+            }
         }
     }
     .into()
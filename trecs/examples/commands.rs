@@ -1,11 +1,12 @@
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use trecs_proc::Component;
 
+use trecs::time::Time;
+use trecs::world::Commands;
 use trecs::world::Query;
 use trecs::World;
-use trecs::{tools::Command, world::Commands};
 
 #[derive(Component)]
 pub struct Str {
@@ -20,7 +21,7 @@ fn spawn_hello_world(mut commands: Commands) {
 
     unsafe {
         commands.spawn(Str {
-            inner: String::from("hello world ") + &COUNTER.to_string(),
+            inner: String::from("hello world ") + &{ COUNTER }.to_string(),
         });
         COUNTER += 1;
     }
@@ -29,8 +30,9 @@ fn spawn_hello_world(mut commands: Commands) {
 fn print_hello_world(q: Query<&Str>, mut commands: Commands) {
     for eb in q.into_eiter() {
         // 读取,然后删除
+        // 删除会被推迟到System结束之后,所以不会影响正在进行的迭代
         println!("{}", eb.inner);
-        commands.remove(eb.entity());
+        commands.despawn(eb.entity());
     }
 }
 
@@ -40,11 +42,10 @@ fn twice_pre_s() {
 
 fn main() {
     let mut world = World::new();
-    let start = Instant::now();
     world
         .add_system(spawn_hello_world)
         .add_system(print_hello_world)
         // 两秒后结束
         .add_system(twice_pre_s)
-        .run_until(|| start.elapsed() > Duration::from_secs(2));
+        .run_until(|world| world.resource::<Time>().unwrap().elapsed() > Duration::from_secs(2));
 }
@@ -1,11 +1,12 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
+use trecs::time::Time;
 use trecs::tools::ResManager;
-use trecs::world::{Res, Resources};
+use trecs::world::{Res, ResMut, Resources};
 use trecs::World;
 
 /// 初始化资源String 为 "Hello world from Res"
-fn init_hello_world(mut res: Res<String>) {
+fn init_hello_world(mut res: ResMut<String>) {
     res.get_or_init(|| String::from("Hello world from Res"));
 }
 
@@ -21,13 +22,26 @@ fn print_hello_world2(mut res: Resources) {
     println!("`{}` from Resources", hw)
 }
 
+/// 通过run_until循环执行时,Time会被自动启用
+fn print_time(time: Res<Time>) {
+    let time = time.get().unwrap();
+    println!(
+        "frame {} delta {:?} elapsed {:.2}s",
+        time.frames(),
+        time.delta(),
+        time.elapsed_secs()
+    );
+}
+
 fn main() {
     let mut world = World::new();
-    let start = Instant::now();
     world
         .add_startup_system(init_hello_world)
         .add_system(print_hello_world1)
         .add_system(print_hello_world2)
+        .add_system(print_time)
+        // 每秒最多执行10帧
+        .set_tick_rate(10.0)
         // 两秒后结束
-        .run_until(|| start.elapsed() > Duration::from_secs(2));
+        .run_until(|world| world.resource::<Time>().unwrap().elapsed() > Duration::from_secs(2));
 }
@@ -2,6 +2,7 @@ use std::time::{Duration, Instant};
 
 use trecs::bundle::{Bundle, Component};
 
+use trecs::time::Time;
 use trecs::tools::Command;
 use trecs::world::Query;
 use trecs::World;
@@ -39,9 +40,9 @@ fn main() {
         while instant.elapsed() < Duration::from_secs_f64(1.0 / 2.0) {}
     }
 
-    // 计时器 五秒后开始返回true
-    let start = Instant::now();
-    let delay = || start.elapsed() > Duration::from_secs(5);
+    // 五秒后开始返回true,run_until会自动启用Time
+    let delay =
+        |world: &World| world.resource::<Time>().unwrap().elapsed() > Duration::from_secs(5);
 
     // 创建世界,创建几个entity
     let mut world = World::new();
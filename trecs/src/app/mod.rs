@@ -0,0 +1,281 @@
+use std::{
+    any::type_name,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{event::Events, system::IntoSystem, World};
+
+/// 可以复用的一组功能,在被添加时向[World]中添加资源,[System]和事件等
+///
+/// 同一个名字的[Plugin]只会被添加一次,所以[Plugin]可以放心地添加自己依赖的[Plugin]
+///
+/// 任何`Fn(&mut World)`都是[Plugin],元组中的[Plugin]会被依次添加
+///
+/// ``` rust
+/// use trecs::{
+///     app::{App, Plugin, TimePlugin},
+///     world::{Res, ResMut},
+///     World,
+/// };
+///
+/// struct Score(u32);
+///
+/// struct ScorePlugin;
+///
+/// impl Plugin for ScorePlugin {
+///     fn build(&self, world: &mut World) {
+///         world
+///             .add_plugin(TimePlugin)
+///             .insert_resource(Score(0))
+///             .add_system(|score: ResMut<Score>| score.into_or_init(|| Score(0)).0 += 1);
+///     }
+/// }
+///
+/// let mut app = App::new();
+/// app.add_plugin((TimePlugin, ScorePlugin)).update().update();
+/// assert_eq!(app.resource::<Score>().map(|s| s.0), Some(2));
+/// ```
+///
+/// [System]: crate::system::System
+pub trait Plugin {
+    /// 把功能添加到[World]中
+    fn build(&self, world: &mut World);
+
+    /// 用于去重的名字,默认为类型名
+    fn name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+}
+
+impl<F: Fn(&mut World)> Plugin for F {
+    fn build(&self, world: &mut World) {
+        (self)(world)
+    }
+}
+
+macro_rules! impl_plugin {
+    ($($t:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($t: Plugin,)*> Plugin for ($($t,)*) {
+            fn build(&self, world: &mut World) {
+                let ($($t,)*) = self;
+                $(world.add_plugin_ref($t);)*
+            }
+        }
+    };
+}
+trecs_proc::all_tuple!(impl_plugin, 16);
+
+/// 启用[Time],见[World::enable_time]
+///
+/// [Time]: crate::time::Time
+pub struct TimePlugin;
+
+impl Plugin for TimePlugin {
+    fn build(&self, world: &mut World) {
+        world.enable_time();
+    }
+}
+
+/// 启用[Diagnostics],见[World::enable_diagnostics]
+///
+/// [Diagnostics]: crate::diagnostic::Diagnostics
+pub struct DiagnosticsPlugin;
+
+impl Plugin for DiagnosticsPlugin {
+    fn build(&self, world: &mut World) {
+        world.enable_diagnostics();
+    }
+}
+
+/// 添加一种事件,见[World::add_event]
+pub struct EventPlugin<T>(std::marker::PhantomData<fn() -> T>);
+
+impl<T> EventPlugin<T> {
+    pub fn new() -> Self {
+        Self(std::marker::PhantomData)
+    }
+}
+
+impl<T> Default for EventPlugin<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static> Plugin for EventPlugin<T> {
+    fn build(&self, world: &mut World) {
+        world.add_event::<T>();
+    }
+}
+
+/// 请求停止循环的事件
+///
+/// 任何[System]都可以通过[EventWriter]发送,[World::run]和[World::run_until]
+/// 会在这一帧结束后停止
+///
+/// ``` rust
+/// use trecs::{app::AppExit, event::EventWriter, world::Local, World};
+///
+/// fn countdown(mut count: Local<u32>, mut exit: EventWriter<AppExit>) {
+///     *count += 1;
+///     if *count == 3 {
+///         exit.send(AppExit);
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.add_system(countdown).run();
+/// ```
+///
+/// [System]: crate::system::System
+/// [EventWriter]: crate::event::EventWriter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AppExit;
+
+impl World {
+    /// 刚结束的这一帧中是否发送了[AppExit]
+    ///
+    /// 供自己实现的循环使用
+    pub fn exit_requested(&self) -> bool {
+        self.resource::<Events<AppExit>>()
+            .is_some_and(|events| !events.is_empty())
+    }
+
+    /// 添加一个[Plugin],同一个名字的[Plugin]已经被添加过时什么都不做
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+        self.add_plugin_ref(&plugin)
+    }
+
+    /// 名为name的[Plugin]是否已经被添加过
+    pub fn has_plugin(&self, name: &str) -> bool {
+        self.plugins.contains(name)
+    }
+
+    fn add_plugin_ref<P: Plugin>(&mut self, plugin: &P) -> &mut Self {
+        // 先记录名字,这样Plugin之间的循环依赖不会无限递归
+        if self.plugins.insert(plugin.name()) {
+            plugin.build(self);
+        }
+        self
+    }
+}
+
+/// 由[Plugin]组合而成的应用
+///
+/// 只是[World]的一层包装,可以通过[Deref]使用[World]的所有方法,
+/// 常用的方法返回[App]以便继续链式调用
+#[derive(Default)]
+pub struct App {
+    world: World,
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            world: World::new(),
+        }
+    }
+
+    /// 见[World::add_plugin]
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+        self.world.add_plugin(plugin);
+        self
+    }
+
+    /// 见[World::add_system]
+    pub fn add_system<M, S: IntoSystem<M>>(&mut self, system: S) -> &mut Self {
+        self.world.add_system(system);
+        self
+    }
+
+    /// 见[World::add_startup_system]
+    pub fn add_startup_system<M, S: IntoSystem<M>>(&mut self, system: S) -> &mut Self {
+        self.world.add_startup_system(system);
+        self
+    }
+
+    /// 执行还没有执行过的startup system,然后执行一帧,见[World::update]
+    #[cfg(not(feature = "async"))]
+    pub fn update(&mut self) -> &mut Self {
+        self.world.update();
+        self
+    }
+
+    /// 循环执行,直到until返回true,见[World::run_until]
+    #[cfg(not(feature = "async"))]
+    pub fn run_until<F: FnMut(&World) -> bool>(&mut self, until: F) -> &mut Self {
+        self.world.run_until(until);
+        self
+    }
+
+    pub fn into_world(self) -> World {
+        self.world
+    }
+}
+
+impl Deref for App {
+    type Target = World;
+
+    fn deref(&self) -> &Self::Target {
+        &self.world
+    }
+}
+
+impl DerefMut for App {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.world
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{event::EventWriter, time::Time, tools::ResManager, world::Local};
+
+    struct Counter;
+
+    impl Plugin for Counter {
+        fn build(&self, world: &mut World) {
+            *world.get_res::<u32>().into_or_init(|| 0) += 1;
+            // 循环依赖只会添加一次
+            world.add_plugin(Counter);
+        }
+    }
+
+    #[test]
+    fn plugins() {
+        let mut app = App::new();
+        app.add_plugin(Counter)
+            .add_plugin((Counter, TimePlugin, EventPlugin::<u8>::new()))
+            .add_plugin(|world: &mut World| {
+                world.insert_resource("closure");
+            })
+            .update();
+        assert_eq!(app.resource::<u32>(), Some(&1));
+        assert_eq!(app.resource::<&str>(), Some(&"closure"));
+        assert!(app.resource::<Time>().is_some());
+        assert!(app.resource::<Events<u8>>().is_some());
+        assert!(app.has_plugin(type_name::<TimePlugin>()));
+        assert!(!app.has_plugin(type_name::<DiagnosticsPlugin>()));
+    }
+
+    #[test]
+    fn exit() {
+        let mut app = App::new();
+        app.enable_manual_time()
+            .add_system(|mut frame: Local<u32>, mut exit: EventWriter<AppExit>| {
+                *frame += 1;
+                if *frame == 3 {
+                    exit.send(AppExit);
+                }
+            })
+            .run_until(|_| false);
+        assert!(app.exit_requested());
+        assert_eq!(app.resource::<Time>().unwrap().frames(), 3);
+
+        // 下一帧结束后不再请求停止
+        app.update();
+        assert!(!app.exit_requested());
+    }
+}
@@ -1,28 +1,196 @@
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
 
 use super::Bundle;
+use crate::reflect::TypeRegistry;
+
+/// [Component]的存储方式
+///
+/// 可以通过`#[component(storage = "sparse")]`指定
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageType {
+    /// 和同一个[Entity]的其他[Component]一起存放在[Chunk]中
+    ///
+    /// 遍历快,添加和移除时需要移动整个[Entity]
+    ///
+    /// [Entity]: crate::storage::Entity
+    /// [Chunk]: crate::storage::Chunk
+    #[default]
+    Table,
+    /// 单独存放,适合经常被添加和移除的[Component]
+    ///
+    /// 添加和移除时不会移动[Entity],遍历时需要逐个[Entity]查找,
+    /// 可以通过[Query]或者[World::get_sparse]等访问
+    ///
+    /// [Entity]: crate::storage::Entity
+    /// [Query]: crate::world::Query
+    /// [World::get_sparse]: crate::World::get_sparse
+    SparseSet,
+}
 /// 最基本的构成单元
 ///
 /// 构成[Bundle],并存储在[Chunk]中
 ///
 /// 此特征实际上只是一个标记
 ///
+/// 因为[System]可能被并行执行,[Component]必须是[Send]+[Sync]的
+///
+/// ``` rust
+/// use trecs::bundle::{Bundle, Component, StorageType};
+///
+/// #[derive(Component)]
+/// #[component(storage = "sparse", name = "Poisoned")]
+/// struct Poison(u32);
+///
+/// #[derive(Component)]
+/// struct Health(u32);
+///
+/// assert_eq!(Poison::STORAGE, StorageType::SparseSet);
+/// assert_eq!(Health::STORAGE, StorageType::Table);
+/// assert_eq!(Health::field_names(), ["0"]);
+/// assert_eq!(Health(3).field(0).unwrap().downcast_ref(), Some(&3u32));
+/// assert_eq!(<(Health, Poison)>::components_names()[1], "Poisoned");
+/// assert_eq!(
+///     <(Health, Poison)>::components_descriptors()[1].storage,
+///     StorageType::SparseSet
+/// );
+/// ```
+///
 /// [Bundle]:crate
-pub trait Component: Any {
+/// [System]: crate::system::System
+pub trait Component: Any + Send + Sync {
+    /// 存储方式,见[StorageType]
+    const STORAGE: StorageType = StorageType::Table;
+
     fn type_id_() -> TypeId;
+
+    /// 给人看的名字,用于调试输出,默认为[type_name]
+    ///
+    /// 可以通过`#[component(name = "...")]`指定
+    fn component_name() -> &'static str {
+        type_name::<Self>()
+    }
+
+    /// 所有字段的名字,元组结构体的字段名为"0","1"...
+    ///
+    /// 由`#[derive(Component)]`实现,见[Component::field]
+    fn field_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// 第index个字段
+    fn field(&self, _index: usize) -> Option<&dyn Any> {
+        None
+    }
+
+    /// 第index个字段的可变引用
+    fn field_mut(&mut self, _index: usize) -> Option<&mut dyn Any> {
+        None
+    }
+
+    /// 在[TypeRegistry]中注册自身,见[TypeRegistry::register_type]
+    ///
+    /// 可以通过`#[component(reflect(default, debug, serde))]`注册更多的信息
+    fn register_type(registry: &mut TypeRegistry)
+    where
+        Self: Sized,
+    {
+        registry.register::<Self>();
+    }
+}
+
+/// [Component]的id
+///
+/// + 静态的[Component]以[Component::type_id_]区分
+/// + 在运行时注册的[Component]以编号区分,见[World::register_component]
+///
+/// [World::register_component]: crate::World::register_component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ComponentId {
+    Static(TypeId),
+    Dynamic(usize),
+}
+
+impl ComponentId {
+    pub fn of<C: Component>() -> Self {
+        Self::Static(C::type_id_())
+    }
+}
+
+impl From<TypeId> for ComponentId {
+    fn from(value: TypeId) -> Self {
+        Self::Static(value)
+    }
 }
 
 pub type Components = Vec<Box<dyn Any>>;
 
 // #[rustfmt::skip]
-mod __impl {
-    use super::{Bundle, Component, Components};
+pub(crate) mod __impl {
+    use super::{Bundle, Component, ComponentId, Components};
+    use crate::{bundle::ComponentDescriptor, storage::Entity};
     use std::{
         any::{type_name, Any, TypeId},
-        cell::OnceCell,
         collections::HashMap,
+        sync::{Mutex, OnceLock},
     };
 
+    type Cache<T> = OnceLock<Mutex<HashMap<TypeId, &'static [T]>>>;
+
+    /// 从cache中读取,不存在时通过init计算
+    ///
+    /// init中可能会读取其他的[Bundle]的缓存(如嵌套的[Bundle]),所以计算时不能持有锁
+    fn cached<T>(
+        cache: &'static Cache<T>,
+        bundle_id: TypeId,
+        init: impl FnOnce() -> Vec<T>,
+    ) -> &'static [T] {
+        let cache = cache.get_or_init(Default::default);
+        if let Some(cached) = cache.lock().unwrap().get(&bundle_id) {
+            return cached;
+        }
+        let value = init();
+        cache
+            .lock()
+            .unwrap()
+            .entry(bundle_id)
+            .or_insert_with(|| Box::leak(value.into_boxed_slice()))
+    }
+
+    /// 缓存每种[Bundle]的components_ids
+    ///
+    /// 泛型函数中的静态变量对于不同的类型是同一个
+    ///
+    /// 所以用上了哈希表
+    pub fn cached_components_ids(
+        bundle_id: TypeId,
+        init: impl FnOnce() -> Vec<ComponentId>,
+    ) -> &'static [ComponentId] {
+        static COMPONENT_IDS: Cache<ComponentId> = OnceLock::new();
+        cached(&COMPONENT_IDS, bundle_id, init)
+    }
+
+    /// 缓存每种[Bundle]的components_names
+    ///
+    /// 同[cached_components_ids]
+    pub fn cached_components_names(
+        bundle_id: TypeId,
+        init: impl FnOnce() -> Vec<&'static str>,
+    ) -> &'static [&'static str] {
+        static COMPONENT_NAMES: Cache<&'static str> = OnceLock::new();
+        cached(&COMPONENT_NAMES, bundle_id, init)
+    }
+
+    /// 缓存每种[Bundle]的components_descriptors
+    ///
+    /// 同[cached_components_ids]
+    pub fn cached_components_descriptors(
+        bundle_id: TypeId,
+        init: impl FnOnce() -> Vec<ComponentDescriptor>,
+    ) -> &'static [ComponentDescriptor] {
+        static COMPONENT_DESCRIPTORS: Cache<ComponentDescriptor> = OnceLock::new();
+        cached(&COMPONENT_DESCRIPTORS, bundle_id, init)
+    }
+
     macro_rules! impl_components {
         ($($t:ty),*) => {
             $(impl Component for $t{
@@ -36,6 +204,7 @@ mod __impl {
     impl_components!(u8, u16, u32, u64, usize, u128);
     impl_components!(i8, i16, i32, i64, isize, i128);
     impl_components!(bool, (), &'static str);
+    impl_components!(Entity);
 
     impl<C: Component> Component for &'static C {
         fn type_id_() -> TypeId {
@@ -47,6 +216,16 @@ mod __impl {
             TypeId::of::<Self>()
         }
     }
+    impl<C: Component> Component for Option<&'static C> {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+    impl<C: Component> Component for Option<&'static mut C> {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
 
     macro_rules! impl_bundle {
         ($($t:ident),*) => {
@@ -58,23 +237,46 @@ mod __impl {
                     ]
                 }
 
-                fn components_ids() -> &'static [TypeId] {
-                    static mut COMPONENT_IDS: OnceCell<HashMap<TypeId, Vec<TypeId>>> = OnceCell::new();
-                    unsafe {
-                        COMPONENT_IDS.get_or_init(|| HashMap::new());
-                        let hashset = COMPONENT_IDS.get_mut().unwrap();
-                        if !hashset.contains_key(&Self::type_id_()) {
-                            hashset.insert(Self::type_id_(), vec![$($t::type_id_(),)*]);
-                        }
-                        hashset.get(&Self::type_id_()).unwrap()
-                    }
+                unsafe fn write_to(self, offset: usize, dst: &mut dyn FnMut(usize) -> *mut u8) {
+                    let ($($t,)*) = self;
+                    // 依次写入,每个Bundle之后的offset增加它的Component的数量
+                    $(
+                        let offset = {
+                            $t.write_to(offset, dst);
+                            offset + <$t as Bundle>::components_ids().len()
+                        };
+                    )*
+                    let _ = (offset, dst);
+                }
+
+                fn components_ids() -> &'static [ComponentId] {
+                    cached_components_ids(Self::type_id_(), || {
+                        let mut ids = Vec::new();
+                        $(ids.extend_from_slice($t::components_ids());)*
+                        ids
+                    })
+                }
+
+                fn components_names() -> &'static [&'static str] {
+                    cached_components_names(Self::type_id_(), || {
+                        let mut names = Vec::new();
+                        $(names.extend_from_slice($t::components_names());)*
+                        names
+                    })
+                }
+
+                fn components_descriptors() -> &'static [ComponentDescriptor] {
+                    cached_components_descriptors(Self::type_id_(), || {
+                        let mut descriptors = Vec::new();
+                        $(descriptors.extend_from_slice($t::components_descriptors());)*
+                        descriptors
+                    })
                 }
 
                 fn drop(cs : Components){
-                    // 迭代 && 颠倒
-                    let mut iter = cs.into_iter().rev();
-                    // 依次赋值
-                    $(let $t = iter.next().unwrap().downcast::<$t>();)*
+                    // 依次还原并drop
+                    let mut iter = cs.into_iter();
+                    $(drop(iter.next().unwrap().downcast::<$t>());)*
                 }
 
                 fn type_name() -> &'static str {
@@ -95,25 +297,24 @@ mod __impl {
             vec![Box::new(self)]
         }
 
+        unsafe fn write_to(self, offset: usize, dst: &mut dyn FnMut(usize) -> *mut u8) {
+            dst(offset).cast::<Self>().write(self)
+        }
+
         // 其实直接创快得多
         // 但是为了统一,代价必须有
-        // 所以使用static mut
-
-        // OnceCell终于稳定了 真香
-        fn components_ids() -> &'static [TypeId] {
-            // 对于不同Component,会有一个一样的静态变量
-            // static mut COMPONENT_ID: OnceCell<[TypeId; 1]> = OnceCell::new();
-
-            // 所以用上了哈希表
-            static mut COMPONENT_IDS: OnceCell<HashMap<TypeId, [TypeId; 1]>> = OnceCell::new();
-            unsafe {
-                COMPONENT_IDS.get_or_init(HashMap::new);
-                let hashset = COMPONENT_IDS.get_mut().unwrap();
-                hashset
-                    .entry(Self::type_id_())
-                    .or_insert_with(|| [Self::type_id_()]);
-                hashset.get(&Self::type_id_()).unwrap()
-            }
+        fn components_ids() -> &'static [ComponentId] {
+            cached_components_ids(Self::type_id_(), || vec![ComponentId::of::<Self>()])
+        }
+
+        fn components_names() -> &'static [&'static str] {
+            cached_components_names(Self::type_id_(), || vec![Self::component_name()])
+        }
+
+        fn components_descriptors() -> &'static [ComponentDescriptor] {
+            cached_components_descriptors(Self::type_id_(), || {
+                vec![ComponentDescriptor::of_component::<Self>()]
+            })
         }
 
         fn drop(mut cs: Components) {
@@ -0,0 +1,169 @@
+use std::{
+    any::type_name,
+    fmt::Debug,
+    hash::Hash,
+    mem::{align_of, size_of},
+};
+
+use crate::storage::ComponentVTable;
+
+use super::{Component, StorageType};
+
+/// 不依赖于[TypeId]的[Component]的描述
+///
+/// 同一个类型被不同的动态库加载时,[TypeId]可能不同,
+/// 而类型名和内存布局是相同的
+///
+/// [TypeId]: std::any::TypeId
+/// [Component]: crate::bundle::Component
+///
+/// 比较和哈希时只考虑类型名,内存布局和[StorageType]
+#[derive(Clone, Copy)]
+pub struct ComponentDescriptor {
+    /// 类型名
+    pub name: &'static str,
+    /// 类型的大小
+    pub size: usize,
+    /// 类型的对齐
+    pub align: usize,
+    /// 存储方式,见[StorageType]
+    pub storage: StorageType,
+    /// 在[Chunk]中存放值的方式
+    ///
+    /// 在运行时注册的[Component]以[DynamicComponent]的形式存放
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    /// [DynamicComponent]: crate::bundle::DynamicComponent
+    pub(crate) vtable: &'static ComponentVTable,
+}
+
+impl ComponentDescriptor {
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            name: type_name::<T>(),
+            size: size_of::<T>(),
+            align: align_of::<T>(),
+            storage: StorageType::Table,
+            vtable: ComponentVTable::of::<T>(),
+        }
+    }
+
+    /// [Component]的描述,包含它的[StorageType]
+    pub fn of_component<C: Component>() -> Self {
+        Self {
+            storage: C::STORAGE,
+            ..Self::of::<C>()
+        }
+    }
+
+    /// 类型名和内存布局的哈希
+    ///
+    /// 使用FNV-1a,不依赖于编译器版本和[TypeId]
+    ///
+    /// [TypeId]: std::any::TypeId
+    pub fn stable_id(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+        let layout = [self.size as u64, self.align as u64];
+        self.name
+            .bytes()
+            .chain(layout.iter().flat_map(|n| n.to_le_bytes()))
+            .fold(OFFSET, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(PRIME)
+            })
+    }
+
+    /// 是否有相同的内存布局
+    pub fn same_layout(&self, other: &Self) -> bool {
+        self.size == other.size && self.align == other.align
+    }
+}
+
+impl PartialEq for ComponentDescriptor {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.same_layout(other) && self.storage == other.storage
+    }
+}
+
+impl Eq for ComponentDescriptor {}
+
+impl Hash for ComponentDescriptor {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self.name, self.size, self.align, self.storage).hash(state);
+    }
+}
+
+impl Debug for ComponentDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComponentDescriptor")
+            .field("name", &self.name)
+            .field("size", &self.size)
+            .field("align", &self.align)
+            .field("storage", &self.storage)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use super::*;
+    use crate::{
+        bundle::{BundleError, BundleId, ComponentId},
+        World,
+    };
+
+    #[test]
+    fn stable_id() {
+        let descriptor = ComponentDescriptor::of::<u32>();
+        assert_eq!(
+            descriptor.stable_id(),
+            ComponentDescriptor::of::<u32>().stable_id()
+        );
+        assert_ne!(
+            descriptor.stable_id(),
+            ComponentDescriptor::of::<i32>().stable_id()
+        );
+        let resized = ComponentDescriptor {
+            size: 8,
+            ..descriptor
+        };
+        assert_ne!(descriptor.stable_id(), resized.stable_id());
+    }
+
+    #[test]
+    fn mismatch() {
+        let mut world = World::new();
+        world.try_register::<(u32, &str)>().unwrap();
+        let u32_ = ComponentDescriptor::of::<u32>();
+        assert_eq!(world.component_type_id(&u32_), Some(TypeId::of::<u32>()));
+
+        // 模拟被另一个动态库注册过的i32
+        let i32_ = ComponentDescriptor::of::<i32>();
+        world
+            .descriptors
+            .insert(i32_.name, (ComponentId::of::<u32>(), i32_));
+        assert_eq!(
+            world.try_register::<(i32, bool)>(),
+            Err(BundleError::TypeIdMismatch { component: "i32" })
+        );
+
+        // 模拟修改了定义之后重新加载的u32
+        let resized = ComponentDescriptor { size: 8, ..u32_ };
+        world
+            .descriptors
+            .insert(u32_.name, (ComponentId::of::<u32>(), resized));
+        assert_eq!(
+            world.try_register::<u32>(),
+            Err(BundleError::LayoutMismatch {
+                registered: resized,
+                found: u32_,
+            })
+        );
+        assert_eq!(world.component_type_id(&u32_), None);
+        // 失败时不会注册
+        assert!(!world.metas.contains_key(&BundleId::of::<(i32, bool)>()));
+        assert!(!world.descriptors.contains_key("bool"));
+    }
+}
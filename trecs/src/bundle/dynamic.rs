@@ -0,0 +1,106 @@
+use std::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    fmt::Debug,
+    ptr::NonNull,
+};
+
+use super::ComponentId;
+
+/// 在运行时注册的[Component]的drop函数,参数指向需要被drop的值
+///
+/// 见[World::register_component]
+///
+/// [Component]: crate::bundle::Component
+/// [World::register_component]: crate::World::register_component
+pub type DynamicDrop = unsafe fn(*mut u8);
+
+/// 在运行时注册的[Component]的一个值
+///
+/// 没有对应的Rust类型,只有一块按照注册时的内存布局分配的内存,
+/// 通过[World::dynamic_component]创建
+///
+/// [Component]: crate::bundle::Component
+/// [World::dynamic_component]: crate::World::dynamic_component
+pub struct DynamicComponent {
+    id: ComponentId,
+    data: NonNull<u8>,
+    layout: Layout,
+    drop: Option<DynamicDrop>,
+}
+
+impl DynamicComponent {
+    /// 复制value指向的layout.size()个字节
+    ///
+    /// # Safety
+    ///
+    /// value必须指向一个符合layout,并且可以被drop正确处理的值,
+    /// 之后value指向的值不能再被使用或者drop
+    pub(crate) unsafe fn new(
+        id: ComponentId,
+        layout: Layout,
+        drop: Option<DynamicDrop>,
+        value: *const u8,
+    ) -> Self {
+        let data = if layout.size() == 0 {
+            // 不需要分配,只需要对齐
+            NonNull::new_unchecked(layout.align() as *mut u8)
+        } else {
+            let data = alloc(layout);
+            let Some(data) = NonNull::new(data) else {
+                handle_alloc_error(layout)
+            };
+            data.as_ptr().copy_from_nonoverlapping(value, layout.size());
+            data
+        };
+        Self {
+            id,
+            data,
+            layout,
+            drop,
+        }
+    }
+
+    /// [Component]的[ComponentId]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    /// 值的内存布局
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// 指向值的指针
+    pub fn as_ptr(&self) -> *const u8 {
+        self.data.as_ptr()
+    }
+
+    /// 指向值的可变指针
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.data.as_ptr()
+    }
+}
+
+impl Drop for DynamicComponent {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(drop) = self.drop {
+                drop(self.data.as_ptr());
+            }
+            if self.layout.size() != 0 {
+                dealloc(self.data.as_ptr(), self.layout);
+            }
+        }
+    }
+}
+
+impl Debug for DynamicComponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynamicComponent")
+            .field("id", &self.id)
+            .field("layout", &self.layout)
+            .finish()
+    }
+}
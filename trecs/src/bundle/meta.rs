@@ -4,9 +4,31 @@ use std::{
     fmt::Debug,
 };
 
-use crate::tools::{MappingTable, WorldFetch, WorldFilter};
+use crate::{
+    storage::ComponentVTable,
+    tools::{MappingTable, WorldFetch, WorldFilter},
+};
+
+use super::{Bundle, BundleError, ComponentId};
 
-use super::{Bundle, Components};
+/// [BundleMeta]在[World]中的键
+///
+/// + 静态的[Bundle]以[Bundle::type_id_]区分
+/// + 通过[World::insert]等在运行时组合出来的布局以编号区分
+///
+/// [World]: crate::World
+/// [World::insert]: crate::World::insert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BundleId {
+    Static(TypeId),
+    Dynamic(usize),
+}
+
+impl BundleId {
+    pub fn of<B: Bundle>() -> Self {
+        Self::Static(B::type_id_())
+    }
+}
 
 /// 一个[Bundle]的信息
 ///
@@ -14,10 +36,16 @@ use super::{Bundle, Components};
 ///
 /// 比如(123,&&str) 就是一个Bundle
 pub struct BundleMeta {
-    /// [Bundle]的typeid
-    pub bundle_id: TypeId,
-    /// [Bundle]的所有Componenets的id
-    pub components_ids: &'static [TypeId],
+    /// [Bundle]的[BundleId]
+    pub bundle_id: BundleId,
+    /// [Bundle]的所有Componenets的[ComponentId]
+    pub components_ids: &'static [ComponentId],
+    /// [Bundle]的所有Componenets的类型名
+    pub components_names: &'static [&'static str],
+    /// 每个Componenet是否被[WorldFetch]访问过
+    ///
+    /// 用于找出从来没有被查询过的[Component]
+    pub queried: Vec<bool>,
     /// [Bundle]对于每种[WorldFilter]的结果
     ///
     /// 避免每次都重新计算
@@ -28,27 +56,91 @@ pub struct BundleMeta {
     pub fetch_cache: HashMap<TypeId, MappingTable>,
     /// [World]中所有存放此类[Bundle]的[Chunk]的下标
     pub chunks: Vec<usize>,
-
-    /// 类似[World]的resources_dropers
+    /// 与components_ids一一对应,每个[Component]在[Chunk]中的存放方式
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    pub(crate) vtables: Vec<&'static ComponentVTable>,
+    /// 添加一种[Component]后的布局
+    ///
+    /// 用于在[World::insert]时直接找到目标布局,而不需要重新排序
     ///
-    /// 是一个用来还原并drop[Bundle]的函数
-    pub droper: Box<dyn Fn(Components)>,
+    /// [World::insert]: crate::World::insert
+    pub(crate) add_edges: HashMap<ComponentId, BundleId>,
+    /// 移除一种[Component]后的布局,见add_edges
+    pub(crate) remove_edges: HashMap<ComponentId, BundleId>,
 
     bundle_info: (&'static str, &'static str),
 }
 
 impl BundleMeta {
-    pub fn new<B: Bundle>() -> Self {
-        let droper = |cs: Components| B::drop(cs);
-        Self {
-            bundle_id: B::type_id_(),
+    /// 包含重复的[Component]时返回[BundleError::DuplicateComponent]
+    pub fn new<B: Bundle>() -> Result<Self, BundleError> {
+        Self::check::<B>()?;
+        Ok(Self {
+            bundle_id: BundleId::of::<B>(),
             components_ids: B::components_ids(),
+            components_names: B::components_names(),
+            queried: vec![false; B::components_ids().len()],
             filter_cache: Default::default(),
             fetch_cache: Default::default(),
             chunks: vec![],
+            add_edges: Default::default(),
+            remove_edges: Default::default(),
             bundle_info: (type_name::<B>(), B::type_name()),
-            droper: Box::new(droper),
+            vtables: B::components_descriptors()
+                .iter()
+                .map(|descriptor| descriptor.vtable)
+                .collect(),
+        })
+    }
+
+    /// 包含重复的[Component]时返回[BundleError::DuplicateComponent]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn check<B: Bundle>() -> Result<(), BundleError> {
+        let ids = B::components_ids();
+        for (index, id) in ids.iter().enumerate() {
+            if ids[..index].contains(id) {
+                return Err(BundleError::DuplicateComponent {
+                    bundle: type_name::<B>(),
+                    component: B::components_names()[index],
+                });
+            }
         }
+        Ok(())
+    }
+
+    /// 在运行时组合出来的布局,components_ids中不能有重复的[Component]
+    ///
+    /// vtables与components_ids一一对应
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn dynamic(
+        id: usize,
+        components_ids: Vec<ComponentId>,
+        components_names: Vec<&'static str>,
+        vtables: Vec<&'static ComponentVTable>,
+    ) -> Self {
+        let name: &'static str =
+            Box::leak(format!("({})", components_names.join(", ")).into_boxed_str());
+        Self {
+            bundle_id: BundleId::Dynamic(id),
+            queried: vec![false; components_ids.len()],
+            components_ids: Box::leak(components_ids.into_boxed_slice()),
+            components_names: Box::leak(components_names.into_boxed_slice()),
+            filter_cache: Default::default(),
+            fetch_cache: Default::default(),
+            chunks: vec![],
+            add_edges: Default::default(),
+            remove_edges: Default::default(),
+            bundle_info: (name, name),
+            vtables,
+        }
+    }
+
+    /// [Bundle]的类型名
+    pub fn name(&self) -> &'static str {
+        self.bundle_info.0
     }
 
     pub fn filter<F: WorldFilter>(&mut self) -> bool {
@@ -65,6 +157,9 @@ impl BundleMeta {
         if let std::collections::hash_map::Entry::Vacant(e) = self.fetch_cache.entry(fetch_id) {
             let mapping_table = F::contain(&mut self.components_ids.to_vec());
             if let Some(mapping_table) = mapping_table {
+                for mapping in mapping_table.mappings() {
+                    self.queried[mapping] = true;
+                }
                 e.insert(mapping_table);
             }
         }
@@ -83,3 +178,31 @@ impl Debug for BundleMeta {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tools::Command, World};
+
+    #[test]
+    fn duplicate_component() {
+        assert_eq!(
+            BundleMeta::new::<(i32, u32, i32)>().err(),
+            Some(BundleError::DuplicateComponent {
+                bundle: type_name::<(i32, u32, i32)>(),
+                component: "i32",
+            })
+        );
+        assert!(BundleMeta::new::<(i32, u32)>().is_ok());
+
+        let mut world = World::new();
+        assert!(world.try_register::<(&str, &str)>().is_err());
+        assert!(world.metas.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "中包含重复的Component")]
+    fn spawn_duplicate() {
+        World::new().spawn((1, 2));
+    }
+}
@@ -1,22 +1,150 @@
 mod component;
+mod descriptor;
+mod dynamic;
 mod meta;
-use std::any::{Any, TypeId};
+use std::{
+    any::{Any, TypeId},
+    error::Error,
+    fmt::Display,
+};
 
-pub use component::{Component, Components};
-pub(crate) use meta::BundleMeta;
+pub use component::{Component, ComponentId, Components, StorageType};
+pub use descriptor::ComponentDescriptor;
+pub use dynamic::{DynamicComponent, DynamicDrop};
+pub(crate) use meta::{BundleId, BundleMeta};
 pub use trecs_proc::{Bundle, Component};
 
+/// 供`#[derive(Bundle)]`生成的代码使用
+#[doc(hidden)]
+pub mod __private {
+    pub use super::component::__impl::{
+        cached_components_descriptors, cached_components_ids, cached_components_names,
+    };
+}
+
+/// 注册[Bundle]时可能发生的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleError {
+    /// [Bundle]中包含了重复的[Component]
+    ///
+    /// 如(i32,i32),会导致无法确定[WorldFetch]访问的是哪一个
+    ///
+    /// [WorldFetch]: crate::tools::WorldFetch
+    DuplicateComponent {
+        /// [Bundle]的类型名
+        bundle: &'static str,
+        /// 重复的[Component]的类型名
+        component: &'static str,
+    },
+    /// 已经注册过同名同布局的[Component],但是[TypeId]不同
+    ///
+    /// 通常是因为同一个类型被不同的动态库加载了
+    TypeIdMismatch {
+        /// [Component]的类型名
+        component: &'static str,
+    },
+    /// 已经注册过同名的[Component],但是内存布局不同
+    ///
+    /// 通常是因为重新加载的代码修改了类型的定义
+    LayoutMismatch {
+        /// 已经注册的[Component]
+        registered: ComponentDescriptor,
+        /// 正在注册的[Component]
+        found: ComponentDescriptor,
+    },
+}
+
+impl Display for BundleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BundleError::DuplicateComponent { bundle, component } => {
+                write!(f, "Bundle {bundle} 中包含重复的Component {component}")
+            }
+            BundleError::TypeIdMismatch { component } => {
+                write!(f, "Component {component} 已经以不同的TypeId注册过了")
+            }
+            BundleError::LayoutMismatch { registered, found } => write!(
+                f,
+                "Component {} 的内存布局(size: {}, align: {})与已经注册的(size: {}, align: {})不同",
+                found.name, found.size, found.align, registered.size, registered.align
+            ),
+        }
+    }
+}
+
+impl Error for BundleError {}
+
 /// 一系列[Component]的组合
 ///
 /// + 任何由[Components]构成的元组
 ///
 /// + 任何由[Components]构成,并且drive了本特征的类型
+///
+/// 通过`#[derive(Bundle)]`实现时,嵌套的[Bundle]会被展开
+///
+/// ``` rust
+/// use trecs::{bundle::{Bundle, Component}, tools::Command, World};
+///
+/// #[derive(Component)]
+/// struct Health(u32);
+///
+/// #[derive(Bundle)]
+/// struct Position(i32, u8);
+///
+/// #[derive(Bundle)]
+/// struct Player<T: Component> {
+///     health: Health,
+///     position: Position,
+///     extra: T,
+/// }
+///
+/// assert_eq!(Player::<bool>::components_ids().len(), 4);
+///
+/// let mut world = World::new();
+/// let entity = world.spawn(Player {
+///     health: Health(100),
+///     position: Position(1, 2),
+///     extra: true,
+/// });
+/// let (health, i, extra) = world.fetch::<(&Health, &i32, &bool)>(entity).unwrap();
+/// assert_eq!((health.0, *i, *extra), (100, 1, true));
+/// ```
 pub trait Bundle: Any {
     /// 对[Bundle]中的全部[Component]的引用    
     fn destory(self) -> Components;
 
-    /// [Bundle]中所有[Component]的[TypeId]
-    fn components_ids() -> &'static [TypeId];
+    /// 把[Bundle]中的全部[Component]依次直接移动到dst给出的位置,不需要经过[Components]
+    ///
+    /// dst的参数为[Component]在[Bundle::components_ids]中的下标加上offset,
+    /// 默认通过[Bundle::destory]实现
+    ///
+    /// # Safety
+    ///
+    /// dst返回的指针必须可以写入对应的[Component],并且那里原来的值不会被drop
+    unsafe fn write_to(self, offset: usize, dst: &mut dyn FnMut(usize) -> *mut u8)
+    where
+        Self: Sized,
+    {
+        let descriptors = Self::components_descriptors();
+        for (index, component) in self.destory().into_iter().enumerate() {
+            descriptors[index]
+                .vtable
+                .write(component, dst(offset + index))
+        }
+    }
+
+    /// [Bundle]中所有[Component]的[ComponentId]
+    fn components_ids() -> &'static [ComponentId];
+
+    /// [Bundle]中所有[Component]的类型名
+    ///
+    /// 与[Bundle::components_ids]一一对应
+    fn components_names() -> &'static [&'static str];
+
+    /// [Bundle]中所有[Component]的[ComponentDescriptor]
+    ///
+    /// 与[Bundle::components_ids]一一对应
+    fn components_descriptors() -> &'static [ComponentDescriptor];
 
     /// 还原并[Drop]
     ///
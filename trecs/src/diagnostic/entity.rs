@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+/// [Entity]相关的诊断信息
+///
+/// 用来发现长时间运行时[Entity]的泄漏
+///
+/// [Entity]: crate::storage::Entity
+#[derive(Debug, Clone)]
+pub struct EntityDiagnostic {
+    live: usize,
+    /// <[Bundle]的类型名,存活的数量>,按照数量从多到少排列
+    ///
+    /// [Bundle]: crate::bundle::Bundle
+    archetypes: Vec<(&'static str, usize)>,
+    spawned: Rate,
+    despawned: Rate,
+}
+
+impl EntityDiagnostic {
+    pub(super) fn new(history: usize) -> Self {
+        Self {
+            live: 0,
+            archetypes: vec![],
+            spawned: Rate::new(history),
+            despawned: Rate::new(history),
+        }
+    }
+
+    pub(super) fn record(
+        &mut self,
+        mut archetypes: Vec<(&'static str, usize)>,
+        spawned: usize,
+        despawned: usize,
+    ) {
+        archetypes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        self.live = archetypes.iter().map(|(.., len)| len).sum();
+        self.archetypes = archetypes;
+        self.spawned.record(spawned);
+        self.despawned.record(despawned);
+    }
+
+    /// 存活的[Entity]的数量
+    ///
+    /// [Entity]: crate::storage::Entity
+    pub fn live(&self) -> usize {
+        self.live
+    }
+
+    /// 每种[Bundle]存活的数量,按照数量从多到少排列
+    ///
+    /// [Bundle]: crate::bundle::Bundle
+    pub fn archetypes(&self) -> &[(&'static str, usize)] {
+        &self.archetypes
+    }
+
+    /// 每帧放入[World]的[Bundle]的数量
+    ///
+    /// [World]: crate::World
+    /// [Bundle]: crate::bundle::Bundle
+    pub fn spawned(&self) -> &Rate {
+        &self.spawned
+    }
+
+    /// 每帧从[World]删除的[Bundle]的数量
+    ///
+    /// [World]: crate::World
+    /// [Bundle]: crate::bundle::Bundle
+    pub fn despawned(&self) -> &Rate {
+        &self.despawned
+    }
+}
+
+/// 每帧发生次数的滚动统计
+#[derive(Debug, Clone)]
+pub struct Rate {
+    history: VecDeque<usize>,
+    capacity: usize,
+    /// 上一帧结束时的累计次数
+    total: Option<usize>,
+}
+
+impl Rate {
+    fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+            total: None,
+        }
+    }
+
+    /// 通过累计次数计算这一帧发生的次数
+    fn record(&mut self, total: usize) {
+        let last = self.total.replace(total).unwrap_or(0);
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(total - last);
+    }
+
+    /// 最近一帧发生的次数
+    pub fn last(&self) -> usize {
+        self.history.back().copied().unwrap_or(0)
+    }
+
+    /// 最近若干帧平均每帧发生的次数
+    pub fn average(&self) -> f64 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+        self.history.iter().sum::<usize>() as f64 / self.history.len() as f64
+    }
+
+    /// 一共发生的次数
+    pub fn total(&self) -> usize {
+        self.total.unwrap_or(0)
+    }
+}
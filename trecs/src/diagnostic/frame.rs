@@ -0,0 +1,74 @@
+use std::time::Instant;
+
+use super::Measurement;
+
+/// 帧相关的诊断信息
+///
+/// 一帧即就是一次[World::run_once]
+///
+/// [World::run_once]: crate::World::run_once
+#[derive(Debug, Clone)]
+pub struct FrameDiagnostic {
+    /// 相邻两帧开始时间的间隔
+    delta: Measurement,
+    /// 每帧从开始到所有[System]执行完的时间
+    ///
+    /// [System]: crate::system::System
+    busy: Measurement,
+    count: u64,
+    last: Option<Instant>,
+}
+
+impl FrameDiagnostic {
+    pub(super) fn new(history: usize) -> Self {
+        Self {
+            delta: Measurement::new(history),
+            busy: Measurement::new(history),
+            count: 0,
+            last: None,
+        }
+    }
+
+    /// 记录开始于start的一帧,在这一帧的[System]都执行完后调用
+    ///
+    /// [System]: crate::system::System
+    pub(super) fn tick(&mut self, start: Instant) {
+        if let Some(last) = self.last {
+            self.delta.push(start - last);
+        }
+        self.busy.push(start.elapsed());
+        self.last = Some(start);
+        self.count += 1;
+    }
+
+    /// 已经运行的帧数
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// 每帧的间隔
+    ///
+    /// 第一帧没有间隔,所以记录次数会比帧数少一
+    pub fn delta(&self) -> &Measurement {
+        &self.delta
+    }
+
+    /// 每帧执行所用的时间,不包括[World::set_tick_rate]限制帧率时等待的时间
+    ///
+    /// [World::set_tick_rate]: crate::World::set_tick_rate
+    pub fn busy(&self) -> &Measurement {
+        &self.busy
+    }
+
+    /// 根据最近若干帧的平均间隔计算的帧率
+    ///
+    /// 少于两帧时返回0
+    pub fn fps(&self) -> f64 {
+        let average = self.delta.average().as_secs_f64();
+        if average == 0.0 {
+            0.0
+        } else {
+            1.0 / average
+        }
+    }
+}
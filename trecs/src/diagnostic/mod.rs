@@ -0,0 +1,311 @@
+mod entity;
+mod frame;
+mod system;
+
+pub use entity::{EntityDiagnostic, Rate};
+pub use frame::FrameDiagnostic;
+pub use system::SystemDiagnostic;
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::system::SystemPanic;
+#[allow(unused_imports)]
+use crate::World;
+
+/// 运行时的诊断信息
+///
+/// 作为资源存放在[World]中,通过[World::enable_diagnostics]启用
+///
+/// 启用后由[World]的循环自动更新
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    /// 每一项数据保留的历史记录数量
+    history: usize,
+    frame: FrameDiagnostic,
+    entities: EntityDiagnostic,
+    /// 按照第一次运行的顺序排列
+    systems: Vec<SystemDiagnostic>,
+}
+
+impl Diagnostics {
+    /// 默认保留的历史记录数量
+    pub const DEFAULT_HISTORY: usize = 120;
+
+    pub fn new() -> Self {
+        Self::with_history(Self::DEFAULT_HISTORY)
+    }
+
+    /// 每一项数据保留history条历史记录,用来计算平均值
+    pub fn with_history(history: usize) -> Self {
+        Self {
+            history: history.max(1),
+            frame: FrameDiagnostic::new(history.max(1)),
+            entities: EntityDiagnostic::new(history.max(1)),
+            systems: vec![],
+        }
+    }
+
+    /// 帧间隔,帧率与帧数
+    pub fn frame(&self) -> &FrameDiagnostic {
+        &self.frame
+    }
+
+    /// [Entity]的数量,以及每帧放入和删除的数量
+    ///
+    /// [Entity]: crate::storage::Entity
+    pub fn entities(&self) -> &EntityDiagnostic {
+        &self.entities
+    }
+
+    /// 名字为name的[System]的诊断信息
+    ///
+    /// 名字就是[System]的类型名,可以通过[std::any::type_name_of_val]获得
+    ///
+    /// 同名的[System]在一帧中的耗时合计为一条记录,
+    /// 例如多次添加的同一个函数,以及[Stage::FixedUpdate]中一帧执行多次的[System]
+    ///
+    /// [System]: crate::system::System
+    /// [Stage::FixedUpdate]: crate::system::Stage::FixedUpdate
+    pub fn system(&self, name: &str) -> Option<&SystemDiagnostic> {
+        self.systems.iter().find(|system| system.name() == name)
+    }
+
+    /// 所有[System]的诊断信息
+    ///
+    /// [System]: crate::system::System
+    pub fn systems(&self) -> impl Iterator<Item = &SystemDiagnostic> {
+        self.systems.iter()
+    }
+
+    /// 平均耗时最长的[System]
+    ///
+    /// [System]: crate::system::System
+    pub fn slowest_system(&self) -> Option<&SystemDiagnostic> {
+        self.systems
+            .iter()
+            .max_by_key(|system| system.time().average())
+    }
+
+    pub(crate) fn record_frame(&mut self, start: Instant) {
+        self.frame.tick(start);
+    }
+
+    pub(crate) fn record_entities(
+        &mut self,
+        archetypes: Vec<(&'static str, usize)>,
+        spawned: usize,
+        despawned: usize,
+    ) {
+        self.entities.record(archetypes, spawned, despawned);
+    }
+
+    /// 记录一帧中每个[System]的耗时,同名的合计为一条记录
+    ///
+    /// [System]: crate::system::System
+    pub(crate) fn record_systems(&mut self, timings: Vec<(&'static str, Duration)>) {
+        let mut frame: Vec<(&'static str, Duration)> = vec![];
+        for (name, elapsed) in timings {
+            match frame.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, total)) => *total += elapsed,
+                None => frame.push((name, elapsed)),
+            }
+        }
+        for (name, elapsed) in frame {
+            self.system_mut(name).time.push(elapsed);
+        }
+    }
+
+    pub(crate) fn record_panic(&mut self, panic: SystemPanic) {
+        self.system_mut(panic.system).panics.push(panic);
+    }
+
+    /// 名字为name的[System]的诊断信息,不存在时创建
+    ///
+    /// [System]: crate::system::System
+    fn system_mut(&mut self, name: &'static str) -> &mut SystemDiagnostic {
+        let index = match self.systems.iter().position(|system| system.name() == name) {
+            Some(index) => index,
+            None => {
+                self.systems.push(SystemDiagnostic::new(name, self.history));
+                self.systems.len() - 1
+            }
+        };
+        &mut self.systems[index]
+    }
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 一项数据的滚动统计
+///
+/// 只保留最近的若干条记录用来计算平均值,最大值则统计全部记录
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    history: VecDeque<Duration>,
+    capacity: usize,
+    max: Duration,
+    count: usize,
+}
+
+impl Measurement {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+            max: Duration::ZERO,
+            count: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: Duration) {
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(value);
+        self.max = self.max.max(value);
+        self.count += 1;
+    }
+
+    /// 最近一次的记录
+    pub fn last(&self) -> Option<Duration> {
+        self.history.back().copied()
+    }
+
+    /// 最近若干次记录的平均值
+    pub fn average(&self) -> Duration {
+        if self.history.is_empty() {
+            return Duration::ZERO;
+        }
+        self.history.iter().sum::<Duration>() / self.history.len() as u32
+    }
+
+    /// 所有记录中的最大值
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// 一共记录的次数
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// 最近的若干次记录,从旧到新
+    pub fn history(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.history.iter().copied()
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurement() {
+        let mut measurement = Measurement::new(2);
+        assert_eq!(measurement.average(), Duration::ZERO);
+
+        for ms in [9, 1, 3] {
+            measurement.push(Duration::from_millis(ms));
+        }
+        // 只有最近两次参与平均,最大值统计全部
+        assert_eq!(measurement.average(), Duration::from_millis(2));
+        assert_eq!(measurement.max(), Duration::from_millis(9));
+        assert_eq!(measurement.last(), Some(Duration::from_millis(3)));
+        assert_eq!(measurement.count(), 3);
+    }
+
+    #[test]
+    fn system_time() {
+        use crate::tools::ResManager;
+
+        fn slow() {
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        fn fast() {}
+
+        let mut world = World::new();
+        world.enable_diagnostics().add_system(slow).add_system(fast);
+        world.run_once();
+        world.run_once();
+
+        let res = world.get_res::<Diagnostics>();
+        let diagnostics = res.get().unwrap();
+        let slow_diagnostic = diagnostics
+            .system(std::any::type_name_of_val(&slow))
+            .unwrap();
+        assert_eq!(slow_diagnostic.time().count(), 2);
+        assert!(slow_diagnostic.time().max() >= Duration::from_millis(2));
+        assert_eq!(
+            diagnostics.slowest_system().unwrap().name(),
+            slow_diagnostic.name()
+        );
+
+        let frame = diagnostics.frame();
+        assert_eq!(frame.count(), 2);
+        assert_eq!(frame.delta().count(), 1);
+        assert!(frame.delta().last().unwrap() >= Duration::from_millis(2));
+        assert_eq!(frame.busy().count(), 2);
+        assert!(frame.busy().max() >= Duration::from_millis(2));
+        assert!(frame.fps() > 0.0);
+    }
+
+    #[test]
+    fn same_name() {
+        use crate::tools::ResManager;
+
+        fn slow() {
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        let mut world = World::new();
+        world.enable_diagnostics().add_system(slow).add_system(slow);
+        world.run_once();
+        world.run_once();
+
+        // 两个同名的System每一帧合计为一条记录
+        let res = world.get_res::<Diagnostics>();
+        let diagnostics = res.get().unwrap();
+        assert_eq!(diagnostics.systems().count(), 1);
+        let time = diagnostics
+            .system(std::any::type_name_of_val(&slow))
+            .unwrap()
+            .time();
+        assert_eq!(time.count(), 2);
+        assert!(time.last().unwrap() >= Duration::from_millis(4));
+    }
+
+    #[test]
+    fn entity_count() {
+        use crate::tools::{Command, ResManager};
+
+        let mut world = World::new();
+        world.enable_diagnostics();
+        world.spawn_many(0..10usize);
+        let entity = world.spawn((1i32, 1usize));
+        world.run_once();
+
+        world.remove(entity);
+        world.spawn(1usize);
+        world.run_once();
+
+        let res = world.get_res::<Diagnostics>();
+        let entities = res.get().unwrap().entities();
+        assert_eq!(entities.live(), 11);
+        assert_eq!(
+            entities.archetypes()[0],
+            (std::any::type_name::<usize>(), 11)
+        );
+        assert_eq!(entities.archetypes()[1].1, 0);
+        assert_eq!(entities.spawned().last(), 1);
+        assert_eq!(entities.spawned().total(), 12);
+        assert_eq!(entities.despawned().last(), 1);
+        assert_eq!(entities.spawned().average(), 6.0);
+    }
+}
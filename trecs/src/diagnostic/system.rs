@@ -0,0 +1,43 @@
+use super::Measurement;
+use crate::system::SystemPanic;
+
+/// 单个[System]的耗时统计
+///
+/// [System]: crate::system::System
+#[derive(Debug, Clone)]
+pub struct SystemDiagnostic {
+    name: &'static str,
+    pub(super) time: Measurement,
+    pub(super) panics: Vec<SystemPanic>,
+}
+
+impl SystemDiagnostic {
+    pub(super) fn new(name: &'static str, history: usize) -> Self {
+        Self {
+            name,
+            time: Measurement::new(history),
+            panics: vec![],
+        }
+    }
+
+    /// [System]的类型名
+    ///
+    /// [System]: crate::system::System
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// 每一帧的耗时,同名的[System]合计在一起
+    ///
+    /// [System]: crate::system::System
+    pub fn time(&self) -> &Measurement {
+        &self.time
+    }
+
+    /// 按照[PanicPolicy::Isolate]被捕获的所有panic,从旧到新
+    ///
+    /// [PanicPolicy::Isolate]: crate::system::PanicPolicy::Isolate
+    pub fn panics(&self) -> &[SystemPanic] {
+        &self.panics
+    }
+}
@@ -0,0 +1,345 @@
+use std::{any::TypeId, marker::PhantomData};
+
+use crate::{
+    system::{state::SystemState, SystemParm},
+    tools::ResManager,
+    world::{Local, Res, ResMut, UnsafeWorldCell},
+    World,
+};
+
+/// 一种事件的双缓冲队列
+///
+/// 作为资源存放在[World]中,每次循环结束时调用[Events::update],
+/// 所以事件在发送的这一帧和下一帧中都可以被读取,之后被丢弃
+///
+/// 通过[EventWriter]发送,通过[EventReader]读取
+///
+/// ``` rust
+/// use trecs::{
+///     event::{EventReader, EventWriter},
+///     World,
+/// };
+///
+/// struct Damage(u32);
+///
+/// fn attack(mut damages: EventWriter<Damage>) {
+///     damages.send(Damage(10));
+/// }
+///
+/// fn hurt(mut damages: EventReader<Damage>) {
+///     for damage in damages.read() {
+///         println!("受到{}点伤害", damage.0);
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.add_system(attack).add_system(hurt);
+/// world.run_once();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Events<T> {
+    /// 上一帧的事件
+    previous: Vec<T>,
+    /// 这一帧的事件
+    current: Vec<T>,
+    /// previous中第一个事件的编号
+    start: usize,
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self {
+            previous: vec![],
+            current: vec![],
+            start: 0,
+        }
+    }
+
+    /// 发送一个事件
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// 进入下一帧,丢弃上一帧的事件
+    pub fn update(&mut self) {
+        self.start += self.previous.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// 丢弃所有事件
+    pub fn clear(&mut self) {
+        self.update();
+        self.update();
+    }
+
+    /// 还没有被丢弃的事件的数量
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 所有还没有被丢弃的事件,按照发送的顺序排列
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(&self.current)
+    }
+
+    /// 这一帧的事件
+    pub(crate) fn current(&self) -> &[T] {
+        &self.current
+    }
+
+    /// 替换这一帧的事件,返回原来的事件
+    pub(crate) fn replace_current(&mut self, events: Vec<T>) -> Vec<T> {
+        std::mem::replace(&mut self.current, events)
+    }
+
+    /// 下一个被发送的事件的编号
+    fn end(&self) -> usize {
+        self.start + self.len()
+    }
+
+    /// 编号从cursor开始的事件
+    fn since(&self, cursor: usize) -> impl Iterator<Item = &T> {
+        self.iter().skip(cursor.saturating_sub(self.start))
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 每次循环结束时更新[Events]
+fn update_events<T: 'static>(world: &mut World) {
+    if let Some(events) = world.get_res::<Events<T>>().get_mut() {
+        events.update();
+    }
+}
+
+impl World {
+    /// 添加一种事件,创建[Events]并且在每次循环结束时更新
+    ///
+    /// 使用[EventWriter]或者[EventReader]时会自动添加
+    pub fn add_event<T: 'static>(&mut self) -> &mut Self {
+        self.get_res::<Events<T>>().get_or_init(Events::new);
+        self.on_frame_end(TypeId::of::<Events<T>>(), update_events::<T>);
+        self
+    }
+
+    /// 在[System]之外发送一个事件
+    ///
+    /// [System]: crate::system::System
+    pub fn send_event<T: 'static>(&mut self, event: T) -> &mut Self {
+        self.add_event::<T>();
+        if let Some(events) = self.get_res::<Events<T>>().get_mut() {
+            events.send(event);
+        }
+        self
+    }
+}
+
+/// 发送事件的[System]参数
+///
+/// 可以修改同一种事件的[EventReader]读取的资源,所以不能在同一个[System]中共存
+///
+/// 只有真正发送了事件时,[Events]才被看作修改过
+///
+/// [System]: crate::system::System
+pub struct EventWriter<'a, T: 'static> {
+    events: ResMut<'a, Events<T>>,
+}
+
+impl<T> EventWriter<'_, T> {
+    /// 发送一个事件
+    pub fn send(&mut self, event: T) {
+        self.events_mut().send(event);
+    }
+
+    /// 依次发送所有事件
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        let mut events = events.into_iter().peekable();
+        if events.peek().is_some() {
+            self.events_mut().current.extend(events);
+        }
+    }
+
+    /// 获取[Events]的可变引用,同时将其标记为修改过
+    fn events_mut(&mut self) -> &mut Events<T> {
+        self.events
+            .get_mut()
+            .unwrap_or_else(|| unreachable!("Events已经在build中创建了"))
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParm for EventWriter<'_, T> {
+    type Item<'w> = EventWriter<'w, T>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        let mut events = ResMut::<Events<T>>::build(world);
+        // 已经在prepare中创建了,不会被标记为修改过
+        events.get_or_init(Events::new);
+        EventWriter { events }
+    }
+
+    fn prepare(world: &mut World) {
+        world.add_event::<T>();
+    }
+
+    fn init(state: &mut SystemState) {
+        ResMut::<Events<T>>::init(state);
+    }
+}
+
+/// [EventReader]读到的位置,即下一个要读的事件的编号
+struct EventCursor<T> {
+    next: usize,
+    _m: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventCursor<T> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            _m: PhantomData,
+        }
+    }
+}
+
+/// 读取事件的[System]参数
+///
+/// 每个[System]各自记录读到的位置,所以每个事件对每个[System]只会出现一次
+///
+/// 超过两帧没有读取的事件会被丢弃
+///
+/// [System]: crate::system::System
+pub struct EventReader<'a, T> {
+    events: &'a Events<T>,
+    cursor: Local<'a, EventCursor<T>>,
+}
+
+impl<T> EventReader<'_, T> {
+    /// 所有还没有读过的事件,按照发送的顺序排列
+    pub fn read(&mut self) -> impl Iterator<Item = &T> {
+        let cursor = std::mem::replace(&mut self.cursor.next, self.events.end());
+        self.events.since(cursor)
+    }
+
+    /// 还没有读过的事件的数量
+    pub fn len(&self) -> usize {
+        self.events.since(self.cursor.next).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 跳过所有还没有读过的事件
+    pub fn clear(&mut self) {
+        self.cursor.next = self.events.end();
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParm for EventReader<'_, T> {
+    type Item<'w> = EventReader<'w, T>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        EventReader {
+            events: Res::<Events<T>>::build(world)
+                .into_ref()
+                .unwrap_or_else(|| unreachable!("Events已经在prepare中创建了")),
+            cursor: Local::<EventCursor<T>>::build(world),
+        }
+    }
+
+    fn prepare(world: &mut World) {
+        world.add_event::<T>();
+    }
+
+    fn init(state: &mut SystemState) {
+        Res::<Events<T>>::init(state);
+        Local::<EventCursor<T>>::init(state);
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        system::IntoSystem,
+        test::{self, Capture},
+    };
+
+    fn read(mut events: EventReader<u32>, capture: ResMut<Capture<u32>>) {
+        let capture = capture.into_or_init(Capture::new);
+        for event in events.read() {
+            capture.push(*event);
+        }
+    }
+
+    #[test]
+    fn events() {
+        fn write(mut events: EventWriter<u32>, mut count: Local<u32>) {
+            *count += 1;
+            events.send_batch([*count, *count * 10]);
+        }
+
+        let mut world = World::new();
+        world
+            .add_system(read.label("read"))
+            .add_system(write.before("read"));
+        test::tick(&mut world);
+        test::tick(&mut world);
+        assert_eq!(test::captured::<u32>(&mut world), [1, 10, 2, 20]);
+    }
+
+    #[test]
+    fn expire() {
+        let mut world = World::new();
+        world.send_event(1u32);
+        test::tick(&mut world);
+        world.send_event(2u32).send_event(3u32);
+        test::tick(&mut world);
+        // 读取之前已经过了两帧,1被丢弃了
+        world.add_system(read);
+        world.send_event(4u32);
+        test::tick(&mut world);
+        assert_eq!(test::captured::<u32>(&mut world), [2, 3, 4]);
+        test::tick(&mut world);
+        assert!(test::captured::<u32>(&mut world).is_empty());
+        assert!(world.get_res::<Events<u32>>().get().unwrap().is_empty());
+    }
+
+    #[test]
+    fn changed_on_send() {
+        fn write(mut events: EventWriter<u32>, mut count: Local<u32>) {
+            *count += 1;
+            if *count == 2 {
+                events.send(*count);
+            }
+            events.send_batch([]);
+        }
+        fn changed(events: Res<Events<u32>>, capture: ResMut<Capture<bool>>) {
+            capture.into_or_init(Capture::new).push(events.is_changed());
+        }
+
+        let mut world = World::new();
+        world
+            .add_system(changed.label("changed"))
+            .add_system(write.before("changed"));
+        for _ in 0..3 {
+            test::tick(&mut world);
+        }
+        assert_eq!(test::captured::<bool>(&mut world), [true, true, false]);
+    }
+
+    #[test]
+    fn conflict() {
+        let mut world = World::new();
+        let result = world.try_add_system(|_: EventReader<u32>, _: EventWriter<u32>| {});
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use std::{any::TypeId, ops::Deref};
+
+use crate::{bundle::Component, reflect::MapEntities, storage::Entity};
+
+/// [Entity]的父级
+///
+/// 由[World::set_parent]等方法维护,与[Hierarchy]总是一致,不要直接放入或者移除
+///
+/// ``` rust
+/// use trecs::{hierarchy::Parent, tools::Command, world::Query, World};
+///
+/// let mut world = World::new();
+/// let [parent, child] = [0, 1].map(|i| world.spawn(i));
+/// world.set_parent(child, parent).unwrap();
+/// let query = Query::<(&i32, &Parent)>::new(&mut world);
+/// let (i, p) = query.single();
+/// assert_eq!((*i, p.get()), (1, parent));
+/// ```
+///
+/// [World::set_parent]: crate::World::set_parent
+/// [Hierarchy]: crate::hierarchy::Hierarchy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parent(pub(super) Entity);
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+impl Component for Parent {
+    fn type_id_() -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
+
+impl MapEntities for Parent {
+    fn visit_entities(&self, visit: &mut dyn FnMut(Entity)) {
+        visit(self.0)
+    }
+
+    fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+        self.0 = map(self.0)
+    }
+}
+
+/// [Entity]的所有子级,顺序与[Hierarchy::children]相同
+///
+/// 由[World::set_parent]等方法维护,与[Hierarchy]总是一致,不要直接放入或者移除;
+/// 没有子级时不会有这个[Component]
+///
+/// [World::set_parent]: crate::World::set_parent
+/// [Hierarchy]: crate::hierarchy::Hierarchy
+/// [Hierarchy::children]: crate::hierarchy::Hierarchy::children
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Children(pub(super) Vec<Entity>);
+
+impl Deref for Children {
+    type Target = [Entity];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Component for Children {
+    fn type_id_() -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
+
+impl MapEntities for Children {
+    fn visit_entities(&self, visit: &mut dyn FnMut(Entity)) {
+        self.0.iter().for_each(|&entity| visit(entity))
+    }
+
+    fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+        self.0.iter_mut().for_each(|entity| *entity = map(*entity))
+    }
+}
@@ -0,0 +1,444 @@
+mod components;
+#[cfg(feature = "system")]
+mod propagate;
+
+pub use components::{Children, Parent};
+#[cfg(feature = "system")]
+pub use propagate::{propagate, Combine, Global, PropagateQuery};
+
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+use crate::{
+    storage::Entity,
+    tools::{Command, ResManager},
+    world::Query,
+    World,
+};
+
+/// 修改[Hierarchy]时可能发生的错误
+///
+/// 发生错误时[Hierarchy]不会被修改
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyError {
+    /// [Entity]不存在或者已经失效
+    NoSuchEntity(Entity),
+    /// 将parent设为child的父级会形成环
+    ///
+    /// 包括将[Entity]设为自己的父级
+    Cycle { child: Entity, parent: Entity },
+}
+
+impl Display for HierarchyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HierarchyError::NoSuchEntity(entity) => write!(f, "{entity:?}不存在或者已经失效"),
+            HierarchyError::Cycle { child, parent } => {
+                write!(f, "将{parent:?}设为{child:?}的父级会形成环")
+            }
+        }
+    }
+}
+
+impl Error for HierarchyError {}
+
+/// [Entity]之间的父子关系
+///
+/// 作为资源存放在[World]中,两个方向的关系总是同时更新
+///
+/// 通过[World::set_parent]等方法修改时,[Parent]和[Children]也会同时更新;
+/// 载入[Scene]等之后会根据[Parent]和[Children]重新构建,见[World::rebuild_hierarchy]
+///
+/// [World]: crate::World
+/// [Scene]: crate::reflect::Scene
+#[derive(Debug, Default, Clone)]
+pub struct Hierarchy {
+    /// <子级,父级>
+    parents: HashMap<Entity, Entity>,
+    /// <父级,所有子级>
+    children: HashMap<Entity, Vec<Entity>>,
+}
+
+impl Hierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 将child设为parent的子级
+    ///
+    /// 如果child原来已有父级,会先从原来的父级中移除
+    ///
+    /// 如果会形成环,返回[HierarchyError::Cycle],并且什么都不做
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> Result<(), HierarchyError> {
+        if self.is_ancestor(child, parent) {
+            return Err(HierarchyError::Cycle { child, parent });
+        }
+        self.remove_parent(child);
+        self.parents.insert(child, parent);
+        self.children.entry(parent).or_default().push(child);
+        Ok(())
+    }
+
+    /// ancestor是否是entity自己,或者entity的祖先
+    pub fn is_ancestor(&self, ancestor: Entity, entity: Entity) -> bool {
+        let mut current = Some(entity);
+        while let Some(entity) = current {
+            if entity == ancestor {
+                return true;
+            }
+            current = self.parent(entity);
+        }
+        false
+    }
+
+    /// 将child从它的父级中移除
+    ///
+    /// 返回原来的父级
+    pub fn remove_parent(&mut self, child: Entity) -> Option<Entity> {
+        let parent = self.parents.remove(&child)?;
+        if let Some(children) = self.children.get_mut(&parent) {
+            children.retain(|&c| c != child);
+            if children.is_empty() {
+                self.children.remove(&parent);
+            }
+        }
+        Some(parent)
+    }
+
+    /// [Entity]的父级
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.parents.get(&entity).copied()
+    }
+
+    /// [Entity]的所有子级
+    pub fn children(&self, entity: Entity) -> &[Entity] {
+        self.children
+            .get(&entity)
+            .map(|children| children.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// 所有的父子关系,格式为(子级,父级)
+    pub fn relations(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.parents.iter().map(|(&child, &parent)| (child, parent))
+    }
+
+    /// [Hierarchy]中所有的根节点:有子级但是没有父级的[Entity]
+    ///
+    /// 不在[Hierarchy]中的[Entity]不会出现,如果需要请使用[Query::into_roots]
+    ///
+    /// [Query::into_roots]: crate::world::Query::into_roots
+    pub fn roots(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.children
+            .keys()
+            .copied()
+            .filter(|parent| !self.parents.contains_key(parent))
+    }
+
+    /// [Hierarchy]中所有的叶节点:有父级但是没有子级的[Entity]
+    ///
+    /// 不在[Hierarchy]中的[Entity]不会出现,如果需要请使用[Query::into_leaves]
+    ///
+    /// [Query::into_leaves]: crate::world::Query::into_leaves
+    pub fn leaves(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.parents
+            .keys()
+            .copied()
+            .filter(|child| !self.children.contains_key(child))
+    }
+
+    /// [Entity]的所有后代,不包括自己,按照深度优先的先序排列
+    pub fn descendants(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        let mut stack = self
+            .children(entity)
+            .iter()
+            .rev()
+            .copied()
+            .collect::<Vec<_>>();
+        std::iter::from_fn(move || {
+            let next = stack.pop()?;
+            stack.extend(self.children(next).iter().rev());
+            Some(next)
+        })
+    }
+
+    /// [Entity]的所有祖先,不包括自己,从父级开始
+    pub fn ancestors(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        std::iter::successors(self.parent(entity), |&entity| self.parent(entity))
+    }
+
+    /// 移除[Entity]的所有关系,它的子级都会变为没有父级
+    ///
+    /// [Entity]被删除时会自动调用
+    pub fn remove_entity(&mut self, entity: Entity) {
+        self.remove_parent(entity);
+        for child in self.children.remove(&entity).unwrap_or_default() {
+            self.parents.remove(&child);
+        }
+    }
+
+    /// [Entity]是否没有父级
+    pub fn is_root(&self, entity: Entity) -> bool {
+        !self.parents.contains_key(&entity)
+    }
+
+    /// [Entity]是否没有子级
+    pub fn is_leaf(&self, entity: Entity) -> bool {
+        !self.children.contains_key(&entity)
+    }
+}
+
+impl World {
+    /// 存放在[World]中的[Hierarchy],不存在时创建
+    fn hierarchy(&mut self) -> &mut Hierarchy {
+        self.get_res::<Hierarchy>().into_or_init(Hierarchy::new)
+    }
+
+    /// 按照[Hierarchy]更新entities的[Parent]和[Children],跳过已经失效的[Entity]
+    ///
+    /// 值没有变化时不会替换,所以不会被标记为修改过
+    pub(crate) fn sync_hierarchy(&mut self, entities: impl IntoIterator<Item = Entity>) {
+        for entity in entities {
+            let Some(current) = self.entity(entity) else {
+                continue;
+            };
+            let parent = current.get::<Parent>().map(Parent::get);
+            let children = current.get::<Children>().map(|c| c.to_vec());
+            let hierarchy = self.peek_res::<Hierarchy>();
+            let new_parent = hierarchy.and_then(|h| h.parent(entity));
+            let new_children = hierarchy
+                .map(|h| h.children(entity).to_vec())
+                .filter(|children| !children.is_empty());
+            if parent != new_parent {
+                match new_parent {
+                    Some(parent) => self.insert(entity, Parent(parent)),
+                    None => self.remove_component::<Parent>(entity).is_some(),
+                };
+            }
+            if children != new_children {
+                match new_children {
+                    Some(children) => self.insert(entity, Children(children)),
+                    None => self.remove_component::<Children>(entity).is_some(),
+                };
+            }
+        }
+    }
+
+    /// 根据所有的[Parent]和[Children]重新构建[Hierarchy]
+    ///
+    /// 子级的顺序与[Children]中的相同;指向失效的[Entity]或者会形成环的关系会被丢弃,
+    /// 对应的[Parent]和[Children]也会被更新
+    ///
+    /// [World::spawn_scene],[World::restore]和[World::apply_diff]之后会自动调用
+    ///
+    /// [World::spawn_scene]: crate::World::spawn_scene
+    /// [World::restore]: crate::World::restore
+    /// [World::apply_diff]: crate::World::apply_diff
+    pub fn rebuild_hierarchy(&mut self) {
+        let parents = Query::<(Entity, &Parent)>::new(self)
+            .into_iter()
+            .map(|(entity, parent)| (entity, parent.get()))
+            .collect::<HashMap<_, _>>();
+        let children = Query::<(Entity, &Children)>::new(self)
+            .into_iter()
+            .map(|(entity, children)| (entity, children.to_vec()))
+            .collect::<Vec<_>>();
+        if parents.is_empty() && self.peek_res::<Hierarchy>().is_none() {
+            return;
+        }
+
+        let mut hierarchy = Hierarchy::new();
+        for (parent, children) in &children {
+            for child in children {
+                if parents.get(child) == Some(parent) {
+                    let _ = hierarchy.set_parent(*child, *parent);
+                }
+            }
+        }
+        // 不在父级的Children中的关系排在最后
+        for (&child, &parent) in &parents {
+            if hierarchy.parent(child).is_none() && self.alive(parent).unwrap_or(false) {
+                let _ = hierarchy.set_parent(child, parent);
+            }
+        }
+        *self.hierarchy() = hierarchy;
+
+        let affected = parents
+            .into_keys()
+            .chain(children.into_iter().map(|(e, _)| e));
+        self.sync_hierarchy(affected.collect::<Vec<_>>());
+    }
+
+    /// 将parent设为child的父级
+    ///
+    /// 父子两边的关系会同时更新
+    ///
+    /// + 任何一方失效时返回[HierarchyError::NoSuchEntity]
+    /// + 会形成环时返回[HierarchyError::Cycle]
+    ///
+    /// 返回错误时什么都不会改变
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> Result<(), HierarchyError> {
+        for entity in [child, parent] {
+            if !self.alive(entity).unwrap_or(false) {
+                return Err(HierarchyError::NoSuchEntity(entity));
+            }
+        }
+        let old = self.hierarchy().parent(child);
+        self.hierarchy().set_parent(child, parent)?;
+        self.sync_hierarchy([child, parent].into_iter().chain(old));
+        Ok(())
+    }
+
+    /// 将child从它的父级中移除
+    ///
+    /// 返回原来的父级
+    pub fn remove_parent(&mut self, child: Entity) -> Option<Entity> {
+        let parent = self.hierarchy().remove_parent(child)?;
+        self.sync_hierarchy([child, parent]);
+        Some(parent)
+    }
+
+    /// 删除[Entity]和它的所有后代,返回实际被删除的数量
+    ///
+    /// 只调用[World::despawn]时,子级会被保留,并且变为没有父级
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, World};
+    ///
+    /// let mut world = World::new();
+    /// let [root, child, grandchild, other] = [0, 1, 2, 3].map(|i| world.spawn(i));
+    /// world.entity_mut(root).unwrap().add_child(child);
+    /// world.entity_mut(child).unwrap().add_child(grandchild);
+    /// assert_eq!(world.despawn_recursive(root), 3);
+    /// assert!(!world.alive(grandchild).unwrap());
+    /// assert!(world.alive(other).unwrap());
+    /// ```
+    pub fn despawn_recursive(&mut self, entity: Entity) -> usize {
+        let mut entities = vec![entity];
+        if let Some(hierarchy) = self.peek_res::<Hierarchy>() {
+            entities.extend(hierarchy.descendants(entity));
+        }
+        self.despawn_batch(entities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Not;
+
+    #[test]
+    fn set_remove_parent() {
+        let (a, b, c) = (Entity::new(0, 0), Entity::new(0, 1), Entity::new(0, 2));
+        let mut hierarchy = Hierarchy::new();
+
+        hierarchy.set_parent(b, a).unwrap();
+        hierarchy.set_parent(c, a).unwrap();
+        assert_eq!(hierarchy.children(a), &[b, c]);
+        assert_eq!(hierarchy.roots().collect::<Vec<_>>(), vec![a]);
+        let mut leaves = hierarchy.leaves().collect::<Vec<_>>();
+        leaves.sort();
+        assert_eq!(leaves, vec![b, c]);
+
+        // 换一个父级,原来的父级中也要删除
+        hierarchy.set_parent(c, b).unwrap();
+        assert_eq!(hierarchy.children(a), &[b]);
+        assert_eq!(hierarchy.parent(c), Some(b));
+
+        assert_eq!(hierarchy.remove_parent(c), Some(b));
+        assert!(hierarchy.children(b).is_empty());
+        assert_eq!(hierarchy.parent(c), None);
+    }
+
+    #[test]
+    fn descendants() {
+        let mut world = World::new();
+        let [a, b, c, d, e] = [0, 1, 2, 3, 4].map(|i| world.spawn(i));
+        world.entity_mut(a).unwrap().add_child(b).add_child(d);
+        world.entity_mut(b).unwrap().add_child(c);
+        world.set_parent(e, d).unwrap();
+
+        let hierarchy = world.resource::<Hierarchy>().unwrap();
+        assert_eq!(hierarchy.descendants(a).collect::<Vec<_>>(), [b, c, d, e]);
+        assert_eq!(hierarchy.ancestors(c).collect::<Vec<_>>(), [b, a]);
+        assert_eq!(hierarchy.descendants(e).count(), 0);
+
+        let hierarchy = hierarchy.clone();
+        let query = crate::world::Query::<&i32>::new(&mut world);
+        let values = query.iter_descendants(&hierarchy, a).copied();
+        assert_eq!(values.collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+        // 只删除一个Entity时,它的关系也会被移除
+        world.despawn(d);
+        let hierarchy = world.resource::<Hierarchy>().unwrap();
+        assert!(hierarchy.children(a) == [b] && hierarchy.is_root(e));
+
+        assert_eq!(world.despawn_recursive(a), 3);
+        assert_eq!(world.alive(e), Some(true));
+        assert_eq!(
+            world.resource::<Hierarchy>().unwrap().relations().count(),
+            0
+        );
+    }
+
+    #[test]
+    fn components() {
+        use crate::tools::{AnyOf, Changed};
+
+        let mut world = World::new();
+        let [a, b, c] = [0, 1, 2].map(|i| world.spawn(i));
+        world.entity_mut(a).unwrap().add_child(b).add_child(c);
+        let children = |world: &mut World, entity| {
+            Query::<&Children>::new(world)
+                .get(entity)
+                .map(|children| children.to_vec())
+        };
+        assert_eq!(children(&mut world, a), Some(vec![b, c]));
+        assert_eq!(world.fetch::<&Parent>(b), Some(&Parent(a)));
+        let roots = Query::<Entity, Not<AnyOf<Parent>>>::new(&mut world);
+        assert_eq!(roots.into_iter().count(), 1);
+
+        // 模拟在之后的System中访问,只有真正变化的Component被标记为修改过
+        world.last_run = world.change_tick;
+        world.change_tick += 1;
+        world.set_parent(c, b).unwrap();
+        assert_eq!(children(&mut world, a), Some(vec![b]));
+        assert_eq!(children(&mut world, b), Some(vec![c]));
+        let changed = Query::<Entity, Changed<Parent>>::new(&mut world);
+        assert_eq!(changed.into_iter().collect::<Vec<_>>(), [c]);
+
+        assert_eq!(world.remove_parent(c), Some(b));
+        assert_eq!(children(&mut world, b), None);
+        assert_eq!(world.fetch::<&Parent>(c), None);
+
+        world.set_parent(c, b).unwrap();
+        world.despawn(b);
+        assert_eq!(children(&mut world, a), None);
+        assert_eq!(world.fetch::<&Parent>(c), None);
+    }
+
+    #[test]
+    fn reject_cycle() {
+        let (a, b, c) = (Entity::new(0, 0), Entity::new(0, 1), Entity::new(0, 2));
+        let mut hierarchy = Hierarchy::new();
+        hierarchy.set_parent(b, a).unwrap();
+        hierarchy.set_parent(c, b).unwrap();
+
+        assert_eq!(
+            hierarchy.set_parent(a, c),
+            Err(HierarchyError::Cycle {
+                child: a,
+                parent: c
+            })
+        );
+        assert_eq!(
+            hierarchy.set_parent(a, a),
+            Err(HierarchyError::Cycle {
+                child: a,
+                parent: a
+            })
+        );
+        // 失败时不会破坏原来的关系
+        assert_eq!(hierarchy.parent(a), None);
+        assert_eq!(hierarchy.children(c), &[]);
+    }
+}
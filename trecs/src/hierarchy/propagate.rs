@@ -0,0 +1,150 @@
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    bundle::Component,
+    iter::EBundle,
+    storage::Entity,
+    world::{Query, Res},
+};
+
+use super::Hierarchy;
+
+/// 可以沿着[Hierarchy]从父级传递给子级的[Component]
+///
+/// 比如变换,可见性,所属的队伍
+pub trait Combine: Component + Clone {
+    /// 将父级传递下来的值与子级自身的值合并
+    fn combine(&self, local: &Self) -> Self;
+}
+
+/// 由[propagate]合并之后的T
+///
+/// 需要和T放在同一个[Bundle]中
+///
+/// [Bundle]: crate::bundle::Bundle
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Global<T>(pub T);
+
+impl<T: Combine> Component for Global<T> {
+    fn type_id_() -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
+
+impl<T> Deref for Global<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Global<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// [propagate]访问的[Query]
+pub type PropagateQuery<'a, T> = Query<'a, (&'static T, &'static mut Global<T>)>;
+
+/// 生成一个沿着[Hierarchy]传递T的[System]
+///
+/// + 根节点(包括不在[Hierarchy]中的[Entity])的[Global<T>]就是它自身的T
+/// + 子级的[Global<T>]是父级的[Global<T>]与自身的T合并的结果
+///
+/// 缺少T或者[Global<T>]的[Entity]连同它的子级都会被跳过
+///
+/// [System]: crate::system::System
+pub fn propagate<T: Combine>() -> fn(PropagateQuery<T>, Res<Hierarchy>) {
+    |query: PropagateQuery<T>, hierarchy: Res<Hierarchy>| {
+        let empty = Hierarchy::new();
+        let hierarchy = hierarchy.get().unwrap_or(&empty);
+        // 先全部取出来,再沿着Hierarchy按Entity访问子级
+        let mut items = query
+            .into_eiter()
+            .map(|eb| (eb.entity(), eb))
+            .collect::<HashMap<_, _>>();
+        let roots = items
+            .keys()
+            .copied()
+            .filter(|&entity| hierarchy.is_root(entity))
+            .collect::<Vec<_>>();
+        for root in roots {
+            let Some((local, global)) = items.get_mut(&root).map(|eb| &mut **eb) else {
+                continue;
+            };
+            global.0 = (*local).clone();
+            let value = global.0.clone();
+            propagate_children(&mut items, hierarchy, root, &value);
+        }
+    }
+}
+
+fn propagate_children<T: Combine>(
+    items: &mut HashMap<Entity, EBundle<'_, (&'static T, &'static mut Global<T>)>>,
+    hierarchy: &Hierarchy,
+    parent: Entity,
+    value: &T,
+) {
+    for &child in hierarchy.children(parent) {
+        let Some((local, global)) = items.get_mut(&child).map(|eb| &mut **eb) else {
+            continue;
+        };
+        global.0 = value.combine(local);
+        let value = global.0.clone();
+        propagate_children(items, hierarchy, child, &value);
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{tools::Command, World};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Depth(usize);
+
+    impl Component for Depth {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+
+    impl Combine for Depth {
+        fn combine(&self, local: &Self) -> Self {
+            Depth(self.0 + local.0)
+        }
+    }
+
+    #[test]
+    fn propagate_depth() {
+        let mut world = World::new();
+        let root = world.spawn((Depth(1), Global(Depth(0))));
+        let child = world.spawn((Depth(2), Global(Depth(0))));
+        let grandchild = world.spawn((Depth(3), Global(Depth(0))));
+        // 没有Global<Depth>,它的子级也不会被传递
+        let broken = world.spawn(Depth(4));
+        let unreachable = world.spawn((Depth(5), Global(Depth(0))));
+        // 不在Hierarchy中
+        let alone = world.spawn((Depth(6), Global(Depth(0))));
+
+        world.set_parent(child, root).unwrap();
+        world.set_parent(grandchild, child).unwrap();
+        world.set_parent(broken, child).unwrap();
+        world.set_parent(unreachable, broken).unwrap();
+
+        world.exec(propagate::<Depth>());
+
+        let mut global = |e| world.fetch::<&Global<Depth>>(e).copied();
+        assert_eq!(global(root), Some(Global(Depth(1))));
+        assert_eq!(global(child), Some(Global(Depth(3))));
+        assert_eq!(global(grandchild), Some(Global(Depth(6))));
+        assert_eq!(global(unreachable), Some(Global(Depth(0))));
+        assert_eq!(global(alone), Some(Global(Depth(6))));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    storage::Entity,
+    tools::{ReadOnlyFetch, WorldFetch, WorldFilter},
+    world::Query,
+};
+
+/// 符合[Query]的[Entity]的所有组合,每个组合中有K个互不相同的[Entity]
+///
+/// 组合是无序的,`[a, b]`和`[b, a]`只会出现一次
+///
+/// 通过[Query::iter_combinations]或者[Query::iter_combinations_mut]获得
+///
+/// 不同的组合中可能有相同的[Entity],
+/// 所以只有[ReadOnlyFetch]可以作为[Iterator]使用,否则请使用[Combinations::fetch_next]
+pub struct Combinations<'q, 'w, F: WorldFetch, Q: WorldFilter, const K: usize> {
+    query: &'q Query<'w, F, Q>,
+    entities: Vec<Entity>,
+    /// 下一个组合中每个[Entity]在entities中的下标,递增
+    ///
+    /// 已经没有组合时为[None]
+    indices: Option<[usize; K]>,
+}
+
+impl<'q, 'w, F: WorldFetch, Q: WorldFilter, const K: usize> Combinations<'q, 'w, F, Q, K> {
+    pub(crate) fn new(query: &'q Query<'w, F, Q>, entities: Vec<Entity>) -> Self {
+        let indices = (K != 0 && K <= entities.len()).then(|| std::array::from_fn(|i| i));
+        Self {
+            query,
+            entities,
+            indices,
+        }
+    }
+
+    /// 下一个组合
+    ///
+    /// 返回的Item在下一次调用之前必须被丢弃,所以可以安全地使用`&mut T`
+    pub fn fetch_next(&mut self) -> Option<[F::Item<'_>; K]> {
+        let indices = self.indices?;
+        self.advance(indices);
+        // 同一个组合中的Entity互不相同,Item之间不会冲突
+        Some(unsafe { Self::items(self.query, &self.entities, indices) })
+    }
+
+    /// # Safety
+    ///
+    /// 返回的Item不能和其他Item冲突
+    unsafe fn items<'a>(
+        query: &'a Query<'w, F, Q>,
+        entities: &[Entity],
+        indices: [usize; K],
+    ) -> [F::Item<'a>; K] {
+        indices.map(|index| {
+            query
+                .get_unchecked(entities[index])
+                .unwrap_or_else(|e| unreachable!("entities中都是符合Query的Entity: {e}"))
+        })
+    }
+
+    /// 按字典序计算indices之后的组合
+    fn advance(&mut self, mut indices: [usize; K]) {
+        let len = self.entities.len();
+        // 找到最右边还可以增加的位置
+        let Some(i) = (0..K).rev().find(|&i| indices[i] < len - K + i) else {
+            self.indices = None;
+            return;
+        };
+        indices[i] += 1;
+        for j in i + 1..K {
+            indices[j] = indices[j - 1] + 1;
+        }
+        self.indices = Some(indices);
+    }
+}
+
+impl<'q, 'w: 'q, F: ReadOnlyFetch, Q: WorldFilter, const K: usize> Iterator
+    for Combinations<'q, 'w, F, Q, K>
+{
+    type Item = [F::Item<'q>; K];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let indices = self.indices?;
+        self.advance(indices);
+        // ReadOnlyFetch的Item之间不会冲突
+        Some(unsafe { Self::items(self.query, &self.entities, indices) })
+    }
+}
@@ -1,12 +1,15 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    iter::FusedIterator,
+    ops::{Deref, DerefMut},
+};
 
 use crate::{
     storage::Entity,
     tools::{WorldFetch, WorldFilter},
-    world::World,
+    world::{UnsafeWorldCell, World},
 };
 
-use super::Iter;
+use super::{Iter, Select};
 
 /// 带有[Entity]的[Bundle]
 ///
@@ -24,6 +27,11 @@ impl<'a, F: WorldFetch> EBundle<'a, F> {
     pub fn entity(&self) -> Entity {
         self.entity
     }
+
+    /// 拆分成[Entity]和[WorldFetch::Item]
+    pub fn into_inner(self) -> (Entity, F::Item<'a>) {
+        (self.entity, self.bundle)
+    }
 }
 
 impl<'a, F: WorldFetch> Deref for EBundle<'a, F> {
@@ -40,16 +48,57 @@ impl<'a, F: WorldFetch> DerefMut for EBundle<'a, F> {
     }
 }
 
+/// 同[Iter],但同时给出每个[Entity],通过[Query::into_eiter]创建
+///
+/// 与[Iter]一样遵循[WorldFilter],给出的[Entity]带有代数,
+/// 可以在迭代之后交给[Commands]或[World::despawn]等使用,[Entity]被删除后不会误用新的[Entity]
+///
+/// ``` rust
+/// use trecs::{
+///     tools::{AnyOf, Command, Not},
+///     world::{Commands, Query},
+///     World,
+/// };
+///
+/// fn cull(mut commands: Commands, query: Query<&i32, Not<AnyOf<bool>>>) {
+///     for eb in query.into_eiter() {
+///         if **eb < 0 {
+///             commands.despawn(eb.entity());
+///         }
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.spawn_many([-1, 1]);
+/// world.spawn((-2, true));
+/// world.exec(cull);
+/// assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 2);
+/// ```
+///
+/// [Query::into_eiter]: crate::world::Query::into_eiter
+/// [Commands]: crate::world::Commands
 pub struct EIter<'a, F: WorldFetch> {
     inner: Iter<'a, F>,
 }
 
 impl<'a, F: WorldFetch> EIter<'a, F> {
-    pub fn new<Q: WorldFilter>(world: &mut World) -> EIter<'_, F> {
+    pub fn new<Q: WorldFilter>(world: &'a mut World) -> EIter<'a, F> {
         EIter {
             inner: Iter::new::<Q>(world),
         }
     }
+
+    /// # Safety
+    ///
+    /// 见[Iter::from_cell]
+    pub(crate) unsafe fn from_select<Q: WorldFilter>(
+        world: UnsafeWorldCell<'a>,
+        select: Select<'a>,
+    ) -> EIter<'a, F> {
+        EIter {
+            inner: Iter::from_select::<Q>(world, select),
+        }
+    }
 }
 
 impl<'a, F: WorldFetch> From<Iter<'a, F>> for EIter<'a, F> {
@@ -62,9 +111,45 @@ impl<'a, F: WorldFetch> Iterator for EIter<'a, F> {
     type Item = EBundle<'a, F>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let item = self.inner.next()?;
-        let iter = &self.inner.iter.as_ref()?.1;
-        let entity = iter.chunk.gen_entity(iter.index);
+        let (entity, item) = self.inner.next_with_entity()?;
         Some(EBundle::new(entity, item))
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<F: WorldFetch> ExactSizeIterator for EIter<'_, F> {}
+
+impl<F: WorldFetch> FusedIterator for EIter<'_, F> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Changed, Command, Mut};
+
+    #[test]
+    fn entities() {
+        let mut world = World::new();
+        let entities = world.spawn_many([1, 2, 3]);
+        world.change_tick = 5;
+        world.fetch::<&mut i32>(entities[1]).map(Mut::into_inner);
+        world.last_run = 3;
+        let changed = EIter::<&i32>::new::<Changed<i32>>(&mut world)
+            .map(|eb| eb.into_inner())
+            .map(|(entity, i)| (entity, *i))
+            .collect::<Vec<_>>();
+        assert_eq!(changed, [(entities[1], 2)]);
+
+        // 删除后同一个位置上的新Entity不会被旧的Entity访问到
+        assert!(world.despawn(entities[1]));
+        let respawned = world.spawn(4);
+        let all = EIter::<&i32>::new::<()>(&mut world)
+            .map(|eb| eb.entity())
+            .collect::<Vec<_>>();
+        assert!(all.contains(&respawned) && !all.contains(&entities[1]));
+        assert!(!world.despawn(entities[1]));
+        assert_eq!(world.fetch::<&i32>(respawned), Some(&4));
+    }
 }
@@ -1,102 +1,352 @@
+mod combinations;
 mod ebundle;
+#[cfg(feature = "rayon")]
+mod par;
 
+pub use combinations::Combinations;
 pub use ebundle::{EBundle, EIter};
-use std::marker::PhantomData;
+#[cfg(feature = "rayon")]
+pub(crate) use par::{par_chunks, SendIter};
+use std::{cell::Cell, iter::FusedIterator, marker::PhantomData};
 
 use crate::{
-    storage::{Chunk, ChunkIter},
-    tools::{
-        MappingTable, WorldFetch,
-        WorldFilter,
-    },
-    world::World,
+    bundle::ComponentId,
+    storage::{Chunk, ChunkIter, ChunkRow, Entity, SparseSets},
+    tools::{MappingTable, WorldFetch, WorldFilter},
+    world::{Matched, QueryCache, UnsafeWorldCell, World},
 };
 
+/// 所有符合条件的[Chunk],以及对应的[MappingTable]和[Bundle]的components_ids
+///
+/// [MappingTable]会被复制,因为之后[BundleMeta]的缓存可能会被修改
+///
+/// [Bundle]: crate::bundle::Bundle
+/// [BundleMeta]: crate::bundle::BundleMeta
 #[derive(Debug, Clone)]
 pub(crate) struct Select<'a> {
-    inner: Vec<(&'a MappingTable, Vec<&'a Chunk>)>,
+    inner: Vec<(MappingTable, &'static [ComponentId], Vec<&'a Chunk>)>,
 }
 
-impl Select<'_> {
-    pub fn new<F: WorldFetch, Q: WorldFilter>(world: &'_ mut World) -> Select<'_> {
-        world
-            .metas
-            .iter_mut()
-            .filter_map(|(.., meta)| {
-                if meta.filter::<Q>() && meta.fetch::<F>().is_some() {
-                    Some(meta)
-                } else {
-                    None
-                }
-            })
-            .filter_map(|meta| {
-                let chunks = meta
-                    .chunks
-                    .iter()
-                    .copied()
-                    .map(|cid| &world.chunks[cid])
-                    .collect::<Vec<_>>();
-                let mapping_table = meta.fetch::<F>()?;
-                Some((chunks, mapping_table))
+impl<'a> Select<'a> {
+    /// # Safety
+    ///
+    /// 见[UnsafeWorldCell::metas_mut]和[UnsafeWorldCell::chunks]
+    pub unsafe fn new<F: WorldFetch, Q: WorldFilter>(world: UnsafeWorldCell<'a>) -> Select<'a> {
+        let mut cache = QueryCache::default();
+        cache.update::<F, Q>(world);
+        Self::from_matched(world, &cache.matched())
+    }
+
+    /// 通过[QueryState]记录的符合的[Bundle]选择[Chunk]
+    ///
+    /// # Safety
+    ///
+    /// 见[UnsafeWorldCell::chunks]
+    ///
+    /// [QueryState]: crate::world::QueryState
+    /// [Bundle]: crate::bundle::Bundle
+    pub unsafe fn from_matched(world: UnsafeWorldCell<'a>, matched: &[Matched]) -> Select<'a> {
+        let chunks = world.chunks();
+        matched
+            .iter()
+            .map(|matched| {
+                let selected = matched.chunks.iter().map(|&cid| &chunks[cid]).collect();
+                (
+                    matched.mapping_table.clone(),
+                    matched.components_ids,
+                    selected,
+                )
             })
-            .map(|(a, b)| (b, a))
             .collect::<Vec<_>>()
             .into()
     }
 
-    pub fn pop(&mut self) -> Option<(&'_ MappingTable, &'_ Chunk)> {
-        let (mapping, chunks) = self.inner.last_mut()?;
-        let Some(chunk) = chunks.pop() else{
+    pub fn pop(&mut self) -> Option<(MappingTable, &'static [ComponentId], &'a Chunk)> {
+        let (mapping, components_ids, chunks) = self.inner.last_mut()?;
+        let Some(chunk) = chunks.pop() else {
             self.inner.pop();
             return self.pop();
         };
-        Some((mapping, chunk))
+        Some((mapping.clone(), components_ids, chunk))
+    }
+
+    /// 还没有被取出的[Chunk],以及对应的[MappingTable]和[Bundle]的components_ids
+    ///
+    /// [Bundle]: crate::bundle::Bundle
+    fn chunks(
+        &self,
+    ) -> impl Iterator<Item = (&MappingTable, &'static [ComponentId], &'a Chunk)> + '_ {
+        (self.inner.iter()).flat_map(|(mapping_table, ids, chunks)| {
+            chunks
+                .iter()
+                .map(move |&chunk| (mapping_table, *ids, chunk))
+        })
     }
 }
 
-impl<'a> From<Vec<(&'a MappingTable, Vec<&'a Chunk>)>> for Select<'a> {
-    fn from(value: Vec<(&'a MappingTable, Vec<&'a Chunk>)>) -> Self {
+impl<'a> From<Vec<(MappingTable, &'static [ComponentId], Vec<&'a Chunk>)>> for Select<'a> {
+    fn from(value: Vec<(MappingTable, &'static [ComponentId], Vec<&'a Chunk>)>) -> Self {
         Self { inner: value }
     }
 }
 
+/// select中所有符合F和Q的[Entity]
+///
+/// 不会访问[Component],也不会把[Component]标记为修改过
+///
+/// # Safety
+///
+/// 见[UnsafeWorldCell::sparse]
+///
+/// [Component]: crate::bundle::Component
+pub(crate) unsafe fn matching_entities<F: WorldFetch, Q: WorldFilter>(
+    world: UnsafeWorldCell<'_>,
+    mut select: Select<'_>,
+) -> Vec<Entity> {
+    let (last_run, _) = world.change_ticks();
+    let sparse = world.sparse();
+    let mut entities = vec![];
+    while let Some((mapping_table, components_ids, chunk)) = select.pop() {
+        for row in chunk.iter() {
+            let row = row.with_sparse(sparse);
+            if (!Q::PER_ENTITY || Q::filter_entity(components_ids, row, last_run))
+                && F::matches(row, &mapping_table)
+            {
+                entities.push(row.entity());
+            }
+        }
+    }
+    entities
+}
+
+/// 逐个过滤[Entity]的[WorldFilter::filter_entity]
+///
+/// [Entity]: crate::storage::Entity
+type EntityFilter = fn(&'static [ComponentId], ChunkRow<'_>, usize) -> bool;
+
+/// 正在迭代的[Chunk]
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkState<'a> {
+    pub(crate) mapping_table: MappingTable,
+    pub(crate) components_ids: &'static [ComponentId],
+    pub(crate) iter: ChunkIter<'a>,
+    /// 是否需要通过[WorldFetch::matches]逐个检查
+    pub(crate) sparse: bool,
+}
+
+impl<'a> ChunkState<'a> {
+    pub(crate) fn new(
+        mapping_table: MappingTable,
+        components_ids: &'static [ComponentId],
+        chunk: &'a Chunk,
+    ) -> Self {
+        Self {
+            sparse: !mapping_table.sparse().is_empty(),
+            mapping_table,
+            components_ids,
+            iter: chunk.iter(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Iter<'a, F: WorldFetch> {
     select: Select<'a>,
-    pub(crate) iter: Option<(&'a MappingTable, ChunkIter<'a>)>,
+    pub(crate) iter: Option<ChunkState<'a>>,
+    /// 不需要逐个过滤时为[None]
+    filter: Option<EntityFilter>,
+    /// [WorldFetch]访问的[StorageType::SparseSet]的[Component]所在的位置
+    ///
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    /// [Component]: crate::bundle::Component
+    sparse: &'a SparseSets,
+    /// [System]上一次执行时的tick
+    ///
+    /// [System]: crate::system::System
+    last_run: usize,
+    /// 当前的tick,通过[Mut]修改的[Component]会被标记为在这时修改过
+    ///
+    /// [Component]: crate::bundle::Component
+    /// [Mut]: crate::tools::Mut
+    change_tick: usize,
+    /// 剩余的数量,第一次通过[Iterator::size_hint]查询时计算
+    len: Cell<Option<usize>>,
     _f: PhantomData<&'a F>,
 }
 
 impl<'a, F: WorldFetch> Iter<'a, F> {
-    pub fn new<Q: WorldFilter>(world: &mut World) -> Iter<'_, F> {
-        let select = Select::new::<F, Q>(world);
+    pub fn new<Q: WorldFilter>(world: &'a mut World) -> Iter<'a, F> {
+        unsafe { Self::from_cell::<Q>(world.as_unsafe_world_cell()) }
+    }
+
+    /// # Safety
+    ///
+    /// 迭代期间不能有与F冲突的访问,也不能增加或者删除[Chunk]
+    pub(crate) unsafe fn from_cell<Q: WorldFilter>(world: UnsafeWorldCell<'a>) -> Iter<'a, F> {
+        Self::from_select::<Q>(world, Select::new::<F, Q>(world))
+    }
+
+    /// 迭代select中的[Chunk]
+    ///
+    /// # Safety
+    ///
+    /// 见[Iter::from_cell]
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    pub(crate) unsafe fn from_select<Q: WorldFilter>(
+        world: UnsafeWorldCell<'a>,
+        select: Select<'a>,
+    ) -> Iter<'a, F> {
+        let (last_run, change_tick) = world.change_ticks();
 
         Iter {
             select,
             iter: None,
+            filter: Q::PER_ENTITY.then_some(Q::filter_entity as EntityFilter),
+            sparse: world.sparse(),
+            last_run,
+            change_tick,
+            len: Cell::new(None),
             _f: PhantomData,
         }
     }
+
+    /// 还没有迭代的符合条件的[Entity]的数量
+    ///
+    /// 需要逐个过滤时会检查每个[Entity]的[ComponentTicks],但不会访问[Component]
+    ///
+    /// [Component]: crate::bundle::Component
+    fn count_remaining(&self) -> usize {
+        let exact = |mapping_table: &MappingTable| {
+            self.filter.is_none() && mapping_table.sparse().is_empty()
+        };
+        let current = self.iter.iter().map(|state| {
+            let (mapping_table, ids) = (&state.mapping_table, state.components_ids);
+            match exact(mapping_table) {
+                true => state.iter.clone().count(),
+                false => (state.iter.clone())
+                    .filter(|&row| self.passes(mapping_table, ids, row))
+                    .count(),
+            }
+        });
+        let rest =
+            self.select
+                .chunks()
+                .map(|(mapping_table, ids, chunk)| match exact(mapping_table) {
+                    true => chunk.len(),
+                    false => (chunk.iter())
+                        .filter(|&row| self.passes(mapping_table, ids, row))
+                        .count(),
+                });
+        current.chain(rest).sum()
+    }
+
+    /// row是否通过了逐个过滤,并且有[WorldFetch]访问的[StorageType::SparseSet]的[Component]
+    ///
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    /// [Component]: crate::bundle::Component
+    fn passes(
+        &self,
+        mapping_table: &MappingTable,
+        components_ids: &'static [ComponentId],
+        row: ChunkRow<'_>,
+    ) -> bool {
+        let row = row.with_sparse(self.sparse);
+        self.filter
+            .is_none_or(|filter| filter(components_ids, row, self.last_run))
+            && F::matches(row, mapping_table)
+    }
+}
+
+impl<'a, F: WorldFetch> Iter<'a, F> {
+    /// 下一个符合条件的[Entity],以及对应的[WorldFetch::Item]
+    pub(crate) fn next_with_entity(&mut self) -> Option<(Entity, F::Item<'a>)> {
+        if self.iter.is_none() {
+            let (mapping_table, components_ids, chunk) = self.select.pop()?;
+            self.iter = Some(ChunkState::new(mapping_table, components_ids, chunk));
+        }
+        let state = self.iter.as_mut()?;
+        // 跳过没有通过逐个过滤的Entity
+        // 用循环而不是递归,否则被跳过的Entity太多时会栈溢出
+        let row = loop {
+            let Some(row) = state.iter.next() else {
+                self.iter = None;
+                return self.next_with_entity();
+            };
+            let row = row.with_sparse(self.sparse);
+            match self.filter {
+                Some(filter) if !filter(state.components_ids, row, self.last_run) => continue,
+                _ if state.sparse && !F::matches(row, &state.mapping_table) => continue,
+                _ => break row,
+            }
+        };
+
+        let entity = row.entity();
+        let change_ticks = (self.last_run, self.change_tick);
+        let item = unsafe { F::build(row, &state.mapping_table, entity, change_ticks) };
+        if let Some(len) = self.len.get() {
+            self.len.set(Some(len - 1));
+        }
+        Some((entity, item))
+    }
 }
 
 impl<'a, F: WorldFetch> Iterator for Iter<'a, F> {
     type Item = F::Item<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.iter.is_none() {
-            // 这里的transmute是为了无界生命周期
-            let (mapping, chunk ) = unsafe { std::mem::transmute::<_,(&MappingTable,&Chunk)>(self.select.pop()?) };
-            let iter = chunk.iter();
-            self.iter = Some((mapping, iter));
+        self.next_with_entity().map(|(_, item)| item)
+    }
+
+    /// 总是准确的,需要逐个过滤时第一次调用会检查剩余的每个[Entity]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len.get().unwrap_or_else(|| {
+            let len = self.count_remaining();
+            self.len.set(Some(len));
+            len
+        });
+        (len, Some(len))
+    }
+}
+
+impl<F: WorldFetch> ExactSizeIterator for Iter<'_, F> {}
+
+impl<F: WorldFetch> FusedIterator for Iter<'_, F> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::CHUNK_SIZE,
+        tools::{Changed, Command, Mut},
+    };
+
+    #[test]
+    fn size_hint() {
+        let mut world = World::new();
+        let entities = world.spawn_many((0..CHUNK_SIZE as i32 + 10).map(|i| (i, true)));
+        world.spawn_many([1i32, 2]);
+        world.despawn(entities[3]);
+        let mut iter = Iter::<&i32>::new::<()>(&mut world);
+        assert_eq!(iter.len(), CHUNK_SIZE + 11);
+        iter.nth(CHUNK_SIZE).unwrap();
+        assert_eq!(iter.size_hint(), (10, Some(10)));
+        assert_eq!(iter.by_ref().count(), 10);
+        assert_eq!((iter.len(), iter.next()), (0, None));
+
+        // 逐个过滤时只计算通过过滤的Entity
+        world.change_tick = 5;
+        for &entity in &entities[..4] {
+            world.fetch::<&mut i32>(entity).map(Mut::into_inner);
         }
-        let (mapping_table, iter) = self.iter.as_mut()?;
-        let Some(components) = iter.next()else{
-            
-            self.iter  = None;
-            return self.next();
-        };
+        world.last_run = 3;
+        let mut iter = Iter::<&i32>::new::<Changed<i32>>(&mut world);
+        assert_eq!(iter.len(), 3);
+        iter.next();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.collect::<Vec<_>>().len(), 2);
 
-        let item = unsafe { F::build(components, mapping_table) };
-        Some(item)
+        let eiter = EIter::<&i32>::new::<()>(&mut world);
+        assert_eq!(eiter.len(), CHUNK_SIZE + 11);
     }
 }
@@ -0,0 +1,46 @@
+use std::marker::PhantomData;
+
+use crate::{
+    tools::{WorldFetch, WorldFilter},
+    world::UnsafeWorldCell,
+};
+
+use super::{EntityFilter, Iter, Select};
+
+/// 只迭代一个[Chunk]的[Iter],可以被发送到其他线程
+///
+/// [Chunk]: crate::storage::Chunk
+pub(crate) struct SendIter<'a, F: WorldFetch>(pub(crate) Iter<'a, F>);
+
+// 不同的SendIter迭代的是不同的Chunk,
+// 而Component和ComponentTicks都是Send + Sync的
+unsafe impl<F: WorldFetch> Send for SendIter<'_, F> {}
+
+/// 将select中符合Q的[Entity]按照[Chunk]分开,每个[Chunk]一个[SendIter]
+///
+/// # Safety
+///
+/// 见[Iter::from_cell]
+///
+/// [Entity]: crate::storage::Entity
+/// [Chunk]: crate::storage::Chunk
+pub(crate) unsafe fn par_chunks<'a, F: WorldFetch, Q: WorldFilter>(
+    world: UnsafeWorldCell<'a>,
+    mut select: Select<'a>,
+) -> Vec<SendIter<'a, F>> {
+    let (last_run, change_tick) = world.change_ticks();
+    let mut iters = vec![];
+    while let Some((mapping_table, components_ids, chunk)) = select.pop() {
+        iters.push(SendIter(Iter {
+            select: vec![(mapping_table, components_ids, vec![chunk])].into(),
+            iter: None,
+            filter: Q::PER_ENTITY.then_some(Q::filter_entity as EntityFilter),
+            sparse: world.sparse(),
+            last_run,
+            change_tick,
+            len: Default::default(),
+            _f: PhantomData,
+        }));
+    }
+    iters
+}
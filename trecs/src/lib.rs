@@ -1,16 +1,61 @@
+/// 由[Plugin]组合而成的[App]
+///
+/// [Plugin]: crate::app::Plugin
+/// [App]: crate::app::App
+#[cfg(feature = "system")]
+pub mod app;
 /// 定义[Bundle]相关
 ///
 /// 如[BundleMeta]等
 pub mod bundle;
+/// 运行时的诊断信息,如每个[System]的耗时
+///
+/// [System]: crate::system::System
+#[cfg(feature = "system")]
+pub mod diagnostic;
+/// [System]之间传递的事件
+///
+/// [System]: crate::system::System
+#[cfg(feature = "system")]
+pub mod event;
+/// [Entity]之间的父子关系
+///
+/// [Entity]: crate::storage::Entity
+pub mod hierarchy;
 /// [Components]迭代器
 ///
 /// [Components]: crate
 pub mod iter;
+/// [Component]的类型信息
+///
+/// [Component]: crate::bundle::Component
+pub mod reflect;
+/// [Entity]之间任意类型的关系,如喜欢,攻击目标等
+///
+/// [Entity]: crate::storage::Entity
+pub mod relation;
+/// 记录并回放外部输入,以便重现问题
+#[cfg(feature = "system")]
+pub mod replay;
+/// 应用的状态,如菜单,加载中,游戏中等
+#[cfg(feature = "system")]
+pub mod state;
 /// 存储[World]中数据的容器
 pub mod storage;
 
 #[cfg(feature = "system")]
 pub mod system;
+/// 在后台线程中执行耗时的任务,完成后把结果交回[World]
+#[cfg(feature = "system")]
+pub mod task;
+/// 编写测试用的工具
+///
+/// 如[test::run_system_once],[test::assert_component_eq],[test::capture_events]等
+#[cfg(feature = "system")]
+pub mod test;
+/// 每一帧的时间信息
+#[cfg(feature = "system")]
+pub mod time;
 ///一些用于操作的trait,以及封装其中的[Command]
 ///
 /// + 对[World]进行[Entity]级操作的[Command]
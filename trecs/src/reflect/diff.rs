@@ -0,0 +1,300 @@
+use std::any::{Any, TypeId};
+
+use crate::{
+    bundle::{Component, ComponentId},
+    storage::Entity,
+    tools::ResManager,
+    World,
+};
+
+use super::{
+    snapshot::{Value, WorldSnapshot},
+    EntityMap, TypeRegistration, TypeRegistry,
+};
+
+/// 类型擦除后的[Clone]和[PartialEq]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DiffFns {
+    pub(super) clone: fn(&dyn Any) -> Box<dyn Any>,
+    pub(super) eq: fn(&dyn Any, &dyn Any) -> bool,
+}
+
+impl DiffFns {
+    fn of<T: Component + Clone + PartialEq>() -> Self {
+        Self {
+            clone: |value| Box::new(value.downcast_ref::<T>().unwrap().clone()),
+            eq: |a, b| a.downcast_ref::<T>() == b.downcast_ref::<T>(),
+        }
+    }
+}
+
+impl TypeRegistry {
+    /// 注册一种可以比较差异的[Component],见[World::snapshot]
+    ///
+    /// 会保留已有的其他注册
+    pub fn register_diff<T: Component + Clone + PartialEq>(&mut self) -> &mut Self {
+        let registration = self
+            .types
+            .entry(TypeId::of::<T>())
+            .or_insert_with(TypeRegistration::of::<T>);
+        registration.diff = Some(DiffFns::of::<T>());
+        self
+    }
+}
+
+impl WorldSnapshot {
+    /// 从self到newer的变化
+    ///
+    /// + 新出现的[Entity]的所有[Component]
+    /// + 已有的[Entity]被添加或者值不同的[Component],以及被移除的[Component]
+    /// + 消失的[Entity]
+    ///
+    /// 没有变化的[Entity]不会出现在结果中
+    pub fn diff(&self, newer: &WorldSnapshot) -> WorldDiff {
+        let mut entities = vec![];
+        for (&entity, new) in &newer.entities {
+            let old = self.entities.get(&entity);
+            let spawned = old.is_none();
+            let old = old.map(Vec::as_slice).unwrap_or_default();
+            let changed = new
+                .iter()
+                .filter(|value| !old.iter().any(|o| o.same(value.type_id, &*value.value)))
+                .cloned()
+                .collect::<Vec<_>>();
+            let removed = old
+                .iter()
+                .filter(|o| !new.iter().any(|value| value.type_id == o.type_id))
+                .map(|o| (o.type_id, o.registration.name))
+                .collect::<Vec<_>>();
+            if spawned || !changed.is_empty() || !removed.is_empty() {
+                entities.push(EntityDiff {
+                    entity,
+                    spawned,
+                    changed,
+                    removed,
+                });
+            }
+        }
+        entities.sort_by_key(|diff| diff.entity);
+
+        let mut despawned = self
+            .entities
+            .keys()
+            .filter(|entity| !newer.contains(**entity))
+            .copied()
+            .collect::<Vec<_>>();
+        despawned.sort();
+        WorldDiff {
+            entities,
+            despawned,
+        }
+    }
+}
+
+/// 一个[Entity]在两个[WorldSnapshot]之间的变化
+#[derive(Debug, Clone)]
+pub struct EntityDiff {
+    entity: Entity,
+    spawned: bool,
+    /// 被添加或者值不同的[Component]
+    changed: Vec<Value>,
+    removed: Vec<(TypeId, &'static str)>,
+}
+
+impl EntityDiff {
+    /// 产生[WorldSnapshot]的[World]中的[Entity]
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// 是否是新出现的[Entity]
+    pub fn is_spawned(&self) -> bool {
+        self.spawned
+    }
+
+    /// 被添加或者值不同的[Component]的类型名
+    pub fn changed(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.changed.iter().map(|value| value.registration.name)
+    }
+
+    /// 被移除的[Component]的类型名
+    pub fn removed(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.removed.iter().map(|(_, name)| *name)
+    }
+}
+
+/// 两个[WorldSnapshot]之间的变化,见[WorldSnapshot::diff]
+///
+/// 可以通过[World::apply_diff]应用到其他的[World]
+#[derive(Debug, Default, Clone)]
+pub struct WorldDiff {
+    entities: Vec<EntityDiff>,
+    despawned: Vec<Entity>,
+}
+
+impl WorldDiff {
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty() && self.despawned.is_empty()
+    }
+
+    /// 新出现或者有变化的[Entity]
+    pub fn entities(&self) -> &[EntityDiff] {
+        &self.entities
+    }
+
+    /// 消失的[Entity]
+    pub fn despawned(&self) -> &[Entity] {
+        &self.despawned
+    }
+}
+
+impl World {
+    /// 在[TypeRegistry]中注册一种可以比较差异的[Component]
+    ///
+    /// 见[World::snapshot]
+    pub fn register_diff<T: Component + Clone + PartialEq>(&mut self) -> &mut Self {
+        self.get_res::<TypeRegistry>()
+            .into_or_init(TypeRegistry::new)
+            .register_diff::<T>();
+        self
+    }
+
+    /// 应用[WorldDiff]
+    ///
+    /// map记录产生[WorldDiff]的[World]中的[Entity]到此[World]中的[Entity]的映射:
+    /// + 没有映射或者映射已经失效的[Entity]会被重新分配,并加入map
+    /// + 消失的[Entity]会被删除,并从map中移除
+    /// + 注册了[MapEntities]的[Component]中的[Entity]会通过map替换
+    ///
+    /// [MapEntities]: crate::reflect::MapEntities
+    pub fn apply_diff(&mut self, diff: &WorldDiff, map: &mut EntityMap) {
+        for entity in &diff.despawned {
+            if let Some(local) = map.remove(*entity) {
+                self.despawn(local);
+            }
+        }
+        let mut spawned = vec![];
+        for entity in diff.entities.iter().map(|diff| diff.entity) {
+            if map.get(entity).and_then(|e| self.entities.get(e)).is_none() {
+                let local = self.entities.alloc();
+                self.place(local, vec![]);
+                spawned.push(local);
+                map.insert(entity, local);
+            }
+        }
+
+        let map = &*map;
+        for diff in &diff.entities {
+            let local = map.map(diff.entity);
+            for (type_id, _) in &diff.removed {
+                self.remove_by_id(local, ComponentId::Static(*type_id));
+            }
+            let inserted = diff.changed.iter().map(|value| {
+                let Value {
+                    type_id,
+                    registration,
+                    mut value,
+                } = value.clone();
+                registration.map_entities(&mut *value, &mut |e| map.map(e));
+                let id = ComponentId::Static(type_id);
+                (id, registration.name, registration.vtable, value)
+            });
+            self.insert_parts(local, inserted);
+        }
+        // 放入所有Component之后才算作生成
+        for local in spawned {
+            self.record_spawn(local);
+        }
+        self.rebuild_hierarchy();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{reflect::MapEntities, tools::Command, world::Query};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Pos(i32, i32);
+    impl Component for Pos {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Target(Entity);
+    impl Component for Target {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+    impl MapEntities for Target {
+        fn visit_entities(&self, visit: &mut dyn FnMut(Entity)) {
+            visit(self.0)
+        }
+
+        fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+            self.0 = map(self.0)
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let mut server = World::new();
+        server
+            .register_diff::<Pos>()
+            .register_diff::<Target>()
+            .register_diff::<u32>()
+            .register_entities::<Target>();
+        let a = server.spawn((Pos(0, 0), 1u32));
+        let b = server.spawn((Pos(1, 1), 2u32, 0i8));
+        let c = server.spawn(Target(a));
+        let first = server.snapshot();
+        assert_eq!(first.len(), 3);
+        assert!(first.diff(&server.snapshot()).is_empty());
+
+        let mut client = World::new();
+        let mut map = EntityMap::new();
+        // 先占用一些Entity,保证两边的Entity不同
+        client.spawn(0u64);
+        let initial = WorldSnapshot::default().diff(&first);
+        assert!(initial.entities().iter().all(EntityDiff::is_spawned));
+        client.apply_diff(&initial, &mut map);
+        assert_eq!(map.len(), 3);
+        assert_ne!(map.map(a), a);
+        assert_eq!(
+            client.fetch::<&Target>(map.map(c)),
+            Some(&Target(map.map(a)))
+        );
+        // 没有注册的i8不会被同步
+        assert_eq!(client.fetch::<&i8>(map.map(b)), None);
+
+        *server.fetch::<&mut Pos>(a).unwrap() = Pos(5, 5);
+        server.remove_component::<u32>(b);
+        server.despawn(c);
+        let d = server.spawn(Target(b));
+        let diff = first.diff(&server.snapshot());
+        let changes = diff
+            .entities()
+            .iter()
+            .map(|e| (e.entity(), e.changed().count(), e.removed().count()))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [(a, 1, 0), (b, 0, 1), (d, 1, 0)]);
+        assert_eq!(diff.despawned(), [c]);
+
+        client.apply_diff(&diff, &mut map);
+        assert_eq!(
+            client.fetch::<(&Pos, &u32)>(map.map(a)),
+            Some((&Pos(5, 5), &1))
+        );
+        assert_eq!(client.fetch::<&u32>(map.map(b)), None);
+        assert_eq!(
+            client.fetch::<&Target>(map.map(d)),
+            Some(&Target(map.map(b)))
+        );
+        assert_eq!(map.get(c), None);
+        let query = Query::<&Target>::new(&mut client);
+        assert_eq!(query.into_iter().count(), 1);
+    }
+}
@@ -0,0 +1,88 @@
+use std::{any::Any, collections::HashMap};
+
+use crate::{bundle::Component, storage::Entity};
+
+/// 内部保存了[Entity]的[Component]
+///
+/// 在[TypeRegistry::register_entities]注册之后,
+/// [World::validate]就可以找出其中已经失效的[Entity]
+///
+/// [TypeRegistry::register_entities]: crate::reflect::TypeRegistry::register_entities
+/// [World::validate]: crate::World::validate
+pub trait MapEntities: Component {
+    /// 依次访问所有保存的[Entity]
+    fn visit_entities(&self, visit: &mut dyn FnMut(Entity));
+
+    /// 依次将所有保存的[Entity]替换为map的结果
+    fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity);
+}
+
+/// 类型擦除后的[MapEntities]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EntitiesFns {
+    pub(crate) visit: fn(&dyn Any, &mut dyn FnMut(Entity)),
+    pub(crate) map: fn(&mut dyn Any, &mut dyn FnMut(Entity) -> Entity),
+}
+
+impl EntitiesFns {
+    pub(crate) fn of<T: MapEntities>() -> Self {
+        Self {
+            visit: |value, visit| value.downcast_ref::<T>().unwrap().visit_entities(visit),
+            map: |value, map| value.downcast_mut::<T>().unwrap().map_entities(map),
+        }
+    }
+}
+
+/// 旧的[Entity]到新的[Entity]的映射
+///
+/// 载入[Scene]时记录被重新分配的[Entity],见[World::spawn_scene]和[World::apply_diff]
+///
+/// [Scene]: crate::reflect::Scene
+/// [World::spawn_scene]: crate::World::spawn_scene
+/// [World::apply_diff]: crate::World::apply_diff
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EntityMap {
+    map: HashMap<Entity, Entity>,
+}
+
+impl EntityMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 返回之前的映射
+    pub fn insert(&mut self, from: Entity, to: Entity) -> Option<Entity> {
+        self.map.insert(from, to)
+    }
+
+    pub fn remove(&mut self, from: Entity) -> Option<Entity> {
+        self.map.remove(&from)
+    }
+
+    pub fn get(&self, from: Entity) -> Option<Entity> {
+        self.map.get(&from).copied()
+    }
+
+    /// 没有映射的[Entity]保持不变
+    pub fn map(&self, entity: Entity) -> Entity {
+        self.get(entity).unwrap_or(entity)
+    }
+
+    /// 用[EntityMap::map]替换value中的所有[Entity]
+    pub fn map_entities<T: MapEntities>(&self, value: &mut T) {
+        value.map_entities(&mut |entity| self.map(entity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// 以(旧的[Entity],新的[Entity])的形式迭代
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.map.iter().map(|(from, to)| (*from, *to))
+    }
+}
@@ -0,0 +1,403 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result},
+};
+
+mod diff;
+mod entities;
+#[cfg(feature = "serde")]
+mod scene;
+#[cfg(feature = "serde")]
+mod serialize;
+mod snapshot;
+
+pub use diff::{EntityDiff, WorldDiff};
+pub use entities::{EntityMap, MapEntities};
+#[cfg(feature = "serde")]
+pub use scene::Scene;
+#[cfg(feature = "serde")]
+pub use serialize::WorldSerializer;
+pub use snapshot::WorldSnapshot;
+
+use crate::{
+    bundle::Component,
+    hierarchy::{Children, Parent},
+    storage::{ComponentVTable, Entity},
+    tools::ResManager,
+    World,
+};
+use diff::DiffFns;
+use entities::EntitiesFns;
+#[cfg(feature = "serde")]
+use serialize::{ResourceRegistration, SerdeFns};
+use snapshot::ResourceSnapshot;
+
+/// 将类型擦除后的[Component]格式化
+type DebugFn = fn(&dyn Any, &mut Formatter<'_>) -> Result;
+
+/// 类型擦除后的[Component::field]
+type FieldFn = fn(&dyn Any, usize) -> Option<&dyn Any>;
+
+/// 类型擦除后的[Component::field_mut]
+type FieldMutFn = fn(&mut dyn Any, usize) -> Option<&mut dyn Any>;
+
+/// 一种[Component]在[TypeRegistry]中的信息
+#[derive(Debug, Clone, Copy)]
+pub struct TypeRegistration {
+    /// 类型名,见[Component::component_name]
+    pub name: &'static str,
+    /// 在[Chunk]中存放值的方式
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    vtable: &'static ComponentVTable,
+    fields: &'static [&'static str],
+    field: FieldFn,
+    field_mut: FieldMutFn,
+    default: Option<fn() -> Box<dyn Any>>,
+    debug: Option<DebugFn>,
+    entities: Option<EntitiesFns>,
+    diff: Option<DiffFns>,
+    #[cfg(feature = "serde")]
+    serde: Option<SerdeFns>,
+}
+
+impl TypeRegistration {
+    pub fn of<T: Component>() -> Self {
+        fn field<T: Component>(value: &dyn Any, index: usize) -> Option<&dyn Any> {
+            value.downcast_ref::<T>().unwrap().field(index)
+        }
+        fn field_mut<T: Component>(value: &mut dyn Any, index: usize) -> Option<&mut dyn Any> {
+            value.downcast_mut::<T>().unwrap().field_mut(index)
+        }
+        Self {
+            name: T::component_name(),
+            vtable: ComponentVTable::of::<T>(),
+            fields: T::field_names(),
+            field: field::<T>,
+            field_mut: field_mut::<T>,
+            default: None,
+            debug: None,
+            entities: None,
+            diff: None,
+            #[cfg(feature = "serde")]
+            serde: None,
+        }
+    }
+
+    pub fn of_debug<T: Component + Debug>() -> Self {
+        fn debug<T: Debug + 'static>(value: &dyn Any, f: &mut Formatter<'_>) -> Result {
+            value.downcast_ref::<T>().unwrap().fmt(f)
+        }
+        Self {
+            debug: Some(debug::<T>),
+            ..Self::of::<T>()
+        }
+    }
+
+    pub fn of_entities<T: MapEntities>() -> Self {
+        Self {
+            entities: Some(EntitiesFns::of::<T>()),
+            ..Self::of::<T>()
+        }
+    }
+
+    /// 所有字段的名字,见[Component::field_names]
+    pub fn fields(&self) -> &'static [&'static str] {
+        self.fields
+    }
+
+    /// 名为name的字段
+    ///
+    /// value的类型不是这个[TypeRegistration]对应的类型时会panic
+    pub fn field<'a>(&self, value: &'a dyn Any, name: &str) -> Option<&'a dyn Any> {
+        let index = self.fields.iter().position(|field| *field == name)?;
+        (self.field)(value, index)
+    }
+
+    /// 名为name的字段的可变引用
+    ///
+    /// value的类型不是这个[TypeRegistration]对应的类型时会panic
+    pub fn field_mut<'a>(&self, value: &'a mut dyn Any, name: &str) -> Option<&'a mut dyn Any> {
+        let index = self.fields.iter().position(|field| *field == name)?;
+        (self.field_mut)(value, index)
+    }
+
+    /// 是否注册了[Default]
+    pub fn is_default(&self) -> bool {
+        self.default.is_some()
+    }
+
+    /// 用[Default]创建一个值
+    ///
+    /// 没有注册[Default]时返回[None]
+    pub fn construct(&self) -> Option<Box<dyn Any>> {
+        self.default.map(|default| default())
+    }
+
+    /// 是否可以用[Debug]输出
+    pub fn is_debug(&self) -> bool {
+        self.debug.is_some()
+    }
+
+    /// 用[Debug]输出value
+    ///
+    /// value的类型不是这个[TypeRegistration]对应的类型时会panic
+    pub fn debug(&self, value: &dyn Any, f: &mut Formatter<'_>) -> Result {
+        match self.debug {
+            Some(debug) => debug(value, f),
+            None => f.write_str(".."),
+        }
+    }
+
+    /// 是否实现了[MapEntities]
+    pub fn is_map_entities(&self) -> bool {
+        self.entities.is_some()
+    }
+
+    /// 见[MapEntities::visit_entities]
+    ///
+    /// 没有实现[MapEntities]时什么都不做
+    pub fn visit_entities(&self, value: &dyn Any, visit: &mut dyn FnMut(Entity)) {
+        if let Some(entities) = self.entities {
+            (entities.visit)(value, visit)
+        }
+    }
+
+    /// 见[MapEntities::map_entities]
+    ///
+    /// 没有实现[MapEntities]时什么都不做
+    pub fn map_entities(&self, value: &mut dyn Any, map: &mut dyn FnMut(Entity) -> Entity) {
+        if let Some(entities) = self.entities {
+            (entities.map)(value, map)
+        }
+    }
+}
+
+/// 记录[Component]的类型信息的资源
+///
+/// 基础类型会被默认注册
+///
+/// 用于[World::debug_entity]等
+///
+/// [World::debug_entity]: crate::World::debug_entity
+#[derive(Debug, Clone)]
+pub struct TypeRegistry {
+    types: HashMap<TypeId, TypeRegistration>,
+    /// 可以被[World::snapshot]复制的资源
+    ///
+    /// [World::snapshot]: crate::World::snapshot
+    snapshot_resources: HashMap<TypeId, ResourceSnapshot>,
+    /// 可以被序列化的资源
+    #[cfg(feature = "serde")]
+    resources: HashMap<TypeId, ResourceRegistration>,
+}
+
+impl TypeRegistry {
+    /// 只注册了基础类型,以及[Parent]和[Children]的[TypeRegistry]
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        macro_rules! register_debug {
+            ($($t:ty),*) => {
+                $(registry.register_debug::<$t>();)*
+            };
+        }
+        register_debug!(u8, u16, u32, u64, usize, u128);
+        register_debug!(i8, i16, i32, i64, isize, i128);
+        register_debug!(bool, (), &'static str);
+        // 载入时需要替换其中的Entity,并重新构建Hierarchy
+        registry
+            .register_debug::<Parent>()
+            .register_entities::<Parent>()
+            .register_diff::<Parent>()
+            .register_debug::<Children>()
+            .register_entities::<Children>()
+            .register_diff::<Children>();
+        #[cfg(feature = "serde")]
+        registry
+            .register_serde::<Parent>()
+            .register_serde::<Children>();
+        registry
+    }
+
+    /// 什么都没有注册的[TypeRegistry]
+    pub fn empty() -> Self {
+        Self {
+            types: HashMap::new(),
+            snapshot_resources: HashMap::new(),
+            #[cfg(feature = "serde")]
+            resources: HashMap::new(),
+        }
+    }
+
+    /// 注册一种[Component]
+    ///
+    /// 不会覆盖已有的注册
+    pub fn register<T: Component>(&mut self) -> &mut Self {
+        self.types
+            .entry(TypeId::of::<T>())
+            .or_insert_with(TypeRegistration::of::<T>);
+        self
+    }
+
+    /// 注册一种可以用[Debug]输出的[Component]
+    ///
+    /// 会保留已有的[MapEntities]注册
+    pub fn register_debug<T: Component + Debug>(&mut self) -> &mut Self {
+        let registration = self
+            .types
+            .entry(TypeId::of::<T>())
+            .or_insert_with(TypeRegistration::of::<T>);
+        registration.debug = TypeRegistration::of_debug::<T>().debug;
+        self
+    }
+
+    /// 注册一种实现了[MapEntities]的[Component]
+    ///
+    /// 会保留已有的[Debug]注册
+    pub fn register_entities<T: MapEntities>(&mut self) -> &mut Self {
+        let registration = self
+            .types
+            .entry(TypeId::of::<T>())
+            .or_insert_with(TypeRegistration::of::<T>);
+        registration.entities = Some(EntitiesFns::of::<T>());
+        self
+    }
+
+    /// 注册一种实现了[Default]的[Component],以便通过[TypeRegistration::construct]创建
+    ///
+    /// 会保留已有的其他注册
+    pub fn register_default<T: Component + Default>(&mut self) -> &mut Self {
+        let registration = self
+            .types
+            .entry(TypeId::of::<T>())
+            .or_insert_with(TypeRegistration::of::<T>);
+        registration.default = Some(|| Box::new(T::default()));
+        self
+    }
+
+    /// 按照[Component::register_type]注册一种[Component]
+    ///
+    /// `#[derive(Component)]`会注册`#[component(reflect(...))]`中指定的信息
+    pub fn register_type<T: Component>(&mut self) -> &mut Self {
+        T::register_type(self);
+        self
+    }
+
+    pub fn get(&self, type_id: TypeId) -> Option<&TypeRegistration> {
+        self.types.get(&type_id)
+    }
+
+    /// 通过[TypeRegistration::name]查找
+    pub fn get_by_name(&self, name: &str) -> Option<(TypeId, &TypeRegistration)> {
+        self.types
+            .iter()
+            .find(|(_, registration)| registration.name == name)
+            .map(|(type_id, registration)| (*type_id, registration))
+    }
+
+    pub fn contains(&self, type_id: TypeId) -> bool {
+        self.types.contains_key(&type_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&TypeId, &TypeRegistration)> {
+        self.types.iter()
+    }
+}
+
+impl World {
+    /// 按照[Component::register_type]在[TypeRegistry]中注册一种[Component]
+    ///
+    /// ``` rust
+    /// use trecs::{bundle::Component, reflect::TypeRegistry, World};
+    ///
+    /// #[derive(Component, Debug, Default)]
+    /// #[component(reflect(default, debug))]
+    /// struct Pos {
+    ///     x: i32,
+    ///     y: i32,
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world.register_type::<Pos>();
+    /// let registry = world.resource::<TypeRegistry>().unwrap();
+    /// let (_, registration) = registry.get_by_name(Pos::component_name()).unwrap();
+    /// assert_eq!(registration.fields(), ["x", "y"]);
+    ///
+    /// let mut pos = registration.construct().unwrap();
+    /// *registration.field_mut(&mut *pos, "y").unwrap().downcast_mut::<i32>().unwrap() = 2;
+    /// assert_eq!(pos.downcast_ref::<Pos>().unwrap().y, 2);
+    /// ```
+    pub fn register_type<T: Component>(&mut self) -> &mut Self {
+        self.get_res::<TypeRegistry>()
+            .into_or_init(TypeRegistry::new)
+            .register_type::<T>();
+        self
+    }
+}
+
+/// 供`#[component(reflect(serde))]`使用
+#[doc(hidden)]
+#[cfg(feature = "serde")]
+#[macro_export]
+macro_rules! __register_serde {
+    ($registry:expr, $t:ty) => {
+        $registry.register_serde::<$t>();
+    };
+}
+
+/// 供`#[component(reflect(serde))]`使用
+#[doc(hidden)]
+#[cfg(not(feature = "serde"))]
+#[macro_export]
+macro_rules! __register_serde {
+    ($registry:expr, $t:ty) => {
+        ::std::compile_error!("reflect(serde)需要启用trecs的serde feature")
+    };
+}
+
+impl Default for TypeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::type_name;
+
+    use super::*;
+
+    struct Value<'a>(&'a TypeRegistration, &'a dyn Any);
+
+    impl Debug for Value<'_> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            self.0.debug(self.1, f)
+        }
+    }
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Pos(i32, i32);
+    impl Component for Pos {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+
+    #[test]
+    fn register() {
+        let mut registry = TypeRegistry::new();
+        assert!(registry.get(TypeId::of::<i32>()).unwrap().is_debug());
+        assert!(!registry.contains(TypeId::of::<Pos>()));
+
+        registry.register::<Pos>();
+        let pos = registry.get(TypeId::of::<Pos>()).unwrap();
+        assert_eq!(pos.name, type_name::<Pos>());
+        assert_eq!(format!("{:?}", Value(pos, &Pos(1, 2))), "..");
+
+        registry.register_debug::<Pos>().register::<Pos>();
+        let pos = registry.get(TypeId::of::<Pos>()).unwrap();
+        assert_eq!(format!("{:?}", Value(pos, &Pos(1, 2))), "Pos(1, 2)");
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt::Debug;
+
+use serde::Deserializer;
+
+use crate::{
+    bundle::ComponentId,
+    storage::{ComponentTicks, Entity},
+    World,
+};
+
+use super::{
+    serialize::{ComponentValues, ResourceValues, SceneVisitor},
+    EntityMap, TypeRegistry,
+};
+
+/// 反序列化得到的,还没有放入[World]的一组[Entity]和资源
+///
+/// 格式与[World::serializer]的结果相同,通过[World::spawn_scene]载入已有的[World]
+///
+/// ``` rust
+/// use trecs::{reflect::Scene, tools::Command, World};
+///
+/// let mut world = World::new();
+/// world.register_serde::<i32>();
+/// let entity = world.spawn(1i32);
+/// let json = serde_json::to_string(&world.serializer()).unwrap();
+///
+/// let registry = world.resource::<trecs::reflect::TypeRegistry>().unwrap();
+/// let scene = Scene::deserialize(registry, &mut serde_json::Deserializer::from_str(&json));
+/// let map = world.spawn_scene(scene.unwrap());
+/// assert_eq!(map.len(), 1);
+/// assert_eq!(world.entity(map.map(entity)).unwrap().get::<i32>(), Some(&1));
+/// ```
+pub struct Scene {
+    /// 序列化时的[Entity],以及它的[Component]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(super) entities: Vec<(Entity, ComponentValues)>,
+    pub(super) resources: ResourceValues,
+}
+
+impl Scene {
+    /// 遇到没有在registry中注册的[Component]或者资源时返回错误
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        registry: &TypeRegistry,
+        deserializer: D,
+    ) -> Result<Scene, D::Error> {
+        deserializer.deserialize_struct("World", &["entities", "resources"], SceneVisitor(registry))
+    }
+
+    /// [Entity]的数量
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// 序列化时的[Entity]
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().map(|(entity, _)| *entity)
+    }
+}
+
+impl Debug for Scene {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let resources = self.resources.iter().map(|(r, _)| r.name);
+        f.debug_struct("Scene")
+            .field("entities", &self.entities().collect::<Vec<_>>())
+            .field("resources", &resources.collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl World {
+    /// 将[Scene]中的[Entity]和资源放入[World]
+    ///
+    /// + 每个[Entity]都会被重新分配,返回旧的[Entity]到新的[Entity]的[EntityMap]
+    /// + 注册了[MapEntities]的[Component]中的[Entity]会被替换,不在[Scene]中的保持不变
+    /// + 资源会替换已有的同类资源
+    ///
+    /// [MapEntities]: crate::reflect::MapEntities
+    /// [Component]: crate::bundle::Component
+    pub fn spawn_scene(&mut self, scene: Scene) -> EntityMap {
+        let mut map = EntityMap::new();
+        for (old, _) in &scene.entities {
+            map.insert(*old, self.entities.alloc());
+        }
+        let tick = self.change_tick;
+        for (old, components) in scene.entities {
+            let entity = map.map(old);
+            let parts = components
+                .into_iter()
+                .map(|(type_id, registration, mut value)| {
+                    registration.map_entities(&mut *value, &mut |e| map.map(e));
+                    let id = ComponentId::Static(type_id);
+                    let vtable = registration.vtable;
+                    (
+                        id,
+                        registration.name,
+                        vtable,
+                        value,
+                        ComponentTicks::new(tick),
+                    )
+                })
+                .collect();
+            self.place(entity, parts);
+            self.index_name(entity);
+            self.record_spawn(entity);
+        }
+        for (registration, value) in scene.resources {
+            (registration.insert)(self, value);
+        }
+        self.rebuild_hierarchy();
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        bundle::Component,
+        hierarchy::{Hierarchy, Parent},
+        reflect::MapEntities,
+        tools::Command,
+        world::Query,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Target(Entity);
+    impl Component for Target {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+    impl MapEntities for Target {
+        fn visit_entities(&self, visit: &mut dyn FnMut(Entity)) {
+            visit(self.0)
+        }
+
+        fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+            self.0 = map(self.0)
+        }
+    }
+
+    fn count(world: &mut World) -> usize {
+        Query::<Entity>::new(world).into_iter().count()
+    }
+
+    #[test]
+    fn scene_hierarchy() {
+        let mut source = World::new();
+        source.register_serde::<u32>();
+        let [a, b, c] = [0u32, 1, 2].map(|i| source.spawn(i));
+        source.entity_mut(a).unwrap().add_child(c).add_child(b);
+        let json = serde_json::to_string(&source.serializer()).unwrap();
+        let registry = source.resource::<TypeRegistry>().unwrap().clone();
+
+        let mut world = World::new();
+        let scene = Scene::deserialize(&registry, &mut serde_json::Deserializer::from_str(&json));
+        let map = world.spawn_scene(scene.unwrap());
+        let [a, b, c] = [a, b, c].map(|e| map.map(e));
+        let hierarchy = world.resource::<Hierarchy>().unwrap();
+        assert_eq!(hierarchy.children(a), [c, b]);
+        assert_eq!(hierarchy.parent(b), Some(a));
+        let query = Query::<(&u32, &Parent)>::new(&mut world);
+        assert_eq!(query.get(c).map(|(i, p)| (*i, p.get())), Some((2, a)));
+    }
+
+    #[test]
+    fn spawn_scene() {
+        let mut source = World::new();
+        source
+            .register_serde::<Target>()
+            .register_serde::<u32>()
+            .register_entities::<Target>()
+            .register_resource_serde::<u64>();
+        let removed = source.spawn(0u32);
+        let skipped = source.spawn(0i8);
+        let a = source.spawn(1u32);
+        let b = source.spawn((Target(a), 2u32));
+        let c = source.spawn(Target(removed));
+        source.despawn(removed);
+        source.insert_resource(3u64);
+        let json = serde_json::to_string(&source.serializer()).unwrap();
+        let registry = source.resource::<TypeRegistry>().unwrap().clone();
+
+        let mut world = World::new();
+        let existing = world.spawn(4u32);
+        world.insert_resource(5u64);
+        let scene = |json: &str| {
+            let scene =
+                Scene::deserialize(&registry, &mut serde_json::Deserializer::from_str(json));
+            scene.unwrap()
+        };
+
+        let first = scene(&json);
+        // 没有注册的i8被跳过,但是Entity仍然存在
+        assert_eq!(first.len(), 4);
+        let map = world.spawn_scene(first);
+        assert_eq!(map.len(), 4);
+        assert_eq!(count(&mut world), 5);
+        assert!(world.entity(map.map(skipped)).is_some());
+        assert_eq!(world.fetch::<&u32>(existing), Some(&4));
+        assert_eq!(world.resource::<u64>(), Some(&3));
+
+        let new_a = map.get(a).unwrap();
+        assert_ne!(new_a, existing);
+        assert_eq!(world.fetch::<&u32>(new_a), Some(&1));
+        assert_eq!(
+            world.fetch::<(&Target, &u32)>(map.map(b)),
+            Some((&Target(new_a), &2))
+        );
+        // 不在Scene中的Entity保持不变
+        assert_eq!(map.get(removed), None);
+        assert_eq!(world.fetch::<&Target>(map.map(c)), Some(&Target(removed)));
+
+        // 同一个Scene可以被多次载入
+        let again = world.spawn_scene(scene(&json));
+        assert_ne!(again.map(a), new_a);
+        assert_eq!(
+            world.fetch::<&Target>(again.map(b)),
+            Some(&Target(again.map(a)))
+        );
+        assert_eq!(count(&mut world), 9);
+    }
+}
@@ -0,0 +1,506 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    fmt::Formatter,
+};
+
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq, SerializeStruct},
+    Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    bundle::Component,
+    storage::{ChunkRow, Entity},
+    tools::ResManager,
+    World,
+};
+
+use super::{Scene, TypeRegistration, TypeRegistry};
+
+type SerializeFn = fn(&dyn Any) -> &dyn erased_serde::Serialize;
+type DeserializeFn = for<'de> fn(
+    &mut dyn erased_serde::Deserializer<'de>,
+) -> Result<Box<dyn Any>, erased_serde::Error>;
+
+/// 类型擦除后的[Serialize]和[Deserialize]
+///
+/// [Deserialize]: serde::Deserialize
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SerdeFns {
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+}
+
+impl SerdeFns {
+    fn of<T: Serialize + DeserializeOwned + 'static>() -> Self {
+        Self {
+            serialize: |value| value.downcast_ref::<T>().unwrap(),
+            deserialize: |deserializer| Ok(Box::new(erased_serde::deserialize::<T>(deserializer)?)),
+        }
+    }
+}
+
+/// 一种可以被序列化的资源在[TypeRegistry]中的信息
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ResourceRegistration {
+    pub(super) name: &'static str,
+    serde: SerdeFns,
+    pub(super) insert: fn(&mut World, Box<dyn Any>),
+}
+
+impl TypeRegistry {
+    /// 注册一种可以被序列化的[Component],见[World::serializer]
+    ///
+    /// 会保留已有的其他注册
+    pub fn register_serde<T: Component + Serialize + DeserializeOwned>(&mut self) -> &mut Self {
+        let registration = self
+            .types
+            .entry(TypeId::of::<T>())
+            .or_insert_with(TypeRegistration::of::<T>);
+        registration.serde = Some(SerdeFns::of::<T>());
+        self
+    }
+
+    /// 注册一种可以被序列化的资源,见[World::serializer]
+    pub fn register_resource<T: Serialize + DeserializeOwned + 'static>(&mut self) -> &mut Self {
+        let registration = ResourceRegistration {
+            name: type_name::<T>(),
+            serde: SerdeFns::of::<T>(),
+            insert: |world, value| {
+                world.insert_resource(*value.downcast::<T>().unwrap());
+            },
+        };
+        self.resources.insert(TypeId::of::<T>(), registration);
+        self
+    }
+
+    /// 是否可以被序列化
+    fn serde(&self, type_id: TypeId) -> Option<(&'static str, SerdeFns)> {
+        let registration = self.get(type_id)?;
+        Some((registration.name, registration.serde?))
+    }
+
+    /// 通过类型名找到可以被序列化的[Component]
+    fn serde_by_name(&self, name: &str) -> Option<(TypeId, &TypeRegistration)> {
+        self.types
+            .iter()
+            .find(|(_, registration)| registration.name == name && registration.serde.is_some())
+            .map(|(type_id, registration)| (*type_id, registration))
+    }
+}
+
+impl World {
+    /// 在[TypeRegistry]中注册一种可以被序列化的[Component]
+    ///
+    /// 见[World::serializer]
+    pub fn register_serde<T: Component + Serialize + DeserializeOwned>(&mut self) -> &mut Self {
+        self.get_res::<TypeRegistry>()
+            .into_or_init(TypeRegistry::new)
+            .register_serde::<T>();
+        self
+    }
+
+    /// 在[TypeRegistry]中注册一种可以被序列化的资源
+    ///
+    /// 见[World::serializer]
+    pub fn register_resource_serde<T: Serialize + DeserializeOwned + 'static>(
+        &mut self,
+    ) -> &mut Self {
+        self.get_res::<TypeRegistry>()
+            .into_or_init(TypeRegistry::new)
+            .register_resource::<T>();
+        self
+    }
+
+    /// 序列化所有[Entity],以及它们的[Component]和资源
+    ///
+    /// 只有在[TypeRegistry]中注册过的[Component]和资源会被序列化,
+    /// 见[World::register_serde]和[World::register_resource_serde]
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, World};
+    ///
+    /// let mut world = World::new();
+    /// world.register_serde::<i32>().register_resource_serde::<String>();
+    /// world.spawn((1i32, 2u8));
+    /// world.insert_resource(String::from("save"));
+    ///
+    /// let json = serde_json::to_string(&world.serializer()).unwrap();
+    /// let registry = world.resource::<trecs::reflect::TypeRegistry>().unwrap().clone();
+    /// let loaded = World::deserialize(registry, &mut serde_json::Deserializer::from_str(&json))
+    ///     .unwrap();
+    /// assert_eq!(loaded.resource::<String>().unwrap(), "save");
+    /// ```
+    pub fn serializer(&self) -> WorldSerializer<'_> {
+        WorldSerializer {
+            world: self,
+            registry: self.peek_res(),
+        }
+    }
+
+    /// 从[World::serializer]的结果创建一个新的[World]
+    ///
+    /// + [Entity]会被重新分配,见[World::spawn_scene]
+    /// + registry会作为资源插入新的[World]中
+    ///
+    /// 遇到没有在registry中注册的[Component]或者资源时返回错误
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        registry: TypeRegistry,
+        deserializer: D,
+    ) -> Result<World, D::Error> {
+        let scene = Scene::deserialize(&registry, deserializer)?;
+        let mut world = World::new();
+        world.spawn_scene(scene);
+        world.insert_resource(registry);
+        Ok(world)
+    }
+}
+
+/// 见[World::serializer]
+///
+/// 序列化为`{ entities: [(Entity, { 类型名: 值 })], resources: { 类型名: 值 } }`
+pub struct WorldSerializer<'w> {
+    world: &'w World,
+    registry: Option<&'w TypeRegistry>,
+}
+
+impl Serialize for WorldSerializer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("World", 2)?;
+        state.serialize_field("entities", &EntitiesSer(self))?;
+        state.serialize_field("resources", &ResourcesSer(self))?;
+        state.end()
+    }
+}
+
+struct EntitiesSer<'a, 'w>(&'a WorldSerializer<'w>);
+
+impl Serialize for EntitiesSer<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let world = self.0.world;
+        let mut entities = world
+            .chunks
+            .iter()
+            .flat_map(|chunk| {
+                let mut iter = chunk.iter();
+                std::iter::from_fn(move || iter.next().map(|row| (row.entity(), row)))
+            })
+            .collect::<Vec<_>>();
+        entities.sort_by_key(|(entity, _)| entity.index);
+
+        let mut seq = serializer.serialize_seq(Some(entities.len()))?;
+        for (entity, row) in entities {
+            let components = ComponentsSer(self.0.registry, row);
+            seq.serialize_element(&(entity, components))?;
+        }
+        seq.end()
+    }
+}
+
+struct ComponentsSer<'a>(Option<&'a TypeRegistry>, ChunkRow<'a>);
+
+impl Serialize for ComponentsSer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        // 持有&World时不会有可变的访问
+        for component in unsafe { self.1.components() } {
+            let type_id = component.type_id();
+            if let Some((name, serde)) = self.0.and_then(|registry| registry.serde(type_id)) {
+                map.serialize_entry(name, (serde.serialize)(component))?;
+            }
+        }
+        map.end()
+    }
+}
+
+struct ResourcesSer<'a, 'w>(&'a WorldSerializer<'w>);
+
+impl Serialize for ResourcesSer<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        let registered = self.0.registry.into_iter().flat_map(|r| &r.resources);
+        for (type_id, registration) in registered {
+            let Some(res) = self.0.world.resources.get(type_id) else {
+                continue;
+            };
+            // 持有&World时不会有可变的访问
+            if let Some(value) = unsafe { &*res.get() } {
+                map.serialize_entry(registration.name, (registration.serde.serialize)(&**value))?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// 一个[Entity]的所有被反序列化的[Component]
+pub(super) type ComponentValues = Vec<(TypeId, TypeRegistration, Box<dyn Any>)>;
+
+/// 被反序列化的资源
+pub(super) type ResourceValues = Vec<(ResourceRegistration, Box<dyn Any>)>;
+
+pub(super) struct SceneVisitor<'a>(pub(super) &'a TypeRegistry);
+
+impl<'de> Visitor<'de> for SceneVisitor<'_> {
+    type Value = Scene;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("Scene")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Scene, A::Error> {
+        let entities = seq
+            .next_element_seed(EntitiesSeed(self.0))?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let resources = seq
+            .next_element_seed(ResourcesSeed(self.0))?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(Scene {
+            entities,
+            resources,
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Scene, A::Error> {
+        let mut entities = None;
+        let mut resources = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "entities" => entities = Some(map.next_value_seed(EntitiesSeed(self.0))?),
+                "resources" => resources = Some(map.next_value_seed(ResourcesSeed(self.0))?),
+                _ => return Err(de::Error::unknown_field(&key, &["entities", "resources"])),
+            }
+        }
+        Ok(Scene {
+            entities: entities.ok_or_else(|| de::Error::missing_field("entities"))?,
+            resources: resources.ok_or_else(|| de::Error::missing_field("resources"))?,
+        })
+    }
+}
+
+struct EntitiesSeed<'a>(&'a TypeRegistry);
+
+impl<'de> DeserializeSeed<'de> for EntitiesSeed<'_> {
+    type Value = Vec<(Entity, ComponentValues)>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntitiesSeed<'_> {
+    type Value = Vec<(Entity, ComponentValues)>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("entities")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entities = vec![];
+        while let Some(entity) = seq.next_element_seed(EntitySeed(self.0))? {
+            entities.push(entity);
+        }
+        Ok(entities)
+    }
+}
+
+struct EntitySeed<'a>(&'a TypeRegistry);
+
+impl<'de> DeserializeSeed<'de> for EntitySeed<'_> {
+    type Value = (Entity, ComponentValues);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntitySeed<'_> {
+    type Value = (Entity, ComponentValues);
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("(Entity, Components)")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let entity = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let components = seq
+            .next_element_seed(ComponentsSeed(self.0))?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok((entity, components))
+    }
+}
+
+struct ComponentsSeed<'a>(&'a TypeRegistry);
+
+impl<'de> DeserializeSeed<'de> for ComponentsSeed<'_> {
+    type Value = ComponentValues;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ComponentsSeed<'_> {
+    type Value = ComponentValues;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("Components")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut components: ComponentValues = vec![];
+        while let Some(name) = map.next_key::<String>()? {
+            let Some((type_id, registration)) = self.0.serde_by_name(&name) else {
+                return Err(de::Error::custom(format!("Component {name} 没有被注册")));
+            };
+            if components.iter().any(|(id, ..)| *id == type_id) {
+                return Err(de::Error::custom(format!("重复的Component {name}")));
+            }
+            let serde = registration.serde.unwrap();
+            let value = map.next_value_seed(ValueSeed(serde))?;
+            components.push((type_id, *registration, value));
+        }
+        Ok(components)
+    }
+}
+
+struct ResourcesSeed<'a>(&'a TypeRegistry);
+
+impl<'de> DeserializeSeed<'de> for ResourcesSeed<'_> {
+    type Value = ResourceValues;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ResourcesSeed<'_> {
+    type Value = ResourceValues;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("resources")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut resources = vec![];
+        while let Some(name) = map.next_key::<String>()? {
+            let registration = self.0.resources.values().find(|r| r.name == name);
+            let Some(registration) = registration.copied() else {
+                return Err(de::Error::custom(format!("资源 {name} 没有被注册")));
+            };
+            let value = map.next_value_seed(ValueSeed(registration.serde))?;
+            resources.push((registration, value));
+        }
+        Ok(resources)
+    }
+}
+
+/// 通过类型擦除后的[SerdeFns]反序列化一个值
+struct ValueSeed(SerdeFns);
+
+impl<'de> DeserializeSeed<'de> for ValueSeed {
+    type Value = Box<dyn Any>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let mut erased = <dyn erased_serde::Deserializer>::erase(deserializer);
+        (self.0.deserialize)(&mut erased).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        reflect::MapEntities,
+        tools::Command,
+        world::{Name, Query},
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Pos(i32, i32);
+    impl Component for Pos {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Target(Entity);
+    impl Component for Target {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+    impl MapEntities for Target {
+        fn visit_entities(&self, visit: &mut dyn FnMut(Entity)) {
+            visit(self.0)
+        }
+
+        fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+            self.0 = map(self.0)
+        }
+    }
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::new();
+        registry
+            .register_serde::<Pos>()
+            .register_serde::<u32>()
+            .register_serde::<Target>()
+            .register_serde::<Name>()
+            .register_entities::<Target>()
+            .register_resource::<u64>();
+        registry
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut world = World::new();
+        world.insert_resource(registry());
+        let removed = world.spawn(0u32);
+        let a = world.spawn((Pos(1, 2), 3u32, Name::new("a")));
+        let b = world.spawn((Target(a), 4i32));
+        world.despawn(removed);
+        world.insert_resource(5u64);
+
+        let json = serde_json::to_string(&world.serializer()).unwrap();
+        let mut loaded =
+            World::deserialize(registry(), &mut serde_json::Deserializer::from_str(&json)).unwrap();
+
+        // Entity被重新分配,Name的索引被重建
+        let new_a = loaded.entity_by_name("a").unwrap();
+        assert_ne!(a, new_a);
+        assert_eq!(loaded.fetch::<(&Pos, &u32)>(new_a), Some((&Pos(1, 2), &3)));
+        let query = Query::<(Entity, &Target)>::new(&mut loaded);
+        let (new_b, target) = query.into_iter().next().unwrap();
+        assert_eq!(target, &Target(new_a));
+        assert_ne!(b, new_b);
+        // 没有注册的i32被跳过
+        assert_eq!(loaded.fetch::<&i32>(new_b), None);
+        assert_eq!(loaded.resource::<u64>(), Some(&5));
+        assert!(loaded.resource::<TypeRegistry>().is_some());
+
+        // 再次序列化的结果相同
+        let again = serde_json::to_string(&loaded.serializer()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&again).unwrap();
+        assert_eq!(value["entities"].as_array().unwrap().len(), 2);
+        assert_eq!(value["resources"]["u64"], 5);
+    }
+
+    #[test]
+    fn unregistered() {
+        let json = r#"{"entities":[[{"generator":1,"index":0},{"i8":1}]],"resources":{}}"#;
+        let result = World::deserialize(
+            TypeRegistry::new(),
+            &mut serde_json::Deserializer::from_str(json),
+        );
+        let Err(e) = result else {
+            panic!("i8没有注册serde");
+        };
+        assert!(e.to_string().contains("Component i8 没有被注册"));
+    }
+}
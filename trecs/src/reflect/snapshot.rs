@@ -0,0 +1,431 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+};
+
+use crate::{
+    bundle::ComponentId,
+    storage::{ComponentTicks, Entity, EntityAllocator},
+    tools::ResManager,
+    World,
+};
+
+use super::{diff::DiffFns, TypeRegistration, TypeRegistry};
+
+/// 被复制的一个[Component]的值
+///
+/// [Component]: crate::bundle::Component
+pub(super) struct Value {
+    pub(super) type_id: TypeId,
+    pub(super) registration: TypeRegistration,
+    pub(super) value: Box<dyn Any>,
+}
+
+impl Value {
+    /// 只有注册了[DiffFns]的[Component]会被复制
+    ///
+    /// [Component]: crate::bundle::Component
+    fn diff(&self) -> DiffFns {
+        self.registration
+            .diff
+            .unwrap_or_else(|| unreachable!("只复制注册了diff的Component"))
+    }
+
+    /// 是否是同一种[Component],并且值相等
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(super) fn same(&self, type_id: TypeId, value: &dyn Any) -> bool {
+        self.type_id == type_id && (self.diff().eq)(&*self.value, value)
+    }
+}
+
+impl Clone for Value {
+    fn clone(&self) -> Self {
+        Self {
+            value: (self.diff().clone)(&*self.value),
+            ..*self
+        }
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.registration.name)?;
+        self.registration.debug(&*self.value, f)?;
+        f.write_str(")")
+    }
+}
+
+/// 一种可以被复制的资源在[TypeRegistry]中的信息
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ResourceSnapshot {
+    name: &'static str,
+    clone: fn(&dyn Any) -> Box<dyn Any>,
+    insert: fn(&mut World, Box<dyn Any>),
+    remove: fn(&mut World),
+}
+
+impl ResourceSnapshot {
+    fn of<T: Clone + 'static>() -> Self {
+        Self {
+            name: type_name::<T>(),
+            clone: |value| Box::new(value.downcast_ref::<T>().unwrap().clone()),
+            insert: |world, value| {
+                world.insert_resource(*value.downcast::<T>().unwrap());
+            },
+            remove: |world| {
+                if world.peek_res::<T>().is_some() {
+                    world.get_res::<T>().remove();
+                }
+            },
+        }
+    }
+}
+
+/// 被复制的一个资源的值
+struct ResourceValue {
+    registration: ResourceSnapshot,
+    value: Box<dyn Any>,
+}
+
+impl Clone for ResourceValue {
+    fn clone(&self) -> Self {
+        Self {
+            value: (self.registration.clone)(&*self.value),
+            ..*self
+        }
+    }
+}
+
+impl Debug for ResourceValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.registration.name)
+    }
+}
+
+impl TypeRegistry {
+    /// 注册一种可以被复制的资源,见[World::snapshot]
+    pub fn register_resource_snapshot<T: Clone + 'static>(&mut self) -> &mut Self {
+        self.snapshot_resources
+            .insert(TypeId::of::<T>(), ResourceSnapshot::of::<T>());
+        self
+    }
+}
+
+/// 某一时刻所有[Entity]的,在[TypeRegistry]中注册了diff的[Component]的副本,
+/// 以及注册过的资源的副本
+///
+/// 通过[World::snapshot]创建,[Default]为空的快照
+///
+/// [Component]: crate::bundle::Component
+#[derive(Debug, Default, Clone)]
+pub struct WorldSnapshot {
+    pub(super) entities: HashMap<Entity, Vec<Value>>,
+    /// 分配[Entity]的状态
+    allocator: EntityAllocator,
+    resources: HashMap<TypeId, ResourceValue>,
+}
+
+impl WorldSnapshot {
+    /// [Entity]的数量
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains_key(&entity)
+    }
+
+    /// 是否复制了资源T
+    pub fn contains_resource<T: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+}
+
+impl World {
+    /// 在[TypeRegistry]中注册一种可以被复制的资源
+    ///
+    /// 见[World::snapshot]
+    pub fn register_resource_snapshot<T: Clone + 'static>(&mut self) -> &mut Self {
+        self.get_res::<TypeRegistry>()
+            .into_or_init(TypeRegistry::new)
+            .register_resource_snapshot::<T>();
+        self
+    }
+
+    /// 复制所有[Entity]的,通过[World::register_diff]注册过的[Component],
+    /// 以及通过[World::register_resource_snapshot]注册过的资源
+    ///
+    /// + 可以通过[World::restore]回到这一时刻
+    /// + 两个[WorldSnapshot]之间的[WorldDiff]可以被应用到其他[World]上,用于同步状态
+    ///
+    /// ``` rust
+    /// use trecs::{
+    ///     reflect::{EntityMap, WorldSnapshot},
+    ///     tools::Command,
+    ///     World,
+    /// };
+    ///
+    /// let mut server = World::new();
+    /// server.register_diff::<i32>();
+    /// let entity = server.spawn(1i32);
+    /// let first = server.snapshot();
+    ///
+    /// let mut client = World::new();
+    /// let mut map = EntityMap::new();
+    /// client.apply_diff(&WorldSnapshot::default().diff(&first), &mut map);
+    ///
+    /// *server.fetch::<&mut i32>(entity).unwrap() = 2;
+    /// let diff = first.diff(&server.snapshot());
+    /// assert_eq!(diff.entities().len(), 1);
+    /// client.apply_diff(&diff, &mut map);
+    /// assert_eq!(client.fetch::<&i32>(map.map(entity)), Some(&2));
+    ///
+    /// server.restore(&first);
+    /// assert_eq!(server.fetch::<&i32>(entity), Some(&1));
+    /// ```
+    ///
+    /// [WorldDiff]: crate::reflect::WorldDiff
+    /// [Component]: crate::bundle::Component
+    pub fn snapshot(&self) -> WorldSnapshot {
+        let registry = self.peek_res::<TypeRegistry>();
+        let mut entities = HashMap::new();
+        for chunk in &self.chunks {
+            for row in chunk.iter() {
+                // 持有&World时不会有可变的访问
+                let values = unsafe { row.components() }
+                    .filter_map(|component| {
+                        let type_id = component.type_id();
+                        let registration = *registry?.get(type_id)?;
+                        Some(Value {
+                            type_id,
+                            registration,
+                            value: (registration.diff?.clone)(component),
+                        })
+                    })
+                    .collect();
+                entities.insert(row.entity(), values);
+            }
+        }
+
+        let mut resources = HashMap::new();
+        let registered = registry.into_iter().flat_map(|r| &r.snapshot_resources);
+        for (type_id, registration) in registered {
+            let Some(res) = self.resources.get(type_id) else {
+                continue;
+            };
+            // 持有&World时不会有可变的访问
+            if let Some(value) = unsafe { &*res.get() } {
+                let value = ResourceValue {
+                    registration: *registration,
+                    value: (registration.clone)(&**value),
+                };
+                resources.insert(*type_id, value);
+            }
+        }
+
+        WorldSnapshot {
+            entities,
+            allocator: self.entities.allocator(),
+            resources,
+        }
+    }
+
+    /// 回到[World::snapshot]时的状态
+    ///
+    /// + 之后出现的[Entity]会被删除,之后被删除的[Entity]会以相同的[Entity]重新出现,
+    ///   之后分配的[Entity]也会与第一次分配的相同
+    /// + 注册了diff的[Component]和注册过的资源会被替换为snapshot中的值,值相等的[Component]不会被替换
+    /// + 没有注册的[Component]和资源保持不变
+    ///
+    /// snapshot必须来自同一个[World]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        let mut alive = vec![];
+        for chunk in &self.chunks {
+            let mut iter = chunk.iter();
+            while iter.next().is_some() {
+                alive.push(chunk.entity(iter.index));
+            }
+        }
+        let (kept, despawned): (Vec<_>, Vec<_>) =
+            alive.into_iter().partition(|e| snapshot.contains(*e));
+        for entity in despawned {
+            self.despawn(entity);
+        }
+        let kept = kept.into_iter().collect::<HashSet<_>>();
+        self.entities.restore(&snapshot.allocator);
+
+        let tick = self.change_tick;
+        for (&entity, values) in &snapshot.entities {
+            let inserted = values.iter().cloned().map(|value| {
+                let id = ComponentId::Static(value.type_id);
+                let registration = value.registration;
+                (id, registration.name, registration.vtable, value.value)
+            });
+            if !kept.contains(&entity) {
+                let parts = inserted
+                    .map(|(id, name, vtable, value)| {
+                        (id, name, vtable, value, ComponentTicks::new(tick))
+                    })
+                    .collect();
+                self.place(entity, parts);
+                self.index_name(entity);
+                self.record_spawn(entity);
+                continue;
+            }
+
+            let location = self.entities.get(entity).unwrap();
+            // 通过&mut World保证独占
+            let row = self.chunks[location.chunk].row(location.row);
+            let registry = self.peek_res::<TypeRegistry>();
+            let removed = unsafe { row.components() }
+                .map(|component| component.type_id())
+                .filter(|type_id| {
+                    let registration = registry.and_then(|r| r.get(*type_id));
+                    registration.is_some_and(|r| r.diff.is_some())
+                        && !values.iter().any(|value| value.type_id == *type_id)
+                })
+                .collect::<Vec<_>>();
+            let changed = values
+                .iter()
+                .filter(|value| {
+                    !unsafe { row.components() }
+                        .any(|component| value.same(component.type_id(), component))
+                })
+                .cloned()
+                .map(|value| {
+                    let id = ComponentId::Static(value.type_id);
+                    let registration = value.registration;
+                    (id, registration.name, registration.vtable, value.value)
+                })
+                .collect::<Vec<_>>();
+
+            for type_id in removed {
+                self.remove_by_id(entity, ComponentId::Static(type_id));
+            }
+            if !changed.is_empty() {
+                self.insert_parts(entity, changed);
+            }
+        }
+
+        let registered = self
+            .peek_res::<TypeRegistry>()
+            .into_iter()
+            .flat_map(|r| r.snapshot_resources.iter().map(|(id, r)| (*id, *r)))
+            .collect::<Vec<_>>();
+        for (type_id, registration) in registered {
+            match snapshot.resources.get(&type_id) {
+                Some(value) => (registration.insert)(self, value.clone().value),
+                None => (registration.remove)(self),
+            }
+        }
+        self.rebuild_hierarchy();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bundle::Component,
+        hierarchy::{Children, Hierarchy, Parent},
+        tools::Command,
+        world::{Name, Query},
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Pos(i32, i32);
+    impl Component for Pos {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+
+    fn entities(world: &mut World) -> Vec<Entity> {
+        let mut entities = Query::<Entity>::new(world).into_iter().collect::<Vec<_>>();
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn restore_hierarchy() {
+        let mut world = World::new();
+        world.register_diff::<u32>();
+        let [a, b, c] = [0u32, 1, 2].map(|i| world.spawn(i));
+        world.set_parent(b, a).unwrap();
+        world.set_parent(c, a).unwrap();
+        let snapshot = world.snapshot();
+
+        world.set_parent(c, b).unwrap();
+        world.despawn(a);
+        world.restore(&snapshot);
+        let hierarchy = world.resource::<Hierarchy>().unwrap();
+        assert_eq!(hierarchy.children(a), [b, c]);
+        assert!(hierarchy.is_leaf(b));
+        assert_eq!(
+            world.fetch::<&Children>(a).map(|c| c.to_vec()),
+            Some(vec![b, c])
+        );
+        assert_eq!(world.fetch::<&Parent>(c).map(Parent::get), Some(a));
+    }
+
+    #[test]
+    fn restore() {
+        let mut world = World::new();
+        world
+            .register_diff::<Pos>()
+            .register_diff::<u32>()
+            .register_diff::<Name>()
+            .register_resource_snapshot::<u64>();
+        let a = world.spawn((Pos(0, 0), Name::new("a")));
+        let b = world.spawn((Pos(1, 1), 2u32, 0i8));
+        let c = world.spawn(Pos(2, 2));
+        world
+            .insert_resource(1u64)
+            .insert_resource(String::from("text"));
+        let snapshot = world.snapshot();
+        assert_eq!(snapshot.len(), 3);
+        assert!(snapshot.contains_resource::<u64>());
+        assert!(!snapshot.contains_resource::<String>());
+        let before = entities(&mut world);
+
+        *world.fetch::<&mut Pos>(a).unwrap() = Pos(9, 9);
+        world.insert(a, (7u32, Name::new("renamed")));
+        world.remove_component::<u32>(b);
+        *world.fetch::<&mut i8>(b).unwrap() = 3;
+        world.despawn(c);
+        let d = world.spawn(Pos(3, 3));
+        world
+            .insert_resource(2u64)
+            .insert_resource(String::from("changed"));
+
+        for _ in 0..2 {
+            world.restore(&snapshot);
+            assert_eq!(entities(&mut world), before);
+            assert_eq!(world.fetch::<(&Pos, &Name)>(a).unwrap().0, &Pos(0, 0));
+            assert_eq!(world.fetch::<&u32>(a), None);
+            assert_eq!(world.entity_by_name("a"), Some(a));
+            assert_eq!(world.entity_by_name("renamed"), None);
+            // 没有注册的i8保持不变
+            assert_eq!(world.fetch::<(&u32, &i8)>(b), Some((&2, &3)));
+            assert_eq!(world.fetch::<&Pos>(c), Some(&Pos(2, 2)));
+            assert_eq!(world.fetch::<&Pos>(d), None);
+            assert_eq!(world.resource::<u64>(), Some(&1));
+            assert_eq!(world.resource::<String>().unwrap(), "changed");
+        }
+
+        // 重复同样的操作,分配的Entity与第一次相同
+        world.despawn(c);
+        assert_eq!(world.spawn(Pos(3, 3)), d);
+        world.restore(&WorldSnapshot::default());
+        assert!(entities(&mut world).is_empty());
+        assert_eq!(world.resource::<u64>(), None);
+    }
+}
@@ -0,0 +1,217 @@
+use std::{any::TypeId, collections::HashMap, fmt::Debug, marker::PhantomData};
+
+use crate::{
+    storage::Entity,
+    tools::{Command, ResManager},
+    World,
+};
+
+/// 类型为R的关系,如`Relations<Likes>`记录了谁喜欢谁
+///
+/// 作为资源存放在[World]中,同[Hierarchy]一样两个方向的关系总是同时更新,
+/// 一个[Entity]可以同时指向多个目标,[Entity]被删除时它的关系会被移除
+///
+/// R只作为标记,不需要实现任何特征
+///
+/// ``` rust
+/// use trecs::{relation::Relations, tools::Command, world::Query, World};
+///
+/// struct Likes;
+///
+/// let mut world = World::new();
+/// let [alice, bob, carol] = ["alice", "bob", "carol"].map(|name| world.spawn(name));
+/// world.relate::<Likes>(alice, carol);
+/// world.relate::<Likes>(bob, carol);
+/// world.relate::<Likes>(carol, alice);
+///
+/// let likes = world.resource::<Relations<Likes>>().unwrap().clone();
+/// let query = Query::<&&str>::new(&mut world);
+/// let fans = query.iter_related(&likes, carol).copied().collect::<Vec<_>>();
+/// assert_eq!(fans, ["alice", "bob"]);
+/// ```
+///
+/// [Hierarchy]: crate::hierarchy::Hierarchy
+pub struct Relations<R> {
+    /// <源,所有目标>
+    targets: HashMap<Entity, Vec<Entity>>,
+    /// <目标,所有源>
+    sources: HashMap<Entity, Vec<Entity>>,
+    _r: PhantomData<fn() -> R>,
+}
+
+impl<R> Relations<R> {
+    pub fn new() -> Self {
+        Self {
+            targets: Default::default(),
+            sources: Default::default(),
+            _r: PhantomData,
+        }
+    }
+
+    /// 添加source指向target的关系,已经存在时返回false
+    pub fn relate(&mut self, source: Entity, target: Entity) -> bool {
+        if self.contains(source, target) {
+            return false;
+        }
+        self.targets.entry(source).or_default().push(target);
+        self.sources.entry(target).or_default().push(source);
+        true
+    }
+
+    /// 移除source指向target的关系,不存在时返回false
+    pub fn unrelate(&mut self, source: Entity, target: Entity) -> bool {
+        let removed = remove_from(&mut self.targets, source, target);
+        if removed {
+            remove_from(&mut self.sources, target, source);
+        }
+        removed
+    }
+
+    /// 是否存在source指向target的关系
+    pub fn contains(&self, source: Entity, target: Entity) -> bool {
+        self.targets(source).contains(&target)
+    }
+
+    /// source指向的所有目标,按照添加的顺序排列
+    pub fn targets(&self, source: Entity) -> &[Entity] {
+        self.targets.get(&source).map_or(&[], Vec::as_slice)
+    }
+
+    /// 所有指向target的源,按照添加的顺序排列
+    pub fn sources(&self, target: Entity) -> &[Entity] {
+        self.sources.get(&target).map_or(&[], Vec::as_slice)
+    }
+
+    /// 所有的关系,格式为(源,目标)
+    pub fn pairs(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        (self.targets.iter())
+            .flat_map(|(&source, targets)| targets.iter().map(move |&target| (source, target)))
+    }
+
+    /// 移除[Entity]作为源和目标的所有关系
+    ///
+    /// [Entity]被删除时会自动调用
+    pub fn remove_entity(&mut self, entity: Entity) {
+        for target in self.targets.remove(&entity).unwrap_or_default() {
+            remove_from(&mut self.sources, target, entity);
+        }
+        for source in self.sources.remove(&entity).unwrap_or_default() {
+            remove_from(&mut self.targets, source, entity);
+        }
+    }
+}
+
+/// 从map[key]中移除value,列表为空时移除key
+fn remove_from(map: &mut HashMap<Entity, Vec<Entity>>, key: Entity, value: Entity) -> bool {
+    let Some(values) = map.get_mut(&key) else {
+        return false;
+    };
+    let Some(index) = values.iter().position(|&v| v == value) else {
+        return false;
+    };
+    values.remove(index);
+    if values.is_empty() {
+        map.remove(&key);
+    }
+    true
+}
+
+impl<R> Default for Relations<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> Clone for Relations<R> {
+    fn clone(&self) -> Self {
+        Self {
+            targets: self.targets.clone(),
+            sources: self.sources.clone(),
+            _r: PhantomData,
+        }
+    }
+}
+
+impl<R> Debug for Relations<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Relations")
+            .field("targets", &self.targets)
+            .finish()
+    }
+}
+
+/// 从[Relations]中移除被删除的[Entity]
+fn remove_relations<R: 'static>(world: &mut World, entity: Entity) {
+    if let Some(relations) = world.get_res::<Relations<R>>().get_mut() {
+        relations.remove_entity(entity);
+    }
+}
+
+impl World {
+    /// 添加source指向target的类型为R的关系,见[Relations]
+    ///
+    /// 任何一方失效,或者关系已经存在时返回false
+    pub fn relate<R: 'static>(&mut self, source: Entity, target: Entity) -> bool {
+        if [source, target]
+            .iter()
+            .any(|&entity| !self.alive(entity).unwrap_or(false))
+        {
+            return false;
+        }
+        self.on_despawn(TypeId::of::<Relations<R>>(), remove_relations::<R>);
+        self.get_res::<Relations<R>>()
+            .into_or_init(Relations::new)
+            .relate(source, target)
+    }
+
+    /// 移除source指向target的类型为R的关系,不存在时返回false
+    pub fn unrelate<R: 'static>(&mut self, source: Entity, target: Entity) -> bool {
+        self.get_res::<Relations<R>>()
+            .get_mut()
+            .is_some_and(|relations| relations.unrelate(source, target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Query;
+
+    struct Likes;
+    struct Attacks;
+
+    #[test]
+    fn relations() {
+        let mut world = World::new();
+        let [a, b, c] = [0, 1, 2].map(|i| world.spawn(i));
+        assert!(world.relate::<Likes>(a, b));
+        assert!(world.relate::<Likes>(a, c));
+        assert!(world.relate::<Likes>(c, b));
+        assert!(!world.relate::<Likes>(a, b));
+        assert!(world.relate::<Attacks>(b, a));
+
+        let likes = world.resource::<Relations<Likes>>().unwrap();
+        assert_eq!(likes.targets(a), [b, c]);
+        assert_eq!(likes.sources(b), [a, c]);
+        assert!(!likes.contains(b, a));
+        assert_eq!(likes.pairs().count(), 3);
+        // 不同类型的关系互不影响
+        let attacks = world.resource::<Relations<Attacks>>().unwrap();
+        assert_eq!(attacks.targets(b), [a]);
+
+        assert!(world.unrelate::<Likes>(c, b));
+        assert!(!world.unrelate::<Likes>(c, b));
+        let likes = world.resource::<Relations<Likes>>().unwrap().clone();
+        assert_eq!(likes.sources(b), [a]);
+        let query = Query::<&i32>::new(&mut world);
+        let targets = query.iter_targets(&likes, a).copied();
+        assert_eq!(targets.collect::<Vec<_>>(), [1, 2]);
+
+        // 删除Entity时移除所有类型中它的关系
+        world.despawn(a);
+        let likes = world.resource::<Relations<Likes>>().unwrap();
+        let attacks = world.resource::<Relations<Attacks>>().unwrap();
+        assert_eq!((likes.pairs().count(), attacks.pairs().count()), (0, 0));
+        assert!(!world.relate::<Likes>(a, b));
+    }
+}
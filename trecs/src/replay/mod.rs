@@ -0,0 +1,480 @@
+use std::{
+    any::TypeId,
+    fmt::{Display, Write},
+    fs, io,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{event::Events, tools::ResManager, World};
+
+/// 每一帧中所有外部输入的记录
+///
+/// 以文本格式保存,每个输入一行,通过[Display]和[FromStr]转换;
+/// 每一帧的开头是输入的数量,以及有外部发送的事件时事件的数量
+///
+/// ``` text
+/// seed 42
+/// frame 2
+/// input-a
+/// input-b
+/// frame 0 1
+/// event-a
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording<I> {
+    /// 随机数种子
+    pub seed: u64,
+    /// 每一帧中的输入
+    pub frames: Vec<Frame<I>>,
+}
+
+/// [Recording]中一帧的记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame<I> {
+    /// 通过[Replay::push]提交的输入
+    pub inputs: Vec<I>,
+    /// 这一帧开始之前,在[System]之外发送的[Events]
+    ///
+    /// [System]: crate::system::System
+    pub events: Vec<I>,
+}
+
+impl<I> Default for Frame<I> {
+    fn default() -> Self {
+        Self {
+            inputs: vec![],
+            events: vec![],
+        }
+    }
+}
+
+impl<I: Display + FromStr> Recording<I> {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            frames: vec![],
+        }
+    }
+
+    /// 保存到文件
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// 从文件读取
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e: ParseRecordingError| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl<I: Display> Display for Recording<I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "seed {}", self.seed)?;
+        let mut line = String::new();
+        for frame in &self.frames {
+            write!(f, "frame {}", frame.inputs.len())?;
+            if !frame.events.is_empty() {
+                write!(f, " {}", frame.events.len())?;
+            }
+            writeln!(f)?;
+            for input in frame.inputs.iter().chain(&frame.events) {
+                line.clear();
+                write!(line, "{input}")?;
+                // 输入中的换行会破坏格式
+                writeln!(f, "{}", line.replace('\\', "\\\\").replace('\n', "\\n"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 解析[Recording]失败
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRecordingError {
+    /// 出错的行号,从1开始
+    pub line: usize,
+}
+
+impl Display for ParseRecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "无法解析录像的第{}行", self.line)
+    }
+}
+
+impl std::error::Error for ParseRecordingError {}
+
+impl<I: FromStr> FromStr for Recording<I> {
+    type Err = ParseRecordingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = Lines(s.lines().enumerate());
+        let seed = lines.number("seed ")?;
+        let mut frames = vec![];
+        while lines.0.clone().next().is_some() {
+            let (inputs, events) = lines.frame()?;
+            let mut frame = Frame::default();
+            for (len, list) in [(inputs, &mut frame.inputs), (events, &mut frame.events)] {
+                for _ in 0..len {
+                    let (line, input) = lines.next()?;
+                    list.push(input.parse().map_err(|_| ParseRecordingError { line })?);
+                }
+            }
+            frames.push(frame);
+        }
+        Ok(Self { seed, frames })
+    }
+}
+
+/// 解析[Recording]用的按行读取器
+struct Lines<'a>(std::iter::Enumerate<std::str::Lines<'a>>);
+
+impl Lines<'_> {
+    /// 读取下一行,返回行号和去除转义后的内容
+    ///
+    /// 没有下一行时的行号为0
+    fn next(&mut self) -> Result<(usize, String), ParseRecordingError> {
+        let (index, content) = self.0.next().ok_or(ParseRecordingError { line: 0 })?;
+        Ok((index + 1, unescape(content)))
+    }
+
+    /// 读取形如"{prefix}{number}"的一行
+    fn number(&mut self, prefix: &str) -> Result<u64, ParseRecordingError> {
+        let (line, content) = self.next()?;
+        content
+            .strip_prefix(prefix)
+            .and_then(|number| number.parse().ok())
+            .ok_or(ParseRecordingError { line })
+    }
+
+    /// 读取形如"frame {输入的数量}"或者"frame {输入的数量} {事件的数量}"的一行
+    fn frame(&mut self) -> Result<(usize, usize), ParseRecordingError> {
+        let (line, content) = self.next()?;
+        let error = ParseRecordingError { line };
+        let mut numbers = content
+            .strip_prefix("frame ")
+            .ok_or(error.clone())?
+            .split(' ');
+        let mut number = |default: Option<usize>| match numbers.next() {
+            Some(number) => number.parse().map_err(|_| error.clone()),
+            None => default.ok_or(error.clone()),
+        };
+        let counts = (number(None)?, number(Some(0))?);
+        match numbers.next() {
+            Some(_) => Err(error),
+            None => Ok(counts),
+        }
+    }
+}
+
+fn unescape(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// [Replay]的工作模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// 记录通过[Replay::push]提交的输入
+    Record,
+    /// 忽略[Replay::push],使用[Recording]中的输入
+    Replay,
+}
+
+/// 记录或回放外部输入的资源
+///
+/// 外部输入(需要执行的指令,随机数种子等)都应该通过[Replay::push]提交,
+/// 各个[System]通过[Replay::inputs]读取,
+/// 这样回放[Recording]时就可以准确地重现每一帧
+///
+/// 类型同样为I的[Events]也会被记录:每次循环开始时,记录在[System]之外发送的事件;
+/// 回放时丢弃在[System]之外发送的事件,改为发送记录的事件
+///
+/// [Commands]推迟的操作是闭包,无法被记录;需要回放的外部指令应该作为输入提交,由[System]执行
+///
+/// 每次循环结束时自动切换到下一帧
+///
+/// [System]: crate::system::System
+/// [Commands]: crate::world::Commands
+#[derive(Debug, Clone)]
+pub struct Replay<I> {
+    recording: Recording<I>,
+    frame: usize,
+    mode: ReplayMode,
+}
+
+impl<I: Display + FromStr + 'static> Replay<I> {
+    pub fn record(seed: u64) -> Self {
+        Self {
+            recording: Recording {
+                seed,
+                frames: vec![Frame::default()],
+            },
+            frame: 0,
+            mode: ReplayMode::Record,
+        }
+    }
+
+    pub fn playback(recording: Recording<I>) -> Self {
+        Self {
+            recording,
+            frame: 0,
+            mode: ReplayMode::Replay,
+        }
+    }
+
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    /// 随机数种子
+    pub fn seed(&self) -> u64 {
+        self.recording.seed
+    }
+
+    /// 当前的帧数
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// 是否已经回放完全部的帧
+    ///
+    /// 记录时总是返回false
+    pub fn finished(&self) -> bool {
+        self.mode == ReplayMode::Replay && self.frame >= self.recording.frames.len()
+    }
+
+    /// 提交一个输入
+    ///
+    /// 回放时会被忽略
+    pub fn push(&mut self, input: I) {
+        if self.mode == ReplayMode::Record {
+            self.recording.frames[self.frame].inputs.push(input);
+        }
+    }
+
+    /// 当前帧中的所有输入
+    pub fn inputs(&self) -> &[I] {
+        self.recording
+            .frames
+            .get(self.frame)
+            .map_or(&[], |frame| frame.inputs.as_slice())
+    }
+
+    /// 当前帧开始之前在[System]之外发送的事件
+    ///
+    /// [System]: crate::system::System
+    pub fn events(&self) -> &[I] {
+        self.recording
+            .frames
+            .get(self.frame)
+            .map_or(&[], |frame| frame.events.as_slice())
+    }
+
+    pub fn recording(&self) -> &Recording<I> {
+        &self.recording
+    }
+
+    pub fn into_recording(self) -> Recording<I> {
+        self.recording
+    }
+
+    /// 切换到下一帧
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+        if self.mode == ReplayMode::Record {
+            self.recording.frames.push(Frame::default());
+        }
+    }
+}
+
+/// 每次循环结束时切换[Replay]到下一帧
+fn next_frame<I: Display + FromStr + 'static>(world: &mut World) {
+    if let Some(replay) = world.get_res::<Replay<I>>().get_mut() {
+        replay.next_frame();
+    }
+}
+
+/// 每次循环开始时记录或者回放在[System]之外发送的[Events]
+///
+/// [Events::update]在上一次循环结束时调用,所以这一帧的事件都是之后在[System]之外发送的
+///
+/// [System]: crate::system::System
+fn sync_events<I: Clone + Display + FromStr + 'static>(world: &mut World) {
+    let Some(replay) = world.peek_res::<Replay<I>>() else {
+        return;
+    };
+    match replay.mode {
+        ReplayMode::Record => {
+            let Some(events) = world.peek_res::<Events<I>>() else {
+                return;
+            };
+            let sent = events.current().to_vec();
+            if let Some(replay) = world.get_res::<Replay<I>>().get_mut() {
+                replay.recording.frames[replay.frame].events = sent;
+            }
+        }
+        // 回放完之后不再丢弃外部发送的事件
+        ReplayMode::Replay if !replay.finished() => {
+            let recorded = replay.events().to_vec();
+            if let Some(events) = world.get_res::<Events<I>>().get_mut() {
+                events.replace_current(recorded);
+            }
+        }
+        ReplayMode::Replay => {}
+    }
+}
+
+impl World {
+    /// 开始记录类型为I的输入和[Events]
+    pub fn record<I: Clone + Display + FromStr + 'static>(&mut self, seed: u64) -> &mut Self {
+        self.set_replay(Replay::<I>::record(seed))
+    }
+
+    /// 开始回放recording
+    pub fn replay<I: Clone + Display + FromStr + 'static>(
+        &mut self,
+        recording: Recording<I>,
+    ) -> &mut Self {
+        self.set_replay(Replay::playback(recording))
+    }
+
+    /// 取出类型为I的输入的[Recording]
+    ///
+    /// 会停止记录或者回放
+    pub fn take_recording<I: Display + FromStr + 'static>(&mut self) -> Option<Recording<I>> {
+        let replay = self.try_get_res::<Replay<I>>()?.take()?;
+        Some(replay.into_recording())
+    }
+
+    fn set_replay<I: Clone + Display + FromStr + 'static>(
+        &mut self,
+        replay: Replay<I>,
+    ) -> &mut Self {
+        let mut res = self.get_res::<Replay<I>>();
+        res.take();
+        res.get_or_init(|| replay);
+        self.on_frame_start(TypeId::of::<Replay<I>>(), sync_events::<I>);
+        self.on_frame_end(TypeId::of::<Replay<I>>(), next_frame::<I>);
+        self
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        event::{EventReader, EventWriter},
+        world::{Res, ResMut},
+    };
+
+    #[test]
+    fn format() {
+        let recording = Recording {
+            seed: 42,
+            frames: vec![
+                Frame {
+                    inputs: vec!["a".to_owned(), "b\nc\\".to_owned()],
+                    events: vec![],
+                },
+                Frame {
+                    inputs: vec![],
+                    events: vec!["e".to_owned()],
+                },
+            ],
+        };
+        let text = recording.to_string();
+        assert_eq!(text, "seed 42\nframe 2\na\nb\\nc\\\\\nframe 0 1\ne\n");
+        assert_eq!(text.parse(), Ok(recording));
+        assert_eq!(
+            "seed 1\nframe 2\n3\n".parse::<Recording<i32>>(),
+            Err(ParseRecordingError { line: 0 })
+        );
+        assert_eq!(
+            "seed 1\nframe 1\nx\n".parse::<Recording<i32>>(),
+            Err(ParseRecordingError { line: 3 })
+        );
+        assert_eq!(
+            "seed 1\nframe 0 0 0\n".parse::<Recording<i32>>(),
+            Err(ParseRecordingError { line: 2 })
+        );
+    }
+
+    #[test]
+    fn record_replay() {
+        static mut SUM: i32 = 0;
+        fn sum(replay: Res<Replay<i32>>) {
+            for input in replay.get().unwrap().inputs() {
+                unsafe { SUM += input }
+            }
+        }
+
+        let mut world = World::new();
+        world.add_system(sum).record::<i32>(7);
+        for frame in 0..3 {
+            let mut replay = world.get_res::<Replay<i32>>();
+            replay.get_mut().unwrap().push(frame);
+            replay.get_mut().unwrap().push(frame * 10);
+            world.run_once();
+        }
+        let recording = world.take_recording::<i32>().unwrap();
+        assert_eq!(unsafe { SUM }, 33);
+        assert_eq!(recording.seed, 7);
+
+        unsafe { SUM = 0 };
+        world.replay(recording);
+        while !world.get_res::<Replay<i32>>().get().unwrap().finished() {
+            // 回放时提交的输入会被忽略
+            world.get_res::<Replay<i32>>().get_mut().unwrap().push(100);
+            world.run_once();
+        }
+        assert_eq!(unsafe { SUM }, 33);
+    }
+
+    #[test]
+    fn record_events() {
+        fn sum(mut reader: EventReader<i32>, sum: ResMut<i32>) {
+            *sum.into_or_init(|| 0) += reader.read().sum::<i32>();
+        }
+        // System中发送的事件不是外部输入,不会被记录
+        fn internal(mut writer: EventWriter<i32>) {
+            writer.send(1000);
+        }
+
+        let mut world = World::new();
+        world.add_system(sum).add_system(internal).record::<i32>(0);
+        for frame in 1..=3 {
+            world.send_event(frame).run_once();
+        }
+        world.run_once();
+        let recording = world.take_recording::<i32>().unwrap();
+        let events = recording.frames.iter().map(|f| f.events.clone());
+        assert_eq!(
+            events.collect::<Vec<_>>(),
+            [vec![1], vec![2], vec![3], vec![], vec![]]
+        );
+        let recorded = world.get_res::<i32>().take().unwrap();
+
+        world.get_res::<Events<i32>>().get_mut().unwrap().clear();
+        world.replay(recording);
+        for _ in 0..4 {
+            // 回放时外部发送的事件会被丢弃
+            world.send_event(100).run_once();
+        }
+        assert_eq!(world.resource::<i32>(), Some(&*recorded));
+    }
+}
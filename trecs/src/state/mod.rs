@@ -0,0 +1,476 @@
+use std::{
+    any::{Any, TypeId},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    system::{state::SystemState, InnerCondition, IntoSystem, System, SystemParm},
+    tools::ResManager,
+    world::{Res, UnsafeWorldCell},
+    World,
+};
+
+/// 可以作为[State]的类型,如菜单,加载中,游戏中等
+///
+/// 为所有满足条件的类型自动实现
+pub trait States: Clone + PartialEq + Send + Sync + 'static {}
+
+impl<T: Clone + PartialEq + Send + Sync + 'static> States for T {}
+
+/// 应用的当前状态
+///
+/// 作为资源存放在[World]中,通过[World::add_state]添加
+///
+/// 通过[State::set]切换到下一个状态,切换在下一帧开始时生效:
+/// 先执行离开旧状态的[OnExit],再执行进入新状态的[OnEnter]
+///
+/// ``` rust
+/// use trecs::{
+///     state::{in_state, OnEnter, State},
+///     system::IntoSystem,
+///     world::ResMut,
+///     World,
+/// };
+///
+/// #[derive(Clone, PartialEq, Debug)]
+/// enum Game {
+///     Menu,
+///     Playing,
+/// }
+///
+/// fn start(mut state: ResMut<State<Game>>) {
+///     state.get_mut().unwrap().set(Game::Playing);
+/// }
+///
+/// let mut world = World::new();
+/// world
+///     .add_state(Game::Menu)
+///     .add_system(start.run_if(in_state(Game::Menu)))
+///     .add_transition_system(OnEnter(Game::Playing), || println!("playing"));
+/// world.run_once();
+/// world.run_once();
+/// assert_eq!(world.state::<Game>(), Some(&Game::Playing));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State<S> {
+    current: S,
+    next: Option<S>,
+}
+
+impl<S: States> State<S> {
+    /// 当前的状态
+    pub fn get(&self) -> &S {
+        &self.current
+    }
+
+    /// 在下一帧开始时切换到next
+    ///
+    /// 多次调用时只有最后一次有效,与当前状态相同时不会切换
+    pub fn set(&mut self, next: S) {
+        self.next = Some(next);
+    }
+
+    /// 等待切换到的状态
+    pub fn next(&self) -> Option<&S> {
+        self.next.as_ref()
+    }
+}
+
+/// 进入状态时执行的[System],见[World::add_transition_system]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnEnter<S>(pub S);
+
+/// 离开状态时执行的[System],见[World::add_transition_system]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnExit<S>(pub S);
+
+/// [State]切换时执行[System]的时机,即[OnEnter]或者[OnExit]
+pub trait Transition {
+    type State: States;
+
+    /// 进入时为true,离开时为false
+    const ENTER: bool;
+
+    fn into_state(self) -> Self::State;
+}
+
+impl<S: States> Transition for OnEnter<S> {
+    type State = S;
+    const ENTER: bool = true;
+
+    fn into_state(self) -> S {
+        self.0
+    }
+}
+
+impl<S: States> Transition for OnExit<S> {
+    type State = S;
+    const ENTER: bool = false;
+
+    fn into_state(self) -> S {
+        self.0
+    }
+}
+
+/// 运行条件 : [State]处于state时返回true
+///
+/// 没有添加[State]时返回false
+pub fn in_state<S: States>(state: S) -> InState<S> {
+    InState(state)
+}
+
+/// 见[in_state]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InState<S>(S);
+
+impl<S: States> InnerCondition<InState<S>> for InState<S> {
+    fn prepare(&self, world: &mut World) {
+        Res::<State<S>>::prepare(world);
+    }
+
+    fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()> {
+        unsafe { std::mem::transmute(Box::new(Res::<State<S>>::build(world))) }
+    }
+
+    fn init(&self) -> SystemState {
+        let mut state = SystemState::new();
+        Res::<State<S>>::init(&mut state);
+        state
+    }
+
+    fn run_once(&mut self, args: Box<()>) -> bool {
+        let state = unsafe { *std::mem::transmute::<Box<()>, Box<Res<'static, State<S>>>>(args) };
+        state.get().is_some_and(|state| state.current == self.0)
+    }
+}
+
+/// 每种[State]的[OnEnter]和[OnExit]中的[System]
+struct Transitions<S> {
+    /// 初始状态的[OnEnter]是否已经执行过
+    entered: bool,
+    enter: Vec<(S, System)>,
+    exit: Vec<(S, System)>,
+}
+
+/// 这一帧需要执行的[OnExit]和[OnEnter]中的[System]
+type Pending<S> = (Box<Transitions<S>>, Option<S>, Option<S>);
+
+/// 在帧开始时切换[State],返回需要离开和进入的状态
+///
+/// 执行完[System]之后需要通过[finish_transition]放回去
+fn begin_transition<S: States>(world: &mut World) -> Option<Pending<S>> {
+    let mut transitions = world.get_res::<Transitions<S>>().take()?;
+    let (exit, enter) = match world.get_res::<State<S>>().get_mut() {
+        Some(state) if !transitions.entered => (None, Some(state.current.clone())),
+        Some(state) => match state.next.take() {
+            Some(next) if next != state.current => {
+                let exit = std::mem::replace(&mut state.current, next);
+                (Some(exit), Some(state.current.clone()))
+            }
+            _ => (None, None),
+        },
+        None => (None, None),
+    };
+    transitions.entered = true;
+    Some((transitions, exit, enter))
+}
+
+/// 按照顺序需要执行的[OnExit]和[OnEnter]中的[System]
+fn transition_systems<S: States>(
+    (transitions, exit, enter): &mut Pending<S>,
+) -> impl Iterator<Item = &mut System> {
+    let exit = transitions
+        .exit
+        .iter_mut()
+        .filter(|(state, _)| exit.as_ref() == Some(state));
+    let enter = transitions
+        .enter
+        .iter_mut()
+        .filter(|(state, _)| enter.as_ref() == Some(state));
+    exit.chain(enter).map(|(_, system)| system)
+}
+
+fn finish_transition<S: States>(world: &mut World, (transitions, ..): Pending<S>) {
+    world
+        .get_res::<Transitions<S>>()
+        .get_or_init(|| *transitions);
+}
+
+/// 在帧开始时切换[State]
+///
+/// 返回第一个发生panic的[System]的名字,以及panic的内容
+#[cfg(not(feature = "async"))]
+pub(crate) fn apply_transition<S: States>(
+    world: &mut World,
+    timings: &mut Vec<(&'static str, Duration)>,
+) -> Option<(&'static str, Box<dyn Any + Send>)> {
+    let mut pending = begin_transition::<S>(world)?;
+    let mut panicked = None;
+    for system in transition_systems(&mut pending) {
+        if !world.schedule.is_enabled(system) {
+            continue;
+        }
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("system", name = system.name()).entered();
+        let start = Instant::now();
+        if let Err(payload) = system.run_once(world) {
+            panicked = Some((system.name(), payload));
+            break;
+        }
+        timings.push((system.name(), start.elapsed()));
+    }
+    finish_transition(world, pending);
+    panicked
+}
+
+/// 异步版本的[apply_transition]返回的[Future]
+///
+/// [Future]: std::future::Future
+#[cfg(feature = "async")]
+pub(crate) type TransitionFuture<'a> = std::pin::Pin<
+    Box<dyn std::future::Future<Output = Option<(&'static str, Box<dyn Any + Send>)>> + 'a>,
+>;
+
+/// 异步版本的[apply_transition]
+#[cfg(feature = "async")]
+pub(crate) fn apply_transition<'a, S: States>(
+    world: &'a mut World,
+    timings: &'a mut Vec<(&'static str, Duration)>,
+) -> TransitionFuture<'a> {
+    Box::pin(async move {
+        let mut pending = begin_transition::<S>(world)?;
+        let mut panicked = None;
+        for system in transition_systems(&mut pending) {
+            if !world.schedule.is_enabled(system) {
+                continue;
+            }
+            let name = system.name();
+            let start = Instant::now();
+            let run = system.run_once(world);
+            #[cfg(feature = "trace")]
+            let run = tracing::Instrument::instrument(run, tracing::info_span!("system", name));
+            if let Err(payload) = run.await {
+                panicked = Some((name, payload));
+                break;
+            }
+            timings.push((name, start.elapsed()));
+        }
+        finish_transition(world, pending);
+        panicked
+    })
+}
+
+impl World {
+    /// 添加初始状态为initial的[State],已经存在时会被替换
+    ///
+    /// 初始状态的[OnEnter]会在下一帧开始时执行
+    pub fn add_state<S: States>(&mut self, initial: S) -> &mut Self {
+        let mut state = self.get_res::<State<S>>();
+        state.take();
+        state.get_or_init(|| State {
+            current: initial,
+            next: None,
+        });
+        let mut transitions = self.get_res::<Transitions<S>>();
+        match transitions.get_mut() {
+            Some(transitions) => transitions.entered = false,
+            None => {
+                transitions.get_or_init(|| Transitions {
+                    entered: false,
+                    enter: vec![],
+                    exit: vec![],
+                });
+            }
+        }
+        let id = TypeId::of::<S>();
+        if self
+            .state_transitions
+            .iter()
+            .all(|(exist, ..)| *exist != id)
+        {
+            self.state_transitions.push((id, apply_transition::<S>));
+        }
+        self
+    }
+
+    /// [State]当前的状态,没有添加时返回[None]
+    pub fn state<S: States>(&self) -> Option<&S> {
+        self.peek_res::<State<S>>().map(State::get)
+    }
+
+    /// 在下一帧开始时切换到next,见[State::set]
+    ///
+    /// 没有添加[State]时会panic
+    pub fn set_state<S: States>(&mut self, next: S) -> &mut Self {
+        self.get_res::<State<S>>()
+            .get_mut()
+            .unwrap_or_else(|| panic!("没有添加State<{}>", std::any::type_name::<S>()))
+            .set(next);
+        self
+    }
+
+    /// 添加一个只在[State]切换时执行的[System]
+    ///
+    /// 同一个时机的[System]按照添加的顺序执行,忽略顺序约束
+    ///
+    /// [System]的参数之间有冲突或者没有添加[State]时会panic,
+    /// 见[World::try_add_transition_system]
+    pub fn add_transition_system<M, T: Transition, S: IntoSystem<M>>(
+        &mut self,
+        transition: T,
+        system: S,
+    ) -> &mut Self {
+        self.try_add_transition_system(transition, system)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// 添加一个只在[State]切换时执行的[System]
+    ///
+    /// [System]的参数之间有冲突时返回[SystemRegistrationError],并且不会添加
+    ///
+    /// 没有添加[State]时会panic
+    ///
+    /// [SystemRegistrationError]: crate::system::SystemRegistrationError
+    pub fn try_add_transition_system<M, T: Transition, S: IntoSystem<M>>(
+        &mut self,
+        transition: T,
+        system: S,
+    ) -> Result<&mut Self, crate::system::SystemRegistrationError> {
+        let system = system.into_system()?;
+        let mut transitions = self.get_res::<Transitions<T::State>>();
+        let transitions = transitions
+            .get_mut()
+            .unwrap_or_else(|| panic!("没有添加State<{}>", std::any::type_name::<T::State>()));
+        match T::ENTER {
+            true => transitions.enter.push((transition.into_state(), system)),
+            false => transitions.exit.push((transition.into_state(), system)),
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{self, Capture},
+        world::ResMut,
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Game {
+        Menu,
+        Playing,
+        Paused,
+    }
+
+    fn record(capture: ResMut<Capture<&'static str>>, name: &'static str) {
+        capture.into_or_init(Capture::new).push(name);
+    }
+
+    #[test]
+    fn transitions() {
+        fn update(capture: ResMut<Capture<&'static str>>) {
+            record(capture, "update");
+        }
+        fn enter_menu(capture: ResMut<Capture<&'static str>>) {
+            record(capture, "enter menu");
+        }
+        fn exit_menu(capture: ResMut<Capture<&'static str>>) {
+            record(capture, "exit menu");
+        }
+        fn enter_playing(capture: ResMut<Capture<&'static str>>) {
+            record(capture, "enter playing");
+        }
+        fn exit_playing(capture: ResMut<Capture<&'static str>>) {
+            record(capture, "exit playing");
+        }
+
+        let mut world = World::new();
+        world
+            .add_state(Game::Menu)
+            .add_system(update.run_if(in_state(Game::Playing)))
+            .add_transition_system(OnEnter(Game::Menu), enter_menu)
+            .add_transition_system(OnExit(Game::Menu), exit_menu)
+            .add_transition_system(OnEnter(Game::Playing), enter_playing)
+            .add_transition_system(OnExit(Game::Playing), exit_playing);
+        test::tick(&mut world);
+        assert_eq!(test::captured::<&str>(&mut world), ["enter menu"]);
+
+        world.set_state(Game::Playing);
+        assert_eq!(world.state(), Some(&Game::Menu));
+        test::tick(&mut world);
+        assert_eq!(world.state(), Some(&Game::Playing));
+        assert_eq!(
+            test::captured::<&str>(&mut world),
+            ["exit menu", "enter playing", "update"]
+        );
+
+        // 与当前状态相同时不会切换
+        world.set_state(Game::Playing);
+        test::tick(&mut world);
+        assert_eq!(test::captured::<&str>(&mut world), ["update"]);
+
+        world.set_state(Game::Paused);
+        test::tick(&mut world);
+        assert_eq!(test::captured::<&str>(&mut world), ["exit playing"]);
+    }
+
+    #[test]
+    fn in_state_without_state() {
+        fn update(capture: ResMut<Capture<&'static str>>) {
+            record(capture, "update");
+        }
+
+        let mut world = World::new();
+        world.add_system(update.run_if(in_state(Game::Menu)));
+        test::tick(&mut world);
+        assert!(test::captured::<&str>(&mut world).is_empty());
+        assert_eq!(world.state::<Game>(), None);
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{system::block_on, world::ResMut};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Game {
+        Menu,
+        Playing,
+    }
+
+    #[test]
+    fn transitions() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let record = |name: &'static str| {
+            let log = log.clone();
+            move || {
+                log.lock().unwrap().push(name);
+                async {}
+            }
+        };
+        let mut world = World::new();
+        world
+            .add_state(Game::Menu)
+            .add_system(record("update").run_if(in_state(Game::Playing)))
+            .add_system(|mut state: ResMut<State<Game>>| {
+                state.get_mut().unwrap().set(Game::Playing);
+                async {}
+            })
+            .add_transition_system(OnEnter(Game::Menu), record("enter menu"))
+            .add_transition_system(OnExit(Game::Menu), record("exit menu"))
+            .add_transition_system(OnEnter(Game::Playing), record("enter playing"));
+        block_on(world.run_once());
+        assert_eq!(world.state(), Some(&Game::Menu));
+        block_on(world.run_once());
+        assert_eq!(world.state(), Some(&Game::Playing));
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["enter menu", "exit menu", "enter playing", "update"]
+        );
+    }
+}
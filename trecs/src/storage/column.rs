@@ -0,0 +1,214 @@
+use std::{
+    alloc::{alloc, dealloc, handle_alloc_error, realloc, Layout},
+    any::Any,
+    fmt::Debug,
+    ptr::NonNull,
+};
+
+use super::ComponentTicks;
+
+/// 类型擦除后的对[Component]的值的操作
+///
+/// 用于在[Column]中存放[Component],以及与[Box<dyn Any>]相互转换,
+/// 每种类型只有一个,通过[ComponentVTable::of]得到
+///
+/// [Component]: crate::bundle::Component
+#[derive(Clone, Copy)]
+pub(crate) struct ComponentVTable {
+    layout: Layout,
+    drop: unsafe fn(*mut u8),
+    as_any: unsafe fn(*mut u8) -> *mut dyn Any,
+    write: unsafe fn(Box<dyn Any>, *mut u8),
+    read: unsafe fn(*mut u8) -> Box<dyn Any>,
+}
+
+impl ComponentVTable {
+    pub fn of<T: 'static>() -> &'static Self {
+        const {
+            &Self {
+                layout: Layout::new::<T>(),
+                drop: |ptr| unsafe { ptr.cast::<T>().drop_in_place() },
+                as_any: |ptr| ptr.cast::<T>() as *mut dyn Any,
+                write: |value, ptr| {
+                    let value = value
+                        .downcast::<T>()
+                        .unwrap_or_else(|_| unreachable!("布局和Component的组合相同"));
+                    unsafe { ptr.cast::<T>().write(*value) }
+                },
+                read: |ptr| unsafe { Box::new(ptr.cast::<T>().read()) },
+            }
+        }
+    }
+}
+
+impl ComponentVTable {
+    /// 把value移动到ptr处,不会drop原来的值
+    ///
+    /// # Safety
+    ///
+    /// ptr必须可以写入value的类型的值,value必须是此[ComponentVTable]对应的类型
+    pub unsafe fn write(&self, value: Box<dyn Any>, ptr: *mut u8) {
+        (self.write)(value, ptr)
+    }
+}
+
+impl Debug for ComponentVTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComponentVTable")
+            .field("layout", &self.layout)
+            .finish()
+    }
+}
+
+/// 连续存放同一种[Component]的内存,以及每个位置的[ComponentTicks]
+///
+/// 不记录哪些位置上有值,由[Chunk]负责
+///
+/// [Component]: crate::bundle::Component
+/// [Chunk]: super::Chunk
+pub(crate) struct Column {
+    data: NonNull<u8>,
+    /// 能存放的值的数量
+    capacity: usize,
+    vtable: &'static ComponentVTable,
+    /// 每个位置的[ComponentTicks],空闲的位置上是过期的值
+    ticks: Vec<ComponentTicks>,
+}
+
+impl Column {
+    pub fn new(vtable: &'static ComponentVTable) -> Self {
+        let layout = vtable.layout;
+        Self {
+            // 不需要分配,只需要对齐;对齐总是非零的
+            data: unsafe {
+                NonNull::new_unchecked(std::ptr::without_provenance_mut(layout.align()))
+            },
+            capacity: if layout.size() == 0 { usize::MAX } else { 0 },
+            vtable,
+            ticks: vec![],
+        }
+    }
+
+    /// 保证至少能存放len个值,扩容时移动所有的值
+    pub fn reserve(&mut self, len: usize) {
+        if len <= self.capacity {
+            return;
+        }
+        let capacity = len.max(self.capacity * 2).max(4);
+        let new = Self::array(self.vtable.layout, capacity);
+        let data = unsafe {
+            if self.capacity == 0 {
+                alloc(new)
+            } else {
+                let old = Self::array(self.vtable.layout, self.capacity);
+                realloc(self.data.as_ptr(), old, new.size())
+            }
+        };
+        self.data = NonNull::new(data).unwrap_or_else(|| handle_alloc_error(new));
+        self.capacity = capacity;
+    }
+
+    fn array(layout: Layout, n: usize) -> Layout {
+        let size = layout.size().checked_mul(n).expect("Column的容量溢出");
+        Layout::from_size_align(size, layout.align()).expect("Column的容量溢出")
+    }
+
+    /// row处的值的指针
+    ///
+    /// 不会创建对整个[Column]的引用,所以同时访问不同的位置不会发生别名冲突
+    pub fn ptr(&self, row: usize) -> *mut u8 {
+        debug_assert!(row < self.capacity);
+        unsafe { self.data.as_ptr().add(row * self.vtable.layout.size()) }
+    }
+
+    /// row处的值的[ComponentTicks]
+    pub fn ticks(&self, row: usize) -> &ComponentTicks {
+        &self.ticks[row]
+    }
+
+    /// 设置row处的值的[ComponentTicks],row至多比已经使用过的位置多一个
+    pub fn set_ticks(&mut self, row: usize, ticks: ComponentTicks) {
+        if row == self.ticks.len() {
+            self.ticks.push(ticks);
+        } else {
+            self.ticks[row] = ticks;
+        }
+    }
+
+    /// 取出row处的值的[ComponentTicks],留下默认值
+    pub fn take_ticks(&mut self, row: usize) -> ComponentTicks {
+        std::mem::take(&mut self.ticks[row])
+    }
+
+    /// row处的值
+    ///
+    /// # Safety
+    ///
+    /// row处必须有值
+    pub unsafe fn get(&self, row: usize) -> *mut dyn Any {
+        (self.vtable.as_any)(self.ptr(row))
+    }
+
+    /// 把value移动到row处,不会drop原来的值
+    ///
+    /// # Safety
+    ///
+    /// row必须在容量之内,value必须是此[Column]存放的类型
+    pub unsafe fn write(&mut self, row: usize, value: Box<dyn Any>) {
+        self.vtable.write(value, self.ptr(row))
+    }
+
+    /// 把row处的值移出,之后row处没有值
+    ///
+    /// # Safety
+    ///
+    /// row处必须有值
+    pub unsafe fn read(&mut self, row: usize) -> Box<dyn Any> {
+        (self.vtable.read)(self.ptr(row))
+    }
+
+    /// 把from处的值移动到to处,之后from处没有值,不会drop to处原来的值
+    ///
+    /// # Safety
+    ///
+    /// from处必须有值,to必须在容量之内,并且与from不同
+    pub unsafe fn relocate(&mut self, from: usize, to: usize) {
+        std::ptr::copy_nonoverlapping(self.ptr(from), self.ptr(to), self.vtable.layout.size())
+    }
+
+    /// 把row处的值移动到dst,之后row处没有值
+    ///
+    /// # Safety
+    ///
+    /// row处必须有值,dst必须是另一个同类[Column]中的空闲位置
+    pub unsafe fn move_to(&self, row: usize, dst: *mut u8) {
+        std::ptr::copy_nonoverlapping(self.ptr(row), dst, self.vtable.layout.size())
+    }
+
+    /// drop row处的值,之后row处没有值
+    ///
+    /// # Safety
+    ///
+    /// row处必须有值
+    pub unsafe fn drop_at(&mut self, row: usize) {
+        (self.vtable.drop)(self.ptr(row))
+    }
+}
+
+impl Drop for Column {
+    fn drop(&mut self) {
+        if self.capacity != 0 && self.vtable.layout.size() != 0 {
+            let layout = Self::array(self.vtable.layout, self.capacity);
+            unsafe { dealloc(self.data.as_ptr(), layout) }
+        }
+    }
+}
+
+impl Debug for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Column")
+            .field("capacity", &self.capacity)
+            .field("vtable", &self.vtable)
+            .finish()
+    }
+}
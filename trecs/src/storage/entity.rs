@@ -2,18 +2,22 @@
 use crate::{
     bundle::{Bundle, Components},
     storage::Chunk,
+    World,
 };
 
-use super::CHUNK_SIZE;
+use super::ALIVE_TAG;
 
 /// 对[Bundle]生成的[Components]在[World]中的索引
+///
+/// 在[Chunk]之间移动时(如[World::insert])不会改变
 #[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity {
-    /// 从[Chunk]的alive数组中拷贝的数据
+    /// 从[Entities]的generators数组中拷贝的数据
     ///
     /// 用来计算[Entity]是否有效
     pub(crate) generator: usize,
-    /// [Entity]指向的[Bundle]所在的位置
+    /// [Entity]在[Entities]中的下标
     pub(crate) index: usize,
 }
 
@@ -21,12 +25,153 @@ impl Entity {
     pub(crate) fn new(generator: usize, index: usize) -> Self {
         Self { generator, index }
     }
+}
+
+/// [Entity]的[Components]所在的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EntityLocation {
+    /// [Chunk]在[World]中的下标
+    pub chunk: usize,
+    /// [Components]在[Chunk]中的下标
+    pub row: usize,
+}
+
+/// 分配[Entity],并记录每个[Entity]的[EntityLocation]
+#[derive(Debug, Default)]
+pub(crate) struct Entities {
+    /// 与[Entity]的generator对应
+    ///
+    /// 第一位表示[Entity]是否"存活",其余位表示这个下标的使用次数
+    generators: Vec<usize>,
+    /// 与generators一一对应
+    locations: Vec<EntityLocation>,
+    /// 空闲的下标
+    free: Vec<usize>,
+}
+
+impl Entities {
+    /// 分配一个[Entity],会优先复用空闲的下标
+    ///
+    /// 需要随后通过[Entities::set]设置[EntityLocation]
+    pub fn alloc(&mut self) -> Entity {
+        let unset = EntityLocation {
+            chunk: usize::MAX,
+            row: usize::MAX,
+        };
+        match self.free.pop() {
+            Some(index) => {
+                self.generators[index] += ALIVE_TAG + 1;
+                self.locations[index] = unset;
+                Entity::new(self.generators[index], index)
+            }
+            None => {
+                self.generators.push(ALIVE_TAG);
+                self.locations.push(unset);
+                Entity::new(ALIVE_TAG, self.generators.len() - 1)
+            }
+        }
+    }
+
+    /// 预留至少additional个新的[Entity]的空间
+    pub fn reserve(&mut self, additional: usize) {
+        let additional = additional.saturating_sub(self.free.len());
+        self.generators.reserve(additional);
+        self.locations.reserve(additional);
+    }
+
+    /// 释放[Entity],返回它原来的[EntityLocation]
+    ///
+    /// [Entity]已经失效时返回[None]
+    pub fn free(&mut self, entity: Entity) -> Option<EntityLocation> {
+        let location = self.get(entity)?;
+        self.generators[entity.index] -= ALIVE_TAG;
+        self.free.push(entity.index);
+        Some(location)
+    }
+
+    /// 计算Entity是否有效
+    ///
+    /// + 返回[Some(bool)]时,[bool]表示[Entity]是否有效
+    ///
+    /// + 返回[None]时,表示[Entity]对应的[Bundle]并不存在
+    pub fn alive(&self, entity: Entity) -> Option<bool> {
+        Some(*self.generators.get(entity.index)? == entity.generator)
+    }
+
+    /// 有效的[Entity]的[EntityLocation]
+    pub fn get(&self, entity: Entity) -> Option<EntityLocation> {
+        self.alive(entity)?.then(|| self.locations[entity.index])
+    }
+
+    /// 更新[Entity]的[EntityLocation]
+    pub fn set(&mut self, entity: Entity, location: EntityLocation) {
+        self.locations[entity.index] = location;
+    }
+
+    /// 复制分配[Entity]的状态,见[Entities::restore]
+    pub fn allocator(&self) -> EntityAllocator {
+        EntityAllocator {
+            generators: self.generators.clone(),
+            free: self.free.clone(),
+        }
+    }
 
-    pub(crate) fn index_in_chunk(&self) -> usize {
-        self.index % CHUNK_SIZE
+    /// 恢复分配[Entity]的状态
+    ///
+    /// 在两边都有效的[Entity]保留原来的[EntityLocation],
+    /// 只在allocator中有效的[Entity]需要随后通过[Entities::set]设置[EntityLocation]
+    pub fn restore(&mut self, allocator: &EntityAllocator) {
+        let unset = EntityLocation {
+            chunk: usize::MAX,
+            row: usize::MAX,
+        };
+        self.generators.clone_from(&allocator.generators);
+        self.free.clone_from(&allocator.free);
+        self.locations.resize(self.generators.len(), unset);
     }
+}
+
+/// 见[Entities::allocator]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct EntityAllocator {
+    generators: Vec<usize>,
+    free: Vec<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alloc_free() {
+        let mut entities = Entities::default();
+        let (a, b) = (entities.alloc(), entities.alloc());
+        assert_eq!(
+            (a, b),
+            (Entity::new(ALIVE_TAG, 0), Entity::new(ALIVE_TAG, 1))
+        );
+
+        let location = EntityLocation { chunk: 1, row: 2 };
+        entities.set(a, location);
+        assert_eq!(entities.free(a), Some(location));
+        assert_eq!(entities.free(a), None);
+        assert_eq!(entities.alive(a), Some(false));
+        assert_eq!(entities.alive(Entity::new(ALIVE_TAG, 2)), None);
+
+        entities.set(b, location);
+        // 复用a的下标
+        let allocator = entities.allocator();
+        let c = entities.alloc();
+        assert_eq!(c, Entity::new(ALIVE_TAG + 1, 0));
+        assert_eq!(entities.alive(a), Some(false));
+        assert_eq!(entities.alive(c), Some(true));
 
-    pub(crate) fn chunk_index(&self) -> usize {
-        self.index / CHUNK_SIZE
+        // 恢复之后会再次分配出c
+        entities.alloc();
+        entities.restore(&allocator);
+        assert_eq!(entities.alive(c), Some(false));
+        assert_eq!(entities.get(b), Some(location));
+        assert_eq!(entities.alloc(), c);
+        assert_eq!(entities.alive(Entity::new(ALIVE_TAG, 2)), None);
     }
 }
@@ -1,6 +1,4 @@
-use crate::{bundle::Components, storage::ALIVE_TAG};
-
-use super::Chunk;
+use super::{Chunk, ChunkRow};
 
 #[derive(Debug, Clone)]
 pub(crate) struct ChunkIter<'a> {
@@ -20,7 +18,7 @@ impl ChunkIter<'_> {
 }
 
 impl<'a> Iterator for ChunkIter<'a> {
-    type Item = &'a Components;
+    type Item = ChunkRow<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if !self.first {
@@ -28,9 +26,9 @@ impl<'a> Iterator for ChunkIter<'a> {
         } else {
             self.first = false;
         }
-        while self.chunk.alive.get(self.index).copied()? < ALIVE_TAG {
+        while self.chunk.entities.get(self.index)?.is_none() {
             self.index += 1;
         }
-        Some(&self.chunk.bundles[self.index])
+        Some(self.chunk.row(self.index))
     }
 }
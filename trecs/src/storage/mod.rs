@@ -1,11 +1,19 @@
+mod column;
 mod entity;
 mod iter;
+mod sparse;
+mod tick;
 
+pub(crate) use column::ComponentVTable;
 pub use entity::Entity;
+pub(crate) use entity::{Entities, EntityAllocator, EntityLocation};
 pub(crate) use iter::ChunkIter;
+pub(crate) use sparse::{SparseSet, SparseSets};
+pub use tick::ComponentTicks;
 
-use crate::bundle::{Bundle, Components};
-use std::{any::TypeId, fmt::Debug};
+use crate::bundle::{Bundle, BundleId, ComponentId, Components};
+use column::Column;
+use std::{any::Any, fmt::Debug};
 
 /// 一个[Chunk]的大小
 ///
@@ -27,110 +35,203 @@ pub const ALIVE_TAG: usize = 1 << 15;
 ///
 /// + 放入[Bundle]
 /// + 复用空间,减少内存分配
-/// + 生成[Entity],计算[Entity]有效性
+/// + 记录每个位置上的[Entity]
 ///
+/// 每种[Component]连续地存放在各自的[Column]中,
+/// 访问时直接得到指向值的指针,而不需要逐个经过[Box<dyn Any>]
+///
+/// [Component]: crate::bundle::Component
 pub(crate) struct Chunk {
-    /// 实际存放[Bundle]的[Vec]
-    ///
-    /// 长度为[CHUNK_SIZE]
-    bundles: Vec<Components>,
-    /// 储存对应下标下的[Entity]的信息
+    /// 与[Bundle]中的[Component]一一对应,实际存放[Component]的[Column]
     ///
-    /// 第一位表示[Entity]是否"存活",
-    /// 避免在available中进行查找
+    /// 通过指针访问,以便在只有不可变引用时修改[Component]
     ///
-    /// 其余位表示这个位置的使用次数
+    /// [Component]: crate::bundle::Component
+    columns: Vec<Column>,
+    /// 每一行存放的[Entity]
     ///
-    /// 如果用来访问的[Entity]和这里存储的不对应,
-    /// 说明用来访问的[Entity]是这个位置之前存放的[Bundle]对应的[Entity],
-    /// 那么访问的[Entity]是一个失效的[Entity]
-    alive: Vec<usize>,
+    /// 为[None]时说明这个位置是空闲的,长度不超过[CHUNK_SIZE]
+    entities: Vec<Option<Entity>>,
     /// 空闲的位置
     removed: Vec<usize>,
     /// 区块的编号
-    ///
-    /// 以便直接生成[Entity]
     index: usize,
-    /// 内部存储的[Bundle]的[TypeId]
+    /// 内部存储的[Bundle]的[BundleId]
     ///
     /// 用于直接使用[WorldFetch]
     ///
     /// [WorldFetch]:crate
-    bundle_id: TypeId,
+    bundle_id: BundleId,
 }
 
 impl Chunk {
-    pub fn new<B: Bundle>(idx: usize) -> Self {
+    /// vtables与[Bundle]中的[Component]一一对应
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn new(idx: usize, bundle_id: BundleId, vtables: &[&'static ComponentVTable]) -> Self {
         Self {
-            bundles: Vec::with_capacity(CHUNK_SIZE),
-            alive: Vec::with_capacity(CHUNK_SIZE),
+            columns: vtables.iter().copied().map(Column::new).collect(),
+            entities: Vec::with_capacity(CHUNK_SIZE),
             removed: vec![],
             index: idx,
-            bundle_id: B::type_id_(),
+            bundle_id,
         }
     }
 
     /// 尝试在[Chunk]中插入一个[Bundle]
     ///
-    /// + 成功则返回对应的[Entity]
+    /// + 成功则返回[Bundle]在[Chunk]中的位置
     ///
     /// + 失败则原路返回[Bundle]
-    pub fn insert<B: Bundle>(&mut self, b: B) -> Result<Entity, B> {
-        if self.bundles.len() != CHUNK_SIZE {
-            self.bundles.push(b.destory());
-            self.alive.push(ALIVE_TAG);
-            return Ok(Entity::new(
-                ALIVE_TAG,
-                self.index * CHUNK_SIZE + self.bundles.len() - 1,
-            ));
+    ///
+    /// [Bundle]中所有[Component]的[ComponentTicks]都被设为tick
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, b: B, tick: usize) -> Result<usize, B> {
+        if self.free() == 0 {
+            return Err(b);
         }
+        let ticks = (0..B::components_ids().len())
+            .map(|_| ComponentTicks::new(tick))
+            .collect();
+        Ok(self.push_bundle(entity, b, ticks))
+    }
 
-        match self.removed.pop() {
-            Some(slot) => {
-                self.bundles[slot] = b.destory();
-                self.alive[slot] += ALIVE_TAG + 1;
-                Ok(self.gen_entity(slot))
-            }
-            None => Err(b),
+    /// 在[Chunk]中放入与[Chunk]的布局相同的[Bundle]
+    ///
+    /// [Component]被直接移动到各自的[Column]中,
+    /// 返回在[Chunk]中的位置,调用者需要保证[Chunk]没有被填满
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn push_bundle<B: Bundle>(
+        &mut self,
+        entity: Entity,
+        b: B,
+        ticks: Vec<ComponentTicks>,
+    ) -> usize {
+        debug_assert_eq!(B::components_ids().len(), self.columns.len());
+        let row = self.alloc(entity, ticks);
+        // row处是空闲的,布局与B相同
+        unsafe { b.write_to(0, &mut |index| self.columns[index].ptr(row)) };
+        row
+    }
+
+    /// 在[Chunk]中放入已经按照布局排列好的[Components]
+    ///
+    /// [Components]被移动到各自的[Column]中,
+    /// 返回在[Chunk]中的位置,调用者需要保证[Chunk]没有被填满
+    pub fn push(
+        &mut self,
+        entity: Entity,
+        components: Components,
+        ticks: Vec<ComponentTicks>,
+    ) -> usize {
+        debug_assert_eq!(components.len(), self.columns.len());
+        let row = self.alloc(entity, ticks);
+        for (column, component) in self.columns.iter_mut().zip(components) {
+            // row处是空闲的
+            unsafe { column.write(row, component) };
         }
+        row
     }
 
-    /// 从[Chunk]中删除[Entity]对应的[Bundle]
+    /// 为[Entity]分配一个空闲的位置,并保证每个[Column]都可以存放
+    ///
+    /// ticks与[Column]一一对应,被放入各自的[Column]中
     ///
-    /// 返回[Entity]对应的[Bundle]是否存在
+    /// 之后调用者需要通过[Chunk::ptr]写入每个[Component],调用者需要保证[Chunk]没有被填满
     ///
-    pub fn remove(&mut self, entity: Entity) -> bool {
-        if self.alive(entity).is_none() {
-            return false;
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn alloc(&mut self, entity: Entity, ticks: Vec<ComponentTicks>) -> usize {
+        debug_assert_eq!(ticks.len(), self.columns.len());
+        let row = if self.entities.len() != CHUNK_SIZE {
+            self.entities.push(Some(entity));
+            self.entities.len() - 1
+        } else {
+            let row = self
+                .removed
+                .pop()
+                .unwrap_or_else(|| unreachable!("Chunk已经被填满了"));
+            self.entities[row] = Some(entity);
+            row
+        };
+        for (column, ticks) in self.columns.iter_mut().zip(ticks) {
+            column.reserve(row + 1);
+            column.set_ticks(row, ticks);
         }
-        let index = entity.index_in_chunk();
-        // 不能remove否则下标会混乱
-        self.bundles[index].clear();
-        self.alive[index] -= ALIVE_TAG;
-        self.removed.push(index);
-        true
+        row
     }
 
-    /// 计算Entity是否有效
+    /// row处第index个[Component]的指针
     ///
-    /// + 返回[Some(bool)]时,[bool]表示[Entity]是否有效
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn ptr(&self, index: usize, row: usize) -> *mut u8 {
+        self.columns[index].ptr(row)
+    }
+
+    /// 把row处第index个[Component]按字节复制到dst
     ///
-    /// + 返回[None]时,表示[Entity]对应的[Bundle]并不存在
-    pub fn alive(&self, entity: Entity) -> Option<bool> {
-        if entity.index / CHUNK_SIZE != self.index {
-            return None;
-        }
-        let index = entity.index % CHUNK_SIZE;
-        Some(*self.alive.get(index)? == entity.generator)
+    /// # Safety
+    ///
+    /// dst必须是同类[Component]的空闲位置,之后row处的这个[Component]视为已经被移走
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) unsafe fn move_to(&self, index: usize, row: usize, dst: *mut u8) {
+        self.columns[index].move_to(row, dst)
+    }
+
+    /// drop row处第index个[Component],之后这个位置是空闲的
+    ///
+    /// # Safety
+    ///
+    /// row处必须存放着有效的[Component]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) unsafe fn drop_at(&mut self, index: usize, row: usize) {
+        self.columns[index].drop_at(row)
+    }
+
+    /// 释放row处的位置,返回每个[Column]中对应的[ComponentTicks]
+    ///
+    /// 不会读取或者drop其中的[Component],调用者需要保证它们已经被移走或者drop
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn free_row(&mut self, row: usize) -> Vec<ComponentTicks> {
+        debug_assert!(self.entities[row].is_some());
+        self.entities[row] = None;
+        self.removed.push(row);
+        self.columns
+            .iter_mut()
+            .map(|column| column.take_ticks(row))
+            .collect()
+    }
+
+    /// 从[Chunk]中移出row处的[Components]和对应的[ComponentTicks]
+    ///
+    /// 调用者需要保证row处存放着有效的[Entity]
+    pub fn remove(&mut self, row: usize) -> (Components, Vec<ComponentTicks>) {
+        // 不能remove否则下标会混乱
+        let components = self
+            .columns
+            .iter_mut()
+            .map(|column| unsafe { column.read(row) })
+            .collect();
+        (components, self.free_row(row))
+    }
+
+    /// 存活的[Bundle]的数量
+    pub fn len(&self) -> usize {
+        self.entities.len() - self.removed.len()
     }
 
     /// 空闲空间的长度
     pub fn free(&self) -> usize {
-        CHUNK_SIZE - self.bundles.len() + self.removed.len()
+        CHUNK_SIZE - self.entities.len() + self.removed.len()
     }
 
-    pub fn gen_entity(&self, index: usize) -> Entity {
-        Entity::new(self.alive[index], self.index * CHUNK_SIZE + index)
+    /// 存放在row处的[Entity]
+    pub fn entity(&self, row: usize) -> Entity {
+        self.entities[row].unwrap_or_else(|| unreachable!("只会访问有效的位置"))
     }
 
     /// 仅仅做引用的迭代器
@@ -138,31 +239,153 @@ impl Chunk {
         ChunkIter::new(self)
     }
 
-    /// 移出所有有效的[Components]
+    /// row处的[Entity]的所有[Component]
     ///
-    /// 清理用
-    pub fn clear(&mut self, clearer: &dyn Fn(Components)) {
-        self.bundles
-            .iter_mut()
-            .enumerate()
-            .filter(|(id, ..)| self.alive[*id] > ALIVE_TAG)
-            .for_each(|(.., cs)| {
-                let mut target = Vec::with_capacity(cs.len());
-                target.append(cs);
-                clearer(target);
-            });
+    /// 调用者需要保证row处存放着有效的[Entity]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn row(&self, row: usize) -> ChunkRow<'_> {
+        debug_assert!(self.entities[row].is_some());
+        ChunkRow {
+            chunk: self,
+            row,
+            sparse: None,
+        }
     }
 
-    /// 直接获取[Entity]对应的[Components]
-    pub unsafe fn get(&self, index: usize) -> &Components {
-        &self.bundles[index]
+    /// row处第index个[Component]的[ComponentTicks]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn ticks(&self, index: usize, row: usize) -> &ComponentTicks {
+        self.columns[index].ticks(row)
     }
 
-    pub fn bundle_id(&self) -> TypeId {
+    pub fn bundle_id(&self) -> BundleId {
         self.bundle_id
     }
 }
 
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        for (row, entity) in self.entities.iter().enumerate() {
+            if entity.is_some() {
+                for column in &mut self.columns {
+                    unsafe { column.drop_at(row) };
+                }
+            }
+        }
+    }
+}
+
+/// [Chunk]中一个有效的位置,即一个[Entity]的所有[Component]
+///
+/// 由[WorldFetch::build]使用
+///
+/// [Component]: crate::bundle::Component
+/// [WorldFetch::build]: crate::tools::WorldFetch::build
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkRow<'a> {
+    chunk: &'a Chunk,
+    row: usize,
+    /// 通过[ChunkRow::with_sparse]指定时,可以访问[Entity]的[StorageType::SparseSet]的[Component]
+    ///
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    /// [Component]: crate::bundle::Component
+    sparse: Option<&'a SparseSets>,
+}
+
+impl<'a> ChunkRow<'a> {
+    /// 同时访问[World]中的[SparseSet]
+    ///
+    /// [World]: crate::World
+    pub(crate) fn with_sparse(self, sparse: &'a SparseSets) -> Self {
+        Self {
+            sparse: Some(sparse),
+            ..self
+        }
+    }
+
+    /// [Entity]的[StorageType::SparseSet]的[Component]及其[ComponentTicks],
+    /// 没有这个[Component],或者没有通过[ChunkRow::with_sparse]指定时为[None]
+    ///
+    /// 通过返回的指针访问时,调用者需要保证没有冲突的访问
+    ///
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn sparse(&self, id: &ComponentId) -> Option<(*mut dyn Any, &'a ComponentTicks)> {
+        self.sparse?.get(id)?.get(self.entity())
+    }
+
+    /// 存放在这里的[Entity]
+    pub fn entity(&self) -> Entity {
+        self.chunk.entity(self.row)
+    }
+
+    /// 第index个[Component]的[ComponentTicks]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn ticks(&self, index: usize) -> &'a ComponentTicks {
+        self.chunk.ticks(index, self.row)
+    }
+
+    /// 第index个[Component]的指针
+    ///
+    /// 不会创建对其他[Component]的引用,
+    /// 所以同时访问不同的[Component]不会发生别名冲突
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn ptr(&self, index: usize) -> *mut u8 {
+        self.chunk.columns[index].ptr(self.row)
+    }
+
+    /// 第index个[Component]
+    ///
+    /// # Safety
+    ///
+    /// 同一时间不能有对这个[Component]的可变访问
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) unsafe fn get(&self, index: usize) -> &'a dyn Any {
+        &*self.chunk.columns[index].get(self.row)
+    }
+
+    /// 第index个[Component]的可变引用
+    ///
+    /// # Safety
+    ///
+    /// 同一时间不能有对这个[Component]的其他访问
+    ///
+    /// [Component]: crate::bundle::Component
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn get_mut(&self, index: usize) -> &'a mut dyn Any {
+        &mut *self.chunk.columns[index].get(self.row)
+    }
+
+    /// 按照布局依次访问所有的[Component]
+    ///
+    /// # Safety
+    ///
+    /// 同一时间不能有对这些[Component]的可变访问
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) unsafe fn components(&self) -> impl Iterator<Item = &'a dyn Any> + 'a {
+        let row = *self;
+        (0..self.chunk.columns.len()).map(move |index| row.get(index))
+    }
+}
+
+impl Debug for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Chunk")
+            // 因为Component  不能 Debug
+            .field("columns", &self.columns)
+            .field("entities", &self.entities)
+            .field("removed", &self.removed)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,37 +394,97 @@ mod tests {
     fn insert_remove() {
         // hso
         // 这个（）是不是代表着什么呢？
-        let mut chunk = Chunk::new::<()>(0);
+        let mut chunk = Chunk::new(0, BundleId::of::<i32>(), &[ComponentVTable::of::<i32>()]);
+        let entity = |idx| Entity::new(ALIVE_TAG, idx);
 
         // 先插进去两个
-        assert_eq!(chunk.insert(123), Ok(Entity::new(ALIVE_TAG, 0)));
-        assert_eq!(chunk.insert(456), Ok(Entity::new(ALIVE_TAG, 1)));
+        assert_eq!(chunk.insert(entity(0), 123, 0), Ok(0));
+        assert_eq!(chunk.insert(entity(1), 456, 0), Ok(1));
 
         // 用一些东西填满她剩下的全部空间
         for idx in 2..CHUNK_SIZE {
-            assert_eq!(chunk.insert(0), Ok(Entity::new(ALIVE_TAG, idx)))
+            assert_eq!(chunk.insert(entity(idx), 0, 0), Ok(idx))
         }
 
         // 一点也插不进去了,已经彻底被填满了~
-        assert_eq!(chunk.insert(123456), Err(123456));
+        assert_eq!(chunk.insert(entity(CHUNK_SIZE), 123456, 0), Err(123456));
 
         // 拔出来一个
-        assert!(chunk.remove(Entity::new(ALIVE_TAG, 1)));
+        let (components, ..) = chunk.remove(1);
+        assert_eq!(components[0].downcast_ref(), Some(&456));
 
         // 换成更大的,再插进去
-        assert_eq!(chunk.insert(114514), Ok(Entity::new(ALIVE_TAG + 1, 1)))
+        assert_eq!(chunk.insert(entity(CHUNK_SIZE), 114514, 0), Ok(1));
+        assert_eq!(chunk.entity(1), entity(CHUNK_SIZE));
     }
-}
 
-impl Debug for Chunk {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Chunk")
-            // 因为Component  不能 Debug
-            // .field("bundles", &self.bundles)
-            .field("bundles", &"...")
-            .field("alive", &self.alive)
-            .field("removed", &self.removed)
-            .field("index", &self.index)
-            .finish()
+    #[test]
+    fn ticks() {
+        let vtables = [ComponentVTable::of::<u8>(), ComponentVTable::of::<()>()];
+        let mut chunk = Chunk::new(0, BundleId::of::<(u8, ())>(), &vtables);
+        let entity = |idx| Entity::new(ALIVE_TAG, idx);
+        assert_eq!(chunk.insert(entity(0), (0u8, ()), 1), Ok(0));
+        assert_eq!(chunk.insert(entity(1), (1u8, ()), 2), Ok(1));
+        chunk.ticks(1, 0).set_changed(3);
+
+        let (_, ticks) = chunk.remove(0);
+        assert_eq!(
+            ticks.iter().map(|t| t.changed()).collect::<Vec<_>>(),
+            [1, 3]
+        );
+        // 每个Column各自存放新位置的ComponentTicks
+        let ticks = vec![ComponentTicks::new(4), ComponentTicks::new(5)];
+        assert_eq!(chunk.alloc(entity(2), ticks), 2);
+        unsafe { chunk.columns[0].write(2, Box::new(2u8)) };
+        assert_eq!(
+            (chunk.ticks(0, 2).added(), chunk.ticks(1, 2).added()),
+            (4, 5)
+        );
+        assert_eq!(chunk.row(1).ticks(0).added(), 2);
+    }
+
+    #[test]
+    fn columns() {
+        use crate::bundle::Component;
+        use std::{any::TypeId, sync::Arc};
+
+        struct Marker;
+        impl Component for Marker {
+            fn type_id_() -> TypeId {
+                TypeId::of::<Self>()
+            }
+        }
+        struct Counted(#[allow(dead_code)] Arc<()>);
+        impl Component for Counted {
+            fn type_id_() -> TypeId {
+                TypeId::of::<Self>()
+            }
+        }
+
+        let vtables = [
+            ComponentVTable::of::<u64>(),
+            ComponentVTable::of::<Marker>(),
+            ComponentVTable::of::<Counted>(),
+        ];
+        let mut chunk = Chunk::new(0, BundleId::of::<(u64, Marker, Counted)>(), &vtables);
+        let entity = |idx| Entity::new(ALIVE_TAG, idx);
+        let rc = Arc::new(());
+        for idx in 0..8 {
+            let bundle = (idx as u64, Marker, Counted(rc.clone()));
+            assert!(chunk.insert(entity(idx), bundle, 0).is_ok());
+        }
+
+        // 同一种Component连续存放
+        let (first, second) = (chunk.row(0), chunk.row(1));
+        assert_eq!(second.ptr(0) as usize - first.ptr(0) as usize, 8);
+        assert_eq!(unsafe { second.get(0) }.downcast_ref(), Some(&1u64));
+        assert!(unsafe { second.get(1) }.is::<Marker>());
+
+        let (components, ..) = chunk.remove(3);
+        assert_eq!(components[0].downcast_ref(), Some(&3u64));
+        drop(components);
+        assert_eq!(Arc::strong_count(&rc), 8);
+        drop(chunk);
+        assert_eq!(Arc::strong_count(&rc), 1);
     }
 }
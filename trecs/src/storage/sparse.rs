@@ -0,0 +1,172 @@
+use std::{any::Any, collections::HashMap};
+
+use super::{column::Column, ComponentTicks, ComponentVTable, Entity};
+use crate::bundle::ComponentId;
+
+/// [World]中所有的[SparseSet]
+///
+/// [World]: crate::World
+pub(crate) type SparseSets = HashMap<ComponentId, SparseSet>;
+
+/// 单独存放一种[StorageType::SparseSet]的[Component]
+///
+/// 以[Entity]的下标为索引,添加和移除都不需要移动[Entity]的其他[Component]
+///
+/// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+/// [Component]: crate::bundle::Component
+#[derive(Debug)]
+pub(crate) struct SparseSet {
+    /// 以[Entity]的下标为下标,值为在dense中的下标
+    sparse: Vec<Option<usize>>,
+    /// 紧密排列的[Component],与entities和ticks一一对应
+    dense: Column,
+    entities: Vec<Entity>,
+    ticks: Vec<ComponentTicks>,
+}
+
+impl SparseSet {
+    pub fn new(vtable: &'static ComponentVTable) -> Self {
+        Self {
+            sparse: vec![],
+            dense: Column::new(vtable),
+            entities: vec![],
+            ticks: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let index = (*self.sparse.get(entity.index)?)?;
+        (self.entities[index] == entity).then_some(index)
+    }
+
+    /// 放入entity的[Component],返回被替换的旧值
+    ///
+    /// 替换时会被标记为在tick时修改过
+    ///
+    /// component必须是此[SparseSet]存放的类型
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn insert(
+        &mut self,
+        entity: Entity,
+        component: Box<dyn Any>,
+        tick: usize,
+    ) -> Option<Box<dyn Any>> {
+        if let Some(index) = self.dense_index(entity) {
+            // index处有值
+            let old = unsafe { self.dense.read(index) };
+            unsafe { self.dense.write(index, component) };
+            self.ticks[index].set_changed(tick);
+            return Some(old);
+        }
+        // 同一个下标上失效的Entity留下的值
+        if let Some(Some(index)) = self.sparse.get(entity.index) {
+            let stale = self.entities[*index];
+            self.remove(stale);
+        }
+        let index = self.len();
+        self.dense.reserve(index + 1);
+        unsafe { self.dense.write(index, component) };
+        self.entities.push(entity);
+        self.ticks.push(ComponentTicks::new(tick));
+        if self.sparse.len() <= entity.index {
+            self.sparse.resize(entity.index + 1, None);
+        }
+        self.sparse[entity.index] = Some(index);
+        None
+    }
+
+    /// 移出entity的[Component],最后一个值会被移动到空出的位置
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn remove(&mut self, entity: Entity) -> Option<Box<dyn Any>> {
+        let index = self.dense_index(entity)?;
+        let last = self.len() - 1;
+        let component = unsafe { self.dense.read(index) };
+        if index != last {
+            unsafe { self.dense.relocate(last, index) };
+            self.sparse[self.entities[last].index] = Some(index);
+        }
+        self.entities.swap_remove(index);
+        self.ticks.swap_remove(index);
+        self.sparse[entity.index] = None;
+        Some(component)
+    }
+
+    /// entity的[Component]及其[ComponentTicks]
+    ///
+    /// 通过返回的指针访问时,调用者需要保证没有冲突的访问
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn get(&self, entity: Entity) -> Option<(*mut dyn Any, &ComponentTicks)> {
+        let index = self.dense_index(entity)?;
+        Some((unsafe { self.dense.get(index) }, &self.ticks[index]))
+    }
+
+    /// 所有的[Entity]和它们的[Component],见[SparseSet::get]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, *mut dyn Any, &ComponentTicks)> + '_ {
+        self.entities
+            .iter()
+            .zip(&self.ticks)
+            .enumerate()
+            .map(|(index, (entity, ticks))| (*entity, unsafe { self.dense.get(index) }, ticks))
+    }
+}
+
+impl Drop for SparseSet {
+    fn drop(&mut self) {
+        for index in 0..self.len() {
+            unsafe { self.dense.drop_at(index) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn sparse_set() {
+        let counter = Arc::new(());
+        let vtable = ComponentVTable::of::<Arc<()>>();
+        let mut set = SparseSet::new(vtable);
+        let a = Entity::new(0, 3);
+        let b = Entity::new(0, 0);
+        let c = Entity::new(0, 7);
+        for entity in [a, b, c] {
+            assert!(set.insert(entity, Box::new(counter.clone()), 1).is_none());
+        }
+        assert_eq!(Arc::strong_count(&counter), 4);
+
+        // 替换会返回旧值并标记修改
+        let old = set.insert(b, Box::new(counter.clone()), 5).unwrap();
+        drop(old);
+        assert_eq!(set.get(b).unwrap().1.changed(), 5);
+
+        // 移除后最后一个值被移动到空出的位置
+        assert!(set.remove(a).is_some());
+        assert!(set.remove(a).is_none());
+        assert_eq!(set.len(), 2);
+        let ptr = set.get(c).unwrap().0;
+        assert!(unsafe { &*ptr }.downcast_ref::<Arc<()>>().is_some());
+        assert_eq!(set.iter().map(|(e, ..)| e).collect::<Vec<_>>(), [c, b]);
+
+        // 复用下标的新Entity不会访问到旧的值
+        let reused = Entity::new(1, 0);
+        assert!(set.get(reused).is_none());
+        assert!(set.insert(reused, Box::new(counter.clone()), 6).is_none());
+        assert!(set.get(b).is_none());
+        assert_eq!(set.len(), 2);
+
+        drop(set);
+        assert_eq!(Arc::strong_count(&counter), 1);
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// [Component]被添加,以及最后一次被修改时的tick
+///
+/// 资源也使用它记录被插入,以及最后一次被修改时的tick
+///
+/// 每个[System]执行时[World]的tick都会增加,
+/// 通过比较tick和[System]上一次执行时的tick,就能知道[Component]是否是新的
+///
+/// 通过[Mut]修改[Component]时就会被看作修改过
+///
+/// 并行执行的[System]可能同时读写同一个tick,所以使用原子类型
+///
+/// [Component]: crate::bundle::Component
+/// [System]: crate::system::System
+/// [World]: crate::World
+/// [Mut]: crate::tools::Mut
+#[derive(Debug, Default)]
+pub struct ComponentTicks {
+    added: AtomicUsize,
+    changed: AtomicUsize,
+}
+
+impl ComponentTicks {
+    pub fn new(tick: usize) -> Self {
+        Self {
+            added: AtomicUsize::new(tick),
+            changed: AtomicUsize::new(tick),
+        }
+    }
+
+    /// 被添加时的tick
+    pub fn added(&self) -> usize {
+        self.added.load(Ordering::Relaxed)
+    }
+
+    /// 最后一次被修改时的tick
+    pub fn changed(&self) -> usize {
+        self.changed.load(Ordering::Relaxed)
+    }
+
+    /// 是否在last_run之后被添加
+    pub fn is_added(&self, last_run: usize) -> bool {
+        self.added() > last_run
+    }
+
+    /// 是否在last_run之后被修改,被添加也算作修改
+    pub fn is_changed(&self, last_run: usize) -> bool {
+        self.changed() > last_run
+    }
+
+    pub(crate) fn set_changed(&self, tick: usize) {
+        self.changed.store(tick, Ordering::Relaxed);
+    }
+
+    /// 被重新添加,如资源被插入时
+    pub(crate) fn set_added(&self, tick: usize) {
+        self.added.store(tick, Ordering::Relaxed);
+        self.changed.store(tick, Ordering::Relaxed);
+    }
+}
+
+impl Clone for ComponentTicks {
+    fn clone(&self) -> Self {
+        Self {
+            added: AtomicUsize::new(self.added()),
+            changed: AtomicUsize::new(self.changed()),
+        }
+    }
+}
+
+impl PartialEq for ComponentTicks {
+    fn eq(&self, other: &Self) -> bool {
+        self.added() == other.added() && self.changed() == other.changed()
+    }
+}
+
+impl Eq for ComponentTicks {}
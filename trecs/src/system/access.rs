@@ -0,0 +1,211 @@
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    thread,
+};
+
+use super::{state::SystemState, SystemParm};
+use crate::world::{UnsafeWorldCell, World};
+
+/// 一个等待执行的访问
+type Access = Box<dyn FnOnce(&mut World) + Send>;
+
+/// 异步[System]请求的,还没有执行的访问
+///
+/// 存放在[World]中,由执行[System]的一方在[Future]等待时执行
+///
+/// [System]: crate::system::System
+#[derive(Default, Clone)]
+pub(crate) struct AccessQueue {
+    inner: Arc<Mutex<Vec<Access>>>,
+}
+
+impl AccessQueue {
+    fn push(&self, access: Access) {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .push(access);
+    }
+
+    fn take(&self) -> Vec<Access> {
+        std::mem::take(
+            &mut *self
+                .inner
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner()),
+        )
+    }
+}
+
+impl World {
+    /// 按顺序执行所有通过[WorldAccess]请求的访问,并执行其中推迟的操作
+    ///
+    /// 返回是否执行了访问
+    pub(crate) fn apply_access(&mut self) -> bool {
+        let queue = self.access_queue.take();
+        let applied = !queue.is_empty();
+        for access in queue {
+            access(self);
+        }
+        applied
+    }
+}
+
+/// 异步[System]在await之后访问[World]的参数
+///
+/// 其他参数只能在[System]同步的部分,即就是返回[Future]之前使用,
+/// 返回的[Future]必须是`'static`的,所以无法跨越await持有它们
+///
+/// [WorldAccess::run]把操作交给执行[System]的一方,在所有[System]都不借用[World]时执行,
+/// 每次调用都会重新获得对[World]的独占访问
+///
+/// ``` rust
+/// use trecs::{system::WorldAccess, tools::Command, world::Query, World};
+///
+/// async fn load() -> i32 {
+///     42
+/// }
+///
+/// fn spawn_loaded(access: WorldAccess) -> impl std::future::Future<Output = ()> {
+///     async move {
+///         let value = load().await;
+///         let count = access
+///             .run(move |world| {
+///                 world.spawn(value);
+///                 Query::<&i32>::new(world).into_iter().count()
+///             })
+///             .await;
+///         assert_eq!(count, 1);
+///     }
+/// }
+///
+/// World::new().add_system(spawn_loaded);
+/// ```
+///
+/// [System]: crate::system::System
+pub struct WorldAccess {
+    queue: AccessQueue,
+}
+
+impl WorldAccess {
+    /// 独占地访问[World],返回f的结果
+    ///
+    /// 返回的[Future]第一次被poll时才请求访问,f中通过[Commands]推迟的操作会在f之后执行
+    ///
+    /// [Commands]: crate::world::Commands
+    pub fn run<R, F>(&self, f: F) -> impl Future<Output = R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut World) -> R + Send + 'static,
+    {
+        RunAccess {
+            queue: self.queue.clone(),
+            f: Some(f),
+            result: Default::default(),
+        }
+    }
+}
+
+impl SystemParm for WorldAccess {
+    type Item<'w> = WorldAccess;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        WorldAccess {
+            queue: world.access_queue().clone(),
+        }
+    }
+
+    fn init(_state: &mut SystemState) {
+        // 访问在其他System不借用World时才执行,不会和任何参数冲突
+    }
+}
+
+/// 见[WorldAccess::run]
+struct RunAccess<F, R> {
+    queue: AccessQueue,
+    f: Option<F>,
+    /// 访问中发生的panic会在poll时继续,这样panic就算作发出请求的[System]的
+    result: Arc<Mutex<Option<thread::Result<R>>>>,
+}
+
+// 不会把Pin投射到任何字段上
+impl<F, R> Unpin for RunAccess<F, R> {}
+
+impl<F, R> Future for RunAccess<F, R>
+where
+    R: Send + 'static,
+    F: FnOnce(&mut World) -> R + Send + 'static,
+{
+    type Output = R;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        let this = self.get_mut();
+        if let Some(result) = this.result.lock().unwrap().take() {
+            return Poll::Ready(result.unwrap_or_else(|payload| panic::resume_unwind(payload)));
+        }
+        if let Some(f) = this.f.take() {
+            let result = this.result.clone();
+            let waker = cx.waker().clone();
+            this.queue.push(Box::new(move |world| {
+                let output = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let output = f(world);
+                    world.apply_commands();
+                    output
+                }));
+                if output.is_err() {
+                    world.discard_commands();
+                }
+                *result.lock().unwrap() = Some(output);
+                waker.wake();
+            }));
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        system::{runtime::block_on, yield_now, Executor},
+        tools::ResManager,
+        world::{Commands, Query},
+    };
+
+    fn counter(access: WorldAccess, mut commands: Commands) -> impl Future<Output = ()> {
+        commands.spawn(0u32);
+        async move {
+            for _ in 0..3 {
+                yield_now().await;
+                // 每次访问都能看到其他System在await期间做出的修改
+                access
+                    .run(|world| {
+                        let count = Query::<&u32>::new(world).into_iter().count() as u32;
+                        *world.get_res::<u32>().into_or_init(|| 0) += count;
+                    })
+                    .await;
+            }
+        }
+    }
+
+    #[test]
+    fn access() {
+        // 同时等待时,两个System同步的部分都在第一次访问之前执行
+        for (executor, sum) in [
+            (Executor::SingleThreaded, 3 + 6),
+            (Executor::Concurrent, 6 + 6),
+        ] {
+            let mut world = World::new();
+            world
+                .set_executor(executor)
+                .add_system(counter)
+                .add_system(counter);
+            block_on(world.run_once());
+            assert_eq!(world.resource::<u32>(), Some(&sum));
+        }
+    }
+}
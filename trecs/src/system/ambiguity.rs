@@ -0,0 +1,181 @@
+use std::fmt::Display;
+
+/// 两个[System]同时运行时会发生冲突的访问
+///
+/// [System]: crate::system::System
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Conflict {
+    /// 至少有一方可变地访问了这个[Component],值为类型名
+    ///
+    /// [Component]: crate::bundle::Component
+    Component(&'static str),
+    /// 至少有一方通过[ResMut]修改了这个资源,值为类型名
+    ///
+    /// [ResMut]: crate::world::ResMut
+    Resource(&'static str),
+    /// 至少有一方使用了[Resources]
+    ///
+    /// [Resources]: crate::world::Resources
+    Resources,
+    /// 至少有一方直接访问了整个[World]
+    ///
+    /// [World]: crate::World
+    World,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Conflict::Component(name) => write!(f, "组件 {name}"),
+            Conflict::Resource(name) => write!(f, "资源 {name}"),
+            Conflict::Resources => write!(f, "Resources"),
+            Conflict::World => write!(f, "World"),
+        }
+    }
+}
+
+/// 访问冲突,并且没有确定先后顺序的两个[System]
+///
+/// [System]: crate::system::System
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    /// 两个[System]所在的阶段,如"startup","update"
+    ///
+    /// [System]: crate::system::System
+    pub stage: &'static str,
+    /// 两个[System]的类型名
+    ///
+    /// [System]: crate::system::System
+    pub systems: (&'static str, &'static str),
+    /// 发生冲突的所有访问
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Display for Ambiguity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] {} 与 {} 在 ",
+            self.stage, self.systems.0, self.systems.1
+        )?;
+        for (index, conflict) in self.conflicts.iter().enumerate() {
+            if index != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{conflict}")?;
+        }
+        write!(f, " 上发生冲突")
+    }
+}
+
+/// [World]中所有的[Ambiguity]
+///
+/// 通过[World::ambiguities]获得
+///
+/// ``` rust
+/// let world = trecs::World::new();
+/// assert!(world.ambiguities().is_empty(), "{}", world.ambiguities());
+/// ```
+///
+/// [World]: crate::World
+/// [World::ambiguities]: crate::World::ambiguities
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AmbiguityReport {
+    pub(crate) ambiguities: Vec<Ambiguity>,
+}
+
+impl AmbiguityReport {
+    pub fn is_empty(&self) -> bool {
+        self.ambiguities.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ambiguities.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Ambiguity> {
+        self.ambiguities.iter()
+    }
+
+    /// 涉及了名为system的[System]的[Ambiguity]
+    ///
+    /// [System]: crate::system::System
+    pub fn involving<'a>(&'a self, system: &'a str) -> impl Iterator<Item = &'a Ambiguity> {
+        self.iter()
+            .filter(move |ambiguity| ambiguity.systems.0 == system || ambiguity.systems.1 == system)
+    }
+}
+
+impl Display for AmbiguityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for ambiguity in &self.ambiguities {
+            writeln!(f, "{ambiguity}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        world::{Commands, Query, Res, ResMut},
+        World,
+    };
+    use std::any::type_name_of_val;
+
+    #[test]
+    fn report() {
+        fn read(_: Query<&i32>) {}
+        fn write(_: Query<(&mut i32, &usize)>, _: ResMut<String>) {}
+        fn read_usize(_: Query<&usize>, _: Res<String>) {}
+        fn spawn(_: Commands) {}
+        fn nothing() {}
+
+        let mut world = World::new();
+        world
+            .add_system(read)
+            .add_system(write)
+            .add_system(read_usize)
+            .add_startup_system(spawn)
+            .add_startup_system(nothing);
+
+        let report = world.ambiguities();
+        assert_eq!(report.len(), 2);
+        assert_eq!(
+            report.involving(type_name_of_val(&read)).next().unwrap(),
+            &Ambiguity {
+                stage: "update",
+                systems: (type_name_of_val(&read), type_name_of_val(&write)),
+                conflicts: vec![Conflict::Component("i32")],
+            }
+        );
+        assert_eq!(
+            report
+                .involving(type_name_of_val(&read_usize))
+                .next()
+                .unwrap()
+                .conflicts,
+            vec![Conflict::Resource("alloc::string::String")],
+        );
+        // Commands的操作被推迟到System结束之后,不会与任何System冲突
+        assert_eq!(report.involving(type_name_of_val(&spawn)).count(), 0);
+    }
+
+    #[test]
+    fn ordered() {
+        use crate::system::IntoSystem;
+
+        fn read(_: Query<&i32>) {}
+        fn write(_: Query<&mut i32>) {}
+        fn write_again(_: Query<&mut i32>) {}
+
+        let mut world = World::new();
+        world
+            .add_system(read.label("read"))
+            .add_system(write.label("write").after("read"))
+            .add_system(write_again.after("write"));
+        // 间接的顺序约束也是确定的
+        assert!(world.ambiguities().is_empty(), "{}", world.ambiguities());
+    }
+}
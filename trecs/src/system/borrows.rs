@@ -0,0 +1,231 @@
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use super::state::AliasMap;
+
+/// 一种[Component]正在被借用的情况
+///
+/// [Component]: crate::bundle::Component
+#[derive(Debug, Default)]
+struct Column {
+    /// 不可变借用的计数
+    shared: usize,
+    /// 可变借用的计数,正常情况下不会超过1
+    exclusive: usize,
+    /// 正在借用的[System]
+    ///
+    /// [System]: crate::system::System
+    holders: Vec<&'static str>,
+}
+
+/// 一个正在执行的[System]借用的所有[Component]
+///
+/// [System]: crate::system::System
+/// [Component]: crate::bundle::Component
+#[derive(Debug)]
+struct Frame {
+    system: &'static str,
+    borrows: Vec<(TypeId, bool)>,
+}
+
+#[derive(Debug, Default)]
+struct Borrows {
+    columns: HashMap<TypeId, Column>,
+    /// 正在执行的[System],[System]中可能会再执行其他[System]
+    ///
+    /// [System]: crate::system::System
+    frames: Vec<Frame>,
+}
+
+/// 在运行时记录每种[Component]的借用情况
+///
+/// 只在启用了debug-borrows特性时存在
+///
+/// 用来发现[AliasMap]无法发现的别名冲突,比如:
+/// + 同一个[System]中有两个可变地访问同一种[Component]的[Query]
+/// + 在迭代[Query]时,通过[UnsafeWorldCell]访问了同一种[Component]
+///
+/// [Component]: crate::bundle::Component
+/// [System]: crate::system::System
+/// [Query]: crate::world::Query
+/// [UnsafeWorldCell]: crate::world::UnsafeWorldCell
+#[derive(Debug, Default)]
+pub(crate) struct BorrowTracker {
+    inner: Mutex<Borrows>,
+}
+
+impl BorrowTracker {
+    /// 发生冲突时会在持有锁时panic,所以忽略锁的污染
+    fn lock(&self) -> MutexGuard<'_, Borrows> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 开始执行名为system的[System]
+    ///
+    /// [System]: crate::system::System
+    pub(crate) fn begin(&self, system: &'static str) {
+        self.lock().frames.push(Frame {
+            system,
+            borrows: vec![],
+        });
+    }
+
+    /// 结束执行最近开始的[System],释放它的所有借用
+    ///
+    /// [System]: crate::system::System
+    pub(crate) fn end(&self) {
+        let mut inner = self.lock();
+        let Some(frame) = inner.frames.pop() else {
+            return;
+        };
+        for (id, exclusive) in frame.borrows {
+            Self::release(&mut inner.columns, frame.system, id, exclusive);
+        }
+    }
+
+    /// 正在执行的[System]已经借用的数量,用于[BorrowTracker::release_since]
+    ///
+    /// [System]: crate::system::System
+    pub(crate) fn mark(&self) -> usize {
+        self.lock()
+            .frames
+            .last()
+            .map_or(0, |frame| frame.borrows.len())
+    }
+
+    /// 提前释放正在执行的[System]在[BorrowTracker::mark]之后的借用
+    ///
+    /// 用于[ParamSet],它的成员不会同时存在
+    ///
+    /// [System]: crate::system::System
+    /// [ParamSet]: crate::system::ParamSet
+    pub(crate) fn release_since(&self, mark: usize) {
+        let mut inner = self.lock();
+        let Borrows { columns, frames } = &mut *inner;
+        let Some(frame) = frames.last_mut() else {
+            return;
+        };
+        for (id, exclusive) in frame.borrows.drain(mark.min(frame.borrows.len())..) {
+            Self::release(columns, frame.system, id, exclusive);
+        }
+    }
+
+    fn release(columns: &mut HashMap<TypeId, Column>, system: &str, id: TypeId, exclusive: bool) {
+        let column = columns.get_mut(&id).unwrap();
+        match exclusive {
+            true => column.exclusive -= 1,
+            false => column.shared -= 1,
+        }
+        if let Some(index) = column.holders.iter().position(|h| *h == system) {
+            column.holders.remove(index);
+        }
+    }
+
+    /// 正在执行的[System]借用alias_map中的所有[Component]
+    ///
+    /// 直到[BorrowTracker::end]才会释放
+    ///
+    /// 发生冲突时会panic
+    ///
+    /// [System]: crate::system::System
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn acquire(&self, alias_map: &AliasMap) {
+        let mut inner = self.lock();
+        let Borrows { columns, frames } = &mut *inner;
+        let Some(frame) = frames.last_mut() else {
+            return;
+        };
+        for (id, name, exclusive) in alias_map.iter() {
+            let column = columns.entry(id).or_default();
+            Self::check(column, frame.system, name, exclusive);
+            match exclusive {
+                true => column.exclusive += 1,
+                false => column.shared += 1,
+            }
+            column.holders.push(frame.system);
+            frame.borrows.push((id, exclusive));
+        }
+    }
+
+    /// 检查alias_map中的[Component]是否可以被访问,但是不借用
+    ///
+    /// 用于[World::fetch]这类借用的生命周期无法追踪的访问
+    ///
+    /// [Component]: crate::bundle::Component
+    /// [World::fetch]: crate::tools::Command::fetch
+    pub(crate) fn check_access(&self, alias_map: &AliasMap) {
+        let inner = self.lock();
+        let system = inner
+            .frames
+            .last()
+            .map_or("World::fetch", |frame| frame.system);
+        for (id, name, exclusive) in alias_map.iter() {
+            if let Some(column) = inner.columns.get(&id) {
+                Self::check(column, system, name, exclusive);
+            }
+        }
+    }
+
+    fn check(column: &Column, system: &str, name: &str, exclusive: bool) {
+        if column.exclusive != 0 || (exclusive && column.shared != 0) {
+            let kind = if exclusive { "可变" } else { "不可变" };
+            panic!(
+                "发生别名冲突: {system} 借用{name}的{kind}引用时, {:?} 仍然在借用它",
+                column.holders
+            );
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use crate::{
+        system::SystemParm,
+        tools::Command,
+        world::{Query, World},
+    };
+
+    #[test]
+    #[should_panic(expected = "发生别名冲突")]
+    fn duplicate_mut_query() {
+        fn system(a: Query<&mut i32>, b: Query<&mut i32>) {
+            a.into_iter().zip(b).for_each(|(mut a, b)| *a += *b);
+        }
+        let mut world = World::new();
+        world.spawn(1);
+        world.exec(system);
+    }
+
+    #[test]
+    #[should_panic(expected = "发生别名冲突")]
+    fn fetch_while_querying() {
+        let mut world = World::new();
+        let entity = world.spawn(1);
+        world.borrows.begin("system");
+        let cell = world.as_unsafe_world_cell();
+        let query = unsafe { Query::<&i32>::build(cell) };
+        for _ in query {
+            unsafe { cell.world_mut() }.fetch::<&mut i32>(entity);
+        }
+    }
+
+    #[test]
+    fn release() {
+        fn read(query: Query<&i32>) {
+            query.into_iter().for_each(drop);
+        }
+        fn write(query: Query<&mut i32>) {
+            query.into_iter().for_each(|mut i| *i += 1);
+        }
+        let mut world = World::new();
+        let entity = world.spawn(1);
+        world.exec(read);
+        world.exec(write);
+        world.fetch::<&mut i32>(entity);
+        world.add_system(read).add_system(write).run_once();
+        world.run_once();
+    }
+}
@@ -0,0 +1,267 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use super::{state::SystemState, SystemParm, SystemRegistrationError};
+use crate::world::{Locals, Res, UnsafeWorldCell, World};
+
+/// 运行条件 : 由实现了[SystemParm]特征的类型作为参数,返回[bool]的函数
+///
+/// 与[InnerSystem]相同,参数的生命周期必须可以是任意的
+///
+/// [InnerSystem]: crate::system::InnerSystem
+pub trait InnerCondition<Marker> {
+    /// 为创建参数做准备,见[InnerSystem::prepare]
+    ///
+    /// [InnerSystem::prepare]: crate::system::InnerSystem::prepare
+    fn prepare(&self, _world: &mut World) {}
+
+    /// 从[World]创建参数
+    fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()>;
+
+    /// 初始化,计算[SystemState]
+    fn init(&self) -> SystemState;
+
+    fn run_once(&mut self, args: Box<()>) -> bool;
+
+    /// 运行条件的名字,即就是类型名
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+macro_rules! impl_condition {
+    ($($t:ident),*) => {
+        impl<F, $($t: SystemParm,)*> InnerCondition<($($t,)*)> for F
+        where
+            F: FnMut($($t,)*) -> bool + for<'w> FnMut($($t::Item<'w>,)*) -> bool,
+        {
+            fn prepare(&self, world: &mut World) {
+                $($t::prepare(world);)*
+            }
+
+            fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()> {
+                unsafe { std::mem::transmute(Box::new(($($t::build(world),)*))) }
+            }
+
+            fn init(&self) -> SystemState {
+                let mut state = SystemState::new();
+                $(state.init_param::<$t>();)*
+                state
+            }
+
+            fn run_once(&mut self, args: Box<()>) -> bool {
+                // 与System相同,'static不会泄露出去
+                let ($($t,)*) = unsafe {
+                    *std::mem::transmute::<Box<()>, Box<($($t::Item<'static>,)*)>>(args)
+                };
+                (self)($($t,)*)
+            }
+        }
+    };
+}
+trecs_proc::all_tuple!(impl_condition, 16);
+
+impl<F> InnerCondition<()> for F
+where
+    F: FnMut() -> bool,
+{
+    fn build_args(&self, _world: UnsafeWorldCell<'_>) -> Box<()> {
+        Box::new(())
+    }
+
+    fn init(&self) -> SystemState {
+        SystemState::new()
+    }
+
+    fn run_once(&mut self, _args: Box<()>) -> bool {
+        (self)()
+    }
+}
+
+/// 资源存在时满足的运行条件
+pub fn resource_exists<T: Send + Sync + 'static>() -> fn(Res<T>) -> bool {
+    |res| res.exists()
+}
+
+/// 资源在上一次判断之后被插入时满足的运行条件
+///
+/// 见[Res::is_added]
+pub fn resource_added<T: Send + Sync + 'static>() -> fn(Res<T>) -> bool {
+    |res| res.is_added()
+}
+
+/// 资源在上一次判断之后被插入或者修改时满足的运行条件
+///
+/// ``` rust
+/// use trecs::{system::{resource_changed, IntoSystem}, World};
+///
+/// struct Config(u32);
+///
+/// fn rebuild() {}
+///
+/// let mut world = World::new();
+/// world.add_system(rebuild.run_if(resource_changed::<Config>()));
+/// ```
+///
+/// 见[Res::is_changed]
+pub fn resource_changed<T: Send + Sync + 'static>() -> fn(Res<T>) -> bool {
+    |res| res.is_changed()
+}
+
+/// 被附加到[System]上的运行条件
+///
+/// [System]: crate::system::System
+pub(crate) struct Condition {
+    inner: Box<dyn InnerCondition<()> + Send>,
+    /// 判断时在运行时借用其中的资源
+    state: SystemState,
+    /// 上一次判断时的tick,用于变更检测
+    last_run: usize,
+    /// 所有[Local]的值
+    ///
+    /// [Local]: crate::world::Local
+    locals: Locals,
+}
+
+impl Condition {
+    /// 计算[SystemState],参数之间有冲突时返回[SystemRegistrationError]
+    pub(crate) fn new<M, F: InnerCondition<M> + Send>(
+        condition: F,
+    ) -> Result<Self, SystemRegistrationError> {
+        let mut state = condition.init();
+        let conflicts = state.take_conflicts();
+        if !conflicts.is_empty() {
+            return Err(SystemRegistrationError {
+                system: condition.name(),
+                conflicts,
+            });
+        }
+        let condition: Box<dyn InnerCondition<M> + Send> = Box::new(condition);
+        let inner = unsafe {
+            std::mem::transmute::<
+                Box<dyn InnerCondition<M> + Send>,
+                Box<dyn InnerCondition<()> + Send>,
+            >(condition)
+        };
+        Ok(Self {
+            inner,
+            state,
+            last_run: 0,
+            locals: Default::default(),
+        })
+    }
+
+    /// 判断一次,并且捕获判断期间的panic
+    ///
+    /// 在[System]创建参数之前调用,所以不会与[System]的参数冲突
+    ///
+    /// [System]: crate::system::System
+    pub(crate) fn evaluate(&mut self, world: &mut World) -> std::thread::Result<bool> {
+        let inner = &mut self.inner;
+        inner.prepare(world);
+        self.last_run = world.advance_tick(self.last_run);
+        #[cfg(feature = "debug-borrows")]
+        world.borrows.begin(inner.name());
+        let cell = unsafe { world.as_unsafe_world_cell().with_locals(&self.locals) };
+        let res = &self.state.res;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _borrows = unsafe { cell.borrow_res(inner.name(), res) };
+            inner.run_once(inner.build_args(cell))
+        }));
+        #[cfg(feature = "debug-borrows")]
+        world.borrows.end();
+        world.last_run = 0;
+        result
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use crate::{
+        system::{IntoSystem, SystemRegistrationError},
+        test::{self, Capture},
+        tools::{Command, ResManager},
+        world::{Local, Query, Res, ResMut},
+        World,
+    };
+
+    use super::{resource_added, resource_changed, resource_exists};
+
+    #[test]
+    fn run_if() {
+        fn count(capture: ResMut<Capture<usize>>) {
+            capture.into_or_init(Capture::new).push(1);
+        }
+        fn enabled(flag: Res<bool>) -> bool {
+            flag.get().copied().unwrap_or(false)
+        }
+        fn has_i32(query: Query<&i32>) -> bool {
+            query.into_iter().next().is_some()
+        }
+
+        let mut world = World::new();
+        world
+            .add_system(count.run_if(enabled))
+            .add_system(count.run_if(enabled).run_if(has_i32));
+        test::tick(&mut world);
+        assert!(test::captured::<usize>(&mut world).is_empty());
+
+        world.get_res::<bool>().get_or_init(|| true);
+        test::tick(&mut world);
+        assert_eq!(test::captured::<usize>(&mut world), [1]);
+
+        world.spawn(1);
+        test::tick(&mut world);
+        assert_eq!(test::captured::<usize>(&mut world), [1, 1]);
+    }
+
+    #[test]
+    fn resource_conditions() {
+        fn count(count: Res<u32>, capture: ResMut<Capture<u32>>) {
+            capture
+                .into_or_init(Capture::new)
+                .push(*count.get().unwrap());
+        }
+        fn added(capture: ResMut<Capture<u32>>) {
+            capture.into_or_init(Capture::new).push(0);
+        }
+        // 每两帧修改一次
+        fn bump(mut skip: Local<bool>, count: ResMut<u32>) {
+            *skip = !*skip;
+            if *skip {
+                *count.into_or_init(|| 0) += 1;
+            }
+        }
+
+        let mut world = World::new();
+        world
+            .add_system(count.run_if(resource_changed::<u32>()))
+            .add_system(added.run_if(resource_added::<u32>()))
+            .add_system(bump);
+        test::tick(&mut world);
+        assert!(test::captured::<u32>(&mut world).is_empty());
+        test::tick(&mut world);
+        assert_eq!(test::captured::<u32>(&mut world), [1, 0]);
+        test::tick(&mut world);
+        assert!(test::captured::<u32>(&mut world).is_empty());
+        test::tick(&mut world);
+        assert_eq!(test::captured::<u32>(&mut world), [2]);
+
+        world.add_system(added.run_if(resource_exists::<i64>()));
+        test::tick(&mut world);
+        assert!(test::captured::<u32>(&mut world).is_empty());
+    }
+
+    #[test]
+    fn conflict() {
+        fn nothing() {}
+        fn conflicting(_: Query<&mut i32>, _: Query<&i32>) -> bool {
+            true
+        }
+        let mut world = World::new();
+        let result = world.try_add_system(nothing.run_if(conflicting));
+        assert!(matches!(
+            result,
+            Err(SystemRegistrationError { system, .. }) if system == std::any::type_name_of_val(&conflicting)
+        ));
+    }
+}
@@ -0,0 +1,369 @@
+use std::{any::Any, error::Error, fmt::Display};
+
+use super::BoxedError;
+
+/// [System]的参数之间的冲突
+///
+/// [System]: crate::system::System
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamConflict {
+    /// 多个[WorldFetch]访问了同一种[Component],并且至少有一个是可变引用
+    ///
+    /// [WorldFetch]: crate::tools::WorldFetch
+    /// [Component]: crate::bundle::Component
+    Alias {
+        /// [Component]的类型名
+        ///
+        /// [Component]: crate::bundle::Component
+        component: &'static str,
+        /// 发生冲突的所有[WorldFetch]的类型名
+        ///
+        /// [WorldFetch]: crate::tools::WorldFetch
+        fetches: Vec<&'static str>,
+        /// fetches中的每个[WorldFetch]属于第几个参数,从0开始
+        ///
+        /// [WorldFetch]: crate::tools::WorldFetch
+        params: Vec<usize>,
+    },
+    /// 修改资源的[ResMut]与其他访问同一种资源的参数共存,或者与[Resources]共存,值为资源的类型名
+    ///
+    /// [ResMut]: crate::world::ResMut
+    /// [Resources]: crate::world::Resources
+    Resource(&'static str),
+    /// `&mut World`与其他访问[World]的参数共存,或者重复
+    ///
+    /// [World]: crate::World
+    World,
+    /// 重复的[Local],值为类型名
+    ///
+    /// [Local]: crate::world::Local
+    Local(&'static str),
+}
+
+impl Display for ParamConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamConflict::Alias {
+                component,
+                fetches,
+                params,
+            } => {
+                write!(
+                    f,
+                    "参数{params:?}中的WorldFetch {fetches:?} 对{component}的访问发生别名冲突"
+                )
+            }
+            ParamConflict::Resource(resource) => {
+                write!(
+                    f,
+                    "{resource}不可和Resources共存,也不可在修改的同时被其他参数访问"
+                )
+            }
+            ParamConflict::World => write!(f, "&mut World不可和其他访问World的参数共存"),
+            ParamConflict::Local(local) => write!(f, "Local<{local}>重复"),
+        }
+    }
+}
+
+/// 添加[System]失败
+///
+/// 在[World::try_add_system]时计算[SystemState]得到,
+/// 而不是等到[System]第一次运行时才panic
+///
+/// [System]: crate::system::System
+/// [World::try_add_system]: crate::World::try_add_system
+/// [SystemState]: crate::system::state::SystemState
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemRegistrationError {
+    /// [System]的名字
+    ///
+    /// [System]: crate::system::System
+    pub system: &'static str,
+    /// 所有冲突
+    pub conflicts: Vec<ParamConflict>,
+}
+
+impl Display for SystemRegistrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "无法添加System {}:", self.system)?;
+        for conflict in &self.conflicts {
+            write!(f, "\n  {conflict}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SystemRegistrationError {}
+
+/// 无法确定[System]的执行顺序
+///
+/// 见[World::build_schedule]
+///
+/// [System]: crate::system::System
+/// [World::build_schedule]: crate::World::build_schedule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// 顺序约束形成了环,值为环上的[System]的名字,按照约束的顺序排列,首尾相同
+    ///
+    /// [System]: crate::system::System
+    Cycle(Vec<&'static str>),
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::Cycle(systems) => {
+                write!(f, "System的顺序约束形成了环: {}", systems.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Error for ScheduleError {}
+
+/// [System]执行时发生了panic
+///
+/// 见[World::poisoned]
+///
+/// [System]: crate::system::System
+/// [World::poisoned]: crate::World::poisoned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemPanic {
+    /// 发生panic的[System]的名字
+    ///
+    /// [System]: crate::system::System
+    pub system: &'static str,
+    /// panic的信息
+    pub message: String,
+}
+
+impl SystemPanic {
+    pub(crate) fn new(system: &'static str, payload: &(dyn Any + Send)) -> Self {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "Box<dyn Any>".to_string(),
+            },
+        };
+        Self { system, message }
+    }
+}
+
+impl Display for SystemPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "System {} 发生panic: {}", self.system, self.message)
+    }
+}
+
+impl Error for SystemPanic {}
+
+/// [System]返回的错误
+///
+/// 见[SystemReturn]和[World::set_error_handler]
+///
+/// [System]: crate::system::System
+/// [SystemReturn]: crate::system::SystemReturn
+/// [World::set_error_handler]: crate::World::set_error_handler
+#[derive(Debug)]
+pub struct SystemError {
+    /// 返回错误的[System]的名字
+    ///
+    /// [System]: crate::system::System
+    pub system: &'static str,
+    pub error: BoxedError,
+}
+
+impl Display for SystemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "System {} 返回了错误: {}", self.system, self.error)
+    }
+}
+
+impl Error for SystemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.error)
+    }
+}
+
+/// [System]发生panic时[World]的处理方式
+///
+/// 除了[PanicPolicy::Isolate],[World]都会被标记为被污染,
+/// 这一帧中剩下的[System]会被跳过,这一帧也不会正常结束
+///
+/// [System]: crate::system::System
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicPolicy {
+    /// 继续panic
+    #[default]
+    Propagate,
+    /// 正常返回,之后可以通过[World::clear_poison]恢复
+    ///
+    /// [World::clear_poison]: crate::World::clear_poison
+    Poison,
+    /// 只禁用发生panic的[System],其他[System]正常执行,[World]不会被污染
+    ///
+    /// + panic会被记录在[Diagnostics]中,被禁用的[System]见[World::isolated]
+    /// + 发生panic的startup system与其他情况相同,不会再次执行
+    /// + 发生panic的[System]通过[Commands]推迟的操作被丢弃
+    /// + 切换[State]时执行的[System]发生panic时与[PanicPolicy::Poison]相同
+    ///
+    /// [Diagnostics]: crate::diagnostic::Diagnostics
+    /// [Commands]: crate::world::Commands
+    /// [World::isolated]: crate::World::isolated
+    /// [State]: crate::state::State
+    Isolate,
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostic::Diagnostics,
+        system::{Executor, ParamSet},
+        tools::ResManager,
+        world::{Query, Res, ResMut, Resources},
+        World,
+    };
+
+    #[test]
+    fn alias_conflict() {
+        let mut world = World::new();
+        let system = |_: Query<&mut i32>, _: Query<(&i32, &u32)>| {};
+        let error = world.try_add_system(system).err().unwrap();
+        assert_eq!(
+            error.conflicts,
+            [ParamConflict::Alias {
+                component: "i32",
+                fetches: vec!["&mut i32", "&i32"],
+                params: vec![0, 1],
+            }]
+        );
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "无法添加System {}:\n  参数[0, 1]中的WorldFetch [\"&mut i32\", \"&i32\"] 对i32的访问发生别名冲突",
+                error.system
+            )
+        );
+        // ParamSet的成员属于同一个参数
+        let system = |_: Res<u32>, _: Query<&i32>, _: ParamSet<(Query<&u8>, Query<&mut i32>)>| {};
+        let error = world.try_add_system(system).err().unwrap();
+        assert_eq!(
+            error.conflicts,
+            [ParamConflict::Alias {
+                component: "i32",
+                fetches: vec!["&i32", "&mut i32"],
+                params: vec![1, 2],
+            }]
+        );
+        // 可变引用之间同样冲突
+        let system = |_: Query<&mut i32>, _: Query<&mut i32>| {};
+        assert!(world.try_add_system(system).is_err());
+        assert!(world
+            .try_add_system(|_: Query<&i32>, _: Query<&i32>| {})
+            .is_ok());
+    }
+
+    #[test]
+    fn resource_conflict() {
+        let mut world = World::new();
+        let error = world
+            .try_add_startup_system(|_: ResMut<i32>, _: Res<i32>| {})
+            .err()
+            .unwrap();
+        assert_eq!(error.conflicts, [ParamConflict::Resource("i32")]);
+        assert!(world
+            .try_add_system(|_: ResMut<i32>, _: ResMut<i32>| {})
+            .is_err());
+        // 只读的访问可以重复
+        assert!(world.try_add_system(|_: Res<i32>, _: Res<i32>| {}).is_ok());
+        let system = |_: Res<i32>, _: Resources| {};
+        assert!(world.try_add_system(system).is_err());
+        assert!(world.try_add_system(|_: Res<i32>, _: Res<u32>| {}).is_ok());
+    }
+
+    fn panic_system() {
+        panic!("boom")
+    }
+
+    fn count(count: ResMut<u32>) {
+        *count.into_or_init(|| 0) += 1;
+    }
+
+    #[test]
+    fn poison() {
+        fn skipped(_: Res<u32>) {
+            unreachable!()
+        }
+        let mut world = World::new();
+        world
+            .set_panic_policy(PanicPolicy::Poison)
+            .add_system(count)
+            .add_system(panic_system)
+            .add_system(skipped);
+        world.run_once();
+        let panic = world.poisoned().unwrap();
+        assert_eq!(panic.system, std::any::type_name_of_val(&panic_system));
+        assert_eq!(panic.message, "boom");
+
+        // 被污染时run_until直接返回
+        world.run_until(|_| false);
+        assert_eq!(world.get_res::<u32>().get(), Some(&1));
+
+        assert!(world.clear_poison().is_some());
+        world.set_panic_policy(PanicPolicy::Propagate);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| world.run_once()));
+        assert!(result.is_err());
+        // System没有丢失
+        assert_eq!(world.get_res::<u32>().get(), Some(&2));
+        assert_eq!(world.schedule.systems().count(), 3);
+    }
+
+    #[test]
+    fn isolate() {
+        for executor in [Executor::SingleThreaded, Executor::MultiThreaded] {
+            let mut world = World::new();
+            world
+                .enable_diagnostics()
+                .set_executor(executor)
+                .set_panic_policy(PanicPolicy::Isolate)
+                .add_startup_system(panic_system)
+                .add_system(count)
+                .add_system(panic_system);
+            world.startup().run_once();
+            world.run_once();
+            assert!(world.poisoned().is_none());
+            assert_eq!(world.get_res::<u32>().get(), Some(&2));
+
+            let name = std::any::type_name_of_val(&panic_system);
+            let isolated = world.isolated().collect::<Vec<_>>();
+            assert_eq!(isolated.len(), 1);
+            assert_eq!((isolated[0].system, &*isolated[0].message), (name, "boom"));
+            // startup system和被禁用前的一次
+            let diagnostics = world.resource::<Diagnostics>().unwrap();
+            assert_eq!(diagnostics.system(name).unwrap().panics().len(), 2);
+
+            assert_eq!(world.restore_isolated().len(), 1);
+            world.run_once();
+            assert_eq!(world.isolated().count(), 1);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "World已经被污染")]
+    fn poisoned_run() {
+        let mut world = World::new();
+        world
+            .set_panic_policy(PanicPolicy::Poison)
+            .add_startup_system(panic_system)
+            .add_system(count);
+        world.startup().run_once();
+    }
+
+    #[test]
+    #[should_panic(expected = "无法添加System")]
+    fn add_system() {
+        World::new().add_system(|_: Query<&mut i32>, _: Query<&i32>| {});
+    }
+}
@@ -0,0 +1,619 @@
+#[cfg(feature = "async")]
+use std::{
+    any::Any,
+    future::poll_fn,
+    task::Poll,
+    time::{Duration, Instant},
+};
+#[cfg(not(feature = "async"))]
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    thread::{self, ScopedJoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "async")]
+use super::poll_caught;
+#[cfg(not(feature = "async"))]
+use super::SystemOutput;
+use super::{schedule::Sets, PanicPolicy, System};
+use crate::world::World;
+
+/// 执行update阶段的[System]的方式
+///
+/// 通过[World::set_executor]设置
+///
+/// [World::set_executor]: crate::World::set_executor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Executor {
+    /// 在当前线程中依次执行
+    #[default]
+    SingleThreaded,
+    /// 根据[SystemState]中记录的访问,把相邻的互不冲突的[System]分为一批,
+    /// 同一批中的[System]在多个线程中同时执行
+    ///
+    /// + 访问冲突的[System]之间仍然按照排好的顺序执行,见[World::ambiguities]
+    /// + 只能在主线程运行的[System]在当前线程中执行,见[World::main_thread_systems]
+    /// + 每个[System]的[Commands]推迟的操作在每一批结束之后按照排好的顺序执行,
+    ///   发生panic的[System]推迟的操作被丢弃,同一批中其他[System]推迟的操作仍然执行
+    ///
+    /// [SystemState]: crate::system::state::SystemState
+    /// [World::ambiguities]: crate::World::ambiguities
+    /// [World::main_thread_systems]: crate::World::main_thread_systems
+    /// [Commands]: crate::world::Commands
+    MultiThreaded,
+    /// 按照排好的顺序执行每个[System]同步的部分,然后同时等待它们返回的[Future],
+    /// 这样一个[System]在等待时不会阻塞其他[System]
+    ///
+    /// 与还在等待的[System]访问冲突,或者需要在它之后执行的[System]会等到它结束之后才开始,
+    /// 排在后面的[System]也会一起等待,以保持排好的顺序
+    ///
+    /// 其他两种方式在async特性下都会依次等待每个[System]
+    ///
+    /// 等待期间[System]通过[WorldAccess]请求的访问会在两次poll之间依次执行
+    ///
+    /// [WorldAccess]: crate::system::WorldAccess
+    #[cfg(feature = "async")]
+    Concurrent,
+}
+
+/// other是否与system冲突,或者必须在system之前执行,即就是system需要等待other结束
+fn must_wait(system: &System, other: &System, sets: &Sets) -> bool {
+    system.state.world
+        || other.state.world
+        || other.runs_before(system, sets)
+        || !other.state.conflicts(&system.state).is_empty()
+}
+
+/// 把已经排好序的systems分为若干批,同一批中的[System]之间没有冲突,也没有顺序约束
+///
+/// 访问整个[World]的[System]单独作为一批
+///
+/// 返回每一批的长度
+#[cfg(not(feature = "async"))]
+fn batches(systems: &[&mut System], sets: &Sets) -> Vec<usize> {
+    let mut batches = vec![];
+    let mut start = 0;
+    for (index, system) in systems.iter().enumerate() {
+        let conflicting = systems[start..index]
+            .iter()
+            .any(|other| must_wait(system, other, sets));
+        if conflicting {
+            batches.push(index - start);
+            start = index;
+        }
+    }
+    if start < systems.len() {
+        batches.push(systems.len() - start);
+    }
+    batches
+}
+
+/// 一个[System]执行的耗时和结果,T为[System]的返回值
+#[cfg(not(feature = "async"))]
+type Outcome<T = ()> = (Duration, thread::Result<T>);
+
+/// 一个准备好参数,等待执行的[System]
+#[cfg(not(feature = "async"))]
+struct Task<'a> {
+    system: &'a mut System,
+    args: Box<()>,
+}
+
+// System本身是Send的,参数与同一批中其他System的参数不冲突
+#[cfg(not(feature = "async"))]
+unsafe impl Send for Task<'_> {}
+
+#[cfg(not(feature = "async"))]
+impl Task<'_> {
+    fn run(self) -> Outcome<SystemOutput> {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("system", name = self.system.name()).entered();
+        let start = Instant::now();
+        let inner = &mut self.system.inner;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| inner.run_once(self.args)));
+        (start.elapsed(), result)
+    }
+}
+
+/// 在多个线程中同时执行systems,返回的结果与systems一一对应
+#[cfg(not(feature = "async"))]
+fn run_tasks(batch: &mut [&mut System], world: &mut World) -> Vec<Outcome<SystemOutput>> {
+    for system in batch.iter_mut() {
+        system.inner.prepare(world);
+    }
+    // 每个System都有各自的tick
+    let ticks = batch
+        .iter_mut()
+        .map(|system| {
+            let last_run = system.last_run;
+            system.last_run = world.advance_tick(last_run);
+            (last_run, system.last_run)
+        })
+        .collect::<Vec<_>>();
+    world.last_run = 0;
+
+    let cell = world.as_unsafe_world_cell();
+    // 创建参数时会修改资源的记录,所以在当前线程中依次创建
+    let args = batch
+        .iter()
+        .zip(ticks)
+        .map(|(system, (last_run, change_tick))| {
+            #[cfg(feature = "debug-borrows")]
+            unsafe { cell.borrows() }.begin(system.name());
+            let cell = unsafe {
+                cell.with_change_ticks(last_run, change_tick)
+                    .with_locals(&system.locals)
+                    .with_commands(&system.commands)
+            };
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                let borrows = unsafe { cell.borrow_res(system.name(), &system.state.res) };
+                (borrows, system.inner.build_args(cell))
+            }))
+        })
+        .collect::<Vec<_>>();
+    // 所有System结束之后才释放资源的借用
+    let mut borrows = Vec::with_capacity(args.len());
+
+    let mut outcomes = Vec::with_capacity(batch.len());
+    thread::scope(|scope| {
+        let mut main_thread = vec![];
+        let mut spawned: Vec<(usize, ScopedJoinHandle<Outcome<SystemOutput>>)> = vec![];
+        for (index, (system, args)) in batch.iter_mut().zip(args).enumerate() {
+            let args = match args {
+                Ok((borrowed, args)) => {
+                    borrows.push(borrowed);
+                    args
+                }
+                Err(payload) => {
+                    outcomes.push(Some((Duration::ZERO, Err(payload))));
+                    continue;
+                }
+            };
+            outcomes.push(None);
+            let main = system.state.main_thread;
+            let task = Task { system, args };
+            match main {
+                true => main_thread.push((index, task)),
+                false => spawned.push((index, scope.spawn(move || task.run()))),
+            }
+        }
+        for (index, task) in main_thread {
+            outcomes[index] = Some(task.run());
+        }
+        for (index, handle) in spawned {
+            // Task::run会捕获panic
+            outcomes[index] = Some(handle.join().unwrap());
+        }
+    });
+    drop(borrows);
+    #[cfg(feature = "debug-borrows")]
+    for _ in batch.iter() {
+        world.borrows.end();
+    }
+    outcomes.into_iter().map(Option::unwrap).collect()
+}
+
+/// 同时执行一批互不冲突的[System],返回的结果与batch一一对应
+///
+/// 每个[System]推迟的操作按照batch中的顺序执行,发生panic的[System]推迟的操作被丢弃,
+/// 执行时发生的panic算作推迟这个操作的[System]的,
+/// 之后在当前线程中依次处理返回的错误
+#[cfg(not(feature = "async"))]
+fn run_batch(batch: &mut [&mut System], world: &mut World) -> Vec<Outcome> {
+    // 运行条件可能访问任何数据,所以在当前线程中依次判断
+    let skipped = batch
+        .iter_mut()
+        .map(|system| match system.should_run(world) {
+            Ok(true) => None,
+            Ok(false) => Some((Duration::ZERO, Ok(Ok(())))),
+            Err(payload) => Some((Duration::ZERO, Err(payload))),
+        })
+        .collect::<Vec<_>>();
+    let mut running = batch
+        .iter_mut()
+        .zip(&skipped)
+        .filter(|(.., skipped)| skipped.is_none())
+        .map(|(system, ..)| &mut **system)
+        .collect::<Vec<_>>();
+    let mut ran = run_tasks(&mut running, world).into_iter();
+    let mut outcomes = skipped
+        .into_iter()
+        .map(|skipped| skipped.unwrap_or_else(|| ran.next().unwrap()))
+        .collect::<Vec<_>>();
+
+    for (system, (_, result)) in batch.iter_mut().zip(&mut outcomes) {
+        if result.is_err() {
+            system.commands.clear();
+            continue;
+        }
+        let applied =
+            panic::catch_unwind(AssertUnwindSafe(|| world.apply_queue(&mut system.commands)));
+        if let Err(payload) = applied {
+            *result = Err(payload);
+        }
+    }
+    batch
+        .iter()
+        .zip(outcomes)
+        .map(|(system, (elapsed, result))| {
+            let result = result.and_then(|output| match output {
+                Ok(()) => Ok(()),
+                Err(error) => panic::catch_unwind(AssertUnwindSafe(|| {
+                    world.handle_error(system.name(), error)
+                })),
+            });
+            (elapsed, result)
+        })
+        .collect()
+}
+
+/// 按照[Executor::MultiThreaded]执行systems
+///
+/// 返回第一个发生panic的[System]的名字,以及panic的内容,
+/// [PanicPolicy::Isolate]时发生panic的[System]被禁用,其他批次继续执行
+#[cfg(not(feature = "async"))]
+pub(crate) fn run_parallel(
+    world: &mut World,
+    mut systems: Vec<&mut System>,
+    timings: &mut Vec<(&'static str, Duration)>,
+) -> Option<(&'static str, Box<dyn Any + Send>)> {
+    let mut rest = &mut systems[..];
+    for len in batches(rest, &world.schedule.sets) {
+        let (batch, tail) = rest.split_at_mut(len);
+        rest = tail;
+        let outcomes = run_batch(batch, world);
+        let mut panicked = None;
+        for (system, (elapsed, result)) in batch.iter_mut().zip(outcomes) {
+            match result {
+                Ok(()) => timings.push((system.name(), elapsed)),
+                Err(payload) if world.panic_policy == PanicPolicy::Isolate => {
+                    world.isolate(system, payload);
+                }
+                Err(payload) => {
+                    panicked.get_or_insert((system.name(), payload));
+                }
+            }
+        }
+        if panicked.is_some() {
+            return panicked;
+        }
+    }
+    None
+}
+
+/// 正在等待的[System]的下标,开始的时间,以及返回的[Future]
+#[cfg(feature = "async")]
+type Pending = (usize, Instant, super::SystemFuture);
+
+/// 按照[Executor::Concurrent]执行已经排好序的systems,
+/// 每个[System]从开始到[Future]结束的耗时按照结束的顺序放入timings
+///
+/// 返回第一个发生panic的[System]的名字,以及panic的内容,其他还在等待的[Future]被丢弃;
+/// [PanicPolicy::Isolate]时发生panic的[System]被禁用,其他[System]继续执行
+#[cfg(feature = "async")]
+pub(crate) async fn run_concurrent(
+    systems: &mut [&mut System],
+    world: &mut World,
+    timings: &mut Vec<(&'static str, Duration)>,
+) -> Option<(&'static str, Box<dyn Any + Send>)> {
+    let mut pending: Vec<Pending> = vec![];
+    let mut next = 0;
+    poll_fn(|cx| loop {
+        let mut panicked = vec![];
+        let mut errors = vec![];
+        pending.retain_mut(|(index, start, future)| match poll_caught(future, cx) {
+            Poll::Ready(Ok(output)) => {
+                timings.push((systems[*index].name(), start.elapsed()));
+                errors.push((*index, output));
+                false
+            }
+            Poll::Ready(Err(payload)) => {
+                panicked.push((*index, payload));
+                false
+            }
+            Poll::Pending => true,
+        });
+        for (index, output) in errors {
+            if let Err(payload) = world.finish_system(systems[index].name(), output) {
+                panicked.push((index, payload));
+            }
+        }
+        let mut started = false;
+        while next < systems.len() {
+            let sets = &world.schedule.sets;
+            if (pending.iter()).any(|(index, ..)| must_wait(systems[next], systems[*index], sets)) {
+                break;
+            }
+            #[cfg(feature = "trace")]
+            let span = tracing::info_span!("system", name = systems[next].name());
+            #[cfg(feature = "trace")]
+            let _enter = span.enter();
+            let start = Instant::now();
+            match systems[next].start(world) {
+                Ok(Some(future)) => {
+                    // Future之后的每次poll也在System的span中
+                    #[cfg(feature = "trace")]
+                    let future = Box::pin(tracing::Instrument::instrument(future, span.clone()));
+                    pending.push((next, start, future));
+                    started = true;
+                }
+                Ok(None) => timings.push((systems[next].name(), start.elapsed())),
+                Err(payload) => panicked.push((next, payload)),
+            }
+            next += 1;
+        }
+        for (index, payload) in panicked {
+            if world.panic_policy != PanicPolicy::Isolate {
+                return Poll::Ready(Some((systems[index].name(), payload)));
+            }
+            world.isolate(systems[index], payload);
+        }
+        if pending.is_empty() && next == systems.len() {
+            return Poll::Ready(None);
+        }
+        // 新开始的Future需要先poll一次才会注册唤醒,请求访问World的Future在操作执行后需要再poll一次
+        if !world.apply_access() && !started {
+            return Poll::Pending;
+        }
+    })
+    .await
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::{
+        test,
+        tools::Command,
+        world::{Commands, NonSend, Query, Res},
+    };
+
+    #[test]
+    fn split_batches() {
+        fn read_i32(_: Query<&i32>) {}
+        fn read_i32_usize(_: Query<(&i32, &usize)>) {}
+        fn write_usize(_: Query<&mut usize>) {}
+        fn string(_: Res<String>) {}
+        fn commands(_: Commands) {}
+        fn exclusive(_: &mut World) {}
+
+        let mut systems = [
+            System::new(read_i32).unwrap(),
+            System::new(read_i32_usize).unwrap(),
+            System::new(string).unwrap(),
+            System::new(write_usize).unwrap(),
+            System::new(commands).unwrap(),
+            System::new(string).unwrap(),
+            System::new(read_i32_usize).unwrap(),
+            System::new(exclusive).unwrap(),
+            System::new(commands).unwrap(),
+        ];
+        let systems = systems.iter_mut().collect::<Vec<_>>();
+        assert_eq!(batches(&systems, &Default::default()), [3, 3, 1, 1, 1]);
+        assert!(batches(&[], &Default::default()).is_empty());
+    }
+
+    #[test]
+    fn run_concurrently() {
+        // 两个System都要等到对方开始执行
+        let (tx_a, rx_a) = mpsc::channel();
+        let (tx_b, rx_b) = mpsc::channel();
+        let a = move |query: Query<&mut i32>| {
+            tx_a.send(()).unwrap();
+            assert!(rx_b.recv_timeout(Duration::from_secs(5)).is_ok());
+            query.into_iter().for_each(|mut i| *i += 1);
+        };
+        let b = move |query: Query<&mut usize>, mut commands: Commands| {
+            tx_b.send(()).unwrap();
+            assert!(rx_a.recv_timeout(Duration::from_secs(5)).is_ok());
+            query.into_iter().for_each(|mut u| *u += 1);
+            commands.spawn("spawned");
+        };
+        let main = thread::current().id();
+        let on_main_thread = move |_: NonSend<u8>| assert_eq!(thread::current().id(), main);
+
+        let mut world = World::new();
+        let entity = world.spawn((0i32, 0usize));
+        world
+            .set_executor(Executor::MultiThreaded)
+            .add_system(a)
+            .add_system(b)
+            .add_system(on_main_thread);
+        world.run_once();
+        world.run_once();
+        test::assert_component_eq(&mut world, entity, &2i32);
+        test::assert_component_eq(&mut world, entity, &2usize);
+        assert_eq!(Query::<&&str>::new(&mut world).into_iter().count(), 2);
+    }
+
+    #[test]
+    fn serialize_conflicts() {
+        fn double(query: Query<&mut i32>) {
+            query.into_iter().for_each(|mut i| *i *= 2);
+        }
+        fn add(query: Query<&mut i32>) {
+            query.into_iter().for_each(|mut i| *i += 1);
+        }
+
+        let mut world = World::new();
+        let entity = world.spawn(1);
+        world
+            .set_executor(Executor::MultiThreaded)
+            .add_system(double)
+            .add_system(add);
+        world.run_once();
+        test::assert_component_eq(&mut world, entity, &3);
+    }
+
+    #[test]
+    fn blame_commands() {
+        fn a(mut commands: Commands) {
+            commands.spawn(1);
+        }
+        fn b(mut commands: Commands) {
+            commands.add(|_| panic!("boom"));
+        }
+
+        let mut world = World::new();
+        world
+            .set_executor(Executor::MultiThreaded)
+            .set_panic_policy(PanicPolicy::Isolate)
+            .add_system(a)
+            .add_system(b);
+        world.run_once();
+        // 推迟的操作按照顺序执行,panic算作推迟它的System的
+        assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 1);
+        let isolated = world.isolated().collect::<Vec<_>>();
+        assert_eq!(isolated.len(), 1);
+        assert_eq!(isolated[0].system, std::any::type_name_of_val(&b));
+    }
+
+    #[test]
+    fn discard_panicked() {
+        fn a(mut commands: Commands) {
+            commands.spawn(1);
+        }
+        fn b(mut commands: Commands) {
+            commands.spawn(2);
+            panic!("boom");
+        }
+
+        let mut world = World::new();
+        world
+            .set_executor(Executor::MultiThreaded)
+            .set_panic_policy(PanicPolicy::Isolate)
+            .add_system(a)
+            .add_system(b);
+        world.run_once();
+        // 只有发生panic的System推迟的操作被丢弃
+        let ints = Query::<&i32>::new(&mut world)
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(ints, [1]);
+        assert_eq!(world.isolated().count(), 1);
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use std::{
+        future::Future,
+        panic::{self, AssertUnwindSafe},
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::Context,
+    };
+
+    use super::*;
+    use crate::{
+        system::runtime::block_on,
+        world::{Commands, Query, Res, ResMut},
+    };
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    /// 被poll若干次之后才结束的[Future]
+    struct Yield(usize);
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 == 0 {
+                return Poll::Ready(());
+            }
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    fn wait(log: &Log, name: &'static str) -> impl Future<Output = ()> + 'static {
+        let log = log.clone();
+        log.lock().unwrap().push(name);
+        async move {
+            Yield(3).await;
+            log.lock().unwrap().push(name);
+        }
+    }
+
+    fn run(executor: Executor) -> Vec<&'static str> {
+        let log = Log::default();
+        let (a, b, c) = (log.clone(), log.clone(), log.clone());
+        let mut world = World::new();
+        world
+            .set_executor(executor)
+            .add_system(move |_: Res<u32>| wait(&a, "a"))
+            .add_system(move |_: Res<u32>| wait(&b, "b"))
+            // 与a和b冲突,需要等待它们结束
+            .add_system(move |_: ResMut<u32>| wait(&c, "c"));
+        block_on(world.run_once());
+        let log = log.lock().unwrap().clone();
+        log
+    }
+
+    #[test]
+    fn concurrent() {
+        assert_eq!(
+            run(Executor::SingleThreaded),
+            ["a", "a", "b", "b", "c", "c"]
+        );
+        assert_eq!(run(Executor::Concurrent), ["a", "b", "a", "b", "c", "c"]);
+    }
+
+    #[test]
+    fn isolate() {
+        fn count(count: ResMut<u32>) -> impl Future<Output = ()> {
+            *count.into_or_init(|| 0) += 1;
+            Yield(1)
+        }
+        async fn boom() {
+            Yield(1).await;
+            panic!("boom");
+        }
+
+        for executor in [Executor::SingleThreaded, Executor::Concurrent] {
+            let mut world = World::new();
+            world
+                .set_executor(executor)
+                .set_panic_policy(PanicPolicy::Isolate)
+                .add_system(boom)
+                .add_system(count);
+            block_on(world.run_once());
+            block_on(world.run_once());
+            assert!(world.poisoned().is_none());
+            assert_eq!(world.resource::<u32>(), Some(&2));
+            let isolated = world.isolated().collect::<Vec<_>>();
+            assert_eq!(isolated.len(), 1);
+            assert_eq!(&*isolated[0].message, "boom");
+
+            world.restore_isolated();
+            world.set_panic_policy(PanicPolicy::Poison);
+            block_on(world.run_once());
+            assert!(world.poisoned().is_some());
+        }
+    }
+    #[test]
+    fn poison() {
+        fn spawn_then_panic(mut commands: Commands) -> Yield {
+            commands.spawn(1);
+            panic!("boom")
+        }
+
+        let mut world = World::new();
+        world.add_system(spawn_then_panic);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| block_on(world.run_once())));
+        assert!(result.is_err());
+        // 推迟的操作被丢弃,World被污染
+        assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 0);
+        assert_eq!(&*world.poisoned().unwrap().message, "boom");
+        let result = panic::catch_unwind(AssertUnwindSafe(|| block_on(world.run_once())));
+        assert!(result.is_err());
+        assert!(world.clear_poison().is_some());
+    }
+}
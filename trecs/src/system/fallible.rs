@@ -0,0 +1,206 @@
+use std::error::Error;
+
+use super::SystemError;
+use crate::{tools::ResManager, World};
+
+/// [System]返回的错误,任何实现了[Error]的类型都可以转换成它
+///
+/// [System]: crate::system::System
+pub type BoxedError = Box<dyn Error + Send + Sync>;
+
+/// 可以作为[System]的返回值的类型
+///
+/// + `()`: 总是成功
+/// + `Result<(), E>`: 返回[Err]时,错误会被交给[World::set_error_handler]设置的函数处理,
+///   E可以是任何能转换为[BoxedError]的类型,比如实现了[Error]的类型和`anyhow::Error`
+///
+/// ``` rust
+/// use trecs::{system::SystemErrors, tools::Command, world::Query, World};
+///
+/// fn parse(query: Query<&&'static str>) -> Result<(), std::num::ParseIntError> {
+///     for s in query {
+///         s.parse::<i32>()?;
+///     }
+///     Ok(())
+/// }
+///
+/// let mut world = World::new();
+/// world.spawn("abc");
+/// world
+///     .set_error_handler(trecs::system::store_error)
+///     .add_system(parse);
+/// world.run_once();
+/// let errors = world.resource::<SystemErrors>().unwrap();
+/// assert_eq!(errors.0[0].error.to_string(), "invalid digit found in string");
+/// ```
+///
+/// [System]: crate::system::System
+pub trait SystemReturn {
+    fn into_result(self) -> Result<(), BoxedError>;
+}
+
+impl SystemReturn for () {
+    fn into_result(self) -> Result<(), BoxedError> {
+        Ok(())
+    }
+}
+
+impl<E: Into<BoxedError>> SystemReturn for Result<(), E> {
+    fn into_result(self) -> Result<(), BoxedError> {
+        self.map_err(Into::into)
+    }
+}
+
+/// [store_error]收集的所有错误,按照发生的顺序排列
+#[derive(Debug, Default)]
+pub struct SystemErrors(pub Vec<SystemError>);
+
+/// 默认的处理方式: 以错误的内容panic,之后按照[PanicPolicy]处理
+///
+/// [PanicPolicy]: crate::system::PanicPolicy
+pub fn panic_on_error(_world: &mut World, error: SystemError) {
+    panic!("{error}")
+}
+
+/// 把错误输出到标准错误
+pub fn log_error(_world: &mut World, error: SystemError) {
+    eprintln!("{error}")
+}
+
+/// 把错误放入资源[SystemErrors]
+pub fn store_error(world: &mut World, error: SystemError) {
+    world
+        .get_res::<SystemErrors>()
+        .into_or_init(Default::default)
+        .0
+        .push(error);
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        system::{Executor, PanicPolicy, PipeSystem},
+        tools::Command,
+        world::{Commands, Query},
+    };
+
+    #[derive(Debug)]
+    struct Boom;
+
+    impl std::fmt::Display for Boom {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "boom")
+        }
+    }
+
+    impl Error for Boom {}
+
+    fn fail(mut commands: Commands, query: Query<&i32>) -> Result<(), Boom> {
+        commands.spawn(0u8);
+        match query.into_iter().count() {
+            0 => Ok(()),
+            _ => Err(Boom),
+        }
+    }
+
+    #[test]
+    fn error_handler() {
+        for executor in [Executor::SingleThreaded, Executor::MultiThreaded] {
+            let mut world = World::new();
+            world
+                .set_executor(executor)
+                .set_error_handler(store_error)
+                .add_system(fail)
+                .add_system(|| -> Result<(), &'static str> { Err("str") })
+                .add_system(|| Ok::<_, Boom>(()));
+            world.run_once();
+            assert_eq!(world.resource::<SystemErrors>().unwrap().0.len(), 1);
+            world.spawn(1);
+            world.run_once();
+            let errors = &world.resource::<SystemErrors>().unwrap().0;
+            let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+            let system = std::any::type_name_of_val(&fail);
+            assert!(messages.contains(&format!("System {system} 返回了错误: boom")));
+            let str_errors = messages.iter().filter(|m| m.ends_with("返回了错误: str"));
+            assert_eq!((messages.len(), str_errors.count()), (3, 2));
+            // 返回错误时推迟的操作仍然会执行
+            assert_eq!(Query::<&u8>::new(&mut world).into_iter().count(), 2);
+        }
+    }
+
+    #[test]
+    fn panic_on_error() {
+        let mut world = World::new();
+        world.spawn(1);
+        world.set_panic_policy(PanicPolicy::Poison).add_system(fail);
+        world.run_once();
+        let panic = world.poisoned().unwrap();
+        assert_eq!(panic.system, std::any::type_name_of_val(&fail));
+        assert!(panic.message.ends_with("返回了错误: boom"));
+
+        // 串联的System和exec也可以返回错误
+        let mut world = World::new();
+        world.set_error_handler(store_error);
+        world.add_system(
+            (|| 1)
+                .pipe(|crate::system::In(n): crate::system::In<i32>| Err::<(), _>(format!("{n}"))),
+        );
+        world.run_once();
+        world.exec(|| Err::<(), _>("exec"));
+        let errors = &world.resource::<SystemErrors>().unwrap().0;
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].error.to_string(), "1");
+        assert_eq!(errors[1].error.to_string(), "exec");
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use super::*;
+    use crate::{
+        system::{runtime::block_on, Executor, PanicPolicy},
+        world::{Commands, Res, ResMut},
+    };
+
+    fn fail(mut commands: Commands) -> impl std::future::Future<Output = Result<(), String>> {
+        commands.spawn(0u8);
+        async { Err("async".to_owned()) }
+    }
+
+    #[test]
+    fn error_handler() {
+        for executor in [Executor::SingleThreaded, Executor::Concurrent] {
+            let mut world = World::new();
+            world
+                .set_executor(executor)
+                .set_error_handler(store_error)
+                .add_system(fail)
+                .add_system(|| async { Ok::<_, String>(()) });
+            block_on(world.run_once());
+            block_on(world.exec(|| async { Err::<(), _>("exec") }));
+            let errors = &world.resource::<SystemErrors>().unwrap().0;
+            let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+            let system = std::any::type_name_of_val(&fail);
+            assert_eq!(messages[0], format!("System {system} 返回了错误: async"));
+            assert!(messages[1].ends_with("返回了错误: exec"));
+            assert_eq!(messages.len(), 2);
+        }
+
+        // 默认以错误的内容panic,按照PanicPolicy处理
+        let mut world = World::new();
+        world.set_panic_policy(PanicPolicy::Poison).add_system(fail);
+        block_on(world.run_once());
+        assert!(world
+            .poisoned()
+            .unwrap()
+            .message
+            .ends_with("返回了错误: async"));
+    }
+
+    #[test]
+    #[should_panic(expected = "无法添加System")]
+    fn exec_conflict() {
+        block_on(World::new().exec(|_: Res<u32>, _: ResMut<u32>| async {}));
+    }
+}
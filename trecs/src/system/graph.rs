@@ -0,0 +1,201 @@
+use std::fmt::Write;
+
+/// [System]的执行顺序,可以导出为DOT或者Mermaid格式
+///
+/// 通过[World::schedule_graph]获得
+///
+/// [System]: crate::system::System
+/// [World::schedule_graph]: crate::World::schedule_graph
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleGraph {
+    nodes: Vec<GraphNode>,
+    sets: Vec<GraphSet>,
+    edges: Vec<(usize, usize)>,
+}
+
+/// [ScheduleGraph]中的节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphNode {
+    pub name: String,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// 一个[System]
+    ///
+    /// [System]: crate::system::System
+    System,
+    /// 同步点:前面的[System]全部结束后,后面的[System]才会开始
+    ///
+    /// [System]: crate::system::System
+    SyncPoint,
+}
+
+/// 一组节点,如startup_systems
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphSet {
+    pub name: String,
+    /// 节点在[ScheduleGraph::nodes]中的下标
+    pub nodes: Vec<usize>,
+}
+
+impl ScheduleGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 所有的节点
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    /// 所有的节点组
+    pub fn sets(&self) -> &[GraphSet] {
+        &self.sets
+    }
+
+    /// 所有的边:(先执行的节点,后执行的节点)
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// 添加一个节点,返回它的下标
+    pub(crate) fn add_node(&mut self, name: impl Into<String>, kind: NodeKind) -> usize {
+        self.nodes.push(GraphNode {
+            name: name.into(),
+            kind,
+        });
+        self.nodes.len() - 1
+    }
+
+    pub(crate) fn add_set(&mut self, name: impl Into<String>, nodes: Vec<usize>) {
+        self.sets.push(GraphSet {
+            name: name.into(),
+            nodes,
+        });
+    }
+
+    pub(crate) fn add_edge(&mut self, before: usize, after: usize) {
+        if !self.edges.contains(&(before, after)) {
+            self.edges.push((before, after));
+        }
+    }
+
+    /// 将nodes按照顺序依次连接
+    pub(crate) fn chain(&mut self, nodes: &[usize]) {
+        for pair in nodes.windows(2) {
+            self.add_edge(pair[0], pair[1]);
+        }
+    }
+
+    /// 不属于任何节点组的节点
+    fn loose_nodes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(|node| !self.sets.iter().any(|set| set.nodes.contains(node)))
+    }
+
+    /// 导出为Graphviz的DOT格式
+    pub fn to_dot(&self) -> String {
+        fn escape(str: &str) -> String {
+            str.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+        fn node(dot: &mut String, indent: &str, index: usize, node: &GraphNode) {
+            let shape = match node.kind {
+                NodeKind::System => "box",
+                NodeKind::SyncPoint => "diamond",
+            };
+            writeln!(
+                dot,
+                "{indent}n{index} [label=\"{}\", shape={shape}];",
+                escape(&node.name)
+            )
+            .unwrap();
+        }
+
+        let mut dot = String::from("digraph schedule {\n");
+        for (index, set) in self.sets.iter().enumerate() {
+            writeln!(dot, "    subgraph cluster_{index} {{").unwrap();
+            writeln!(dot, "        label=\"{}\";", escape(&set.name)).unwrap();
+            for &index in &set.nodes {
+                node(&mut dot, "        ", index, &self.nodes[index]);
+            }
+            dot.push_str("    }\n");
+        }
+        for index in self.loose_nodes() {
+            node(&mut dot, "    ", index, &self.nodes[index]);
+        }
+        for (before, after) in &self.edges {
+            writeln!(dot, "    n{before} -> n{after};").unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// 导出为Mermaid的flowchart格式
+    ///
+    /// 可以直接贴进markdown中显示
+    pub fn to_mermaid(&self) -> String {
+        fn escape(str: &str) -> String {
+            str.replace('"', "#quot;")
+        }
+        fn node(mermaid: &mut String, indent: &str, index: usize, node: &GraphNode) {
+            let name = escape(&node.name);
+            match node.kind {
+                NodeKind::System => writeln!(mermaid, "{indent}n{index}[\"{name}\"]"),
+                NodeKind::SyncPoint => writeln!(mermaid, "{indent}n{index}{{{{\"{name}\"}}}}"),
+            }
+            .unwrap();
+        }
+
+        let mut mermaid = String::from("flowchart TD\n");
+        for (index, set) in self.sets.iter().enumerate() {
+            writeln!(mermaid, "    subgraph s{index} [\"{}\"]", escape(&set.name)).unwrap();
+            for &index in &set.nodes {
+                node(&mut mermaid, "        ", index, &self.nodes[index]);
+            }
+            mermaid.push_str("    end\n");
+        }
+        for index in self.loose_nodes() {
+            node(&mut mermaid, "    ", index, &self.nodes[index]);
+        }
+        for (before, after) in &self.edges {
+            writeln!(mermaid, "    n{before} --> n{after}").unwrap();
+        }
+        mermaid
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use crate::World;
+
+    #[test]
+    fn export() {
+        fn init() {}
+        fn first() {}
+        fn second() {}
+
+        let mut world = World::new();
+        world
+            .add_startup_system(init)
+            .add_system(first)
+            .add_system(second);
+
+        let graph = world.schedule_graph();
+        assert_eq!(graph.nodes().len(), 4);
+        assert_eq!(graph.edges(), &[(0, 1), (1, 2), (2, 3)]);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("label=\"startup\";"));
+        assert!(dot.contains("n1 [label=\"sync\", shape=diamond];"));
+        assert!(dot.contains("n2 -> n3;"));
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("    n1{{\"sync\"}}\n"));
+        assert!(mermaid.contains(&format!(
+            "        n3[\"{}\"]\n",
+            std::any::type_name_of_val(&second)
+        )));
+    }
+}
@@ -1,31 +1,168 @@
+#[cfg(feature = "async")]
+mod access;
+mod ambiguity;
+#[cfg(feature = "debug-borrows")]
+pub(crate) mod borrows;
+mod condition;
+mod error;
+mod executor;
+mod fallible;
+mod graph;
+mod param_set;
+mod pipe;
+#[cfg(feature = "async")]
+mod runtime;
+mod schedule;
 pub(crate) mod state;
-use std::{future::Future, pin::Pin};
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "async")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
-use crate::world::World;
-use state::SystemState;
+#[cfg(feature = "async")]
+pub(crate) use access::AccessQueue;
+#[cfg(feature = "async")]
+pub use access::WorldAccess;
+pub use ambiguity::{Ambiguity, AmbiguityReport, Conflict};
+pub use condition::{resource_added, resource_changed, resource_exists, InnerCondition};
+pub use error::SystemError;
+pub use error::{PanicPolicy, ParamConflict, ScheduleError, SystemPanic, SystemRegistrationError};
+#[cfg(feature = "async")]
+pub(crate) use executor::run_concurrent;
+#[cfg(not(feature = "async"))]
+pub(crate) use executor::run_parallel;
+pub use executor::Executor;
+pub use fallible::{
+    log_error, panic_on_error, store_error, BoxedError, SystemErrors, SystemReturn,
+};
+pub use graph::{GraphNode, GraphSet, NodeKind, ScheduleGraph};
+pub use param_set::ParamSet;
+pub use pipe::{In, Pipe, PipeSystem};
+#[cfg(all(test, feature = "async"))]
+pub(crate) use runtime::block_on;
+#[cfg(feature = "async-std")]
+pub use runtime::AsyncStd;
+#[cfg(feature = "smol")]
+pub use runtime::Smol;
+#[cfg(feature = "tokio")]
+pub use runtime::Tokio;
+#[cfg(feature = "async")]
+pub use runtime::{sleep, yield_now, AsyncSleep, AsyncSpawn, Runtime};
+pub(crate) use schedule::{sort_systems, sorted_or_unchanged, Schedule};
+pub use schedule::{IntoSystem, Stage, StartupStage, SystemConfig, SystemSet};
+pub use state::SystemState;
+pub use trecs_proc::SystemParm;
 
-type AsyncUnit = Option<Pin<Box<dyn Future<Output = ()>>>>;
+use crate::world::{Locals, UnsafeWorldCell, World};
+use condition::Condition;
+use schedule::SystemOrder;
+
+/// 异步[System]返回的[Future],结果见[SystemReturn]
+#[cfg(feature = "async")]
+pub(crate) type SystemFuture = Pin<Box<dyn Future<Output = Result<(), BoxedError>>>>;
+
+#[cfg(feature = "async")]
+type SystemOutput = Option<SystemFuture>;
+
+/// poll一次[System]返回的[Future],并且捕获poll期间的panic
+#[cfg(feature = "async")]
+pub(crate) fn poll_caught(
+    future: &mut SystemFuture,
+    cx: &mut Context<'_>,
+) -> Poll<std::thread::Result<Result<(), BoxedError>>> {
+    match panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+        Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+        Ok(Poll::Pending) => Poll::Pending,
+        Err(payload) => Poll::Ready(Err(payload)),
+    }
+}
+/// [System]的返回值转换成的结果,见[SystemReturn]
+#[cfg(not(feature = "async"))]
+type SystemOutput = Result<(), BoxedError>;
 
 /// 函数系统 : 由实现了[FnSystemParm]特征的类型作为参数,并且加上
 /// [proc::system]属性的的函数
+///
+/// 参数的生命周期必须可以是任意的,因此`impl FnMut(Query<&T>)`这样的不透明类型
+/// 目前无法通过编译器的检查,需要时请使用函数指针
 pub trait InnerSystem<Marker> {
+    /// 为创建参数做准备,如为[Res]创建资源的位置
+    ///
+    /// 在[InnerSystem::build_args]之前调用
+    ///
+    /// [Res]: crate::world::Res
+    fn prepare(&self, _world: &mut World) {}
+
     /// 从[World]创建参数
-    fn build_args(&self, world: &World) -> Box<()>;
+    fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()>;
+
+    /// 初始化,计算[SystemState]
+    fn init(&self) -> SystemState;
 
-    /// 初始化
-    fn init(&self);
+    fn run_once(&mut self, args: Box<()>) -> SystemOutput;
 
-    fn run_once(&mut self, args: Box<()>) -> AsyncUnit;
+    /// [System]的名字,即就是类型名
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 /// 实现此特征 就可以作为[System]的参数
-pub(crate) trait SystemParm {
+///
+/// [System]对于任何生命周期的[SystemParm::Item]都必须可以执行,
+/// 所以参数的生命周期不能被指定为`'static`之类的,参数也就无法在[System]执行完之后继续存在
+///
+/// 可以通过`#[derive(SystemParm)]`把多个参数组合成一个结构体,
+/// 结构体至多有一个生命周期参数
+///
+/// ``` rust
+/// use trecs::{
+///     bundle::Component,
+///     system::SystemParm,
+///     world::{Local, Query, Res},
+///     World,
+/// };
+///
+/// #[derive(Component)]
+/// struct Speed(f32);
+///
+/// struct Gravity(f32);
+///
+/// #[derive(SystemParm)]
+/// struct Physics<'w> {
+///     speeds: Query<'w, &'static mut Speed>,
+///     gravity: Res<'w, Gravity>,
+///     steps: Local<'w, usize>,
+/// }
+///
+/// fn fall(mut physics: Physics) {
+///     *physics.steps += 1;
+///     let gravity = physics.gravity.get().map_or(9.8, |g| g.0);
+///     for mut speed in physics.speeds.into_iter() {
+///         speed.0 += gravity;
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.add_system(fall);
+/// world.run_once();
+/// ```
+pub trait SystemParm {
+    /// 生命周期为'w的参数,通常就是实现这个特征的类型
+    type Item<'w>;
+
     /// 从[World]创建
     ///
     /// # Safety
     ///
-    /// 这个函数的安全性通过[FnSystemParm::init]保证
-    unsafe fn build(world: &World) -> Self;
+    /// 这个函数的安全性通过[SystemParm::init]保证
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w>;
+
+    /// 在[SystemParm::build]之前调用,可以修改[World]
+    fn prepare(_world: &mut World) {}
 
     /// 初始化,通过[SystemState]保证安全性
     fn init(state: &mut SystemState);
@@ -38,43 +175,52 @@ mod __impl {
         use super::super::*;
         macro_rules! impl_fnsystem {
         ($($t:ident),*) => {
-            impl<F,$($t : SystemParm,)*> InnerSystem<($($t,)*)> for F
-            where F : FnMut($($t,)*) {
-                fn build_args(&self, world: &World) -> Box<()>{
+            impl<F,R,$($t : SystemParm,)*> InnerSystem<($($t,)*)> for F
+            where F : FnMut($($t,)*) -> R + for<'w> FnMut($($t::Item<'w>,)*) -> R,
+                  R: SystemReturn,
+            {
+                fn prepare(&self, world: &mut World) {
+                    $($t::prepare(world);)*
+                }
+
+                fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()>{
                     unsafe{
                         std::mem::transmute(Box::new(($($t::build(world),)*)))
                     }
                 }
 
-                fn init(&self) {
+                fn init(&self) -> SystemState {
                     let mut state = SystemState::new();
-                    $($t::init(&mut state);)*
+                    $(state.init_param::<$t>();)*
+                    state
                 }
 
-                fn run_once(&mut self, args: Box<()>) -> AsyncUnit{
+                fn run_once(&mut self, args: Box<()>) -> SystemOutput{
+                    // System对于任何生命周期都可以执行,所以'static不会泄露出去
                     let ($($t,)*) = unsafe{
-                        *std::mem::transmute::<_,Box<($($t,)*)>>(args)
+                        *std::mem::transmute::<Box<()>, Box<($($t::Item<'static>,)*)>>(args)
                     };
-                    (self)($($t,)*);
-                    None
+                    (self)($($t,)*).into_result()
                 }
             }
             };
         }
         trecs_proc::all_tuple!(impl_fnsystem, 16);
-        impl<F> InnerSystem<()> for F
+        impl<F, R> InnerSystem<()> for F
         where
-            F: FnMut(),
+            F: FnMut() -> R,
+            R: SystemReturn,
         {
-            fn build_args(&self, _world: &World) -> Box<()> {
+            fn build_args(&self, _world: UnsafeWorldCell<'_>) -> Box<()> {
                 Box::new(())
             }
 
-            fn init(&self) {}
+            fn init(&self) -> SystemState {
+                SystemState::new()
+            }
 
-            fn run_once(&mut self, _args: Box<()>) -> AsyncUnit {
-                (self)();
-                None
+            fn run_once(&mut self, _args: Box<()>) -> SystemOutput {
+                (self)().into_result()
             }
         }
     }
@@ -85,26 +231,33 @@ mod __impl {
         macro_rules! impl_async_fnsystem {
         ($($t:ident),*) => {
             impl<F,R,$($t : SystemParm,)*> InnerSystem<($($t,)*)> for F
-            where F : FnMut($($t,)*) -> R,
-                  R: Future<Output = ()> + 'static,
+            where F : FnMut($($t,)*) -> R + for<'w> FnMut($($t::Item<'w>,)*) -> R,
+                  R: Future + 'static,
+                  R::Output: SystemReturn,
             {
-                fn build_args(&self, world: &World) -> Box<()>{
+                fn prepare(&self, world: &mut World) {
+                    $($t::prepare(world);)*
+                }
+
+                fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()>{
                     unsafe{
                         std::mem::transmute(Box::new(($($t::build(world),)*)))
                     }
                 }
 
-                fn init(&self) {
+                fn init(&self) -> SystemState {
                     let mut state = SystemState::new();
-                    $($t::init(&mut state);)*
+                    $(state.init_param::<$t>();)*
+                    state
                 }
 
-                fn run_once(&mut self, args: Box<()>) -> AsyncUnit{
+                fn run_once(&mut self, args: Box<()>) -> SystemOutput{
+                    // System对于任何生命周期都可以执行,所以'static不会泄露出去
                     let ($($t,)*) = unsafe{
-                        *std::mem::transmute::<_,Box<($($t,)*)>>(args)
+                        *std::mem::transmute::<Box<()>, Box<($($t::Item<'static>,)*)>>(args)
                     };
-                    (self)($($t,)*);
-                    None
+                    let future = (self)($($t,)*);
+                    Some(Box::pin(async move { future.await.into_result() }))
                 }
             }
             };
@@ -114,50 +267,185 @@ mod __impl {
         impl<F, R> InnerSystem<()> for F
         where
             F: FnMut() -> R,
-            R: Future<Output = ()> + 'static,
+            R: Future + 'static,
+            R::Output: SystemReturn,
         {
-            fn build_args(&self, _world: &World) -> Box<()> {
+            fn build_args(&self, _world: UnsafeWorldCell<'_>) -> Box<()> {
                 Box::new(())
             }
 
-            fn init(&self) {}
+            fn init(&self) -> SystemState {
+                SystemState::new()
+            }
 
-            fn run_once(&mut self, _args: Box<()>) -> AsyncUnit {
-                Some(Box::pin((self)()))
+            fn run_once(&mut self, _args: Box<()>) -> SystemOutput {
+                let future = (self)();
+                Some(Box::pin(async move { future.await.into_result() }))
             }
         }
     }
 }
 
-#[non_exhaustive]
-pub enum System {
+/// 被添加进[World]的[System]
+///
+/// 保存着被添加时计算出的[SystemState]
+pub struct System {
+    inner: Box<dyn InnerSystem<()> + Send>,
+    pub(crate) state: SystemState,
+    /// 上一次执行时的tick,用于变更检测
+    last_run: usize,
+    /// 标签和顺序约束,见[IntoSystem]
+    order: SystemOrder,
+    /// 运行条件,见[IntoSystem::run_if]
+    conditions: Vec<Condition>,
+    /// 所有[Local]的值
+    ///
+    /// [Local]: crate::world::Local
+    locals: Locals,
+    /// 被[Executor::MultiThreaded]并行执行时,[Commands]推迟的操作
+    ///
+    /// [Commands]: crate::world::Commands
     #[cfg(not(feature = "async"))]
-    Normal(Box<dyn InnerSystem<()>>),
-    #[cfg(feature = "async")]
-    Async(Box<dyn InnerSystem<()>>),
+    commands: crate::world::CommandQueue,
+    /// 按照[PanicPolicy::Isolate]被禁用时,导致禁用的panic
+    pub(crate) isolated: Option<SystemPanic>,
 }
 
 impl System {
-    pub(crate) fn new<M, F: InnerSystem<M>>(fn_system: F) -> Self {
-        fn_system.init();
-        let fn_system: Box<dyn InnerSystem<M>> = Box::new(fn_system);
+    /// 计算[SystemState],参数之间有冲突时返回[SystemRegistrationError]
+    ///
+    /// [System]需要是[Send]的,才能被[Executor::MultiThreaded]执行
+    pub(crate) fn new<M, F: InnerSystem<M> + Send>(
+        fn_system: F,
+    ) -> Result<Self, SystemRegistrationError> {
+        let mut state = fn_system.init();
+        let conflicts = state.take_conflicts();
+        if !conflicts.is_empty() {
+            return Err(SystemRegistrationError {
+                system: fn_system.name(),
+                conflicts,
+            });
+        }
+        let fn_system: Box<dyn InnerSystem<M> + Send> = Box::new(fn_system);
 
-        let inner: Box<dyn InnerSystem<()>> = unsafe { std::mem::transmute(fn_system) };
+        let inner: Box<dyn InnerSystem<()> + Send> = unsafe {
+            std::mem::transmute::<Box<dyn InnerSystem<M> + Send>, Box<dyn InnerSystem<()> + Send>>(
+                fn_system,
+            )
+        };
 
-        #[cfg(feature = "async")]
-        return Self::Async(inner);
-        #[cfg(not(feature = "async"))]
-        return Self::Normal(inner);
+        Ok(Self {
+            inner,
+            state,
+            last_run: 0,
+            order: Default::default(),
+            conditions: vec![],
+            locals: Default::default(),
+            #[cfg(not(feature = "async"))]
+            commands: Default::default(),
+            isolated: None,
+        })
+    }
+
+    /// [System]的名字,即就是类型名
+    pub(crate) fn name(&self) -> &'static str {
+        self.inner.name()
     }
 
+    /// 依次判断所有运行条件,有一个返回false时就不再判断
+    pub(crate) fn should_run(&mut self, world: &mut World) -> std::thread::Result<bool> {
+        for condition in &mut self.conditions {
+            if !condition.evaluate(world)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// 执行一次,并且捕获执行期间的panic
+    ///
+    /// 返回的错误交给[World::set_error_handler]设置的函数处理
+    ///
+    /// 运行条件不满足时什么都不做
     #[cfg(not(feature = "async"))]
-    pub(crate) fn run_once(&mut self, world: &World) {
-        let System::Normal(inner) = self;
-        inner.run_once(inner.build_args(world));
+    pub(crate) fn run_once(&mut self, world: &mut World) -> std::thread::Result<()> {
+        if !self.should_run(world)? {
+            return Ok(());
+        }
+        let inner = &mut self.inner;
+        inner.prepare(world);
+        self.last_run = world.advance_tick(self.last_run);
+        #[cfg(feature = "debug-borrows")]
+        world.borrows.begin(inner.name());
+        let cell = unsafe { world.as_unsafe_world_cell().with_locals(&self.locals) };
+        let res = &self.state.res;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _borrows = unsafe { cell.borrow_res(inner.name(), res) };
+            inner.run_once(inner.build_args(cell))
+        }));
+        #[cfg(feature = "debug-borrows")]
+        world.borrows.end();
+        world.last_run = 0;
+        let output = match result {
+            Ok(output) => output,
+            Err(payload) => {
+                world.discard_commands();
+                return Err(payload);
+            }
+        };
+        // 执行被推迟的操作和处理错误时发生的panic也算作这个System的
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            world.apply_commands();
+            if let Err(error) = output {
+                world.handle_error(inner.name(), error);
+            }
+        }))
     }
+    /// 执行同步的部分,即就是构造参数并且调用[System],返回需要等待的[Future],
+    /// 并且捕获执行期间的panic
+    ///
+    /// 返回之前已经执行了被推迟的操作,返回的[Future]只能通过[WorldAccess]访问[World]
+    ///
+    /// 运行条件不满足时返回[None]
+    #[cfg(feature = "async")]
+    pub(crate) fn start(&mut self, world: &mut World) -> std::thread::Result<SystemOutput> {
+        if !self.should_run(world)? {
+            return Ok(None);
+        }
+        let inner = &mut self.inner;
+        inner.prepare(world);
+        self.last_run = world.advance_tick(self.last_run);
+        #[cfg(feature = "debug-borrows")]
+        world.borrows.begin(inner.name());
+        let cell = unsafe { world.as_unsafe_world_cell().with_locals(&self.locals) };
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| inner.run_once(inner.build_args(cell))));
+        #[cfg(feature = "debug-borrows")]
+        world.borrows.end();
+        world.last_run = 0;
+        let future = match result {
+            Ok(future) => future,
+            Err(payload) => {
+                world.discard_commands();
+                return Err(payload);
+            }
+        };
+        // 执行被推迟的操作时发生的panic也算作这个System的
+        panic::catch_unwind(AssertUnwindSafe(|| world.apply_commands()))?;
+        Ok(future)
+    }
+
+    /// 执行一次,等待返回的[Future]结束,见[System::start]
+    ///
+    /// 等待期间执行[Future]通过[WorldAccess]请求的操作,
+    /// 请求的操作中发生的panic也算作这个[System]的;
+    /// 返回的错误交给[World::set_error_handler]设置的函数处理
     #[cfg(feature = "async")]
-    pub(crate) async fn run_once(&mut self, world: &World) {
-        let System::Async(inner) = self;
-        inner.run_once(inner.build_args(world)).unwrap().await;
+    pub(crate) async fn run_once(&mut self, world: &mut World) -> std::thread::Result<()> {
+        let Some(future) = self.start(world)? else {
+            return Ok(());
+        };
+        let output = world.wait_system(future).await?;
+        world.finish_system(self.name(), output)
     }
 }
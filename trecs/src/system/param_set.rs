@@ -0,0 +1,163 @@
+use std::marker::PhantomData;
+
+use super::{state::SystemState, SystemParm};
+use crate::world::{UnsafeWorldCell, World};
+
+/// 一组可以互相冲突的参数,同一时间只能访问其中一个
+///
+/// T是由至多8个[SystemParm]组成的元组,通过`p0`,`p1`...依次访问,
+/// 每次访问都会重新创建参数,返回的参数借用了[ParamSet],所以不会同时存在
+///
+/// 成员之间的冲突会被忽略,但是每个成员自己,以及整个[ParamSet]与[System]的其他参数之间,
+/// 仍然不能冲突
+///
+/// ``` rust
+/// use trecs::{system::ParamSet, world::Query, World};
+///
+/// fn heal(mut set: ParamSet<(Query<&mut u32>, Query<(&u32, &&'static str)>)>) {
+///     for mut hp in set.p0().into_iter() {
+///         *hp += 1;
+///     }
+///     for (hp, name) in set.p1().into_iter() {
+///         println!("{name}: {hp}");
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.add_system(heal);
+/// world.run_once();
+/// ```
+///
+/// [System]: crate::system::System
+pub struct ParamSet<'w, T> {
+    world: UnsafeWorldCell<'w>,
+    /// 创建[ParamSet]时已经借用的数量,访问下一个成员之前释放之后的借用
+    #[cfg(feature = "debug-borrows")]
+    mark: usize,
+    _p: PhantomData<fn() -> T>,
+}
+
+macro_rules! impl_param_set {
+    ($(($t:ident, $p:ident)),*) => {
+        impl<$($t: SystemParm,)*> SystemParm for ParamSet<'_, ($($t,)*)> {
+            type Item<'w> = ParamSet<'w, ($($t,)*)>;
+
+            unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+                ParamSet {
+                    world,
+                    #[cfg(feature = "debug-borrows")]
+                    mark: world.borrows().mark(),
+                    _p: PhantomData,
+                }
+            }
+
+            fn prepare(world: &mut World) {
+                $($t::prepare(world);)*
+            }
+
+            fn init(state: &mut SystemState) {
+                let mut members = SystemState::new();
+                $(
+                    let mut member = state.member();
+                    $t::init(&mut member);
+                    state.conflicts.extend(member.take_conflicts());
+                    members.merge(member);
+                )*
+                // 成员之间的冲突是允许的
+                members.take_conflicts();
+                state.merge(members);
+            }
+        }
+
+        impl<$($t: SystemParm,)*> ParamSet<'_, ($($t,)*)> {
+            $(
+                #[doc = concat!("访问`", stringify!($t), "`")]
+                pub fn $p(&mut self) -> $t::Item<'_> {
+                    // 上一个成员已经不存在了
+                    #[cfg(feature = "debug-borrows")]
+                    unsafe {
+                        self.world.borrows().release_since(self.mark);
+                    }
+                    unsafe { $t::build(self.world) }
+                }
+            )*
+        }
+    };
+}
+
+impl_param_set!((P0, p0));
+impl_param_set!((P0, p0), (P1, p1));
+impl_param_set!((P0, p0), (P1, p1), (P2, p2));
+impl_param_set!((P0, p0), (P1, p1), (P2, p2), (P3, p3));
+impl_param_set!((P0, p0), (P1, p1), (P2, p2), (P3, p3), (P4, p4));
+impl_param_set!((P0, p0), (P1, p1), (P2, p2), (P3, p3), (P4, p4), (P5, p5));
+impl_param_set!(
+    (P0, p0),
+    (P1, p1),
+    (P2, p2),
+    (P3, p3),
+    (P4, p4),
+    (P5, p5),
+    (P6, p6)
+);
+impl_param_set!(
+    (P0, p0),
+    (P1, p1),
+    (P2, p2),
+    (P3, p3),
+    (P4, p4),
+    (P5, p5),
+    (P6, p6),
+    (P7, p7)
+);
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{self, Capture},
+        tools::Command,
+        world::{Local, Query, Res, ResMut},
+    };
+
+    #[test]
+    fn param_set() {
+        fn double(
+            mut set: ParamSet<(Query<&mut i32>, Query<&i32>)>,
+            capture: ResMut<Capture<i32>>,
+        ) {
+            for mut i in set.p0().into_iter() {
+                *i *= 2;
+            }
+            let capture = capture.into_or_init(Capture::new);
+            for i in set.p1().into_iter() {
+                capture.push(*i);
+            }
+        }
+
+        let mut world = World::new();
+        world.spawn((1, "a"));
+        world.spawn(2);
+        world.add_system(double);
+        test::tick(&mut world);
+        let mut captured = test::captured::<i32>(&mut world);
+        captured.sort();
+        assert_eq!(captured, [2, 4]);
+    }
+
+    #[test]
+    fn conflict() {
+        let mut world = World::new();
+        // 成员自己的冲突
+        let result = world.try_add_system(|_: ParamSet<(Query<(&i32, &mut i32)>,)>| {});
+        assert!(result.is_err());
+        // 与其他参数的冲突
+        let result =
+            world.try_add_system(|_: ParamSet<(Query<&i32>, Query<&u32>)>, _: Query<&mut u32>| {});
+        assert!(result.is_err());
+        let result = world.try_add_system(|_: ParamSet<(Local<u8>, Res<u8>)>, _: Local<u8>| {});
+        assert!(result.is_err());
+        let result = world.try_add_system(|_: ParamSet<(Query<&i32>, Res<u8>)>, _: Query<&i32>| {});
+        assert!(result.is_ok());
+    }
+}
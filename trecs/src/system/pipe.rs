@@ -0,0 +1,295 @@
+use std::marker::PhantomData;
+
+use super::{state::SystemState, InnerSystem, SystemOutput, SystemParm, SystemReturn};
+use crate::world::{UnsafeWorldCell, World};
+
+/// 从前一个[System]传入的值,只能作为第一个参数
+///
+/// 见[PipeSystem::pipe]
+///
+/// [System]: crate::system::System
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct In<T>(pub T);
+
+/// 可以被串联的[System] : 第一个参数可以是[In],可以有返回值
+///
+/// 通过[PipeSystem::pipe]串联之后,前一个的返回值会作为后一个的[In]传入,
+/// 最后一个的返回值为`()`或者`Result<(), E>`时,整个串联的[System]可以被添加进[World],
+/// 见[SystemReturn]
+///
+/// ``` rust
+/// use trecs::{
+///     system::{In, PipeSystem},
+///     world::Query,
+///     World,
+/// };
+///
+/// fn parse(query: Query<&&'static str>) -> Result<Vec<i32>, std::num::ParseIntError> {
+///     query.into_iter().map(|s| s.parse()).collect()
+/// }
+///
+/// fn handle(In(result): In<Result<Vec<i32>, std::num::ParseIntError>>) {
+///     if let Err(e) = result {
+///         println!("{e}");
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.add_system(parse.pipe(handle));
+/// world.run_once();
+/// ```
+///
+/// [System]: crate::system::System
+pub trait PipeSystem<Marker> {
+    /// 从前一个[System]传入的值的类型,没有[In]参数时为`()`
+    ///
+    /// [System]: crate::system::System
+    type In;
+    /// 返回值的类型
+    type Out;
+
+    /// 见[InnerSystem::prepare]
+    fn prepare(&self, world: &mut World);
+
+    /// 从[World]创建除了[In]之外的参数
+    fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()>;
+
+    /// 把参数的访问记录到state中
+    ///
+    /// 串联的[System]的访问被记录在同一个[SystemState]中
+    ///
+    /// [System]: crate::system::System
+    fn init(&self, state: &mut SystemState);
+
+    fn run(&mut self, input: Self::In, args: Box<()>) -> Self::Out;
+
+    /// 名字,即就是类型名
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// 把返回值作为next的[In]传入
+    fn pipe<N, M>(self, next: N) -> Pipe<Self, N, Marker, M>
+    where
+        Self: Sized,
+        N: PipeSystem<M, In = Self::Out>,
+    {
+        Pipe {
+            first: self,
+            second: next,
+            _m: PhantomData,
+        }
+    }
+}
+
+macro_rules! impl_pipe_system {
+    ($($t:ident),*) => {
+        // 没有参数时会生成多余的代码
+        #[allow(unused_variables, clippy::useless_transmute, clippy::let_unit_value)]
+        impl<F, R, $($t: SystemParm,)*> PipeSystem<(R, ($($t,)*))> for F
+        where
+            F: FnMut($($t,)*) -> R + for<'w> FnMut($($t::Item<'w>,)*) -> R,
+        {
+            type In = ();
+            type Out = R;
+
+            fn prepare(&self, world: &mut World) {
+                $($t::prepare(world);)*
+            }
+
+            fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()> {
+                unsafe { std::mem::transmute(Box::new(($($t::build(world),)*))) }
+            }
+
+            fn init(&self, state: &mut SystemState) {
+                $(state.init_param::<$t>();)*
+            }
+
+            fn run(&mut self, _input: (), args: Box<()>) -> R {
+                // 与System相同,'static不会泄露出去
+                let ($($t,)*) = unsafe {
+                    *std::mem::transmute::<Box<()>, Box<($($t::Item<'static>,)*)>>(args)
+                };
+                (self)($($t,)*)
+            }
+        }
+
+        #[allow(unused_variables, clippy::useless_transmute, clippy::let_unit_value)]
+        impl<F, I, R, $($t: SystemParm,)*> PipeSystem<(In<I>, R, ($($t,)*))> for F
+        where
+            F: FnMut(In<I>, $($t,)*) -> R + for<'w> FnMut(In<I>, $($t::Item<'w>,)*) -> R,
+        {
+            type In = I;
+            type Out = R;
+
+            fn prepare(&self, world: &mut World) {
+                $($t::prepare(world);)*
+            }
+
+            fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()> {
+                unsafe { std::mem::transmute(Box::new(($($t::build(world),)*))) }
+            }
+
+            fn init(&self, state: &mut SystemState) {
+                $(state.init_param::<$t>();)*
+            }
+
+            fn run(&mut self, input: I, args: Box<()>) -> R {
+                let ($($t,)*) = unsafe {
+                    *std::mem::transmute::<Box<()>, Box<($($t::Item<'static>,)*)>>(args)
+                };
+                (self)(In(input), $($t,)*)
+            }
+        }
+    };
+}
+impl_pipe_system!();
+trecs_proc::all_tuple!(impl_pipe_system, 16);
+
+/// 由[PipeSystem::pipe]串联的两个[System]
+///
+/// 两个[System]的参数在执行之前一起创建,所以它们之间也不能冲突
+///
+/// [System]: crate::system::System
+pub struct Pipe<A, B, MA, MB> {
+    first: A,
+    second: B,
+    _m: PhantomData<fn() -> (MA, MB)>,
+}
+
+impl<A, B, MA, MB> PipeSystem<Pipe<(), (), MA, MB>> for Pipe<A, B, MA, MB>
+where
+    A: PipeSystem<MA>,
+    B: PipeSystem<MB, In = A::Out>,
+{
+    type In = A::In;
+    type Out = B::Out;
+
+    fn prepare(&self, world: &mut World) {
+        self.first.prepare(world);
+        self.second.prepare(world);
+    }
+
+    fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()> {
+        let args = (self.first.build_args(world), self.second.build_args(world));
+        unsafe { std::mem::transmute(Box::new(args)) }
+    }
+
+    fn init(&self, state: &mut SystemState) {
+        self.first.init(state);
+        self.second.init(state);
+    }
+
+    fn run(&mut self, input: A::In, args: Box<()>) -> B::Out {
+        let (first, second) =
+            unsafe { *std::mem::transmute::<Box<()>, Box<(Box<()>, Box<()>)>>(args) };
+        let output = self.first.run(input, first);
+        self.second.run(output, second)
+    }
+}
+
+impl<A, B, MA, MB> InnerSystem<Pipe<(), (), MA, MB>> for Pipe<A, B, MA, MB>
+where
+    A: PipeSystem<MA, In = ()>,
+    B: PipeSystem<MB, In = A::Out>,
+    B::Out: SystemReturn,
+{
+    fn prepare(&self, world: &mut World) {
+        PipeSystem::prepare(self, world);
+    }
+
+    fn build_args(&self, world: UnsafeWorldCell<'_>) -> Box<()> {
+        PipeSystem::build_args(self, world)
+    }
+
+    fn init(&self) -> SystemState {
+        let mut state = SystemState::new();
+        PipeSystem::init(self, &mut state);
+        state
+    }
+
+    #[cfg(not(feature = "async"))]
+    fn run_once(&mut self, args: Box<()>) -> SystemOutput {
+        self.run((), args).into_result()
+    }
+
+    /// 串联的[System]是同步的,返回已经完成的[Future]
+    ///
+    /// [System]: crate::system::System
+    /// [Future]: std::future::Future
+    #[cfg(feature = "async")]
+    fn run_once(&mut self, args: Box<()>) -> SystemOutput {
+        let output = self.run((), args).into_result();
+        Some(Box::pin(std::future::ready(output)))
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        system::SystemRegistrationError,
+        test::{self, Capture},
+        tools::Command,
+        world::{Query, ResMut},
+    };
+
+    fn count(query: Query<&i32>) -> usize {
+        query.into_iter().count()
+    }
+
+    #[test]
+    fn pipe() {
+        fn double(In(count): In<usize>) -> usize {
+            count * 2
+        }
+        fn record(In(count): In<usize>, capture: ResMut<Capture<usize>>) {
+            capture.into_or_init(Capture::new).push(count);
+        }
+
+        let mut world = World::new();
+        world.spawn(1);
+        world.spawn(2);
+        world
+            .add_system(count.pipe(record))
+            .add_system(count.pipe(double).pipe(record));
+        test::tick(&mut world);
+        assert_eq!(test::captured::<usize>(&mut world), [2, 4]);
+    }
+
+    #[test]
+    fn conflict() {
+        fn write(In(_): In<usize>, _: Query<&mut i32>) {}
+
+        let mut world = World::new();
+        let result = world.try_add_system(count.pipe(write));
+        assert!(matches!(result, Err(SystemRegistrationError { .. })));
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod async_tests {
+    use super::*;
+    use crate::{
+        system::runtime::block_on,
+        test::{self, Capture},
+        tools::Command,
+        world::{Query, ResMut},
+    };
+
+    #[test]
+    fn pipe() {
+        fn count(query: Query<&i32>) -> usize {
+            query.into_iter().count()
+        }
+        fn record(In(count): In<usize>, capture: ResMut<Capture<usize>>) {
+            capture.into_or_init(Capture::new).push(count);
+        }
+
+        let mut world = World::new();
+        world.spawn(1);
+        world.add_system(count.pipe(record));
+        block_on(world.run_once());
+        assert_eq!(test::captured::<usize>(&mut world), [1]);
+    }
+}
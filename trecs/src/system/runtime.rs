@@ -0,0 +1,411 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex, MutexGuard, OnceLock, Weak,
+    },
+    task::{Context, Poll, Wake, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::World;
+
+/// 异步运行时提供的sleep函数,见[World::set_sleep]
+pub type AsyncSleep = fn(Duration) -> Pin<Box<dyn Future<Output = ()>>>;
+
+/// 异步运行时提供的spawn函数,见[World::set_runtime]
+pub type AsyncSpawn = fn(Pin<Box<dyn Future<Output = ()> + Send>>);
+
+/// 异步运行时的适配器,通过[World::set_runtime]使用
+///
+/// 启用`tokio`,`smol`或者`async-std` feature时分别提供[Tokio],[Smol]和[AsyncStd]
+pub trait Runtime {
+    /// 等待duration,[World::run_until]按照[World::set_tick_rate]等待时使用
+    fn sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()>>>;
+
+    /// 在运行时中执行一个独立的任务,见[World::spawn_task]
+    fn spawn(future: Pin<Box<dyn Future<Output = ()> + Send>>);
+}
+
+/// [tokio](https://docs.rs/tokio)的适配器
+///
+/// [World]需要在tokio的运行时中执行,并且启用了时间驱动
+#[cfg(feature = "tokio")]
+pub struct Tokio;
+
+#[cfg(feature = "tokio")]
+impl Runtime for Tokio {
+    fn sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()>>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn spawn(future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        drop(tokio::spawn(future));
+    }
+}
+
+/// [smol](https://docs.rs/smol)的适配器,任务在smol的全局执行器中执行
+#[cfg(feature = "smol")]
+pub struct Smol;
+
+#[cfg(feature = "smol")]
+impl Runtime for Smol {
+    fn sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()>>> {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+
+    fn spawn(future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        smol::spawn(future).detach();
+    }
+}
+
+/// [async-std](https://docs.rs/async-std)的适配器
+#[cfg(feature = "async-std")]
+pub struct AsyncStd;
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStd {
+    fn sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()>>> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+
+    fn spawn(future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        drop(async_std::task::spawn(future));
+    }
+}
+
+/// 见[yield_now]
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// 让出一次执行权,这样同一个运行时中的其他任务也可以执行
+///
+/// 不依赖于任何运行时
+pub fn yield_now() -> impl Future<Output = ()> {
+    YieldNow(false)
+}
+
+/// [Sleep]最近一次被poll时的[Waker],计时线程通过它唤醒
+type SharedWaker = Arc<Mutex<Waker>>;
+
+/// 见[sleep]
+struct Sleep {
+    deadline: Instant,
+    /// 第一次poll时向计时线程注册,之后只更新其中的[Waker]
+    waker: Option<SharedWaker>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        match &self.waker {
+            Some(waker) => {
+                let mut waker = lock(waker);
+                if !waker.will_wake(cx.waker()) {
+                    waker.clone_from(cx.waker());
+                }
+            }
+            None => {
+                let waker = Arc::new(Mutex::new(cx.waker().clone()));
+                // 计时线程退出时只能直接唤醒,退化为不断地poll
+                if timer()
+                    .send((self.deadline, Arc::downgrade(&waker)))
+                    .is_err()
+                {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                self.waker = Some(waker);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poison| poison.into_inner())
+}
+
+/// 向计时线程注册的结束时间和[SharedWaker]
+type Registration = (Instant, Weak<Mutex<Waker>>);
+
+/// 唯一的计时线程,到达时间后唤醒对应的[Waker]
+///
+/// 只持有[Weak],已经被Drop的[Sleep]不会被唤醒
+fn timer() -> Sender<Registration> {
+    static TIMER: OnceLock<Mutex<Sender<Registration>>> = OnceLock::new();
+    let timer = TIMER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Registration>();
+        std::thread::Builder::new()
+            .name("trecs-timer".into())
+            .spawn(move || {
+                let mut pending: Vec<Registration> = vec![];
+                loop {
+                    let now = Instant::now();
+                    pending.retain(|(deadline, waker)| {
+                        let waiting = *deadline > now;
+                        if !waiting {
+                            if let Some(waker) = waker.upgrade() {
+                                lock(&waker).wake_by_ref();
+                            }
+                        }
+                        waiting && waker.strong_count() != 0
+                    });
+                    let received = match pending.iter().map(|(deadline, _)| *deadline).min() {
+                        Some(deadline) => receiver.recv_timeout(deadline - now),
+                        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    };
+                    match received {
+                        Ok(entry) => pending.push(entry),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            })
+            .expect("无法创建计时线程");
+        Mutex::new(sender)
+    });
+    lock(timer).clone()
+}
+
+/// 等待duration,期间不会阻塞当前线程
+///
+/// 不依赖于任何运行时,由一个单独的计时线程在到达时间后唤醒
+pub fn sleep(duration: Duration) -> impl Future<Output = ()> {
+    Sleep {
+        deadline: Instant::now() + duration,
+        waker: None,
+    }
+}
+
+impl World {
+    /// 设置异步运行时的sleep函数,[World::run_until]按照[World::set_tick_rate]等待时使用
+    ///
+    /// 没有设置时使用[sleep],不会阻塞运行时的线程,但是每次等待都需要和计时线程通信
+    ///
+    /// ``` rust,ignore
+    /// world
+    ///     .set_sleep(|duration| Box::pin(tokio::time::sleep(duration)))
+    ///     .run_at(60.0)
+    ///     .await;
+    /// ```
+    pub fn set_sleep(&mut self, sleep: AsyncSleep) -> &mut Self {
+        self.sleep = Some(sleep);
+        self
+    }
+
+    /// 使用运行时R的sleep和spawn,见[Runtime]
+    ///
+    /// ``` rust,ignore
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let mut world = World::new();
+    ///     world.set_runtime::<Tokio>().run_at(60.0).await;
+    /// }
+    /// ```
+    pub fn set_runtime<R: Runtime>(&mut self) -> &mut Self {
+        self.sleep = Some(R::sleep);
+        self.spawn = Some(R::spawn);
+        self
+    }
+
+    /// 在[World::set_runtime]设置的运行时中执行一个独立的任务,任务的结果被丢弃
+    ///
+    /// 没有设置时在一个新的线程中执行,异步[System]可以通过[WorldAccess]调用
+    ///
+    /// [WorldAccess]: crate::system::WorldAccess
+    pub fn spawn_task(&self, future: impl Future<Output = ()> + Send + 'static) {
+        match self.spawn {
+            Some(spawn) => spawn(Box::pin(future)),
+            None => {
+                std::thread::Builder::new()
+                    .name("trecs-async-task".into())
+                    .spawn(move || block_on(future))
+                    .expect("无法创建执行任务的线程");
+            }
+        }
+    }
+}
+
+/// 在当前线程中执行future,没有被唤醒时park当前线程
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(std::thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static SLEPT: AtomicUsize = AtomicUsize::new(0);
+
+    fn fake_sleep(_: Duration) -> Pin<Box<dyn Future<Output = ()>>> {
+        SLEPT.fetch_add(1, Ordering::Relaxed);
+        Box::pin(yield_now())
+    }
+
+    #[test]
+    fn runner() {
+        let mut world = World::new();
+        let mut frames = 0;
+        block_on(world.run_until(|_| {
+            frames += 1;
+            frames > 3
+        }));
+        assert_eq!(SLEPT.load(Ordering::Relaxed), 0);
+
+        // 假的sleep不会真的等待,所以下一帧开始的时间总是在1秒之后,每一帧之后都需要等待
+        frames = 0;
+        world.set_tick_rate(1.0).set_sleep(fake_sleep);
+        block_on(world.run_until(|_| {
+            frames += 1;
+            frames > 3
+        }));
+        assert_eq!(SLEPT.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn register_once() {
+        struct Flag(std::sync::atomic::AtomicBool);
+
+        impl Wake for Flag {
+            fn wake(self: Arc<Self>) {
+                self.0.store(true, Ordering::Release);
+            }
+        }
+
+        let [first, second] = [(); 2].map(|_| Arc::new(Flag(Default::default())));
+        let mut sleep = std::pin::pin!(sleep(Duration::from_millis(20)));
+        for flag in [&first, &second] {
+            let waker = Waker::from(flag.clone());
+            let poll = sleep.as_mut().poll(&mut Context::from_waker(&waker));
+            assert!(poll.is_pending());
+        }
+        // 只注册了一次,到达时间后唤醒最近一次poll时的Waker
+        let start = Instant::now();
+        while !second.0.load(Ordering::Acquire) {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::yield_now();
+        }
+        assert!(!first.0.load(Ordering::Acquire));
+    }
+
+    /// 在运行时中执行的[World],System中使用运行时的计时器,通过[World::spawn_task]发送结果
+    #[cfg(any(feature = "tokio", feature = "smol", feature = "async-std"))]
+    fn drive<R: Runtime + 'static>() -> impl Future<Output = usize> {
+        let (sender, receiver) = mpsc::channel();
+        let mut world = World::new();
+        world
+            .set_runtime::<R>()
+            .set_tick_rate(200.0)
+            .add_system(|| R::sleep(Duration::from_millis(1)))
+            .add_system(move |access: crate::system::WorldAccess| {
+                let sender = sender.clone();
+                async move {
+                    access
+                        .run(move |world| world.spawn_task(async move { sender.send(()).unwrap() }))
+                        .await;
+                }
+            });
+        async move {
+            let mut frames = 0;
+            world
+                .run_until(|_| {
+                    frames += 1;
+                    frames > 3
+                })
+                .await;
+            // 任务可能在其他线程中执行
+            let start = Instant::now();
+            while receiver.try_iter().count() < 3 {
+                assert!(start.elapsed() < Duration::from_secs(5));
+                yield_now().await;
+            }
+            frames
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let frames = runtime.block_on(drive::<Tokio>());
+        assert_eq!(frames, 4);
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn smol() {
+        assert_eq!(smol::block_on(drive::<Smol>()), 4);
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn async_std() {
+        let frames = async_std::task::block_on(drive::<AsyncStd>());
+        assert_eq!(frames, 4);
+    }
+
+    #[test]
+    fn spawn_thread() {
+        let (sender, receiver) = mpsc::channel();
+        World::new().spawn_task(async move { sender.send(1).unwrap() });
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(1));
+    }
+
+    #[test]
+    fn real_sleep() {
+        let start = Instant::now();
+        block_on(sleep(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // 没有设置sleep函数时也会真的等待
+        let mut world = World::new();
+        let start = Instant::now();
+        let mut frames = 0;
+        block_on(world.set_tick_rate(100.0).run_until(|_| {
+            frames += 1;
+            frames > 5
+        }));
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
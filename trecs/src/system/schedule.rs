@@ -0,0 +1,789 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use super::{
+    condition::{Condition, InnerCondition},
+    InnerSystem, ScheduleError, System, SystemRegistrationError,
+};
+
+/// [System]的标签,以及与其他[System]的顺序约束
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SystemOrder {
+    pub(crate) labels: Vec<&'static str>,
+    /// 在带有这些标签的[System]之前执行
+    pub(crate) before: Vec<&'static str>,
+    /// 在带有这些标签的[System]之后执行
+    pub(crate) after: Vec<&'static str>,
+    pub(crate) stage: Stage,
+    /// 作为startup system添加时所在的阶段
+    pub(crate) startup_stage: StartupStage,
+}
+
+/// 可以被添加进[World]的[System]
+///
+/// 可以加上标签,并且通过标签指定与其他[System]的顺序,
+/// 带有相同标签的[System]组成一个[SystemSet]
+///
+/// ``` rust
+/// use trecs::{system::IntoSystem, World};
+///
+/// fn movement() {}
+/// fn render() {}
+///
+/// let mut world = World::new();
+/// world
+///     .add_system(render.after("movement"))
+///     .add_system(movement.label("movement"));
+/// world.run_once();
+/// ```
+///
+/// [World]: crate::World
+pub trait IntoSystem<Marker>: Sized {
+    /// 计算[SystemState],参数之间有冲突时返回[SystemRegistrationError]
+    ///
+    /// [SystemState]: crate::system::state::SystemState
+    fn into_system(self) -> Result<System, SystemRegistrationError>;
+
+    /// 加上标签,一个[System]可以有多个标签,多个[System]也可以有相同的标签
+    ///
+    /// 见[World::configure_set]和[World::set_enabled]
+    ///
+    /// [World::configure_set]: crate::World::configure_set
+    /// [World::set_enabled]: crate::World::set_enabled
+    fn label(self, label: &'static str) -> SystemConfig {
+        SystemConfig::new(self).map(|order| order.labels.push(label))
+    }
+
+    /// 在所有带有label的[System]之前执行
+    fn before(self, label: &'static str) -> SystemConfig {
+        SystemConfig::new(self).map(|order| order.before.push(label))
+    }
+
+    /// 在所有带有label的[System]之后执行
+    fn after(self, label: &'static str) -> SystemConfig {
+        SystemConfig::new(self).map(|order| order.after.push(label))
+    }
+
+    /// 只有condition返回true时才执行,可以附加多个运行条件
+    ///
+    /// 每次执行之前都会判断,运行条件的参数之间有冲突时,添加[System]会失败
+    fn run_if<M>(self, condition: impl InnerCondition<M> + Send) -> SystemConfig {
+        let system = SystemConfig::new(self).system.and_then(|mut system| {
+            system.conditions.push(Condition::new(condition)?);
+            Ok(system)
+        });
+        SystemConfig { system }
+    }
+
+    /// 在stage阶段执行,默认为[Stage::Update]
+    ///
+    /// 顺序约束只对同一阶段中的[System]有效
+    fn in_stage(self, stage: Stage) -> SystemConfig {
+        SystemConfig::new(self).map(|order| order.stage = stage)
+    }
+
+    /// 作为startup system添加时,在stage阶段执行,默认为[StartupStage::Startup]
+    ///
+    /// 同[IntoSystem::in_stage]一样,顺序约束只对同一阶段中的startup system有效
+    fn in_startup_stage(self, stage: StartupStage) -> SystemConfig {
+        SystemConfig::new(self).map(|order| order.startup_stage = stage)
+    }
+}
+
+impl<M, S: InnerSystem<M> + Send> IntoSystem<M> for S {
+    fn into_system(self) -> Result<System, SystemRegistrationError> {
+        System::new(self)
+    }
+}
+
+/// 加上了标签或者顺序约束的[System]
+///
+/// 通过[IntoSystem::label],[IntoSystem::before]或者[IntoSystem::after]获得
+pub struct SystemConfig {
+    system: Result<System, SystemRegistrationError>,
+}
+
+impl SystemConfig {
+    fn new<M>(system: impl IntoSystem<M>) -> Self {
+        Self {
+            system: system.into_system(),
+        }
+    }
+
+    fn map(mut self, f: impl FnOnce(&mut SystemOrder)) -> Self {
+        if let Ok(system) = &mut self.system {
+            f(&mut system.order);
+        }
+        self
+    }
+}
+
+impl IntoSystem<SystemConfig> for SystemConfig {
+    fn into_system(self) -> Result<System, SystemRegistrationError> {
+        self.system
+    }
+}
+
+impl System {
+    /// 是否带有label
+    pub(crate) fn has_label(&self, label: &str) -> bool {
+        self.order.labels.contains(&label)
+    }
+
+    /// 作为startup system添加时所在的阶段
+    pub(crate) fn startup_stage(&self) -> StartupStage {
+        self.order.startup_stage
+    }
+
+    /// 所有标签对应的[SystemSet]
+    fn sets<'a>(&'a self, sets: &'a Sets) -> impl Iterator<Item = &'a SystemSet> {
+        self.order.labels.iter().filter_map(|label| sets.get(label))
+    }
+
+    /// 是否因为顺序约束,必须在other之前执行
+    ///
+    /// 包括通过[SystemSet]对标签施加的约束
+    pub(crate) fn runs_before(&self, other: &System, sets: &Sets) -> bool {
+        let mut before =
+            (self.order.before.iter()).chain(self.sets(sets).flat_map(|set| &set.before));
+        let mut after =
+            (other.order.after.iter()).chain(other.sets(sets).flat_map(|set| &set.after));
+        before.any(|label| other.order.labels.contains(label))
+            || after.any(|label| self.order.labels.contains(label))
+    }
+}
+
+/// 每个[System]必须在哪些[System]之前执行,不包括间接的
+fn successors(systems: &[System], sets: &Sets) -> Vec<Vec<usize>> {
+    systems
+        .iter()
+        .enumerate()
+        .map(|(index, system)| {
+            (0..systems.len())
+                .filter(|&other| other != index && system.runs_before(&systems[other], sets))
+                .collect()
+        })
+        .collect()
+}
+
+/// 按照顺序约束排好序之后,每个[System]原来的下标
+///
+/// 没有约束的[System]之间保持原来的顺序,顺序约束形成环时返回[ScheduleError::Cycle]
+fn sorted(systems: &[System], sets: &Sets) -> Result<Vec<usize>, ScheduleError> {
+    let successors = successors(systems, sets);
+    let mut in_degree = vec![0; systems.len()];
+    for &next in successors.iter().flatten() {
+        in_degree[next] += 1;
+    }
+    // 每次都选出下标最小的,这样没有约束时顺序不变
+    let mut ready = (0..systems.len())
+        .filter(|&index| in_degree[index] == 0)
+        .collect::<BTreeSet<_>>();
+    let mut order = Vec::with_capacity(systems.len());
+    while let Some(index) = ready.pop_first() {
+        order.push(index);
+        for &next in &successors[index] {
+            in_degree[next] -= 1;
+            if in_degree[next] == 0 {
+                ready.insert(next);
+            }
+        }
+    }
+    if order.len() != systems.len() {
+        return Err(ScheduleError::Cycle(find_cycle(
+            systems,
+            &successors,
+            &in_degree,
+        )));
+    }
+    Ok(order)
+}
+
+/// 找出一个环,in_degree不为0的[System]都在环上,或者在环之后
+fn find_cycle(
+    systems: &[System],
+    successors: &[Vec<usize>],
+    in_degree: &[usize],
+) -> Vec<&'static str> {
+    // 这些System都至少有一个同样不为0的前驱,沿着前驱走下去一定会回到走过的System
+    let predecessor = |node: usize| {
+        (0..systems.len())
+            .find(|&prev| in_degree[prev] != 0 && successors[prev].contains(&node))
+            .unwrap()
+    };
+    let mut path = vec![in_degree.iter().position(|&degree| degree != 0).unwrap()];
+    loop {
+        let prev = predecessor(*path.last().unwrap());
+        if let Some(start) = path.iter().position(|&node| node == prev) {
+            let mut cycle = path[start..]
+                .iter()
+                .rev()
+                .map(|&node| systems[node].name())
+                .collect::<Vec<_>>();
+            cycle.push(cycle[0]);
+            return cycle;
+        }
+        path.push(prev);
+    }
+}
+
+/// 按照order重新排列systems,order中是每个位置上[System]原来的下标
+fn reorder(systems: &mut Vec<System>, order: Vec<usize>) {
+    let mut slots = std::mem::take(systems)
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+    *systems = order
+        .into_iter()
+        .map(|index| slots[index].take().unwrap())
+        .collect();
+}
+
+/// 按照顺序约束对systems排序,见[sorted]
+///
+/// 约束形成环时返回[ScheduleError::Cycle],并且不会改变顺序
+pub(crate) fn sort_systems(systems: &mut Vec<System>, sets: &Sets) -> Result<(), ScheduleError> {
+    let order = sorted(systems, sets)?;
+    reorder(systems, order);
+    Ok(())
+}
+
+/// 按照顺序约束排好序之后的[System],约束形成环时保持原来的顺序
+pub(crate) fn sorted_or_unchanged<'a>(systems: &'a [System], sets: &Sets) -> Vec<&'a System> {
+    match sorted(systems, sets) {
+        Ok(order) => order.into_iter().map(|index| &systems[index]).collect(),
+        Err(_) => systems.iter().collect(),
+    }
+}
+
+/// ordered\[i\]\[j\]表示第i个[System]是否必须在第j个[System]之前执行,包括间接的
+fn ordered(systems: &[System], sets: &Sets) -> Vec<Vec<bool>> {
+    let successors = successors(systems, sets);
+    let mut ordered = vec![vec![false; systems.len()]; systems.len()];
+    for (start, row) in ordered.iter_mut().enumerate() {
+        let mut stack = successors[start].clone();
+        while let Some(node) = stack.pop() {
+            if !row[node] {
+                row[node] = true;
+                stack.extend(&successors[node]);
+            }
+        }
+    }
+    ordered
+}
+
+/// 每一帧中依次执行的阶段
+///
+/// 阶段之间是同步点,前一个阶段的[System]全部结束之后,后一个阶段的[System]才会开始
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Stage {
+    PreUpdate,
+    /// 以固定的时间间隔执行,每一帧可能执行零次或者多次,见[FixedTime]
+    ///
+    /// [FixedTime]: crate::time::FixedTime
+    FixedUpdate,
+    #[default]
+    Update,
+    PostUpdate,
+}
+
+impl Stage {
+    /// 所有阶段,按照执行的顺序排列
+    pub const ALL: [Stage; 4] = [
+        Stage::PreUpdate,
+        Stage::FixedUpdate,
+        Stage::Update,
+        Stage::PostUpdate,
+    ];
+
+    /// 阶段的名字,如"update"
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::PreUpdate => "pre_update",
+            Stage::FixedUpdate => "fixed_update",
+            Stage::Update => "update",
+            Stage::PostUpdate => "post_update",
+        }
+    }
+}
+
+/// startup system执行的阶段,在第一帧之前依次执行
+///
+/// 每个阶段中的startup system按照与[Stage]中相同的顺序约束排序,
+/// 所以初始化资源的[System]可以放在[StartupStage::PreStartup],
+/// 或者通过标签保证在使用资源的[System]之前执行
+///
+/// ``` rust
+/// use trecs::{
+///     system::{IntoSystem, StartupStage},
+///     world::{Res, ResMut},
+///     World,
+/// };
+///
+/// struct Config(u32);
+///
+/// fn load(config: ResMut<Config>) {
+///     config.into_or_init(|| Config(3));
+/// }
+///
+/// fn spawn_enemies(config: Res<Config>, log: ResMut<Vec<u32>>) {
+///     log.into_or_init(Vec::new).push(config.get().unwrap().0);
+/// }
+///
+/// fn check(log: Res<Vec<u32>>) {
+///     assert_eq!(log.get().unwrap(), &[3]);
+/// }
+///
+/// let mut world = World::new();
+/// world
+///     .add_startup_system(check.in_startup_stage(StartupStage::PostStartup))
+///     .add_startup_system(spawn_enemies.after("load"))
+///     .add_startup_system(load.label("load"));
+/// world.startup();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum StartupStage {
+    PreStartup,
+    #[default]
+    Startup,
+    PostStartup,
+}
+
+impl StartupStage {
+    /// 所有阶段,按照执行的顺序排列
+    pub const ALL: [StartupStage; 3] = [
+        StartupStage::PreStartup,
+        StartupStage::Startup,
+        StartupStage::PostStartup,
+    ];
+
+    /// 阶段的名字,如"startup"
+    pub fn name(self) -> &'static str {
+        match self {
+            StartupStage::PreStartup => "pre_startup",
+            StartupStage::Startup => "startup",
+            StartupStage::PostStartup => "post_startup",
+        }
+    }
+}
+
+/// 带有同一个标签的一组[System]
+///
+/// 通过[World::configure_set]为组中所有[System]指定顺序
+///
+/// ``` rust
+/// use trecs::{system::{IntoSystem, SystemSet}, World};
+///
+/// fn gravity() {}
+/// fn collide() {}
+/// fn input() {}
+///
+/// let mut world = World::new();
+/// world
+///     .configure_set(SystemSet::new("physics").after("input"))
+///     .add_system(gravity.label("physics"))
+///     .add_system(collide.label("physics"))
+///     .add_system(input.label("input"));
+/// world.run_once();
+/// ```
+///
+/// [World::configure_set]: crate::World::configure_set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemSet {
+    label: &'static str,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+}
+
+impl SystemSet {
+    pub fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            before: vec![],
+            after: vec![],
+        }
+    }
+
+    /// 组中的[System]都在带有label的[System]之前执行
+    pub fn before(mut self, label: &'static str) -> Self {
+        self.before.push(label);
+        self
+    }
+
+    /// 组中的[System]都在带有label的[System]之后执行
+    pub fn after(mut self, label: &'static str) -> Self {
+        self.after.push(label);
+        self
+    }
+}
+
+/// 所有被配置过的[SystemSet],以标签为键
+pub(crate) type Sets = HashMap<&'static str, SystemSet>;
+
+/// [World]中每个[Stage]的[System],以及它们的顺序约束
+#[derive(Default)]
+pub(crate) struct Schedule {
+    /// 与[Stage::ALL]一一对应
+    stages: [Vec<System>; 4],
+    pub(crate) sets: Sets,
+    /// 被禁用的标签
+    disabled: HashSet<&'static str>,
+    /// 是否需要重新排序
+    changed: bool,
+    /// 是否正在执行各个[Stage],这期间添加和移除[System]推迟到这一帧结束时生效
+    running: bool,
+    /// 执行期间添加的[System]
+    added: Vec<System>,
+    /// 执行期间移除的标签
+    removed: Vec<&'static str>,
+}
+
+impl Schedule {
+    /// 使用已经配置过的sets创建空的[Schedule]
+    pub(crate) fn with_sets(sets: Sets) -> Self {
+        Self {
+            sets,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn stage(&self, stage: Stage) -> &Vec<System> {
+        &self.stages[stage as usize]
+    }
+
+    pub(crate) fn stage_mut(&mut self, stage: Stage) -> &mut Vec<System> {
+        &mut self.stages[stage as usize]
+    }
+
+    /// 按照执行的顺序排列的所有[System]
+    pub(crate) fn systems(&self) -> impl Iterator<Item = &System> {
+        self.stages.iter().flatten()
+    }
+
+    pub(crate) fn systems_mut(&mut self) -> impl Iterator<Item = &mut System> {
+        self.stages.iter_mut().flatten()
+    }
+
+    /// 取出所有[System]
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn take_systems(&mut self) -> Vec<System> {
+        self.stages.iter_mut().flat_map(std::mem::take).collect()
+    }
+
+    pub(crate) fn add(&mut self, system: System) {
+        if self.running {
+            self.added.push(system);
+            return;
+        }
+        let stage = system.order.stage;
+        self.stage_mut(stage).push(system);
+        self.changed = true;
+    }
+
+    /// 移除带有label的所有[System],不会改变其他[System]的顺序
+    pub(crate) fn remove(&mut self, label: &'static str) {
+        self.added.retain(|system| !system.has_label(label));
+        if self.running {
+            self.removed.push(label);
+            return;
+        }
+        for systems in &mut self.stages {
+            systems.retain(|system| !system.has_label(label));
+        }
+    }
+
+    /// 开始或者结束执行各个[Stage],结束时执行期间的添加和移除才生效
+    pub(crate) fn set_running(&mut self, running: bool) {
+        self.running = running;
+        if running {
+            return;
+        }
+        for label in std::mem::take(&mut self.removed) {
+            self.remove(label);
+        }
+        for system in std::mem::take(&mut self.added) {
+            self.add(system);
+        }
+    }
+
+    pub(crate) fn configure_set(&mut self, set: SystemSet) {
+        self.sets.insert(set.label, set);
+        self.changed = true;
+    }
+
+    pub(crate) fn set_enabled(&mut self, label: &'static str, enabled: bool) {
+        match enabled {
+            true => self.disabled.remove(label),
+            false => self.disabled.insert(label),
+        };
+    }
+
+    pub(crate) fn is_label_enabled(&self, label: &str) -> bool {
+        !self.disabled.contains(label)
+    }
+
+    /// [System]的名字和所有标签都没有被禁用
+    pub(crate) fn is_enabled(&self, system: &System) -> bool {
+        self.is_label_enabled(system.name())
+            && system
+                .order
+                .labels
+                .iter()
+                .all(|label| self.is_label_enabled(label))
+    }
+
+    pub(crate) fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// 按照顺序约束对每个[Stage]进行排序
+    ///
+    /// 有顺序约束形成环时返回[ScheduleError::Cycle],并且不会改变任何[Stage]
+    pub(crate) fn build(&mut self) -> Result<(), ScheduleError> {
+        let orders = self
+            .stages
+            .iter()
+            .map(|systems| sorted(systems, &self.sets))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (systems, order) in self.stages.iter_mut().zip(orders) {
+            reorder(systems, order);
+        }
+        self.changed = false;
+        Ok(())
+    }
+
+    /// 见[ordered]
+    pub(crate) fn ordered(&self, systems: &[System]) -> Vec<Vec<bool>> {
+        ordered(systems, &self.sets)
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use std::any::type_name_of_val as name;
+
+    use super::*;
+    use crate::{
+        test::{self, Capture},
+        world::ResMut,
+        World,
+    };
+
+    fn names(systems: &[System]) -> Vec<&'static str> {
+        systems.iter().map(System::name).collect()
+    }
+
+    #[test]
+    fn sort_by_labels() {
+        fn a() {}
+        fn b() {}
+        fn c() {}
+        fn d() {}
+
+        let mut schedule = Schedule::default();
+        schedule.add(c.after("b").into_system().unwrap());
+        schedule.add(a.label("a").into_system().unwrap());
+        schedule.add(d.into_system().unwrap());
+        schedule.add(b.label("b").after("a").into_system().unwrap());
+        schedule.build().unwrap();
+        let systems = schedule.stage(Stage::Update);
+        assert_eq!(names(systems), [name(&a), name(&d), name(&b), name(&c)]);
+
+        let ordered = schedule.ordered(systems);
+        assert!(ordered[0][3] && ordered[2][3] && ordered[0][2]);
+        assert!(!ordered[1][2] && !ordered[3][0]);
+    }
+
+    #[test]
+    fn cycle() {
+        fn a() {}
+        fn b() {}
+        fn c() {}
+
+        let mut schedule = Schedule::default();
+        schedule.add(c.into_system().unwrap());
+        schedule.add(a.label("a").after("b").into_system().unwrap());
+        schedule.add(b.label("b").before("c").after("a").into_system().unwrap());
+        let before = names(schedule.stage(Stage::Update));
+        let Err(ScheduleError::Cycle(cycle)) = schedule.build() else {
+            panic!("没有发现环");
+        };
+        assert_eq!(cycle.len(), 3);
+        assert_eq!(cycle[0], cycle[2]);
+        assert_eq!(names(schedule.stage(Stage::Update)), before);
+    }
+
+    #[test]
+    fn sets() {
+        fn a() {}
+        fn b() {}
+        fn c() {}
+
+        let mut schedule = Schedule::default();
+        schedule.add(a.label("physics").into_system().unwrap());
+        schedule.add(b.label("physics").into_system().unwrap());
+        schedule.add(c.label("input").into_system().unwrap());
+        schedule.configure_set(SystemSet::new("physics").after("input"));
+        schedule.build().unwrap();
+        let systems = schedule.stage(Stage::Update);
+        assert_eq!(names(systems), [name(&c), name(&a), name(&b)]);
+    }
+
+    #[test]
+    fn run_in_order() {
+        fn first(capture: ResMut<Capture<&'static str>>) {
+            capture.into_or_init(Capture::new).push("first");
+        }
+        fn second(capture: ResMut<Capture<&'static str>>) {
+            capture.into_or_init(Capture::new).push("second");
+        }
+
+        let mut world = World::new();
+        world
+            .add_system(second.label("second").after("first"))
+            .add_system(first.label("first"));
+        test::tick(&mut world);
+        world.add_system(first.after("second"));
+        test::tick(&mut world);
+        assert_eq!(
+            test::captured::<&str>(&mut world),
+            ["first", "second", "first", "second", "first"]
+        );
+    }
+
+    #[test]
+    fn stages() {
+        fn pre(capture: ResMut<Capture<&'static str>>) {
+            capture.into_or_init(Capture::new).push("pre");
+        }
+        fn update(capture: ResMut<Capture<&'static str>>) {
+            capture.into_or_init(Capture::new).push("update");
+        }
+        fn post(capture: ResMut<Capture<&'static str>>) {
+            capture.into_or_init(Capture::new).push("post");
+        }
+
+        let mut world = World::new();
+        world
+            .add_system(post.in_stage(Stage::PostUpdate))
+            .add_system(update)
+            .add_system(pre.in_stage(Stage::PreUpdate).label("pre"));
+        test::tick(&mut world);
+        world.set_enabled("pre", false);
+        assert!(!world.is_enabled("pre"));
+        test::tick(&mut world);
+        // 没有标签的System可以通过名字禁用
+        world
+            .set_enabled("pre", true)
+            .set_enabled(name(&update), false);
+        test::tick(&mut world);
+        assert_eq!(
+            test::captured::<&str>(&mut world),
+            ["pre", "update", "post", "update", "post", "pre", "post"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "形成了环")]
+    fn run_cycle() {
+        fn a() {}
+        let mut world = World::new();
+        world.add_system(a.label("a").before("b"));
+        world.add_system(a.label("b").before("a"));
+        world.run_once();
+    }
+
+    #[test]
+    fn add_and_remove_at_runtime() {
+        fn a(log: ResMut<Vec<&'static str>>) {
+            log.into_or_init(Vec::new).push("a");
+        }
+        fn b(log: ResMut<Vec<&'static str>>) {
+            log.into_or_init(Vec::new).push("b");
+        }
+        fn swap(world: &mut World) {
+            world
+                .remove_system("a")
+                .add_system(b.label("b").in_stage(Stage::PostUpdate));
+        }
+
+        let mut world = World::new();
+        world
+            .add_startup_system(a)
+            .add_system(a.label("a").in_stage(Stage::PostUpdate))
+            .add_system(swap.label("swap"));
+        world.remove_system("nothing");
+        world.startup();
+        // 执行期间的修改在这一帧结束时才生效
+        world.run_once();
+        world.remove_system("swap");
+        world.run_once();
+        let log = world.resource::<Vec<&'static str>>().unwrap();
+        assert_eq!(log, &["a", "a", "b"]);
+        assert_eq!(names(world.schedule.stage(Stage::PostUpdate)), [name(&b)]);
+        assert!(world.schedule.stage(Stage::Update).is_empty());
+    }
+
+    #[test]
+    fn startup_stages() {
+        macro_rules! log {
+            ($($name:ident),*) => {$(
+                fn $name(log: ResMut<Vec<&'static str>>) {
+                    log.into_or_init(Vec::new).push(stringify!($name));
+                }
+            )*};
+        }
+        log!(pre, first, init, after_init, post, later);
+        fn add_later(world: &mut World) {
+            world.add_startup_system(later.in_startup_stage(StartupStage::PreStartup));
+        }
+
+        let mut world = World::new();
+        world
+            .add_startup_system(post.in_startup_stage(StartupStage::PostStartup))
+            .add_startup_system(after_init.after("init"))
+            .add_startup_system(first)
+            .add_startup_system(init.label("init"))
+            .add_startup_system(pre.in_startup_stage(StartupStage::PreStartup))
+            .add_startup_system(add_later);
+        let graph = world.schedule_graph();
+        // 阶段之间有两个同步点
+        assert_eq!((graph.sets().len(), graph.nodes().len()), (3, 8));
+
+        // 执行期间添加的PreStartup阶段的System在PostStartup之前执行
+        world.startup();
+        let log = world.resource::<Vec<&'static str>>().unwrap();
+        assert_eq!(
+            log,
+            &["pre", "first", "init", "after_init", "later", "post"]
+        );
+        assert!(world.startup_systems.iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn named_schedules() {
+        fn render(capture: ResMut<Capture<&'static str>>) {
+            capture.into_or_init(Capture::new).push("render");
+        }
+        fn prepare(capture: ResMut<Capture<&'static str>>) {
+            capture.into_or_init(Capture::new).push("prepare");
+        }
+        fn add_more(world: &mut World) {
+            world.add_system_to("render", prepare.label("prepare").before("render"));
+        }
+
+        let mut world = World::new();
+        world
+            .add_system_to("render", render.label("render"))
+            .add_system_to("render", add_more.in_stage(Stage::PreUpdate))
+            .run_schedule("missing");
+        test::tick(&mut world);
+        assert!(test::captured::<&str>(&mut world).is_empty());
+        // 执行期间添加的System从下一次开始执行,并且会被排序
+        world.run_schedule("render").run_schedule("render");
+        world.remove_system("prepare").run_schedule("render");
+        assert_eq!(
+            test::captured::<&str>(&mut world),
+            ["render", "prepare", "render", "render"]
+        );
+    }
+}
@@ -1,10 +1,12 @@
 use std::{
     any::{type_name, Any, TypeId},
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap},
 };
 
 use crate::tools::WorldFetch;
 
+use super::{Conflict, ParamConflict, SystemParm};
+
 /// [System]的"状态"
 ///
 /// 计算[System]获取的资源是否会破坏别名规则,
@@ -12,11 +14,32 @@ use crate::tools::WorldFetch;
 ///
 /// 算是最后抢救一下unsafe遍布的代码吧
 ///
-/// 仅仅在[System]第一次执行时进行计算,
+/// 在[System]被添加进[World]时计算,并且由[System]保存
+///
+/// [System]: crate::system::System
+/// [World]: crate::World
 pub struct SystemState {
     pub(crate) alias_map: AliasMap,
     pub(crate) resources: bool,
-    pub(crate) res: HashSet<TypeId>,
+    /// <资源的TypeId,(资源的类型名,是否可以修改)>
+    pub(crate) res: HashMap<TypeId, (&'static str, Alias)>,
+    /// 是否直接访问整个[World]
+    ///
+    /// [World]: crate::World
+    pub(crate) world: bool,
+    /// <[Local]的TypeId,类型名>,[Local]只属于[System]自己,不会与其他[System]冲突
+    ///
+    /// [Local]: crate::world::Local
+    /// [System]: crate::system::System
+    pub(crate) locals: HashMap<TypeId, &'static str>,
+    /// 参数之间的冲突,除了[AliasMap]中的
+    pub(crate) conflicts: Vec<ParamConflict>,
+    /// 已经通过[SystemState::init_param]计算过的参数的数量
+    params: usize,
+    /// 是否只能在主线程运行,如访问了[NonSend]
+    ///
+    /// [NonSend]: crate::world::NonSend
+    pub(crate) main_thread: bool,
 }
 
 impl SystemState {
@@ -25,8 +48,136 @@ impl SystemState {
             alias_map: Default::default(),
             resources: false,
             res: Default::default(),
+            world: false,
+            locals: Default::default(),
+            conflicts: vec![],
+            params: 0,
+            main_thread: false,
         }
     }
+
+    /// 计算下一个参数P的访问,冲突中会记录它是第几个参数
+    ///
+    /// 串联的[System]的参数依次计数
+    ///
+    /// [System]: crate::system::System
+    pub(crate) fn init_param<P: SystemParm>(&mut self) {
+        self.alias_map.param = self.params;
+        P::init(self);
+        self.params += 1;
+    }
+
+    /// 用于计算[ParamSet]的一个成员,访问属于与[ParamSet]相同的参数
+    ///
+    /// [ParamSet]: crate::system::ParamSet
+    pub(crate) fn member(&self) -> Self {
+        let mut member = Self::new();
+        member.alias_map.param = self.alias_map.param;
+        member
+    }
+
+    /// 取出参数之间的所有冲突
+    pub(crate) fn take_conflicts(&mut self) -> Vec<ParamConflict> {
+        let mut conflicts = std::mem::take(&mut self.alias_map.conflicts);
+        conflicts.append(&mut self.conflicts);
+        // 参数的顺序是任意的,所以在最后检查
+        if self.world && !self.is_empty_except_world() && !conflicts.contains(&ParamConflict::World)
+        {
+            conflicts.push(ParamConflict::World);
+        }
+        conflicts
+    }
+
+    /// 把other的访问合并进来,与已有的访问冲突时记录冲突
+    ///
+    /// 用于[ParamSet],它的成员分别计算后合并
+    ///
+    /// [ParamSet]: crate::system::ParamSet
+    pub(crate) fn merge(&mut self, other: SystemState) {
+        self.alias_map.merge(other.alias_map);
+        if (other.resources && (self.resources || !self.res.is_empty()))
+            || (self.resources && !other.res.is_empty())
+        {
+            self.conflicts.push(ParamConflict::Resource("Resources"));
+        }
+        for (id, (name, alias)) in other.res {
+            match self.res.entry(id) {
+                Entry::Vacant(e) => {
+                    e.insert((name, alias));
+                }
+                Entry::Occupied(mut e) => {
+                    let (_, exist) = e.get_mut();
+                    if exist.is_mut() || alias.is_mut() {
+                        *exist = Alias::Mut;
+                        self.conflicts.push(ParamConflict::Resource(name));
+                    }
+                }
+            }
+        }
+        if self.world && other.world {
+            self.conflicts.push(ParamConflict::World);
+        }
+        for (id, name) in other.locals {
+            if self.locals.insert(id, name).is_some() {
+                self.conflicts.push(ParamConflict::Local(name));
+            }
+        }
+        self.resources |= other.resources;
+        self.world |= other.world;
+        self.main_thread |= other.main_thread;
+        self.conflicts.extend(other.conflicts);
+    }
+
+    /// 是否什么都不访问
+    fn is_empty(&self) -> bool {
+        self.is_empty_except_world() && !self.world
+    }
+
+    /// 除了整个[World]以外,是否什么都不访问
+    ///
+    /// [World]: crate::World
+    fn is_empty_except_world(&self) -> bool {
+        self.alias_map.inner.is_empty() && !self.resources && self.res.is_empty()
+    }
+
+    /// 计算两个[System]同时运行时会发生冲突的访问
+    ///
+    /// [System]: crate::system::System
+    pub(crate) fn conflicts(&self, other: &SystemState) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+        if (self.world && !other.is_empty()) || (other.world && !self.is_empty()) {
+            conflicts.push(Conflict::World);
+        }
+        if (self.resources && (other.resources || !other.res.is_empty()))
+            || (other.resources && !self.res.is_empty())
+        {
+            conflicts.push(Conflict::Resources);
+        }
+        let mut res = self
+            .res
+            .iter()
+            .filter_map(|(id, (name, alias))| {
+                let (_, other) = other.res.get(id)?;
+                (alias.is_mut() || other.is_mut()).then_some(Conflict::Resource(name))
+            })
+            .collect::<Vec<_>>();
+        res.sort();
+        conflicts.extend(res);
+
+        let mut components = self
+            .alias_map
+            .inner
+            .iter()
+            .filter_map(|(id, entry)| {
+                let other = other.alias_map.inner.get(id)?;
+                (entry.alias.is_mut() || other.alias.is_mut()).then_some(entry.name)
+            })
+            .map(Conflict::Component)
+            .collect::<Vec<_>>();
+        components.sort();
+        conflicts.extend(components);
+        conflicts
+    }
 }
 
 impl Default for SystemState {
@@ -38,6 +189,7 @@ impl Default for SystemState {
 /// 用来检测别名冲突
 ///
 /// 枚举引用的使用情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Alias {
     /// 有不可变引用
     Imut,
@@ -63,39 +215,102 @@ impl Alias {
     }
 }
 
+/// [AliasMap]中一种类型的使用情况
+struct AliasEntry {
+    alias: Alias,
+    /// 类型名
+    name: &'static str,
+    /// 使用类型的Fetch,以及它属于第几个参数
+    users: Vec<(&'static str, usize)>,
+}
+
 /// 计算别名冲突
 pub struct AliasMap {
-    /// <类型的ID,(别名情况,使用类型的Fetch)
-    inner: HashMap<TypeId, (Alias, Vec<&'static str>)>,
+    /// <类型的ID,使用情况>
+    inner: HashMap<TypeId, AliasEntry>,
+    /// 发生的别名冲突
+    conflicts: Vec<ParamConflict>,
+    /// 正在计算的参数的下标
+    pub(crate) param: usize,
 }
 
 impl AliasMap {
     pub fn new() -> Self {
         Self {
             inner: Default::default(),
+            conflicts: vec![],
+            param: 0,
         }
     }
 
     pub fn insert<F: WorldFetch, T: Any>(&mut self, usage: Alias) {
-        let (ty, ty_name) = (TypeId::of::<T>(), type_name::<T>());
-        if let std::collections::hash_map::Entry::Vacant(e) = self.inner.entry(ty) {
-            e.insert((usage, vec![type_name::<F>()]));
-        } else {
-            let (alias, users) = self.inner.get_mut(&ty).unwrap();
-            if usage.is_mut() && alias.is_imut() {
-                let users = users
-                    .iter()
-                    .fold(String::new(), |str, user| str + user + " ");
-                panic!("发生别名冲突: WorldFetch [{}] 都使用了{}的不可变引用,而 WorldFetch {}使用了{}的可变引用\n",
-                    users,ty_name,type_name::<F>(),ty_name);
+        self.insert_entry(
+            TypeId::of::<T>(),
+            type_name::<T>(),
+            usage,
+            vec![(type_name::<F>(), self.param)],
+        );
+    }
+
+    /// 把other中的所有访问合并进来
+    pub(crate) fn merge(&mut self, other: AliasMap) {
+        for (ty, entry) in other.inner {
+            self.insert_entry(ty, entry.name, entry.alias, entry.users);
+        }
+        self.conflicts.extend(other.conflicts);
+    }
+
+    fn insert_entry(
+        &mut self,
+        ty: TypeId,
+        ty_name: &'static str,
+        usage: Alias,
+        mut new_users: Vec<(&'static str, usize)>,
+    ) {
+        match self.inner.entry(ty) {
+            Entry::Vacant(e) => {
+                e.insert(AliasEntry {
+                    alias: usage,
+                    name: ty_name,
+                    users: new_users,
+                });
             }
-            if usage.is_imut() && alias.is_mut() {
-                panic!("发生别名冲突: WorldFetch [{}] 使用了{}的不可变引用,而 WorldFetch {}使用了{}的可变引用\n",
-                    type_name::<F>(),ty_name,users[0],ty_name);
+            Entry::Occupied(mut e) => {
+                let AliasEntry { alias, users, .. } = e.get_mut();
+                users.append(&mut new_users);
+                if usage.is_mut() || alias.is_mut() {
+                    *alias = Alias::Mut;
+                    // 同一种类型只记录一次冲突
+                    let (fetches, params) = users.iter().copied().unzip();
+                    match self.conflicts.iter_mut().find(
+                        |conflict| matches!(conflict, ParamConflict::Alias { component, .. } if *component == ty_name),
+                    ) {
+                        Some(ParamConflict::Alias {
+                            fetches: exist,
+                            params: exist_params,
+                            ..
+                        }) => {
+                            *exist = fetches;
+                            *exist_params = params;
+                        }
+                        _ => self.conflicts.push(ParamConflict::Alias {
+                            component: ty_name,
+                            fetches,
+                            params,
+                        }),
+                    }
+                }
             }
-            users.push(type_name::<F>());
         }
     }
+
+    /// 所有被访问的类型,格式为(类型的ID,类型名,是否是可变引用)
+    #[cfg(feature = "debug-borrows")]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (TypeId, &'static str, bool)> + '_ {
+        self.inner
+            .iter()
+            .map(|(id, entry)| (*id, entry.name, entry.alias.is_mut()))
+    }
 }
 
 impl Default for AliasMap {
@@ -0,0 +1,380 @@
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    num::NonZeroUsize,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    bundle::{Bundle, Component, StorageType},
+    storage::Entity,
+    tools::ResManager,
+    World,
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// 在[World]中执行的操作,由完成的任务发送
+type Apply = Box<dyn FnOnce(&mut World) + Send>;
+
+/// 把一种[Task]的结果交回[World]的函数
+type Applier = fn(&mut World);
+
+/// 在后台线程中执行耗时的计算或IO的线程池
+///
+/// 通过[World::enable_task_pool]作为资源添加,[System]中通过`Res<TaskPool>`使用
+///
+/// + [TaskPool::spawn]返回[Task],把它插入到[Entity]上,
+///   完成后[Task]会在帧结束时被替换成结果
+/// + [TaskPool::spawn_resource]的结果会在完成后的帧结束时作为资源插入
+///
+/// 任务panic时不会产生结果,[Task]也会被移除
+///
+/// [TaskPool]被Drop时会丢弃还没有开始的任务,它们的[Task]永远不会完成,
+/// 然后阻塞等待正在执行的任务完成
+///
+/// ``` rust
+/// use trecs::{
+///     bundle::Component,
+///     storage::Entity,
+///     task::{Task, TaskPool},
+///     tools::Command,
+///     world::{Commands, Query, Res},
+///     World,
+/// };
+///
+/// #[derive(Component)]
+/// struct Path(Vec<u32>);
+///
+/// fn find_path(mut commands: Commands, pool: Res<TaskPool>, query: Query<(Entity, &u32)>) {
+///     let pool = pool.get().unwrap();
+///     for (entity, &goal) in query {
+///         commands
+///             .entity(entity)
+///             .insert(pool.spawn(move || Path((0..=goal).collect())));
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// let entity = world.spawn(3u32);
+/// world.enable_task_pool(1).exec(find_path);
+/// while world.get_sparse::<Task<Path>>(entity).is_some() {
+///     world.run_once();
+/// }
+/// assert_eq!(world.fetch::<&Path>(entity).unwrap().0, [0, 1, 2, 3]);
+/// ```
+///
+/// [System]: crate::system::System
+pub struct TaskPool {
+    jobs: Option<Sender<Job>>,
+    /// 工作线程共享的任务队列
+    queue: Arc<Mutex<Receiver<Job>>>,
+    /// 为true时工作线程不再开始新的任务
+    cancelled: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+    results: (Sender<Apply>, Mutex<Receiver<Apply>>),
+    /// 已经生成过的[Task]的结果类型,以及交回这种结果的函数
+    appliers: Mutex<Vec<(TypeId, Applier)>>,
+}
+
+impl TaskPool {
+    /// 创建有threads个线程的[TaskPool],threads为0时使用1个线程
+    pub fn new(threads: usize) -> Self {
+        let (jobs, receiver) = channel::<Job>();
+        let queue = Arc::new(Mutex::new(receiver));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let workers = (0..threads.max(1))
+            .map(|index| {
+                let queue = queue.clone();
+                let cancelled = cancelled.clone();
+                thread::Builder::new()
+                    .name(format!("trecs-task-{index}"))
+                    .spawn(move || loop {
+                        // 只在取任务时持有锁,发送端被Drop后退出
+                        let Ok(job) = queue.lock().unwrap().recv() else {
+                            break;
+                        };
+                        if !cancelled.load(Ordering::Acquire) {
+                            job();
+                        }
+                    })
+                    .unwrap_or_else(|e| panic!("{e}"))
+            })
+            .collect();
+        let (sender, receiver) = channel();
+        Self {
+            jobs: Some(jobs),
+            queue,
+            cancelled,
+            workers,
+            results: (sender, Mutex::new(receiver)),
+            appliers: Default::default(),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// 在后台执行f,返回的[Task]可以插入到[Entity]上,见[Task]
+    pub fn spawn<T, F>(&self, f: F) -> Task<T>
+    where
+        T: Bundle + Send,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        {
+            let mut appliers = self.appliers.lock().unwrap();
+            if appliers.iter().all(|(id, _)| *id != TypeId::of::<T>()) {
+                appliers.push((TypeId::of::<T>(), apply_tasks::<T>));
+            }
+        }
+        let slot = Arc::new(Mutex::new(Slot::Running));
+        let task = Task {
+            slot: slot.clone(),
+            _marker: PhantomData,
+        };
+        self.execute(move || {
+            let result = catch_unwind(AssertUnwindSafe(f));
+            *slot.lock().unwrap() = match result {
+                Ok(value) => Slot::Finished(value),
+                Err(_) => Slot::Panicked,
+            };
+        });
+        task
+    }
+
+    /// 在后台执行f,完成后在帧结束时把结果作为资源插入,会替换已有的资源
+    pub fn spawn_resource<T, F>(&self, f: F)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let sender = self.results.0.clone();
+        self.execute(move || {
+            if let Ok(value) = catch_unwind(AssertUnwindSafe(f)) {
+                // World已经被Drop时结果没有去处,直接丢弃
+                let _ = sender.send(Box::new(move |world: &mut World| {
+                    world.insert_resource(value);
+                }));
+            }
+        });
+    }
+
+    fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        if let Some(jobs) = &self.jobs {
+            // 工作线程只在发送端被Drop后退出,所以总是能发送成功
+            let _ = jobs.send(Box::new(job));
+        }
+    }
+}
+
+impl Default for TaskPool {
+    /// 线程数为[thread::available_parallelism]
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().map_or(1, NonZeroUsize::get))
+    }
+}
+
+impl Drop for TaskPool {
+    /// 丢弃还没有开始的任务,并等待所有已经开始的任务完成
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+        self.jobs.take();
+        // 发送端已经被Drop,空闲的工作线程会很快释放锁
+        let queued = self.queue.lock().unwrap().try_iter().collect::<Vec<_>>();
+        drop(queued);
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+enum Slot<T> {
+    Running,
+    Finished(T),
+    Panicked,
+}
+
+/// 在[TaskPool]中执行的任务,结果的类型为T
+///
+/// 作为[StorageType::SparseSet]的[Component]插入到[Entity]上,
+/// 任务完成后的帧结束时会被移除,并把结果T插入到同一个[Entity]上
+///
+/// [Entity]被删除时结果会被丢弃
+pub struct Task<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Task<T> {
+    /// 任务是否已经结束,包括panic
+    pub fn is_finished(&self) -> bool {
+        !matches!(*self.slot.lock().unwrap(), Slot::Running)
+    }
+
+    /// 取出已经完成的结果,之后[Task::is_finished]仍然为true
+    ///
+    /// 还没有完成,结果已经被取出,或者任务panic时返回[None]
+    pub fn try_take(&self) -> Option<T> {
+        let mut slot = self.slot.lock().unwrap();
+        match *slot {
+            Slot::Finished(_) => match std::mem::replace(&mut *slot, Slot::Panicked) {
+                Slot::Finished(value) => Some(value),
+                _ => unreachable!(),
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<T: Send + 'static> Component for Task<T> {
+    const STORAGE: StorageType = StorageType::SparseSet;
+
+    fn type_id_() -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
+
+/// 把所有完成的[Task]替换成它们的结果
+fn apply_tasks<T: Bundle + Send>(world: &mut World) {
+    let finished = world
+        .sparse_iter::<Task<T>>()
+        .filter(|(_, task)| task.is_finished())
+        .map(|(entity, _)| entity)
+        .collect::<Vec<Entity>>();
+    for entity in finished {
+        let result = world
+            .remove_component::<Task<T>>(entity)
+            .and_then(|task| task.try_take());
+        if let Some(result) = result {
+            world.insert(entity, result);
+        }
+    }
+}
+
+/// 交回所有完成的任务的结果
+fn apply_results(world: &mut World) {
+    let Some(pool) = world.resource::<TaskPool>() else {
+        return;
+    };
+    let appliers = pool.appliers.lock().unwrap().clone();
+    let results = pool
+        .results
+        .1
+        .lock()
+        .unwrap()
+        .try_iter()
+        .collect::<Vec<_>>();
+    for apply in appliers.into_iter().map(|(_, apply)| apply) {
+        apply(world);
+    }
+    for apply in results {
+        apply(world);
+    }
+}
+
+impl World {
+    /// 添加有threads个线程的[TaskPool]资源,threads为0时使用[TaskPool::default]
+    ///
+    /// 已经添加过时什么都不做
+    pub fn enable_task_pool(&mut self, threads: usize) -> &mut Self {
+        self.get_res::<TaskPool>().get_or_init(|| match threads {
+            0 => TaskPool::default(),
+            threads => TaskPool::new(threads),
+        });
+        self.on_frame_end(TypeId::of::<TaskPool>(), apply_results);
+        self
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        tools::Command,
+        world::{Commands, Res},
+    };
+
+    #[test]
+    fn task() {
+        let mut world = World::new();
+        world.enable_task_pool(2);
+        let entity = world.spawn(1u8);
+        // 持有锁期间任务不会完成
+        let lock = Arc::new(Mutex::new(()));
+        let guard = lock.lock().unwrap();
+        let task_lock = lock.clone();
+        world.exec(move |mut commands: Commands, pool: Res<TaskPool>| {
+            let pool = pool.get().unwrap();
+            let lock = task_lock.clone();
+            commands.entity(entity).insert(pool.spawn(move || {
+                let _guard = lock.lock().unwrap();
+                2u32
+            }));
+            pool.spawn_resource(|| "done");
+        });
+
+        world.run_once();
+        assert!(!world.get_sparse::<Task<u32>>(entity).unwrap().is_finished());
+        assert_eq!(world.fetch::<&u32>(entity), None);
+
+        drop(guard);
+        while world.get_sparse::<Task<u32>>(entity).is_some() {
+            world.run_once();
+        }
+        assert_eq!(world.fetch::<&u32>(entity), Some(&2));
+        assert_eq!(world.fetch::<&u8>(entity), Some(&1));
+        while world.resource::<&str>().is_none() {
+            world.run_once();
+        }
+    }
+
+    #[test]
+    fn panicked() {
+        let pool = TaskPool::new(1);
+        let task = pool.spawn(|| -> u32 { panic!("task") });
+        let after = pool.spawn(|| 1u32);
+        // 单线程时任务按顺序执行,panic不会让线程退出
+        while !after.is_finished() {
+            thread::yield_now();
+        }
+        assert!(task.is_finished());
+        assert_eq!(task.try_take(), None);
+        assert_eq!(after.try_take(), Some(1));
+        assert_eq!(after.try_take(), None);
+        assert_eq!(pool.threads(), 1);
+    }
+
+    #[test]
+    fn drop_queued() {
+        let pool = TaskPool::new(1);
+        let (started, wait_started) = channel();
+        let (release, wait_release) = channel::<()>();
+        let finished = Arc::new(AtomicBool::new(false));
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = finished.clone();
+        pool.spawn_resource(move || {
+            started.send(()).unwrap();
+            // 排队的任务被丢弃时release也被Drop
+            let _ = wait_release.recv();
+            flag.store(true, Ordering::Release);
+        });
+        let flag = ran.clone();
+        pool.spawn_resource(move || {
+            let _release = release;
+            flag.store(true, Ordering::Release);
+        });
+        wait_started.recv().unwrap();
+
+        // 等待正在执行的任务,丢弃还没有开始的任务
+        drop(pool);
+        assert!(finished.load(Ordering::Acquire));
+        assert!(!ran.load(Ordering::Acquire));
+    }
+}
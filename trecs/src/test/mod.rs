@@ -0,0 +1,248 @@
+use std::fmt::Debug;
+
+#[cfg(not(feature = "async"))]
+use crate::system::{InnerSystem, System};
+use crate::{
+    bundle::Component,
+    event::EventReader,
+    storage::Entity,
+    system::{IntoSystem, Stage},
+    tools::{Command, ResManager},
+    world::ResMut,
+    World,
+};
+
+/// 执行一次system
+///
+/// 与[World::exec]不同,会先检查system的参数之间是否有别名冲突
+///
+/// ``` rust
+/// use trecs::{test, tools::Command, world::Query, World};
+///
+/// let mut world = World::new();
+/// let entity = world.spawn(1);
+/// test::run_system_once(&mut world, |query: Query<&mut i32>| {
+///     query.into_iter().for_each(|mut i| *i += 1);
+/// });
+/// test::assert_component_eq(&mut world, entity, &2);
+/// ```
+#[cfg(not(feature = "async"))]
+pub fn run_system_once<M, S: InnerSystem<M> + Send>(world: &mut World, system: S) {
+    let mut system = System::new(system).unwrap_or_else(|e| panic!("{e}"));
+    if let Err(payload) = system.run_once(world) {
+        std::panic::resume_unwind(payload);
+    }
+}
+
+/// 执行一帧
+///
+/// 还没有执行过的startup system会先被执行
+#[cfg(not(feature = "async"))]
+pub fn tick(world: &mut World) {
+    world.startup().run_once();
+}
+
+/// 断言entity有值为expected的T
+///
+/// entity已经失效,或者没有T时也会panic
+#[track_caller]
+pub fn assert_component_eq<T: Component + PartialEq + Debug>(
+    world: &mut World,
+    entity: Entity,
+    expected: &T,
+) {
+    match world.fetch::<&T>(entity) {
+        Some(actual) => assert_eq!(
+            actual,
+            expected,
+            "{entity:?}的{}与预期不符",
+            std::any::type_name::<T>()
+        ),
+        None => panic!("{entity:?}已经失效,或者没有{}", std::any::type_name::<T>()),
+    }
+}
+
+/// 收集system中产生的值,以便在测试中检查
+///
+/// 作为资源存放在[World]中,system通过[ResMut<Capture<T>>]添加
+///
+/// ``` rust
+/// use trecs::{test::{self, Capture}, world::ResMut, World};
+///
+/// let mut world = World::new();
+/// world.add_system(|mut capture: ResMut<Capture<i32>>| {
+///     capture.get_or_init(Capture::new);
+///     capture.get_mut().unwrap().push(1);
+/// });
+/// test::tick(&mut world);
+/// test::tick(&mut world);
+/// assert_eq!(test::captured::<i32>(&mut world), vec![1, 1]);
+/// ```
+///
+/// [ResMut<Capture<T>>]: crate::world::ResMut
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture<T> {
+    values: Vec<T>,
+}
+
+impl<T> Capture<T> {
+    pub fn new() -> Self {
+        Self { values: vec![] }
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.values.push(value)
+    }
+
+    /// 已经收集的值
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// 取出已经收集的值
+    pub fn take(&mut self) -> Vec<T> {
+        std::mem::take(&mut self.values)
+    }
+}
+
+impl<T> Default for Capture<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 取出[World]中的[Capture<T>]收集的值
+///
+/// 没有[Capture<T>]时返回空的[Vec]
+pub fn captured<T: 'static>(world: &mut World) -> Vec<T> {
+    world
+        .try_get_res::<Capture<T>>()
+        .and_then(|mut capture| capture.get_mut().map(Capture::take))
+        .unwrap_or_default()
+}
+
+/// 收集每一帧中E类型的事件,之后通过[captured]取出
+///
+/// 添加一个在[Stage::PostUpdate]中读取[Events<E>]的[System],把读到的事件放入[Capture<E>],
+/// 所以每个事件只会被收集一次,只需要调用一次
+///
+/// ``` rust
+/// use trecs::{event::EventWriter, test, World};
+///
+/// let mut world = World::new();
+/// test::capture_events::<u32>(&mut world);
+/// world.add_system(|mut events: EventWriter<u32>| events.send(1));
+/// world.send_event(0u32);
+/// test::tick(&mut world);
+/// test::tick(&mut world);
+/// assert_eq!(test::captured::<u32>(&mut world), [0, 1, 1]);
+/// ```
+///
+/// [Events<E>]: crate::event::Events
+/// [System]: crate::system::System
+pub fn capture_events<E: Clone + Send + Sync + 'static>(world: &mut World) {
+    #[cfg(not(feature = "async"))]
+    world.add_system(record_events::<E>.in_stage(Stage::PostUpdate));
+    #[cfg(feature = "async")]
+    world.add_system(record_events_async::<E>.in_stage(Stage::PostUpdate));
+}
+
+/// 见[capture_events]
+fn record_events<E: Clone + Send + Sync + 'static>(
+    mut events: EventReader<E>,
+    capture: ResMut<Capture<E>>,
+) {
+    let capture = capture.into_or_init(Capture::new);
+    for event in events.read() {
+        capture.push(event.clone());
+    }
+}
+
+/// 异步版本的[record_events]
+#[cfg(feature = "async")]
+fn record_events_async<E: Clone + Send + Sync + 'static>(
+    events: EventReader<E>,
+    capture: ResMut<Capture<E>>,
+) -> std::future::Ready<()> {
+    record_events(events, capture);
+    std::future::ready(())
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        event::EventWriter,
+        world::{Local, Query},
+    };
+
+    #[test]
+    #[should_panic(expected = "与预期不符")]
+    fn assert_component() {
+        let mut world = World::new();
+        let entity = world.spawn((1, "a"));
+        assert_component_eq(&mut world, entity, &"a");
+        assert_component_eq(&mut world, entity, &2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn run_alias_conflict() {
+        let mut world = World::new();
+        run_system_once(&mut world, |_: Query<&mut i32>, _: Query<&i32>| {});
+    }
+
+    #[test]
+    fn capture() {
+        fn startup(mut capture: ResMut<Capture<&'static str>>) {
+            capture.get_or_init(Capture::new);
+            capture.get_mut().unwrap().push("startup");
+        }
+        fn update(mut capture: ResMut<Capture<&'static str>>) {
+            capture.get_mut().unwrap().push("update");
+        }
+
+        let mut world = World::new();
+        world.add_startup_system(startup).add_system(update);
+        tick(&mut world);
+        tick(&mut world);
+        assert_eq!(
+            captured::<&str>(&mut world),
+            vec!["startup", "update", "update"]
+        );
+        assert!(captured::<&str>(&mut world).is_empty());
+    }
+
+    #[test]
+    fn capture_events() {
+        fn send(mut events: EventWriter<u32>, mut count: Local<u32>) {
+            *count += 1;
+            events.send(*count);
+        }
+
+        let mut world = World::new();
+        super::capture_events::<u32>(&mut world);
+        world.add_system(send).send_event(0u32);
+        tick(&mut world);
+        assert_eq!(captured::<u32>(&mut world), [0, 1]);
+        tick(&mut world);
+        tick(&mut world);
+        // 同一个事件在两帧中都存在,但是只被收集一次
+        assert_eq!(captured::<u32>(&mut world), [2, 3]);
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod async_tests {
+    use super::*;
+    use crate::system::block_on;
+
+    #[test]
+    fn capture_events() {
+        let mut world = World::new();
+        super::capture_events::<u32>(&mut world);
+        world.send_event(1u32);
+        block_on(world.run_once());
+        assert_eq!(captured::<u32>(&mut world), [1]);
+    }
+}
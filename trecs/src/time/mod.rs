@@ -0,0 +1,487 @@
+use std::time::{Duration, Instant};
+
+use crate::{system::Stage, tools::ResManager, World};
+
+/// [Time]的时钟
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Clock {
+    /// 使用真实的时间
+    Real { last: Option<Instant> },
+    /// 只有调用[Time::advance]时才会前进
+    Manual { pending: Duration },
+}
+
+/// 每一帧的时间信息
+///
+/// 作为资源存放在[World]中,通过[World::enable_time]或者[World::enable_manual_time]启用,
+/// 通过[World::run_until]循环执行时,没有启用的话会自动启用使用真实时间的[Time]
+///
+/// 每次循环开始时更新,同一帧中的所有[System]看到的时间都相同
+///
+/// 使用[Time::manual]时,时间只会通过[Time::advance]前进,
+/// 这样依赖时间的[System]在测试中就不需要真的等待
+///
+/// ``` rust
+/// use std::time::Duration;
+/// use trecs::{time::Time, tools::ResManager, World};
+///
+/// let mut world = World::new();
+/// world.enable_manual_time();
+/// world.advance_time(Duration::from_millis(16));
+/// world.run_once();
+///
+/// let time = world.get_res::<Time>();
+/// assert_eq!(time.get().unwrap().delta(), Duration::from_millis(16));
+/// ```
+///
+/// [System]: crate::system::System
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Time {
+    clock: Clock,
+    delta: Duration,
+    elapsed: Duration,
+    frames: u64,
+}
+
+impl Time {
+    /// 使用真实时间的[Time]
+    pub fn new() -> Self {
+        Self::with_clock(Clock::Real { last: None })
+    }
+
+    /// 只有调用[Time::advance]时才会前进的[Time]
+    pub fn manual() -> Self {
+        Self::with_clock(Clock::Manual {
+            pending: Duration::ZERO,
+        })
+    }
+
+    fn with_clock(clock: Clock) -> Self {
+        Self {
+            clock,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            frames: 0,
+        }
+    }
+
+    /// 与上一帧之间的时间
+    ///
+    /// 第一帧为[Duration::ZERO]
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// 与上一帧之间的时间,单位为秒
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// 所有帧的delta之和
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// 所有帧的delta之和,单位为秒
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// 已经更新过的帧数
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn is_manual(&self) -> bool {
+        matches!(self.clock, Clock::Manual { .. })
+    }
+
+    /// 让时间前进duration,在下一帧开始时生效
+    ///
+    /// 多次调用会累加
+    ///
+    /// 使用真实时间时会panic
+    pub fn advance(&mut self, duration: Duration) {
+        match &mut self.clock {
+            Clock::Manual { pending } => *pending += duration,
+            Clock::Real { .. } => panic!("只有Time::manual可以手动前进"),
+        }
+    }
+
+    /// 进入下一帧
+    pub(crate) fn update(&mut self) {
+        self.delta = match &mut self.clock {
+            Clock::Real { last } => {
+                let now = Instant::now();
+                let delta = last.map_or(Duration::ZERO, |last| now - last);
+                *last = Some(now);
+                delta
+            }
+            Clock::Manual { pending } => std::mem::take(pending),
+        };
+        self.elapsed += self.delta;
+        self.frames += 1;
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// [Stage::FixedUpdate]的固定时间间隔
+///
+/// 作为资源存放在[World]中,每一帧把[Time::delta]累积起来,
+/// 每累积一个[FixedTime::timestep]就执行一次[Stage::FixedUpdate],
+/// 剩下的不足一个间隔的时间留到下一帧
+///
+/// 每一帧最多执行[FixedTime::max_steps]次,卡顿之后超出的整数个间隔会被丢弃,
+/// 避免固定更新越来越慢而追不上真实时间
+///
+/// 没有启用[Time]时不会累积,[Stage::FixedUpdate]也不会执行
+///
+/// ``` rust
+/// use std::time::Duration;
+/// use trecs::{system::{IntoSystem, Stage}, time::FixedTime, tools::ResManager, World};
+///
+/// fn physics() {}
+///
+/// let mut world = World::new();
+/// world
+///     .enable_manual_time()
+///     .set_fixed_timestep(Duration::from_millis(20))
+///     .add_system(physics.in_stage(Stage::FixedUpdate));
+/// world.advance_time(Duration::from_millis(50));
+/// // physics执行了两次
+/// world.run_once();
+///
+/// let fixed = world.get_res::<FixedTime>();
+/// assert_eq!(fixed.get().unwrap().overstep_fraction(), 0.5);
+/// ```
+///
+/// [Stage::FixedUpdate]: crate::system::Stage::FixedUpdate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedTime {
+    timestep: Duration,
+    overstep: Duration,
+    steps: u64,
+    max_steps: usize,
+}
+
+impl FixedTime {
+    /// 默认的时间间隔,即每秒64次
+    pub const DEFAULT_TIMESTEP: Duration = Duration::from_micros(15625);
+
+    /// 默认的每一帧最多执行的次数
+    pub const DEFAULT_MAX_STEPS: usize = 8;
+
+    /// 时间间隔为timestep的[FixedTime]
+    ///
+    /// timestep为零时会panic
+    pub fn new(timestep: Duration) -> Self {
+        assert!(!timestep.is_zero(), "FixedTime的时间间隔不能为零");
+        Self {
+            timestep,
+            overstep: Duration::ZERO,
+            steps: 0,
+            max_steps: Self::DEFAULT_MAX_STEPS,
+        }
+    }
+
+    /// 设置每一帧最多执行的次数,为零时会panic
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        assert!(max_steps != 0, "FixedTime每一帧最多执行的次数不能为零");
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// 每一帧最多执行[Stage::FixedUpdate]的次数
+    ///
+    /// [Stage::FixedUpdate]: crate::system::Stage::FixedUpdate
+    pub fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    /// 每次执行[Stage::FixedUpdate]的时间间隔
+    ///
+    /// 在[Stage::FixedUpdate]中应该用它代替[Time::delta]
+    ///
+    /// [Stage::FixedUpdate]: crate::system::Stage::FixedUpdate
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// 时间间隔,单位为秒
+    pub fn timestep_secs(&self) -> f32 {
+        self.timestep.as_secs_f32()
+    }
+
+    /// 累积了但是还不足一个时间间隔的时间
+    pub fn overstep(&self) -> Duration {
+        self.overstep
+    }
+
+    /// [FixedTime::overstep]占时间间隔的比例,在0到1之间
+    ///
+    /// 可以用于在两次固定更新之间插值
+    pub fn overstep_fraction(&self) -> f32 {
+        self.overstep.as_secs_f32() / self.timestep.as_secs_f32()
+    }
+
+    /// 已经执行过的[Stage::FixedUpdate]的次数
+    ///
+    /// [Stage::FixedUpdate]: crate::system::Stage::FixedUpdate
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// 累积delta,返回这一帧需要执行的次数
+    ///
+    /// 超过[FixedTime::max_steps]的部分被丢弃,只保留不足一个间隔的时间
+    pub(crate) fn accumulate(&mut self, delta: Duration) -> usize {
+        let overstep = (self.overstep + delta).as_nanos();
+        let timestep = self.timestep.as_nanos();
+        let steps = (overstep / timestep).min(self.max_steps as u128) as usize;
+        self.overstep = Duration::from_nanos((overstep % timestep) as u64);
+        self.steps += steps as u64;
+        steps
+    }
+}
+
+impl Default for FixedTime {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TIMESTEP)
+    }
+}
+
+impl World {
+    /// 启用使用真实时间的[Time]
+    pub fn enable_time(&mut self) -> &mut Self {
+        self.set_time(Time::new())
+    }
+
+    /// 启用只能手动前进的[Time]
+    ///
+    /// 见[Time::manual]
+    pub fn enable_manual_time(&mut self) -> &mut Self {
+        self.set_time(Time::manual())
+    }
+
+    /// 没有启用[Time]时启用使用真实时间的[Time]
+    pub(crate) fn init_time(&mut self) {
+        if self.peek_res::<Time>().is_none() {
+            self.enable_time();
+        }
+    }
+
+    /// 让[Time]前进duration
+    ///
+    /// 见[Time::advance]
+    pub fn advance_time(&mut self, duration: Duration) -> &mut Self {
+        if let Some(time) = self.get_res::<Time>().get_mut() {
+            time.advance(duration);
+        }
+        self
+    }
+
+    fn set_time(&mut self, time: Time) -> &mut Self {
+        let mut res = self.get_res::<Time>();
+        res.take();
+        res.get_or_init(|| time);
+        self
+    }
+
+    /// 设置[FixedTime]的时间间隔,累积的时间会被清空
+    ///
+    /// 没有设置时使用[FixedTime::DEFAULT_TIMESTEP]
+    pub fn set_fixed_timestep(&mut self, timestep: Duration) -> &mut Self {
+        let mut res = self.get_res::<FixedTime>();
+        let max_steps = res
+            .take()
+            .map_or(FixedTime::DEFAULT_MAX_STEPS, |f| f.max_steps);
+        res.get_or_init(|| FixedTime::new(timestep).with_max_steps(max_steps));
+        self
+    }
+
+    /// 设置[FixedTime]每一帧最多执行的次数,见[FixedTime::with_max_steps]
+    ///
+    /// 没有设置时使用[FixedTime::DEFAULT_MAX_STEPS]
+    pub fn set_fixed_max_steps(&mut self, max_steps: usize) -> &mut Self {
+        let mut res = self.get_res::<FixedTime>();
+        let fixed = res.take().map_or_else(FixedTime::default, |f| *f);
+        res.get_or_init(|| fixed.with_max_steps(max_steps));
+        self
+    }
+
+    /// 这一帧需要执行[Stage::FixedUpdate]的次数
+    ///
+    /// 没有启用[Time],或者[Stage::FixedUpdate]中没有[System]时为0,此时不会访问[FixedTime];
+    /// 只有执行次数不为0时[FixedTime]才被看作修改过
+    ///
+    /// [Stage::FixedUpdate]: crate::system::Stage::FixedUpdate
+    /// [System]: crate::system::System
+    pub(crate) fn fixed_steps(&mut self) -> usize {
+        if self.schedule.stage(Stage::FixedUpdate).is_empty() {
+            return 0;
+        }
+        let Some(delta) = self.peek_res::<Time>().map(Time::delta) else {
+            return 0;
+        };
+        self.get_res::<FixedTime>().get_or_init(FixedTime::default);
+        let steps = self
+            .peek_res_mut::<FixedTime>()
+            .unwrap_or_else(|| unreachable!("FixedTime已经被创建了"))
+            .accumulate(delta);
+        if steps > 0 {
+            // 通过ResMut获取可变引用,标记为修改过
+            self.get_res::<FixedTime>().get_mut();
+        }
+        steps
+    }
+
+    /// 更新[Time]
+    ///
+    /// 没有启用[Time]时什么都不做
+    pub(crate) fn update_time(&mut self) {
+        if let Some(time) = self
+            .try_get_res::<Time>()
+            .as_mut()
+            .and_then(|res| res.get_mut())
+        {
+            time.update();
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        system::{IntoSystem, Stage},
+        test::{self, Capture},
+        world::{Res, ResMut},
+    };
+
+    #[test]
+    fn manual() {
+        // 每经过一秒记录一次
+        fn cooldown(time: Res<Time>, mut capture: ResMut<Capture<u64>>, left: ResMut<Duration>) {
+            let time = time.get().unwrap();
+            let left = left.into_or_init(|| Duration::from_secs(1));
+            match left.checked_sub(time.delta()) {
+                Some(rest) if !rest.is_zero() => *left = rest,
+                _ => {
+                    *left = Duration::from_secs(1);
+                    capture.get_or_init(Capture::new);
+                    capture.get_mut().unwrap().push(time.frames());
+                }
+            }
+        }
+
+        let mut world = World::new();
+        world.enable_manual_time().add_system(cooldown);
+        for _ in 0..10 {
+            world.advance_time(Duration::from_millis(250));
+            test::tick(&mut world);
+        }
+        assert_eq!(test::captured::<u64>(&mut world), vec![4, 8]);
+
+        let time = world.get_res::<Time>();
+        let time = time.get().unwrap();
+        assert_eq!(time.elapsed(), Duration::from_millis(2500));
+        assert_eq!(time.frames(), 10);
+    }
+
+    #[test]
+    fn runner() {
+        let mut world = World::new();
+        let mut frames = 0;
+        world.run_until(|_| {
+            frames += 1;
+            frames > 3
+        });
+        let time = world.resource::<Time>().unwrap();
+        assert!(!time.is_manual());
+        assert_eq!(time.frames(), 3);
+
+        // 已经启用的Time不会被替换
+        let mut world = World::new();
+        world.enable_manual_time().run_until(|_| true);
+        assert!(world.resource::<Time>().unwrap().is_manual());
+    }
+
+    #[test]
+    fn fixed_update() {
+        fn physics(time: Res<Time>, mut capture: ResMut<Capture<u64>>) {
+            capture.get_or_init(Capture::new);
+            capture
+                .get_mut()
+                .unwrap()
+                .push(time.get().unwrap().frames());
+        }
+
+        let mut world = World::new();
+        world
+            .enable_manual_time()
+            .set_fixed_timestep(Duration::from_millis(100))
+            .add_system(physics.in_stage(Stage::FixedUpdate));
+        world.advance_time(Duration::from_millis(250));
+        test::tick(&mut world);
+        assert_eq!(test::captured::<u64>(&mut world), vec![1, 1]);
+        let fixed = *world.get_res::<FixedTime>().get().unwrap();
+        assert_eq!(fixed.overstep(), Duration::from_millis(50));
+        assert_eq!(fixed.steps(), 2);
+        assert_eq!(fixed.overstep_fraction(), 0.5);
+
+        world.advance_time(Duration::from_millis(40));
+        test::tick(&mut world);
+        assert!(test::captured::<u64>(&mut world).is_empty());
+
+        world.advance_time(Duration::from_millis(10));
+        test::tick(&mut world);
+        assert_eq!(test::captured::<u64>(&mut world), vec![3]);
+
+        // 卡顿之后最多执行max_steps次,超出的整数个间隔被丢弃
+        world.set_fixed_max_steps(3);
+        world.advance_time(Duration::from_millis(1030));
+        test::tick(&mut world);
+        assert_eq!(test::captured::<u64>(&mut world), vec![4, 4, 4]);
+        let fixed = *world.get_res::<FixedTime>().get().unwrap();
+        assert_eq!(fixed.overstep(), Duration::from_millis(30));
+        assert_eq!(fixed.steps(), 6);
+        assert_eq!(fixed.max_steps(), 3);
+    }
+
+    #[test]
+    fn fixed_time_changed() {
+        fn changed(world: &World) -> usize {
+            world.resources[&std::any::TypeId::of::<FixedTime>()]
+                .ticks()
+                .changed()
+        }
+
+        // FixedUpdate中没有System时不会创建FixedTime
+        let mut world = World::new();
+        world
+            .enable_manual_time()
+            .advance_time(Duration::from_secs(1));
+        test::tick(&mut world);
+        assert!(world.resource::<FixedTime>().is_none());
+
+        world
+            .set_fixed_timestep(Duration::from_millis(100))
+            .add_system((|| {}).in_stage(Stage::FixedUpdate))
+            .add_system(|| {});
+        test::tick(&mut world);
+        let added = changed(&world);
+        // 没有执行FixedUpdate的帧不算作修改
+        world.advance_time(Duration::from_millis(50));
+        test::tick(&mut world);
+        assert_eq!(changed(&world), added);
+        world.advance_time(Duration::from_millis(50));
+        test::tick(&mut world);
+        assert!(changed(&world) > added);
+        assert_eq!(world.resource::<FixedTime>().unwrap().steps(), 1);
+    }
+}
@@ -1,7 +1,13 @@
-use std::any::TypeId;
+use std::{
+    any::{type_name, TypeId},
+    error::Error,
+    fmt::Display,
+};
 
+use super::Mut;
 #[allow(unused_imports)]
-use crate::bundle::{Bundle, Component, Components};
+use crate::bundle::{Bundle, Component, ComponentId, Components, StorageType};
+use crate::storage::{ChunkRow, ComponentTicks, Entity};
 #[cfg(feature = "system")]
 use crate::system::state::AliasMap;
 
@@ -33,6 +39,13 @@ use crate::system::state::AliasMap;
 pub enum MappingTable {
     Node(Vec<MappingTable>),
     Mapping(usize),
+    /// 不对应[Bundle]中的任何[Component],
+    /// 如`Option<&T>`在[Bundle]中没有T时,或者[Entity]
+    Missing,
+    /// [StorageType::SparseSet]的[Component],不在[Bundle]中,需要逐个[Entity]查找
+    ///
+    /// 没有这个[Component]的[Entity]会被[WorldFetch::matches]过滤掉
+    Sparse(ComponentId),
 }
 
 impl MappingTable {
@@ -51,6 +64,124 @@ impl MappingTable {
             None
         }
     }
+
+    /// 所有被映射的下标
+    pub fn mappings(&self) -> Vec<usize> {
+        match self {
+            Self::Node(nodes) => nodes.iter().flat_map(Self::mappings).collect(),
+            Self::Mapping(mapping) => vec![*mapping],
+            Self::Missing | Self::Sparse(_) => vec![],
+        }
+    }
+
+    /// 所有被访问的[StorageType::SparseSet]的[Component]
+    pub fn sparse(&self) -> Vec<ComponentId> {
+        match self {
+            Self::Node(nodes) => nodes.iter().flat_map(Self::sparse).collect(),
+            Self::Sparse(id) => vec![*id],
+            Self::Mapping(_) | Self::Missing => vec![],
+        }
+    }
+}
+
+/// 占位用的类型
+///
+/// 已经被[WorldFetch]使用的[Component]的[TypeId]会被替换为它
+struct Fetched;
+
+/// 在components_ids中找到T的位置,生成[MappingTable::Mapping]
+///
+/// 找到的位置会被标记为已使用,而不是直接移除,否则后面的下标会错位
+fn take_mapping<T: Component>(components_ids: &mut [ComponentId]) -> Option<MappingTable> {
+    let mapping = components_ids
+        .iter()
+        .position(|id| *id == ComponentId::of::<T>())?;
+    components_ids[mapping] = ComponentId::Static(TypeId::of::<Fetched>());
+    Some(MappingTable::Mapping(mapping))
+}
+
+/// 按照T的[StorageType]生成[MappingTable::Mapping]或者[MappingTable::Sparse]
+///
+/// [StorageType::SparseSet]的T不在components_ids中,放入T的[ComponentId]标记为已使用
+fn take_component<T: Component>(components_ids: &mut Vec<ComponentId>) -> Option<MappingTable> {
+    if T::STORAGE == StorageType::Table {
+        return take_mapping::<T>(components_ids);
+    }
+    let id = ComponentId::of::<T>();
+    if components_ids.contains(&id) {
+        return None;
+    }
+    components_ids.push(id);
+    Some(MappingTable::Sparse(id))
+}
+
+/// T的指针及其[ComponentTicks]
+///
+/// # Safety
+///
+/// 同[WorldFetch::build]
+unsafe fn locate<'a, T: Component>(
+    row: ChunkRow<'a>,
+    mapping_table: &MappingTable,
+) -> (*mut T, &'a ComponentTicks) {
+    match mapping_table {
+        MappingTable::Mapping(index) => (row.ptr(*index).cast(), row.ticks(*index)),
+        MappingTable::Sparse(id) => match row.sparse(id) {
+            Some((ptr, ticks)) => (ptr.cast(), ticks),
+            None => mismatch::<T>(),
+        },
+        _ => mismatch::<T>(),
+    }
+}
+
+/// 没有被过滤掉的row中是否有T,即就是`Option<&T>`是否为[Some]
+fn present(row: ChunkRow<'_>, mapping_table: &MappingTable) -> bool {
+    match mapping_table {
+        MappingTable::Missing => false,
+        MappingTable::Sparse(id) => row.sparse(id).is_some(),
+        _ => true,
+    }
+}
+
+/// 通过[Entity]访问[Component]失败
+///
+/// 见[World::try_fetch]
+///
+/// [World::try_fetch]: crate::World::try_fetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchError {
+    /// [Entity]不存在或者已经失效
+    NoSuchEntity(Entity),
+    /// [Entity]对应的[Bundle]中缺少[WorldFetch]访问的[Component]
+    Mismatch {
+        entity: Entity,
+        /// [Bundle]的类型名
+        bundle: &'static str,
+        /// [WorldFetch]的类型名
+        fetch: &'static str,
+    },
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::NoSuchEntity(entity) => write!(f, "{entity:?}不存在或者已经失效"),
+            FetchError::Mismatch {
+                entity,
+                bundle,
+                fetch,
+            } => write!(f, "{entity:?}对应的Bundle {bundle} 无法转换为 {fetch}"),
+        }
+    }
+}
+
+impl Error for FetchError {}
+
+/// [MappingTable]与[Components]不一致
+///
+/// [MappingTable]由[WorldFetch::contain]生成,不会发生
+fn mismatch<T>() -> ! {
+    unreachable!("MappingTable与{}不一致", type_name::<T>())
 }
 
 /// 从[World]中筛选[Bundle],并且转换[Bundle]
@@ -63,50 +194,109 @@ pub trait WorldFetch {
 
     type Bundle: Bundle;
 
-    /// 从[Componnets],根据[MappingTable]生成[WorldFetch::Item]
+    /// 只读取[Component]的版本,如`&mut T`对应`&T`,见[Query::as_readonly]
+    ///
+    /// 必须与自身生成相同的[MappingTable]
+    ///
+    /// [Query::as_readonly]: crate::world::Query::as_readonly
+    type ReadOnly: ReadOnlyFetch;
+
+    /// 是否只会读取[Component],元组只有在所有成员都只读时才只读
+    ///
+    /// 实现了[ReadOnlyFetch]时必须为true
+    const READ_ONLY: bool = false;
+
+    /// 从[ChunkRow],根据[MappingTable]生成[WorldFetch::Item]
+    ///
+    /// change_ticks见[Mut],可变的[WorldFetch::Item]通过它标记修改
     ///
     /// # Safety
     ///
-    /// 因为绕开了rust的别名模型,并且进行了一系列类型转换,标记为unsafe
+    /// 会通过指针修改[ChunkRow]中的[Component],标记为unsafe
     ///
-    /// 安全性实际上已经由[FnSystem]保证
+    /// 调用者需要保证同一时间没有与[WorldFetch::Item]冲突的访问,
+    /// 对于[System]来说,这由[SystemState]保证
     ///
-    /// [FnSystem]: crate
-    unsafe fn build<'a>(components: &'a Components, mapping_table: &MappingTable)
-        -> Self::Item<'a>;
+    /// [System]: crate::system::System
+    /// [SystemState]: crate::system::state::SystemState
+    unsafe fn build<'a>(
+        row: ChunkRow<'a>,
+        mapping_table: &MappingTable,
+        entity: Entity,
+        change_ticks: (usize, usize),
+    ) -> Self::Item<'a>;
 
     /// 通过[Bundle]的信息生成[MappingTable]
     ///
     /// + 返回[Some]说明可以通过[MappingTable]转换[Components]为[WorldFetch::Item]
     /// + 返回[None]代表无法转换
-    fn contain(components_ids: &mut Vec<TypeId>) -> Option<MappingTable>;
+    fn contain(components_ids: &mut Vec<ComponentId>) -> Option<MappingTable>;
+
+    /// mapping_table中会被[WorldFetch::Item]修改的下标
+    fn mutable_mappings(_mapping_table: &MappingTable) -> Vec<usize> {
+        vec![]
+    }
+
+    /// mapping_table中会被[WorldFetch::Item]修改的[StorageType::SparseSet]的[Component]
+    fn mutable_sparse(_mapping_table: &MappingTable) -> Vec<ComponentId> {
+        vec![]
+    }
+
+    /// 逐个检查[Entity]是否有[MappingTable::Sparse]对应的[Component]
+    ///
+    /// 只有通过检查的[Entity]才会被[WorldFetch::build]
+    fn matches(_row: ChunkRow<'_>, _mapping_table: &MappingTable) -> bool {
+        true
+    }
 
     /// 计算[WorldFetch]可能导致的别名冲突
     ///
-    /// 如果存在别名冲突,带有发生冲突的[WorldFetch]在第一次执行时会发生painc
+    /// 如果存在别名冲突,带有发生冲突的[WorldFetch]的[System]无法被添加
     #[cfg(feature = "system")]
     fn alias_conflict(alias_map: &mut AliasMap);
 }
 
+/// 只会读取[Component]的[WorldFetch]
+///
+/// 见[Query::get]
+///
+/// # Safety
+///
+/// [WorldFetch::Item]不能修改[Component],
+/// 因为同时存在多个相同的[WorldFetch::Item]时不会发生别名冲突,
+/// [WorldFetch::READ_ONLY]必须为true
+///
+/// [Query::get]: crate::world::Query::get
+pub unsafe trait ReadOnlyFetch: WorldFetch {}
+
+unsafe impl<T: Component> ReadOnlyFetch for &T {}
+unsafe impl<T: Component> ReadOnlyFetch for Option<&T> {}
+unsafe impl ReadOnlyFetch for Entity {}
+
 impl<T: Component> WorldFetch for &T {
     type Item<'a> = &'a T;
 
     type Bundle = &'static T;
 
+    type ReadOnly = Self;
+
+    const READ_ONLY: bool = true;
+
     unsafe fn build<'a>(
-        components: &'a Components,
+        row: ChunkRow<'a>,
         mapping_table: &MappingTable,
+        _entity: Entity,
+        _change_ticks: (usize, usize),
     ) -> Self::Item<'a> {
-        components[mapping_table.as_mapping().copied().unwrap()]
-            .downcast_ref()
-            .unwrap()
+        &*locate::<T>(row, mapping_table).0
     }
 
-    fn contain(components_ids: &mut Vec<TypeId>) -> Option<MappingTable> {
-        let mapping = components_ids.binary_search(&TypeId::of::<T>()).ok()?;
-        components_ids.remove(mapping);
+    fn contain(components_ids: &mut Vec<ComponentId>) -> Option<MappingTable> {
+        take_component::<T>(components_ids)
+    }
 
-        Some(MappingTable::Mapping(mapping))
+    fn matches(row: ChunkRow<'_>, mapping_table: &MappingTable) -> bool {
+        present(row, mapping_table)
     }
 
     #[cfg(feature = "system")]
@@ -116,26 +306,36 @@ impl<T: Component> WorldFetch for &T {
 }
 
 impl<T: Component> WorldFetch for &'_ mut T {
-    type Item<'a> = &'a mut T;
+    type Item<'a> = Mut<'a, T>;
 
     type Bundle = &'static mut T;
 
+    type ReadOnly = &'static T;
+
     unsafe fn build<'a>(
-        components: &'a Components,
+        row: ChunkRow<'a>,
         mapping_table: &MappingTable,
+        _entity: Entity,
+        change_ticks: (usize, usize),
     ) -> Self::Item<'a> {
-        let imref = components[mapping_table.as_mapping().copied().unwrap()]
-            .downcast_ref::<T>()
-            .unwrap();
-        // 编译器有很努力防止我破坏别名模型
-        #[allow(mutable_transmutes)]
-        std::mem::transmute(imref)
+        let (value, ticks) = locate::<T>(row, mapping_table);
+        Mut::new(&mut *value, ticks, change_ticks)
+    }
+
+    fn contain(components_ids: &mut Vec<ComponentId>) -> Option<MappingTable> {
+        take_component::<T>(components_ids)
+    }
+
+    fn mutable_mappings(mapping_table: &MappingTable) -> Vec<usize> {
+        mapping_table.mappings()
     }
 
-    fn contain(components_ids: &mut Vec<TypeId>) -> Option<MappingTable> {
-        let mapping = components_ids.binary_search(&TypeId::of::<T>()).ok()?;
-        components_ids.remove(mapping);
-        Some(MappingTable::Mapping(mapping))
+    fn mutable_sparse(mapping_table: &MappingTable) -> Vec<ComponentId> {
+        mapping_table.sparse()
+    }
+
+    fn matches(row: ChunkRow<'_>, mapping_table: &MappingTable) -> bool {
+        present(row, mapping_table)
     }
 
     #[cfg(feature = "system")]
@@ -144,10 +344,108 @@ impl<T: Component> WorldFetch for &'_ mut T {
     }
 }
 
+/// [Bundle]中有T时为[Some],否则为[None]
+///
+/// 不会因为缺少T而过滤掉[Bundle]
+impl<T: Component> WorldFetch for Option<&T> {
+    type Item<'a> = Option<&'a T>;
+
+    type Bundle = Option<&'static T>;
+
+    type ReadOnly = Self;
+
+    const READ_ONLY: bool = true;
+
+    unsafe fn build<'a>(
+        row: ChunkRow<'a>,
+        mapping_table: &MappingTable,
+        entity: Entity,
+        change_ticks: (usize, usize),
+    ) -> Self::Item<'a> {
+        present(row, mapping_table).then(|| <&T>::build(row, mapping_table, entity, change_ticks))
+    }
+
+    fn contain(components_ids: &mut Vec<ComponentId>) -> Option<MappingTable> {
+        Some(take_component::<T>(components_ids).unwrap_or(MappingTable::Missing))
+    }
+
+    #[cfg(feature = "system")]
+    fn alias_conflict(alias_map: &mut AliasMap) {
+        alias_map.insert::<Self, T>(crate::system::state::Alias::Imut)
+    }
+}
+
+/// [Bundle]中有T时为[Some],否则为[None]
+///
+/// 不会因为缺少T而过滤掉[Bundle]
+impl<T: Component> WorldFetch for Option<&'_ mut T> {
+    type Item<'a> = Option<Mut<'a, T>>;
+
+    type Bundle = Option<&'static mut T>;
+
+    type ReadOnly = Option<&'static T>;
+
+    unsafe fn build<'a>(
+        row: ChunkRow<'a>,
+        mapping_table: &MappingTable,
+        entity: Entity,
+        change_ticks: (usize, usize),
+    ) -> Self::Item<'a> {
+        present(row, mapping_table)
+            .then(|| <&mut T>::build(row, mapping_table, entity, change_ticks))
+    }
+
+    fn contain(components_ids: &mut Vec<ComponentId>) -> Option<MappingTable> {
+        Some(take_component::<T>(components_ids).unwrap_or(MappingTable::Missing))
+    }
+
+    fn mutable_mappings(mapping_table: &MappingTable) -> Vec<usize> {
+        mapping_table.mappings()
+    }
+
+    fn mutable_sparse(mapping_table: &MappingTable) -> Vec<ComponentId> {
+        mapping_table.sparse()
+    }
+
+    #[cfg(feature = "system")]
+    fn alias_conflict(alias_map: &mut AliasMap) {
+        alias_map.insert::<Self, T>(crate::system::state::Alias::Mut)
+    }
+}
+
+/// 被访问的[Bundle]对应的[Entity]
+///
+/// 所有[Bundle]都有[Entity],不会过滤掉任何[Bundle]
+impl WorldFetch for Entity {
+    type Item<'a> = Entity;
+
+    type Bundle = Entity;
+
+    type ReadOnly = Self;
+
+    const READ_ONLY: bool = true;
+
+    unsafe fn build<'a>(
+        _row: ChunkRow<'a>,
+        _mapping_table: &MappingTable,
+        entity: Entity,
+        _change_ticks: (usize, usize),
+    ) -> Self::Item<'a> {
+        entity
+    }
+
+    fn contain(_components_ids: &mut Vec<ComponentId>) -> Option<MappingTable> {
+        Some(MappingTable::Missing)
+    }
+
+    #[cfg(feature = "system")]
+    fn alias_conflict(_alias_map: &mut AliasMap) {}
+}
+
 #[rustfmt::skip]
 mod __impl {
     
-    use super::{Components, MappingTable, TypeId, WorldFetch,};
+    use super::{mismatch, ChunkRow, ComponentId, Entity, MappingTable, ReadOnlyFetch, WorldFetch,};
     #[cfg(feature = "system")]
     use super::AliasMap;
 
@@ -158,17 +456,26 @@ mod __impl {
 
                 type Bundle = ($($t::Bundle,)*);
 
+                type ReadOnly = ($($t::ReadOnly,)*);
+
+                const READ_ONLY: bool = $($t::READ_ONLY)&&*;
+
                 unsafe fn build<'a>(
-                    components: &'a Components,
+                    row: ChunkRow<'a>,
                     mapping_table: &MappingTable,
+                    entity: Entity,
+                    change_ticks: (usize, usize),
                 ) -> Self::Item<'a> {
-                    let mut mappings = mapping_table.as_node().unwrap().into_iter();
+                    let Some(mappings) = mapping_table.as_node() else {
+                        mismatch::<Self>()
+                    };
+                    let mut mappings = mappings.iter();
                     ($(
-                        $t::build(components,mappings.next().unwrap()),
+                        $t::build(row,mappings.next().unwrap_or_else(|| mismatch::<Self>()),entity,change_ticks),
                     )*)
                 }
 
-                fn contain(components_ids : &mut Vec<TypeId>) -> Option<MappingTable>{
+                fn contain(components_ids : &mut Vec<ComponentId>) -> Option<MappingTable>{
                     let  mappings = vec![
                         $(
                             $t::contain(components_ids)?,
@@ -178,6 +485,40 @@ mod __impl {
                     Some(MappingTable::Node(mappings))
                 }
 
+                fn mutable_mappings(mapping_table: &MappingTable) -> Vec<usize> {
+                    let Some(mappings) = mapping_table.as_node() else {
+                        mismatch::<Self>()
+                    };
+                    let mut mappings = mappings.iter();
+                    let mut mutable = vec![];
+                    $(
+                        mutable.extend($t::mutable_mappings(mappings.next().unwrap_or_else(|| mismatch::<Self>())));
+                    )*
+                    mutable
+                }
+
+                fn mutable_sparse(mapping_table: &MappingTable) -> Vec<ComponentId> {
+                    let Some(mappings) = mapping_table.as_node() else {
+                        mismatch::<Self>()
+                    };
+                    let mut mappings = mappings.iter();
+                    let mut mutable = vec![];
+                    $(
+                        mutable.extend($t::mutable_sparse(mappings.next().unwrap_or_else(|| mismatch::<Self>())));
+                    )*
+                    mutable
+                }
+
+                fn matches(row: ChunkRow<'_>, mapping_table: &MappingTable) -> bool {
+                    let Some(mappings) = mapping_table.as_node() else {
+                        mismatch::<Self>()
+                    };
+                    let mut mappings = mappings.iter();
+                    $(
+                        $t::matches(row, mappings.next().unwrap_or_else(|| mismatch::<Self>()))
+                    )&&*
+                }
+
                 #[cfg(feature = "system")]
                 fn alias_conflict(alias_map: &mut AliasMap) {
                     $($t::alias_conflict(alias_map);)*
@@ -188,4 +529,168 @@ mod __impl {
     
     // 一次性从(T0)impl到(T0,T1,..,T15)
     trecs_proc::all_tuple!(impl_fetch, 16);
+
+    macro_rules! impl_read_only {
+        ($($t:ident),*) => {
+            unsafe impl<$($t:ReadOnlyFetch),*> ReadOnlyFetch for ($($t,)*) {}
+        };
+    }
+
+    trecs_proc::all_tuple!(impl_read_only, 16);
+}
+
+// 只经过存储和WorldFetch,不经过System,可以用Miri检查:
+// cargo +nightly miri test -p trecs --no-default-features --lib -- tools::fetch storage
+#[cfg(test)]
+mod tests {
+    use super::{FetchError, WorldFetch};
+    use crate::{iter::Iter, storage::Entity, tools::Command, world::Query, World};
+
+    #[test]
+    fn read_only() {
+        let read_only = [
+            <(Entity, &i32, Option<&usize>)>::READ_ONLY,
+            <(&i32, &mut usize)>::READ_ONLY,
+            <Option<&mut i32>>::READ_ONLY,
+        ];
+        assert_eq!(read_only, [true, false, false]);
+    }
+
+    #[test]
+    fn spawn_fetch() {
+        let mut world = World::new();
+        let entities = world.spawn_many((0..4).map(|i| (i, i as usize)));
+
+        let (mut int, size) = world.fetch::<(&mut i32, &usize)>(entities[1]).unwrap();
+        *int += *size as i32;
+        assert_eq!(world.fetch::<&i32>(entities[1]), Some(&2));
+
+        // 失效的Entity不能访问复用后的位置
+        world.remove(entities[1]);
+        assert_eq!(world.fetch::<&i32>(entities[1]), None);
+        let reused = world.spawn((10, 10usize));
+        assert_eq!(world.fetch::<&i32>(entities[1]), None);
+        assert_eq!(world.fetch::<&i32>(reused), Some(&10));
+    }
+
+    #[test]
+    fn optional() {
+        let mut world = World::new();
+        world.spawn_many([(1, 1usize), (2, 2usize)]);
+        let alone = world.spawn(3);
+
+        Query::<(&i32, Option<&mut usize>)>::new(&mut world)
+            .into_iter()
+            .for_each(|(i, size)| match size {
+                Some(mut size) => *size += *i as usize,
+                None => assert_eq!(*i, 3),
+            });
+        let mut sizes: Vec<_> = Query::<Option<&usize>>::new(&mut world)
+            .into_iter()
+            .map(|size| size.copied())
+            .collect();
+        sizes.sort();
+        assert_eq!(sizes, [None, Some(2), Some(4)]);
+
+        assert_eq!(
+            world.fetch::<(&i32, Option<&usize>)>(alone),
+            Some((&3, None))
+        );
+        // 同一个Component仍然不能被获取两次
+        assert!(world
+            .fetch::<(&i32, Option<&i32>)>(alone)
+            .is_some_and(|(_, i)| i.is_none()));
+    }
+
+    #[test]
+    fn entity() {
+        let mut world = World::new();
+        let mut entities = world.spawn_many([1, 2, 3]);
+        entities.push(world.spawn((4, "a")));
+
+        let mut fetched: Vec<_> = Query::<(Entity, &i32)>::new(&mut world)
+            .into_iter()
+            .map(|(entity, i)| (entity, *i))
+            .collect();
+        fetched.sort();
+        assert_eq!(
+            fetched,
+            entities.iter().copied().zip(1..=4).collect::<Vec<_>>()
+        );
+        assert_eq!(world.fetch::<Entity>(entities[3]), Some(entities[3]));
+    }
+
+    #[test]
+    fn try_fetch() {
+        let mut world = World::new();
+        let entity = world.spawn((1, "a"));
+        assert_eq!(world.try_fetch::<&&str>(entity), Ok(&"a"));
+        assert_eq!(
+            world.try_fetch::<(&i32, &usize)>(entity),
+            Err(FetchError::Mismatch {
+                entity,
+                bundle: std::any::type_name::<(i32, &str)>(),
+                fetch: std::any::type_name::<(&i32, &usize)>(),
+            })
+        );
+        world.remove(entity);
+        assert_eq!(
+            world.try_fetch::<&i32>(entity),
+            Err(FetchError::NoSuchEntity(entity))
+        );
+    }
+
+    #[test]
+    fn query_mutation() {
+        let mut world = World::new();
+        world.spawn_many((0..4).map(|i| (i, "a")));
+        world.spawn_many(0..4);
+
+        Query::<&mut i32>::new(&mut world)
+            .into_iter()
+            .for_each(|mut i| *i += 1);
+        Query::<(&mut i32, &mut &'static str)>::new(&mut world)
+            .into_iter()
+            .for_each(|(mut i, mut str)| {
+                *i *= 10;
+                *str = "b";
+            });
+
+        let mut ints: Vec<_> = Query::<&i32>::new(&mut world)
+            .into_iter()
+            .copied()
+            .collect();
+        ints.sort();
+        assert_eq!(ints, [1, 2, 3, 4, 10, 20, 30, 40]);
+        assert!(Query::<&&str>::new(&mut world)
+            .into_iter()
+            .all(|str| *str == "b"));
+    }
+
+    #[test]
+    fn disjoint_components() {
+        let mut world = World::new();
+        world.spawn_many((0..4).map(|i| (i, i as usize)));
+
+        // 同时持有同一个Entity的不同Component的引用
+        let cell = world.as_unsafe_world_cell();
+        let (ints, sizes) = unsafe {
+            (
+                Iter::<&mut i32>::from_cell::<()>(cell),
+                Iter::<&mut usize>::from_cell::<()>(cell),
+            )
+        };
+        let pairs: Vec<_> = ints.zip(sizes).collect();
+        for (mut int, mut size) in pairs {
+            *size += 1;
+            *int += *size as i32;
+        }
+
+        let mut items: Vec<_> = Query::<(&i32, &usize)>::new(&mut world)
+            .into_iter()
+            .map(|(i, s)| (*i, *s))
+            .collect();
+        items.sort();
+        assert_eq!(items, [(1, 1), (3, 2), (5, 3), (7, 4)]);
+    }
 }
@@ -1,12 +1,12 @@
-use std::{
-    any::{Any, TypeId},
-    collections::HashSet,
-    marker::PhantomData,
-};
+use std::{any::Any, collections::HashSet, marker::PhantomData};
 
-use crate::bundle::BundleMeta;
 #[allow(unused_imports)]
 use crate::{bundle::Bundle, tools::WorldFetch};
+use crate::{
+    bundle::{BundleMeta, Component, ComponentId, StorageType},
+    storage::{ChunkRow, ComponentTicks},
+    world::Disabled,
+};
 /// 用来过滤[Bundle]
 ///
 /// 作为[WorldFetch]的附属使用
@@ -15,12 +15,45 @@ pub trait WorldFilter: Any {
     ///
     /// + 返回true表示通过
     /// + 返回false表示没
-    fn filter(components_ids: &'static [TypeId]) -> bool;
+    fn filter(components_ids: &'static [ComponentId]) -> bool;
 
     /// 加速版本,会从缓存读取,否则重新计算
     ///
     /// 主要是为了让嵌套的[WorldFilter]可以更快
     fn filter_by_meta(meta: &mut BundleMeta) -> bool;
+
+    /// 是否需要逐个过滤[Entity],如[Changed]
+    ///
+    /// 为false时只会通过[WorldFilter::filter]过滤[Bundle]
+    ///
+    /// [Entity]: crate::storage::Entity
+    const PER_ENTITY: bool = false;
+
+    /// 逐个过滤[Entity],只在[WorldFilter::PER_ENTITY]为true时调用
+    ///
+    /// + components_ids与[ChunkRow::ticks]一一对应
+    /// + last_run是[System]上一次执行时的tick
+    ///
+    /// 只有已经通过了[WorldFilter::filter]的[Bundle]中的[Entity]才会被过滤
+    ///
+    /// [Entity]: crate::storage::Entity
+    /// [System]: crate::system::System
+    fn filter_entity(
+        components_ids: &'static [ComponentId],
+        _row: ChunkRow<'_>,
+        _last_run: usize,
+    ) -> bool {
+        Self::filter(components_ids)
+    }
+
+    /// 是否包含被禁用的[Entity],见[Disabled]
+    ///
+    /// [IncludeDisabled],以及提到了[Disabled]的[WorldFilter]为true
+    ///
+    /// [Entity]: crate::storage::Entity
+    fn include_disabled() -> bool {
+        false
+    }
 }
 
 /// [Bundle]是B的子集时通过
@@ -28,15 +61,56 @@ pub trait WorldFilter: Any {
 pub struct All<B: Bundle>(PhantomData<B>);
 
 /// [Bundle]与B有交集时通过
+///
+/// 只考虑[Bundle]中的[Component],[StorageType::SparseSet]的[Component]需要通过[WorldFetch]访问
+///
+/// [Component]: crate::bundle::Component
 #[derive(Debug, Clone, Copy)]
 pub struct AnyOf<B: Bundle>(PhantomData<B>);
 
-/// [Bundle]与B没有交集时通过
+/// F不通过时通过
+///
+/// 如`Not<AnyOf<B>>`,[Bundle]与B没有交集时通过
+#[derive(Debug, Clone, Copy)]
+pub struct Not<F: WorldFilter>(PhantomData<F>);
+
+/// [Bundle]中有T,并且T在[System]上一次执行之后被添加时通过
+///
+/// [StorageType::SparseSet]的T会逐个[Entity]查找
+///
+/// [System]: crate::system::System
+/// [Entity]: crate::storage::Entity
+#[derive(Debug, Clone, Copy)]
+pub struct Added<T: Component>(PhantomData<T>);
+
+/// [Bundle]中有T,并且T在[System]上一次执行之后被修改时通过
+///
+/// 新添加的T也算作被修改,只有通过[Mut]写入T才会被看作修改,只读取不算
+///
+/// [System]: crate::system::System
+/// [Mut]: crate::tools::Mut
+#[derive(Debug, Clone, Copy)]
+pub struct Changed<T: Component>(PhantomData<T>);
+
+/// F中任意一个[WorldFilter]通过时通过
+///
+/// F是由[WorldFilter]构成的元组,如`Or<(AnyOf<A>, AnyOf<B>)>`
+#[derive(Debug, Clone, Copy)]
+pub struct Or<F>(PhantomData<F>);
+
+/// 总是通过,并且包含被禁用的[Entity],见[Disabled]
+///
+/// [Entity]: crate::storage::Entity
 #[derive(Debug, Clone, Copy)]
-pub struct Not<B: Bundle>(PhantomData<B>);
+pub struct IncludeDisabled;
+
+/// ids中是否有[Disabled]
+fn mentions_disabled(ids: &[ComponentId]) -> bool {
+    ids.contains(&ComponentId::of::<Disabled>())
+}
 
 impl<B: Bundle> WorldFilter for All<B> {
-    fn filter(components_ids: &'static [TypeId]) -> bool {
+    fn filter(components_ids: &'static [ComponentId]) -> bool {
         let set = B::components_ids()
             .iter()
             .copied()
@@ -50,10 +124,14 @@ impl<B: Bundle> WorldFilter for All<B> {
     fn filter_by_meta(meta: &mut BundleMeta) -> bool {
         meta.filter::<Self>()
     }
+
+    fn include_disabled() -> bool {
+        mentions_disabled(B::components_ids())
+    }
 }
 
 impl<B: Bundle> WorldFilter for AnyOf<B> {
-    fn filter(components_ids: &'static [TypeId]) -> bool {
+    fn filter(components_ids: &'static [ComponentId]) -> bool {
         let set = B::components_ids()
             .iter()
             .copied()
@@ -67,45 +145,182 @@ impl<B: Bundle> WorldFilter for AnyOf<B> {
     fn filter_by_meta(meta: &mut BundleMeta) -> bool {
         meta.filter::<Self>()
     }
+
+    fn include_disabled() -> bool {
+        mentions_disabled(B::components_ids())
+    }
 }
 
-impl<B: Bundle> WorldFilter for Not<B> {
-    fn filter(components_ids: &'static [TypeId]) -> bool {
-        let set = B::components_ids()
-            .iter()
-            .copied()
-            .fold(HashSet::new(), |mut set, id| {
-                set.insert(id);
-                set
-            });
-        !components_ids.iter().any(|id| set.contains(id))
+impl<F: WorldFilter> WorldFilter for Not<F> {
+    fn filter(components_ids: &'static [ComponentId]) -> bool {
+        // 需要逐个过滤时,同一个Bundle中的Entity有可能通过也有可能不通过
+        F::PER_ENTITY || !F::filter(components_ids)
     }
 
     fn filter_by_meta(meta: &mut BundleMeta) -> bool {
         meta.filter::<Self>()
     }
+
+    const PER_ENTITY: bool = F::PER_ENTITY;
+
+    fn filter_entity(
+        components_ids: &'static [ComponentId],
+        row: ChunkRow<'_>,
+        last_run: usize,
+    ) -> bool {
+        !F::filter_entity(components_ids, row, last_run)
+    }
+
+    fn include_disabled() -> bool {
+        F::include_disabled()
+    }
+}
+
+/// [Bundle]中是否可能有T
+///
+/// [StorageType::SparseSet]的T不在[Bundle]中,任何[Bundle]都可能有
+fn may_contain<T: Component>(components_ids: &[ComponentId]) -> bool {
+    T::STORAGE == StorageType::SparseSet || components_ids.contains(&ComponentId::of::<T>())
+}
+
+/// row中T的[ComponentTicks],没有T时为[None]
+fn ticks_of<'a, T: Component>(
+    components_ids: &[ComponentId],
+    row: ChunkRow<'a>,
+) -> Option<&'a ComponentTicks> {
+    if T::STORAGE == StorageType::SparseSet {
+        return row.sparse(&ComponentId::of::<T>()).map(|(_, ticks)| ticks);
+    }
+    let index = (components_ids.iter()).position(|id| *id == ComponentId::of::<T>())?;
+    Some(row.ticks(index))
+}
+
+impl<T: Component> WorldFilter for Added<T> {
+    fn filter(components_ids: &'static [ComponentId]) -> bool {
+        may_contain::<T>(components_ids)
+    }
+
+    fn filter_by_meta(meta: &mut BundleMeta) -> bool {
+        meta.filter::<Self>()
+    }
+
+    const PER_ENTITY: bool = true;
+
+    fn filter_entity(
+        components_ids: &'static [ComponentId],
+        row: ChunkRow<'_>,
+        last_run: usize,
+    ) -> bool {
+        ticks_of::<T>(components_ids, row).is_some_and(|ticks| ticks.is_added(last_run))
+    }
+
+    fn include_disabled() -> bool {
+        ComponentId::of::<T>() == ComponentId::of::<Disabled>()
+    }
+}
+
+impl<T: Component> WorldFilter for Changed<T> {
+    fn filter(components_ids: &'static [ComponentId]) -> bool {
+        may_contain::<T>(components_ids)
+    }
+
+    fn filter_by_meta(meta: &mut BundleMeta) -> bool {
+        meta.filter::<Self>()
+    }
+
+    const PER_ENTITY: bool = true;
+
+    fn filter_entity(
+        components_ids: &'static [ComponentId],
+        row: ChunkRow<'_>,
+        last_run: usize,
+    ) -> bool {
+        ticks_of::<T>(components_ids, row).is_some_and(|ticks| ticks.is_changed(last_run))
+    }
+
+    fn include_disabled() -> bool {
+        ComponentId::of::<T>() == ComponentId::of::<Disabled>()
+    }
+}
+
+impl WorldFilter for IncludeDisabled {
+    fn filter(_: &'static [ComponentId]) -> bool {
+        true
+    }
+
+    fn filter_by_meta(_meta: &mut BundleMeta) -> bool {
+        true
+    }
+
+    fn include_disabled() -> bool {
+        true
+    }
 }
 
 mod __impl {
-    use super::{BundleMeta, TypeId, WorldFilter};
+    use super::{BundleMeta, ChunkRow, ComponentId, Or, WorldFilter};
     macro_rules! impl_filter {
         ($($t:ident),*) => {
             impl<$($t:WorldFilter),*> WorldFilter for ($($t,)*) {
-                fn filter(components_ids : &'static [TypeId]) -> bool{
+                fn filter(components_ids : &'static [ComponentId]) -> bool{
                     $($t::filter(components_ids))&&*
                 }
 
                 fn filter_by_meta(meta: &mut BundleMeta) -> bool {
                    $($t::filter_by_meta(meta))&&*
                 }
+
+                const PER_ENTITY: bool = $($t::PER_ENTITY)||*;
+
+                fn filter_entity(
+                    components_ids: &'static [ComponentId],
+                    row: ChunkRow<'_>,
+                    last_run: usize,
+                ) -> bool {
+                    $($t::filter_entity(components_ids, row, last_run))&&*
+                }
+
+                fn include_disabled() -> bool {
+                    $($t::include_disabled())||*
+                }
             }
         };
     }
 
     trecs_proc::all_tuple!(impl_filter, 16);
 
+    macro_rules! impl_or {
+        ($($t:ident),*) => {
+            impl<$($t:WorldFilter),*> WorldFilter for Or<($($t,)*)> {
+                fn filter(components_ids : &'static [ComponentId]) -> bool{
+                    $($t::filter(components_ids))||*
+                }
+
+                fn filter_by_meta(meta: &mut BundleMeta) -> bool {
+                    meta.filter::<Self>()
+                }
+
+                const PER_ENTITY: bool = $($t::PER_ENTITY)||*;
+
+                fn filter_entity(
+                    components_ids: &'static [ComponentId],
+                    row: ChunkRow<'_>,
+                    last_run: usize,
+                ) -> bool {
+                    $($t::filter_entity(components_ids, row, last_run))||*
+                }
+
+                fn include_disabled() -> bool {
+                    $($t::include_disabled())||*
+                }
+            }
+        };
+    }
+
+    trecs_proc::all_tuple!(impl_or, 16);
+
     impl WorldFilter for () {
-        fn filter(_: &'static [TypeId]) -> bool {
+        fn filter(_: &'static [ComponentId]) -> bool {
             true
         }
 
@@ -114,3 +329,67 @@ mod __impl {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tools::Command, world::Query, World};
+
+    #[test]
+    fn or_not() {
+        let mut world = World::new();
+        world.spawn((1i32, 1u8));
+        world.spawn((2i32, 2u16));
+        world.spawn((3i32, 3u8, false));
+        world.spawn((4i32, 4u32));
+
+        // 有u8或者u16,但是没有bool
+        let query = Query::<&i32, (Or<(AnyOf<u8>, AnyOf<u16>)>, Not<AnyOf<bool>>)>::new(&mut world);
+        let mut ints = query.into_iter().copied().collect::<Vec<_>>();
+        ints.sort();
+        assert_eq!(ints, [1, 2]);
+
+        let query = Query::<&i32, Not<Or<(AnyOf<u8>, AnyOf<u16>)>>>::new(&mut world);
+        assert_eq!(query.into_iter().copied().collect::<Vec<_>>(), [4]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    fn added_changed() {
+        use crate::{
+            test::{self, Capture},
+            world::ResMut,
+        };
+
+        fn record(
+            added: Query<&i32, Added<i32>>,
+            changed: Query<&i32, Changed<i32>>,
+            capture: ResMut<Capture<(usize, usize)>>,
+        ) {
+            let counts = (added.into_iter().count(), changed.into_iter().count());
+            capture.into_or_init(Capture::new).push(counts);
+        }
+        fn bump(query: Query<&mut i32, Not<AnyOf<bool>>>) {
+            query.into_iter().for_each(|mut i| *i += 1);
+        }
+
+        let mut world = World::new();
+        world.spawn_many([1, 2]);
+        world.spawn((3, true));
+        world.add_system(record).add_system(bump);
+
+        test::tick(&mut world);
+        test::tick(&mut world);
+        // 在两帧之间放入
+        world.spawn(4);
+        test::tick(&mut world);
+        assert_eq!(
+            test::captured::<(usize, usize)>(&mut world),
+            [(3, 3), (0, 2), (1, 3)]
+        );
+
+        // 在System之外,所有Component都被看作新的
+        let query = Query::<&i32, (Added<i32>, Not<Changed<i32>>)>::new(&mut world);
+        assert_eq!(query.into_iter().count(), 0);
+    }
+}
@@ -1,9 +1,13 @@
 mod command;
 mod fetch;
 mod filter;
+mod mutation;
 mod resources;
+mod timer;
 
 pub use command::*;
 pub use fetch::*;
 pub use filter::*;
+pub use mutation::*;
 pub use resources::*;
+pub use timer::*;
@@ -0,0 +1,140 @@
+use std::{
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+};
+
+use crate::storage::ComponentTicks;
+
+/// 通过`&mut T`访问[Component]时得到的可变引用
+///
+/// 只读访问不会改变[ComponentTicks],
+/// 只有通过[DerefMut]访问时才会把[Component]标记为修改过,
+/// 所以[Changed]只会包含真正被修改的[Component]
+///
+/// ``` rust
+/// use trecs::{tools::Command, world::Query, World};
+///
+/// let mut world = World::new();
+/// world.spawn_many([1, 2, 3]);
+/// for mut i in Query::<&mut i32>::new(&mut world) {
+///     // 只有2被标记为修改过
+///     if *i == 2 {
+///         *i *= 10;
+///     }
+/// }
+/// let mut ints = Query::<&i32>::new(&mut world)
+///     .into_iter()
+///     .copied()
+///     .collect::<Vec<_>>();
+/// ints.sort();
+/// assert_eq!(ints, [1, 3, 20]);
+/// ```
+///
+/// [Component]: crate::bundle::Component
+/// [Changed]: crate::tools::Changed
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    ticks: &'a ComponentTicks,
+    /// 见[UnsafeWorldCell::change_ticks]
+    ///
+    /// [UnsafeWorldCell::change_ticks]: crate::world::UnsafeWorldCell::change_ticks
+    change_ticks: (usize, usize),
+}
+
+impl<'a, T> Mut<'a, T> {
+    pub(crate) fn new(
+        value: &'a mut T,
+        ticks: &'a ComponentTicks,
+        change_ticks: (usize, usize),
+    ) -> Self {
+        Self {
+            value,
+            ticks,
+            change_ticks,
+        }
+    }
+
+    /// 是否在上一次执行之后被添加
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added(self.change_ticks.0)
+    }
+
+    /// 是否在上一次执行之后被修改,被添加也算作修改
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.change_ticks.0)
+    }
+
+    /// 获取可变引用,并且标记为修改过
+    ///
+    /// 与[DerefMut]不同,返回的引用的生命周期与[Mut]本身无关
+    pub fn into_inner(self) -> &'a mut T {
+        self.ticks.set_changed(self.change_ticks.1);
+        self.value
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.ticks.set_changed(self.change_ticks.1);
+        self.value
+    }
+}
+
+impl<T> AsRef<T> for Mut<'_, T> {
+    fn as_ref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: Debug> Debug for Mut<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        tools::{Changed, Command},
+        world::Query,
+        World,
+    };
+
+    fn changed(world: &mut World) -> Vec<i32> {
+        let mut ints = Query::<&i32, Changed<i32>>::new(world)
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        ints.sort();
+        ints
+    }
+
+    #[test]
+    fn mark_on_write() {
+        let mut world = World::new();
+        let [a, b, _] = [1, 2, 3].map(|i| world.spawn(i));
+        // 模拟在之后的System中访问
+        world.last_run = 1;
+        world.change_tick = 2;
+
+        // 只读取不算作修改
+        for i in Query::<&mut i32>::new(&mut world) {
+            assert!(!i.is_changed() && !i.is_added());
+        }
+        assert!(changed(&mut world).is_empty());
+
+        *world.fetch::<&mut i32>(a).unwrap() += 10;
+        let mut query = Query::<Option<&mut i32>>::new(&mut world);
+        *query.get_mut(b).unwrap().unwrap().into_inner() += 20;
+        assert_eq!(changed(&mut world), [11, 22]);
+        assert!(world.fetch::<&mut i32>(a).unwrap().is_changed());
+    }
+}
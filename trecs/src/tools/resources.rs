@@ -1,4 +1,4 @@
-use crate::world::Res;
+use crate::world::ResMut;
 #[allow(unused_imports)]
 use crate::world::World;
 
@@ -8,12 +8,12 @@ pub trait ResManager {
     /// 重复获取会报错
     ///
     /// 如果原来不存在资源,会为资源创建一个位置
-    fn get_res<T: 'static>(&mut self) -> Res<'_, T>;
+    fn get_res<T: 'static>(&mut self) -> ResMut<'_, T>;
 
     /// 获取类型对应资源的一个[ResOwner]
     ///
     /// 如果原来不存在资源,不会为资源创建一个位置,并返回[none]
-    fn try_get_res<T: 'static>(&mut self) -> Option<Res<'_, T>>;
+    fn try_get_res<T: 'static>(&mut self) -> Option<ResMut<'_, T>>;
 
     /// 为类型创建一个位置,准备储存资源
     ///
@@ -0,0 +1,286 @@
+use std::time::Duration;
+
+/// [Timer]结束后的行为
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimerMode {
+    /// 只结束一次,之后保持结束的状态
+    #[default]
+    Once,
+    /// 结束后从头开始计时
+    Repeating,
+}
+
+/// 经过一段时间后结束的计时器
+///
+/// 通过[Timer::tick]前进,通常传入[Time::delta],
+/// 可以作为资源或[Component]存放在[World]中
+///
+/// ``` rust
+/// use std::time::Duration;
+/// use trecs::{
+///     time::Time,
+///     tools::{ResManager, Timer, TimerMode},
+///     world::{Res, ResMut},
+///     World,
+/// };
+///
+/// struct Spawned(u32);
+///
+/// fn spawner(time: Res<Time>, mut timer: ResMut<Timer>, mut spawned: ResMut<Spawned>) {
+///     let delta = time.get().unwrap().delta();
+///     if timer.get_mut().unwrap().tick(delta).just_finished() {
+///         spawned.get_mut().unwrap().0 += 1;
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world
+///     .enable_manual_time()
+///     .insert_resource(Timer::from_secs(1.0, TimerMode::Repeating))
+///     .insert_resource(Spawned(0))
+///     .add_system(spawner);
+/// for _ in 0..5 {
+///     world.advance_time(Duration::from_millis(500));
+///     world.run_once();
+/// }
+/// assert_eq!(world.resource::<Spawned>().unwrap().0, 2);
+/// ```
+///
+/// [Time::delta]: crate::time::Time::delta
+/// [Component]: crate::bundle::Component
+/// [World]: crate::World
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Timer {
+    duration: Duration,
+    elapsed: Duration,
+    mode: TimerMode,
+    paused: bool,
+    finished: bool,
+    times_finished_this_tick: u32,
+}
+
+impl Timer {
+    pub fn new(duration: Duration, mode: TimerMode) -> Self {
+        Self {
+            duration,
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// 同[Timer::new],时长的单位为秒
+    pub fn from_secs(secs: f32, mode: TimerMode) -> Self {
+        Self::new(Duration::from_secs_f32(secs), mode)
+    }
+
+    /// 让[Timer]前进delta
+    ///
+    /// + [TimerMode::Once]时,经过的时间达到时长时结束,之后不再前进
+    /// + [TimerMode::Repeating]时,每达到一次时长就结束一次,多出的时间计入下一轮
+    ///
+    /// 暂停时什么都不做
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        self.times_finished_this_tick = 0;
+        if self.paused {
+            return self;
+        }
+        if self.mode == TimerMode::Once && self.finished {
+            return self;
+        }
+        self.elapsed += delta;
+        self.finished = self.elapsed >= self.duration;
+        if !self.finished {
+            return self;
+        }
+        match self.mode {
+            TimerMode::Once => {
+                self.elapsed = self.duration;
+                self.times_finished_this_tick = 1;
+            }
+            // 时长为0时每次都只结束一次
+            TimerMode::Repeating if self.duration.is_zero() => {
+                self.elapsed = Duration::ZERO;
+                self.times_finished_this_tick = 1;
+            }
+            TimerMode::Repeating => {
+                let times = self.elapsed.as_nanos() / self.duration.as_nanos();
+                self.times_finished_this_tick = times.try_into().unwrap_or(u32::MAX);
+                self.elapsed = Duration::from_nanos(
+                    (self.elapsed.as_nanos() % self.duration.as_nanos()) as u64,
+                );
+            }
+        }
+        self
+    }
+
+    /// 是否已经结束
+    ///
+    /// [TimerMode::Repeating]只在结束的那一次[Timer::tick]之后为true
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// 是否在最近一次[Timer::tick]中结束
+    pub fn just_finished(&self) -> bool {
+        self.times_finished_this_tick > 0
+    }
+
+    /// 最近一次[Timer::tick]中结束的次数,delta大于时长时[TimerMode::Repeating]可能结束多次
+    pub fn times_finished_this_tick(&self) -> u32 {
+        self.times_finished_this_tick
+    }
+
+    /// 这一轮中经过的时间
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// 这一轮中剩余的时间
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    /// 这一轮完成的比例,在0到1之间
+    pub fn fraction(&self) -> f32 {
+        match self.duration.is_zero() {
+            true => 1.0,
+            false => self.elapsed.as_secs_f32() / self.duration.as_secs_f32(),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// 修改时长,不会影响已经经过的时间
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TimerMode) {
+        self.mode = mode;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// 从头开始计时,不会改变是否暂停
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+        self.times_finished_this_tick = 0;
+    }
+}
+
+/// 记录经过了多长时间,同[Timer]一样通过[Stopwatch::tick]前进
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Stopwatch {
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl Stopwatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 让[Stopwatch]前进delta,暂停时什么都不做
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        if !self.paused {
+            self.elapsed += delta;
+        }
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// 经过的时间,单位为秒
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// 归零,不会改变是否暂停
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn timer() {
+        let mut once = Timer::new(MS * 10, TimerMode::Once);
+        assert!(!once.tick(MS * 6).finished());
+        assert_eq!(once.remaining(), MS * 4);
+        assert!(once.tick(MS * 6).just_finished());
+        assert_eq!((once.elapsed(), once.fraction()), (MS * 10, 1.0));
+        // 结束后保持结束的状态,但不会再次just_finished
+        assert!(once.tick(MS * 6).finished());
+        assert!(!once.just_finished());
+        once.reset();
+        assert!(!once.finished());
+
+        let mut repeating = Timer::new(MS * 10, TimerMode::Repeating);
+        assert!(repeating.tick(MS * 25).just_finished());
+        assert_eq!(repeating.times_finished_this_tick(), 2);
+        assert_eq!(repeating.elapsed(), MS * 5);
+        assert!(!repeating.tick(MS * 2).finished());
+        repeating.pause();
+        assert_eq!(repeating.tick(MS * 10).elapsed(), MS * 7);
+        repeating.unpause();
+        assert_eq!(repeating.tick(MS * 3).times_finished_this_tick(), 1);
+        assert_eq!(repeating.elapsed(), Duration::ZERO);
+
+        let mut zero = Timer::new(Duration::ZERO, TimerMode::Repeating);
+        assert_eq!(zero.tick(MS).times_finished_this_tick(), 1);
+    }
+
+    #[test]
+    fn stopwatch() {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.tick(MS * 3);
+        stopwatch.pause();
+        stopwatch.tick(MS * 3);
+        assert!(stopwatch.paused());
+        assert_eq!(stopwatch.elapsed(), MS * 3);
+        stopwatch.unpause();
+        assert_eq!(stopwatch.tick(MS).elapsed(), MS * 4);
+        stopwatch.reset();
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    fmt::Debug,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{AnRes, UnsafeWorldCell};
+use crate::system::state::Alias;
+
+/// 可变借用时[BorrowFlag]中的值
+const EXCLUSIVE: usize = usize::MAX;
+
+/// 在运行时记录一种资源的借用情况
+///
+/// + 0表示没有借用
+/// + [EXCLUSIVE]表示正在被可变借用
+/// + 其他值为不可变借用的数量
+///
+/// 与debug-borrows特性的BorrowTracker不同,总是存在,只需要一次原子操作
+#[derive(Default)]
+pub(crate) struct BorrowFlag(AtomicUsize);
+
+impl BorrowFlag {
+    /// 借用失败时返回已有的借用是否是可变的
+    fn try_acquire(&self, exclusive: bool) -> Result<(), bool> {
+        let result = match exclusive {
+            true => self
+                .0
+                .compare_exchange(0, EXCLUSIVE, Ordering::Acquire, Ordering::Relaxed),
+            false => self
+                .0
+                .fetch_update(Ordering::Acquire, Ordering::Relaxed, |n| {
+                    (n < EXCLUSIVE - 1).then(|| n + 1)
+                }),
+        };
+        result.map(|_| ()).map_err(|n| n == EXCLUSIVE)
+    }
+
+    /// 释放一次通过[BorrowFlag::try_acquire]得到的借用
+    fn release(&self, exclusive: bool) {
+        match exclusive {
+            true => self.0.store(0, Ordering::Release),
+            false => {
+                self.0.fetch_sub(1, Ordering::Release);
+            }
+        }
+    }
+}
+
+impl Debug for BorrowFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.load(Ordering::Relaxed) {
+            EXCLUSIVE => write!(f, "BorrowFlag(exclusive)"),
+            n => write!(f, "BorrowFlag({n})"),
+        }
+    }
+}
+
+/// 一个[System]在执行期间借用的所有资源,Drop时释放
+///
+/// [System]: crate::system::System
+pub(crate) struct ResBorrows<'w> {
+    resources: &'w HashMap<TypeId, AnRes>,
+    borrowed: Vec<(TypeId, bool)>,
+}
+
+impl Drop for ResBorrows<'_> {
+    fn drop(&mut self) {
+        for (id, exclusive) in &self.borrowed {
+            if let Some(res) = self.resources.get(id) {
+                res.borrow.release(*exclusive);
+            }
+        }
+    }
+}
+
+impl<'w> UnsafeWorldCell<'w> {
+    /// 在运行时借用名为system的[System]访问的所有资源,直到返回值被Drop
+    ///
+    /// 资源已经被冲突地借用时panic,已经借用的资源会被释放
+    ///
+    /// # Safety
+    ///
+    /// 见[UnsafeWorldCell::resources]
+    ///
+    /// [System]: crate::system::System
+    pub(crate) unsafe fn borrow_res(
+        self,
+        system: &str,
+        res: &HashMap<TypeId, (&'static str, Alias)>,
+    ) -> ResBorrows<'w> {
+        let mut borrows = ResBorrows {
+            resources: self.resources(),
+            borrowed: vec![],
+        };
+        for (id, (name, alias)) in res {
+            let Some(an_res) = borrows.resources.get(id) else {
+                continue;
+            };
+            let exclusive = alias.is_mut();
+            if let Err(held) = an_res.borrow.try_acquire(exclusive) {
+                let kind = |exclusive| if exclusive { "可变" } else { "不可变" };
+                panic!(
+                    "发生别名冲突: {system} 借用资源{name}的{}引用时,它已经被{}借用",
+                    kind(exclusive),
+                    kind(held)
+                );
+            }
+            borrows.borrowed.push((*id, exclusive));
+        }
+        borrows
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        tools::ResManager,
+        world::{Res, ResMut, World},
+    };
+
+    #[test]
+    fn borrow_flag() {
+        let flag = BorrowFlag::default();
+        assert_eq!(flag.try_acquire(false), Ok(()));
+        assert_eq!(flag.try_acquire(false), Ok(()));
+        assert_eq!(format!("{flag:?}"), "BorrowFlag(2)");
+        assert_eq!(flag.try_acquire(true), Err(false));
+        flag.release(false);
+        flag.release(false);
+        assert_eq!(flag.try_acquire(true), Ok(()));
+        assert_eq!(format!("{flag:?}"), "BorrowFlag(exclusive)");
+        assert_eq!(flag.try_acquire(false), Err(true));
+        flag.release(true);
+        assert_eq!(format!("{flag:?}"), "BorrowFlag(0)");
+    }
+
+    #[test]
+    fn release() {
+        let mut world = World::new();
+        world.insert_resource(1u32);
+        world.exec(|a: Res<u32>| assert_eq!(a.get(), Some(&1)));
+        world.exec(|mut a: ResMut<u32>| *a.get_mut().unwrap() += 1);
+        world.add_system(|_: Res<u32>| {});
+        world.add_system(|_: Res<u32>| {});
+        world.run_once();
+        world.run_once();
+        assert_eq!(world.resource::<u32>(), Some(&2));
+        let res = &world.resources[&TypeId::of::<u32>()];
+        assert_eq!(format!("{:?}", res.borrow), "BorrowFlag(0)");
+    }
+
+    #[test]
+    #[should_panic(expected = "借用资源u32的不可变引用时,它已经被可变借用")]
+    fn conflict() {
+        let mut world = World::new();
+        world.get_res::<u32>().get_or_init(|| 1);
+        world.resources[&TypeId::of::<u32>()]
+            .borrow
+            .try_acquire(true)
+            .unwrap();
+        world.exec(|_: Res<u32>| {});
+    }
+}
@@ -0,0 +1,349 @@
+#[cfg(feature = "system")]
+use std::any::TypeId;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::{MutexGuard, PoisonError},
+};
+
+use crate::{
+    bundle::{BundleId, BundleMeta},
+    storage::{Chunk, Entities, SparseSets},
+};
+
+use super::World;
+#[cfg(feature = "system")]
+use super::{commands::CommandQueue, local::Locals, usage::ResourcesUsage, AnRes, Droper};
+
+/// 可以同时存在多个的,对[World]的可变访问权限
+///
+/// 只能从`&mut World`创建,所有绕开借用检查的访问都应该通过它进行,
+/// 而不是把`&World`转换为`&mut World`
+///
+/// 通过它访问[World]的各个部分时,调用者需要保证同一时间不存在冲突的访问,
+/// 对于[System]来说,这由[SystemState]保证
+///
+/// [System]: crate::system::System
+/// [SystemState]: crate::system::state::SystemState
+#[derive(Debug, Clone, Copy)]
+pub struct UnsafeWorldCell<'w> {
+    ptr: *mut World,
+    /// 见[UnsafeWorldCell::change_ticks]
+    last_run: usize,
+    change_tick: usize,
+    /// 正在创建参数的[System]的[Local]的值,见[UnsafeWorldCell::with_locals]
+    ///
+    /// [System]: crate::system::System
+    /// [Local]: crate::world::Local
+    #[cfg(feature = "system")]
+    locals: *const Locals,
+    /// 正在创建参数的[System]自己的[CommandQueue],见[UnsafeWorldCell::with_commands]
+    ///
+    /// [System]: crate::system::System
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    commands: *const CommandQueue,
+    _p: PhantomData<&'w mut World>,
+}
+
+impl World {
+    pub fn as_unsafe_world_cell(&mut self) -> UnsafeWorldCell<'_> {
+        UnsafeWorldCell {
+            last_run: self.last_run,
+            change_tick: self.change_tick,
+            #[cfg(feature = "system")]
+            locals: std::ptr::null(),
+            #[cfg(all(feature = "system", not(feature = "async")))]
+            commands: std::ptr::null(),
+            ptr: self,
+            _p: PhantomData,
+        }
+    }
+}
+
+/// 持有锁的所有[BundleMeta]
+///
+/// 由[UnsafeWorldCell::metas_mut]返回,被[Drop]时释放锁
+pub(crate) struct MetasMut<'w> {
+    metas: &'w mut HashMap<BundleId, BundleMeta>,
+    _guard: MutexGuard<'w, ()>,
+}
+
+impl Deref for MetasMut<'_> {
+    type Target = HashMap<BundleId, BundleMeta>;
+
+    fn deref(&self) -> &Self::Target {
+        self.metas
+    }
+}
+
+impl DerefMut for MetasMut<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.metas
+    }
+}
+
+impl<'w> UnsafeWorldCell<'w> {
+    /// 整个[World]的可变引用
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能有任何其他通过这个[UnsafeWorldCell]进行的访问
+    pub unsafe fn world_mut(self) -> &'w mut World {
+        &mut *self.ptr
+    }
+
+    /// 整个[World]的不可变引用
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能有任何可变的访问
+    pub unsafe fn world(self) -> &'w World {
+        &*self.ptr
+    }
+
+    /// 所有[BundleMeta]
+    ///
+    /// 并行执行的[System]可能同时访问,所以需要加锁,
+    /// 锁被持有时不能再次调用,否则会死锁
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能有不经过这个锁的对[BundleMeta]的访问
+    ///
+    /// [System]: crate::system::System
+    pub(crate) unsafe fn metas_mut(self) -> MetasMut<'w> {
+        let world = self.ptr;
+        MetasMut {
+            _guard: (*world)
+                .metas_lock
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+            metas: &mut (*world).metas,
+        }
+    }
+
+    /// 所有[Chunk]
+    ///
+    /// [Chunk]中的[Components]被[UnsafeCell]包裹,可以通过不可变引用修改
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能增加或者删除[Chunk]
+    ///
+    /// [Components]: crate::bundle::Components
+    /// [UnsafeCell]: std::cell::UnsafeCell
+    pub(crate) unsafe fn chunks(self) -> &'w [Chunk] {
+        &(*self.ptr).chunks
+    }
+
+    /// 所有[Entity]以及它们的位置
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能增加或者删除[Entity]
+    ///
+    /// [Entity]: crate::storage::Entity
+    pub(crate) unsafe fn entities(self) -> &'w Entities {
+        &(*self.ptr).entities
+    }
+
+    /// 所有[StorageType::SparseSet]的[Component]
+    ///
+    /// [Component]被[SparseSet]间接存放,所以同[UnsafeWorldCell::chunks]一样可以同时被修改
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能增加或者删除[StorageType::SparseSet]的[Component]
+    ///
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    /// [Component]: crate::bundle::Component
+    /// [SparseSet]: crate::storage::SparseSet
+    pub(crate) unsafe fn sparse(self) -> &'w SparseSets {
+        &(*self.ptr).sparse
+    }
+
+    /// 正在执行的[System]上一次执行时的tick,以及当前的tick
+    ///
+    /// 在创建时从[World]复制
+    ///
+    /// [System]: crate::system::System
+    pub(crate) fn change_ticks(self) -> (usize, usize) {
+        (self.last_run, self.change_tick)
+    }
+
+    /// 替换[UnsafeWorldCell::change_ticks]
+    ///
+    /// 用于并行执行的[System],它们各自有不同的tick
+    ///
+    /// [System]: crate::system::System
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    pub(crate) fn with_change_ticks(self, last_run: usize, change_tick: usize) -> Self {
+        Self {
+            last_run,
+            change_tick,
+            ..self
+        }
+    }
+
+    /// 为[System]创建参数时使用它自己的[Local]的值
+    ///
+    /// # Safety
+    ///
+    /// 在通过返回的[UnsafeWorldCell]创建的参数存在期间,locals必须有效,并且不能被其他地方访问
+    ///
+    /// [System]: crate::system::System
+    /// [Local]: crate::world::Local
+    #[cfg(feature = "system")]
+    pub(crate) unsafe fn with_locals(self, locals: &Locals) -> Self {
+        Self { locals, ..self }
+    }
+
+    /// 为[System]创建参数时把[Commands]推迟的操作放入它自己的queue,
+    /// 而不是[World]中的,用于并行执行的[System]
+    ///
+    /// # Safety
+    ///
+    /// 在通过返回的[UnsafeWorldCell]创建的参数存在期间,queue必须有效
+    ///
+    /// [System]: crate::system::System
+    /// [Commands]: crate::world::Commands
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    pub(crate) unsafe fn with_commands(self, queue: &CommandQueue) -> Self {
+        Self {
+            commands: queue,
+            ..self
+        }
+    }
+
+    /// 正在创建参数的[System]的所有[Local]的值
+    ///
+    /// 没有通过[UnsafeWorldCell::with_locals]指定时会panic
+    ///
+    /// # Safety
+    ///
+    /// 同[UnsafeWorldCell::with_locals]
+    ///
+    /// [System]: crate::system::System
+    /// [Local]: crate::world::Local
+    #[cfg(feature = "system")]
+    pub(crate) unsafe fn locals(self) -> &'w mut HashMap<TypeId, Box<dyn std::any::Any + Send>> {
+        self.try_locals().expect("Local只能作为System的参数")
+    }
+
+    /// 同[UnsafeWorldCell::locals],没有指定时返回[None]
+    ///
+    /// # Safety
+    ///
+    /// 同[UnsafeWorldCell::with_locals]
+    #[cfg(feature = "system")]
+    pub(crate) unsafe fn try_locals(
+        self,
+    ) -> Option<&'w mut HashMap<TypeId, Box<dyn std::any::Any + Send>>> {
+        (!self.locals.is_null()).then(|| &mut *(*self.locals).get())
+    }
+
+    /// 所有资源
+    ///
+    /// 资源被[UnsafeCell]包裹,可以通过不可变引用修改
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能增加或者删除资源
+    ///
+    /// [UnsafeCell]: std::cell::UnsafeCell
+    #[cfg(feature = "system")]
+    pub(crate) unsafe fn resources(self) -> &'w HashMap<TypeId, AnRes> {
+        &(*self.ptr).resources
+    }
+
+    /// 被[Commands]推迟的操作,通过[UnsafeWorldCell::with_commands]指定时为指定的queue
+    ///
+    /// 内部有锁,可以同时存在多个引用
+    ///
+    /// [Commands]: crate::world::Commands
+    #[cfg(feature = "system")]
+    pub(crate) unsafe fn command_queue(self) -> &'w CommandQueue {
+        #[cfg(not(feature = "async"))]
+        if !self.commands.is_null() {
+            return &*self.commands;
+        }
+        &(*self.ptr).command_queue
+    }
+
+    /// 异步[System]通过[WorldAccess]请求的操作,同[UnsafeWorldCell::command_queue]
+    ///
+    /// [System]: crate::system::System
+    /// [WorldAccess]: crate::system::WorldAccess
+    #[cfg(all(feature = "system", feature = "async"))]
+    pub(crate) unsafe fn access_queue(self) -> &'w crate::system::AccessQueue {
+        &(*self.ptr).access_queue
+    }
+
+    /// 运行时的借用检查
+    #[cfg(feature = "debug-borrows")]
+    pub(crate) unsafe fn borrows(self) -> &'w crate::system::borrows::BorrowTracker {
+        &(*self.ptr).borrows
+    }
+
+    /// 资源读取的记录
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能有其他对记录的访问
+    #[cfg(feature = "system")]
+    pub(crate) unsafe fn resources_usage(self) -> &'w mut ResourcesUsage {
+        &mut (*self.ptr).resources_usage
+    }
+
+    /// 所有资源,以及对应的droper,和读取的记录
+    ///
+    /// # Safety
+    ///
+    /// 返回的引用存在期间,不能有其他对资源的访问
+    #[cfg(feature = "system")]
+    pub(crate) unsafe fn resources_mut(
+        self,
+    ) -> (
+        &'w mut HashMap<TypeId, AnRes>,
+        &'w mut HashMap<TypeId, Droper>,
+        &'w mut ResourcesUsage,
+    ) {
+        let world = self.ptr;
+        (
+            &mut (*world).resources,
+            &mut (*world).resources_dropers,
+            &mut (*world).resources_usage,
+        )
+    }
+}
+
+#[cfg(all(test, feature = "system", not(feature = "async")))]
+mod tests {
+    use crate::{
+        tools::Command,
+        world::{Query, World},
+    };
+
+    #[test]
+    fn interleaved_queries() {
+        fn add(ints: Query<&mut i32>, strs: Query<(&usize, &&'static str)>, usizes: Query<&usize>) {
+            let mut ints = ints.into_iter();
+            *ints.next().unwrap() += 10;
+            // 后面的Query会向同一个BundleMeta中添加新的MappingTable缓存
+            assert_eq!(strs.into_iter().count(), 0);
+            assert_eq!(usizes.into_iter().count(), 3);
+            ints.for_each(|mut i| *i += 10);
+        }
+        fn check(ints: Query<&i32>) {
+            let mut ints: Vec<_> = ints.into_iter().copied().collect();
+            ints.sort();
+            assert_eq!(ints, vec![10, 11, 12]);
+        }
+
+        let mut world = World::new();
+        world.spawn_many((0..3).map(|i| (i, i as usize)));
+        world.exec(add);
+        world.exec(check);
+    }
+}
@@ -1,53 +1,466 @@
-use crate::{tools::Command, World};
+use std::sync::Mutex;
+
+use crate::{
+    bundle::{Bundle, Component},
+    hierarchy::HierarchyError,
+    storage::Entity,
+    tools::Command,
+    World,
+};
 
 #[cfg(feature = "system")]
-use crate::system::SystemParm;
+use crate::{system::SystemParm, world::UnsafeWorldCell};
+
+/// 一个被推迟的操作
+type Deferred = Box<dyn FnOnce(&mut World) + Send>;
+
+/// 被[Commands]推迟,还没有执行的操作
+///
+/// 存放在[World]中,通过[World::apply_commands]执行
+#[derive(Default)]
+pub(crate) struct CommandQueue {
+    inner: Mutex<Vec<Deferred>>,
+}
+
+impl CommandQueue {
+    fn push(&self, deferred: Deferred) {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .push(deferred);
+    }
+
+    fn take(&mut self) -> Vec<Deferred> {
+        std::mem::take(
+            self.inner
+                .get_mut()
+                .unwrap_or_else(|poison| poison.into_inner()),
+        )
+    }
+
+    /// 丢弃所有被推迟的操作
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    pub(crate) fn clear(&mut self) {
+        self.take();
+    }
+}
+
+impl World {
+    /// 按顺序执行所有被[Commands]推迟的操作
+    ///
+    /// [System]结束之后会自动调用
+    ///
+    /// [System]: crate::system::System
+    pub fn apply_commands(&mut self) {
+        let mut queue = self.command_queue.take();
+        if queue.is_empty() {
+            return;
+        }
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("apply_commands").entered();
+        // 执行期间可能会推迟新的操作
+        while !queue.is_empty() {
+            for deferred in queue {
+                deferred(self);
+            }
+            queue = self.command_queue.take();
+        }
+    }
+
+    /// 丢弃所有被推迟的操作
+    #[cfg(feature = "system")]
+    pub(crate) fn discard_commands(&mut self) {
+        self.command_queue.take();
+    }
+
+    /// 按顺序执行一个[System]自己的queue中推迟的操作,以及执行期间推迟的新操作
+    ///
+    /// [System]: crate::system::System
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    pub(crate) fn apply_queue(&mut self, queue: &mut CommandQueue) {
+        let queue = queue.take();
+        if !queue.is_empty() {
+            #[cfg(feature = "trace")]
+            let _span = tracing::info_span!("apply_queue").entered();
+            for deferred in queue {
+                deferred(self);
+            }
+        }
+        self.apply_commands();
+    }
+}
 
+/// 推迟对[World]的结构性修改,如放入和删除[Bundle]
+///
+/// 操作会在[System]结束之后按顺序执行,所以[Commands]可以和任何[Query]同时使用
+///
+/// 在[System]之外使用时,需要调用[World::apply_commands]
+///
+/// ``` rust
+/// use trecs::{world::{Commands, Query}, World};
+///
+/// fn double(query: Query<&i32>, mut commands: Commands) {
+///     for eb in query.into_eiter() {
+///         commands.spawn(**eb * 2);
+///         commands.despawn(eb.entity());
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// let mut commands = Commands::new(&mut world);
+/// commands.spawn(1);
+/// world.apply_commands();
+/// world.exec(double);
+/// ```
+///
+/// [System]: crate::system::System
+/// [Query]: crate::world::Query
 pub struct Commands<'a> {
-    inner: &'a mut World,
+    queue: &'a CommandQueue,
 }
 
-impl Command for Commands<'_> {
-    fn register<B: crate::bundle::Bundle>(&mut self) {
-        self.inner.register::<B>()
+impl<'a> Commands<'a> {
+    pub fn new(world: &mut World) -> Commands<'_> {
+        Commands {
+            queue: &world.command_queue,
+        }
     }
 
-    fn spawn<B: crate::bundle::Bundle>(&mut self, b: B) -> crate::storage::Entity {
-        self.inner.spawn(b)
+    /// 推迟任意的操作
+    pub fn add<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnOnce(&mut World) + Send + 'static,
+    {
+        self.queue.push(Box::new(f));
+        self
     }
 
-    fn spawn_many<B: crate::bundle::Bundle, I: IntoIterator<Item = B>>(
-        &mut self,
-        i: I,
-    ) -> Vec<crate::storage::Entity> {
-        self.inner.spawn_many(i)
+    /// 推迟将[Bundle]放入[World]
+    pub fn spawn<B: Bundle + Send>(&mut self, b: B) -> &mut Self {
+        self.add(move |world| {
+            world.spawn(b);
+        })
     }
 
-    fn alive(&self, entity: crate::storage::Entity) -> Option<bool> {
-        self.inner.alive(entity)
+    /// 推迟将大量同类型[Bundle]放入[World]
+    pub fn spawn_many<B, I>(&mut self, i: I) -> &mut Self
+    where
+        B: Bundle + Send,
+        I: IntoIterator<Item = B>,
+    {
+        let bundles = i.into_iter().collect::<Vec<_>>();
+        self.add(move |world| {
+            world.spawn_many(bundles);
+        })
     }
 
-    fn remove(&mut self, entity: crate::storage::Entity) -> bool {
-        self.inner.remove(entity)
+    /// 推迟从[World]中删除[Entity]代表的[Bundle]
+    ///
+    /// 执行时[Entity]已经失效的话什么都不做
+    pub fn despawn(&mut self, entity: Entity) -> &mut Self {
+        self.add(move |world| {
+            world.remove(entity);
+        })
     }
 
-    fn fetch<F: crate::tools::WorldFetch>(
-        &mut self,
-        entity: crate::storage::Entity,
-    ) -> Option<F::Item<'_>> {
-        self.inner.fetch::<F>(entity)
+    /// 推迟删除[Entity]和它的所有后代
+    ///
+    /// 见[World::despawn_recursive]
+    pub fn despawn_recursive(&mut self, entity: Entity) -> &mut Self {
+        self.add(move |world| {
+            world.despawn_recursive(entity);
+        })
+    }
+
+    /// 推迟删除所有[Entity]
+    ///
+    /// 见[World::despawn_batch]
+    pub fn despawn_batch<I>(&mut self, entities: I) -> &mut Self
+    where
+        I: IntoIterator<Item = Entity>,
+    {
+        let entities = entities.into_iter().collect::<Vec<_>>();
+        self.add(move |world| {
+            world.despawn_batch(entities);
+        })
+    }
+
+    /// 对单个[Entity]进行操作
+    pub fn entity(&mut self, entity: Entity) -> EntityCommands<'_, 'a> {
+        EntityCommands {
+            commands: self,
+            entity,
+        }
+    }
+}
+
+/// 报告被推迟的[World::set_parent]发生的错误,此时[Hierarchy]没有被修改
+///
+/// 有`system`特性时交给[World::set_error_handler]设置的函数,command作为[System]的名字,
+/// 否则输出到标准错误
+///
+/// [Hierarchy]: crate::hierarchy::Hierarchy
+/// [System]: crate::system::System
+fn report_hierarchy_error(
+    world: &mut World,
+    command: &'static str,
+    result: Result<(), HierarchyError>,
+) {
+    let Err(error) = result else {
+        return;
+    };
+    #[cfg(feature = "system")]
+    world.handle_error(command, Box::new(error));
+    #[cfg(not(feature = "system"))]
+    {
+        let _ = world;
+        eprintln!("{command}: {error}");
+    }
+}
+
+/// 对单个[Entity]进行操作的[Commands]
+///
+/// 通过[Commands::entity]获得
+pub struct EntityCommands<'a, 'w> {
+    commands: &'a mut Commands<'w>,
+    entity: Entity,
+}
+
+impl EntityCommands<'_, '_> {
+    /// 被操作的[Entity]
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// 推迟将parent设为[Entity]的父级
+    ///
+    /// 执行时发生错误则跳过这个操作,错误见[report_hierarchy_error]
+    pub fn set_parent(&mut self, parent: Entity) -> &mut Self {
+        let child = self.entity;
+        self.commands.add(move |world| {
+            let result = world.set_parent(child, parent);
+            report_hierarchy_error(world, "EntityCommands::set_parent", result);
+        });
+        self
+    }
+
+    /// 推迟将child设为[Entity]的子级
+    ///
+    /// 执行时发生错误则跳过这个操作,错误见[report_hierarchy_error]
+    pub fn add_child(&mut self, child: Entity) -> &mut Self {
+        let parent = self.entity;
+        self.commands.add(move |world| {
+            let result = world.set_parent(child, parent);
+            report_hierarchy_error(world, "EntityCommands::add_child", result);
+        });
+        self
+    }
+
+    /// 推迟添加[Entity]指向target的类型为R的关系,见[World::relate]
+    pub fn relate<R: 'static>(&mut self, target: Entity) -> &mut Self {
+        let source = self.entity;
+        self.commands.add(move |world| {
+            world.relate::<R>(source, target);
+        });
+        self
+    }
+
+    /// 推迟移除[Entity]指向target的类型为R的关系,见[World::unrelate]
+    pub fn unrelate<R: 'static>(&mut self, target: Entity) -> &mut Self {
+        let source = self.entity;
+        self.commands.add(move |world| {
+            world.unrelate::<R>(source, target);
+        });
+        self
+    }
+
+    /// 推迟将[Entity]从它的父级中移除
+    pub fn remove_parent(&mut self) -> &mut Self {
+        let child = self.entity;
+        self.commands.add(move |world| {
+            world.remove_parent(child);
+        });
+        self
+    }
+
+    /// 推迟为[Entity]添加[Bundle]中的所有[Component]
+    ///
+    /// 执行时[Entity]已经失效的话什么都不做,见[World::insert]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn insert<B: Bundle + Send>(&mut self, bundle: B) -> &mut Self {
+        let entity = self.entity;
+        self.commands.add(move |world| {
+            world.insert(entity, bundle);
+        });
+        self
+    }
+
+    /// 推迟从[Entity]中移除一个[Component]
+    ///
+    /// 见[World::remove_component]
+    pub fn remove<C: Component>(&mut self) -> &mut Self {
+        let entity = self.entity;
+        self.commands.add(move |world| {
+            world.remove_component::<C>(entity);
+        });
+        self
+    }
+
+    /// 推迟禁用[Entity],见[World::disable]
+    pub fn disable(&mut self) -> &mut Self {
+        let entity = self.entity;
+        self.commands.add(move |world| {
+            world.disable(entity);
+        });
+        self
+    }
+
+    /// 推迟启用被禁用的[Entity],见[World::enable]
+    pub fn enable(&mut self) -> &mut Self {
+        let entity = self.entity;
+        self.commands.add(move |world| {
+            world.enable(entity);
+        });
+        self
+    }
+
+    /// 推迟删除[Entity]
+    pub fn despawn(&mut self) {
+        self.commands.despawn(self.entity);
+    }
+
+    /// 推迟删除[Entity]和它的所有后代
+    pub fn despawn_recursive(&mut self) {
+        self.commands.despawn_recursive(self.entity);
     }
 }
 
 #[cfg(feature = "system")]
 impl SystemParm for Commands<'_> {
-    unsafe fn build(world: &World) -> Self {
-        #[allow(mutable_transmutes)]
-        let world: &mut World = std::mem::transmute(world);
-        Commands { inner: world }
+    type Item<'w> = Commands<'w>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        Commands {
+            queue: world.command_queue(),
+        }
     }
 
     fn init(_state: &mut crate::system::state::SystemState) {
-        // commands无约束
+        // 操作都被推迟到System结束之后,不会和任何参数冲突
+    }
+}
+
+#[cfg(all(test, feature = "system", not(feature = "async")))]
+mod tests {
+    use crate::{
+        hierarchy::{Hierarchy, HierarchyError},
+        system::{store_error, SystemErrors},
+        tools::{Command, ResManager},
+        world::{Commands, Query},
+        World,
+    };
+
+    #[test]
+    fn deferred_until_system_end() {
+        fn respawn(query: Query<&i32>, mut commands: Commands) {
+            for eb in query.into_eiter() {
+                commands.despawn(eb.entity()).spawn(**eb as usize);
+            }
+        }
+        fn check(query: Query<&i32>, mut commands: Commands) {
+            commands.spawn(10);
+            // 还没有执行
+            assert_eq!(query.into_iter().count(), 0);
+        }
+
+        let mut world = World::new();
+        let entities = world.spawn_many(0..3);
+        world.exec(respawn);
+        assert!(entities.iter().all(|&e| !world.alive(e).unwrap()));
+        assert_eq!(Query::<&usize>::new(&mut world).into_iter().count(), 3);
+
+        world.exec(check);
+        assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 1);
+    }
+
+    #[test]
+    fn entity_commands() {
+        let mut world = World::new();
+        let [parent, child] = [0, 1].map(|i| world.spawn(i));
+
+        let mut commands = Commands::new(&mut world);
+        commands.entity(child).set_parent(parent);
+        commands.add(|world| {
+            world.spawn("nested");
+        });
+        world.apply_commands();
+        let hierarchy = world.get_res::<Hierarchy>();
+        assert_eq!(hierarchy.get().unwrap().parent(child), Some(parent));
+
+        Commands::new(&mut world)
+            .entity(parent)
+            .insert((true, "parent"))
+            .remove::<i32>();
+        world.apply_commands();
+        assert_eq!(
+            world.fetch::<(&bool, &&str)>(parent),
+            Some((&true, &"parent"))
+        );
+        assert_eq!(world.fetch::<&i32>(parent), None);
+
+        Commands::new(&mut world)
+            .entity(child)
+            .remove_parent()
+            .despawn();
+        world.apply_commands();
+        assert_eq!(world.alive(child), Some(false));
+        assert_eq!(Query::<&&str>::new(&mut world).into_iter().count(), 2);
+        let grandchild = world.spawn(2);
+        let child = world.spawn(1);
+        let mut commands = Commands::new(&mut world);
+        commands.entity(child).add_child(grandchild);
+        commands.entity(parent).add_child(child).despawn_recursive();
+        world.apply_commands();
+        assert_eq!(world.alive(grandchild), Some(false));
+        assert_eq!(world.alive(parent), Some(false));
+    }
+
+    #[test]
+    fn hierarchy_error() {
+        let mut world = World::new();
+        world.set_error_handler(store_error);
+        let [parent, child] = [0, 1].map(|i| world.spawn(i));
+        world.set_parent(child, parent).unwrap();
+
+        let mut commands = Commands::new(&mut world);
+        commands.entity(parent).set_parent(child);
+        commands.entity(child).add_child(parent).insert(true);
+        world.apply_commands();
+        // 形成环的操作被跳过,之后的操作照常执行
+        let hierarchy = world.get_res::<Hierarchy>();
+        assert_eq!(hierarchy.get().unwrap().parent(parent), None);
+        assert_eq!(world.fetch::<&bool>(child), Some(&true));
+        let errors = &world.resource::<SystemErrors>().unwrap().0;
+        assert_eq!(
+            errors.iter().map(|e| e.system).collect::<Vec<_>>(),
+            ["EntityCommands::set_parent", "EntityCommands::add_child"]
+        );
+        assert!(errors[0].error.is::<HierarchyError>());
+    }
+
+    #[test]
+    fn discard_on_panic() {
+        fn panicking(mut commands: Commands) {
+            commands.spawn(1);
+            panic!("boom");
+        }
+        let mut world = World::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.exec(panicking);
+        }));
+        assert!(result.is_err());
+        world.apply_commands();
+        assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 0);
     }
 }
@@ -0,0 +1,190 @@
+use std::{
+    any::Any,
+    fmt::{Debug, Display, Formatter, Result},
+};
+
+use crate::{
+    bundle::Component,
+    reflect::{TypeRegistration, TypeRegistry},
+    storage::{ChunkRow, Entity},
+    tools::ResManager,
+};
+
+use super::World;
+
+/// [World::debug_entity]的结果
+///
+/// 用[Debug]输出[Entity]和它的所有[Component]
+///
+/// 没有在[TypeRegistry]中注册的[Component]只会输出[TypeId]
+pub struct EntityDebug<'a> {
+    entity: Entity,
+    bundle: &'static str,
+    components: ChunkRow<'a>,
+    registry: Option<&'a TypeRegistry>,
+}
+
+impl EntityDebug<'_> {
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// [Entity]对应的[Bundle]的类型名
+    ///
+    /// [Bundle]: crate::bundle::Bundle
+    pub fn bundle(&self) -> &'static str {
+        self.bundle
+    }
+}
+
+/// 格式化单个[Component]的值
+///
+/// 没有注册[Debug]时,会逐个输出字段
+struct ComponentDebug<'a>(&'a TypeRegistry, &'a TypeRegistration, &'a dyn Any);
+
+impl Debug for ComponentDebug<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let (registry, registration) = (self.0, self.1);
+        if registration.is_debug() || registration.fields().is_empty() {
+            return registration.debug(self.2, f);
+        }
+        let mut s = f.debug_struct(registration.name);
+        for name in registration.fields() {
+            let field = registration.field(self.2, name).unwrap();
+            match registry.get((*field).type_id()) {
+                Some(registration) => s.field(name, &ComponentDebug(registry, registration, field)),
+                None => s.field(name, &format_args!("..")),
+            };
+        }
+        s.finish()
+    }
+}
+
+impl Debug for EntityDebug<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut map = f.debug_map();
+        map.entry(&"entity", &self.entity);
+        map.entry(&"bundle", &self.bundle);
+        // 持有&World时不会有可变的访问
+        for component in unsafe { self.components.components() } {
+            let type_id = component.type_id();
+            let registry = self.registry.and_then(|r| Some((r, r.get(type_id)?)));
+            match registry {
+                Some((registry, registration)) => map.entry(
+                    &registration.name,
+                    &ComponentDebug(registry, registration, component),
+                ),
+                None => map.entry(&type_id, &".."),
+            };
+        }
+        map.finish()
+    }
+}
+
+impl Display for EntityDebug<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{self:#?}")
+    }
+}
+
+impl World {
+    /// 在[TypeRegistry]中注册一种可以用[Debug]输出的[Component]
+    ///
+    /// 以便[World::debug_entity]输出它的值
+    pub fn register_debug<T: Component + Debug>(&mut self) -> &mut Self {
+        self.get_res::<TypeRegistry>()
+            .into_or_init(TypeRegistry::new)
+            .register_debug::<T>();
+        self
+    }
+
+    /// 输出[Entity]和它的所有[Component]
+    ///
+    /// [Entity]无效时返回[None]
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, World};
+    ///
+    /// let mut world = World::new();
+    /// let entity = world.spawn((1, "a"));
+    /// println!("{}", world.debug_entity(entity).unwrap());
+    /// ```
+    pub fn debug_entity(&self, entity: Entity) -> Option<EntityDebug<'_>> {
+        let location = self.entities.get(entity)?;
+        let chunk = &self.chunks[location.chunk];
+        let components = chunk.row(location.row);
+        let bundle = self.metas.get(&chunk.bundle_id())?.name();
+        Some(EntityDebug {
+            entity,
+            bundle,
+            components,
+            registry: self.peek_res(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use super::*;
+    use crate::tools::Command;
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Pos(i32, i32);
+    impl Component for Pos {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+
+    #[test]
+    fn debug_entity() {
+        let mut world = World::new();
+        let entity = world.spawn((Pos(1, 2), 3));
+        let debug = format!("{:?}", world.debug_entity(entity).unwrap());
+        // 没有注册TypeRegistry时不会输出值
+        assert!(!debug.contains("Pos(1, 2)"));
+
+        world.register_debug::<Pos>();
+        let debug = format!("{:?}", world.debug_entity(entity).unwrap());
+        assert!(debug.contains("Pos(1, 2)"));
+        assert!(debug.contains("\"i32\": 3"));
+
+        world.remove(entity);
+        assert!(world.debug_entity(entity).is_none());
+    }
+
+    struct Size {
+        w: u32,
+        h: f32,
+    }
+    impl Component for Size {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+
+        fn field_names() -> &'static [&'static str] {
+            &["w", "h"]
+        }
+
+        fn field(&self, index: usize) -> Option<&dyn Any> {
+            match index {
+                0 => Some(&self.w),
+                1 => Some(&self.h),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn debug_fields() {
+        let mut world = World::new();
+        world.register_type::<Size>();
+        let entity = world.spawn(Size { w: 1, h: 2.0 });
+        let debug = format!("{:?}", world.debug_entity(entity).unwrap());
+        // 没有注册的f32不会输出值
+        assert!(debug.contains("Size { w: 1, h: .. }"));
+    }
+}
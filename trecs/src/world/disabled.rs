@@ -0,0 +1,146 @@
+use std::any::TypeId;
+
+use crate::{
+    bundle::{Component, ComponentId},
+    storage::Entity,
+    tools::{MappingTable, WorldFilter},
+};
+
+use super::World;
+
+/// 被禁用的[Entity]的标记
+///
+/// 带有[Disabled]的[Entity]默认会被所有[Query]跳过,包括[Query::get],
+/// 但是仍然存在,[World::fetch]等直接访问[Entity]的方法不受影响,
+/// 可以用于对象池或者暂时停用,而不需要删除再重新生成
+///
+/// 以下[Query]会包含被禁用的[Entity]:
+///
+/// + 使用了[IncludeDisabled]的
+/// + [WorldFilter]中提到了[Disabled]的,如`AnyOf<Disabled>`
+/// + 访问了[Disabled]的,如`Query<(&i32, Option<&Disabled>)>`
+///
+/// 只会禁用[Entity]本身,不会禁用它的子级
+///
+/// ``` rust
+/// use trecs::{
+///     tools::{Command, IncludeDisabled},
+///     world::Query,
+///     World,
+/// };
+///
+/// let mut world = World::new();
+/// let [a, _] = [1, 2].map(|i| world.spawn(i));
+/// world.disable(a);
+/// assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 1);
+/// let query = Query::<&i32, IncludeDisabled>::new(&mut world);
+/// assert_eq!(query.into_iter().count(), 2);
+/// world.enable(a);
+/// assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 2);
+/// ```
+///
+/// [Query]: crate::world::Query
+/// [Query::get]: crate::world::Query::get
+/// [World::fetch]: crate::tools::Command::fetch
+/// [IncludeDisabled]: crate::tools::IncludeDisabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Disabled;
+
+impl Component for Disabled {
+    fn type_id_() -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
+
+/// components_ids对应的[Bundle]是否可以被[Query]访问
+///
+/// 没有[Disabled]时总是可以,否则需要Q包含被禁用的[Entity],或者mapping_table访问了[Disabled]
+///
+/// [Bundle]: crate::bundle::Bundle
+/// [Query]: crate::world::Query
+pub(crate) fn allows_disabled<Q: WorldFilter>(
+    components_ids: &[ComponentId],
+    mapping_table: &MappingTable,
+) -> bool {
+    let Some(index) = (components_ids.iter()).position(|id| *id == ComponentId::of::<Disabled>())
+    else {
+        return true;
+    };
+    Q::include_disabled() || mapping_table.mappings().contains(&index)
+}
+
+impl World {
+    /// 为[Entity]添加[Disabled],返回[Entity]是否存在
+    pub fn disable(&mut self, entity: Entity) -> bool {
+        self.insert(entity, Disabled)
+    }
+
+    /// 从[Entity]中移除[Disabled],[Entity]已经失效或者没有被禁用时返回false
+    pub fn enable(&mut self, entity: Entity) -> bool {
+        self.remove_component::<Disabled>(entity).is_some()
+    }
+
+    /// [Entity]是否被禁用,[Entity]已经失效时返回false
+    pub fn is_disabled(&self, entity: Entity) -> bool {
+        self.entities.get(entity).is_some_and(|location| {
+            let bundle_id = self.chunks[location.chunk].bundle_id();
+            self.metas[&bundle_id]
+                .components_ids
+                .contains(&ComponentId::of::<Disabled>())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tools::{AnyOf, Command, IncludeDisabled, Not},
+        world::{DynamicAccess, DynamicQuery, Query},
+    };
+
+    fn ints(world: &mut World) -> Vec<i32> {
+        let mut ints = Query::<&i32>::new(world)
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        ints.sort();
+        ints
+    }
+
+    #[test]
+    fn disabled() {
+        let mut world = World::new();
+        let [a, b, c] = [1, 2, 3].map(|i| world.spawn(i));
+        assert!(world.disable(b));
+        assert!(world.is_disabled(b) && !world.is_disabled(a));
+
+        assert_eq!(ints(&mut world), [1, 3]);
+        let query = Query::<&i32>::new(&mut world);
+        assert!(query.get(b).is_none() && query.get(c).is_some());
+        // 直接访问Entity不受影响
+        assert_eq!(world.fetch::<&i32>(b), Some(&2));
+
+        // 显式地包含被禁用的Entity
+        let query = Query::<&i32, IncludeDisabled>::new(&mut world);
+        assert_eq!(query.into_iter().count(), 3);
+        let query = Query::<&i32, AnyOf<Disabled>>::new(&mut world);
+        assert_eq!(query.into_iter().copied().collect::<Vec<_>>(), [2]);
+        let query = Query::<&i32, Not<AnyOf<Disabled>>>::new(&mut world);
+        assert_eq!(query.into_iter().count(), 2);
+        let query = Query::<(Entity, Option<&Disabled>)>::new(&mut world);
+        let disabled = query.into_iter().filter(|(_, d)| d.is_some());
+        assert_eq!(disabled.map(|(e, _)| e).collect::<Vec<_>>(), [b]);
+
+        let query = DynamicQuery::new([DynamicAccess::Read(TypeId::of::<i32>().into())]).unwrap();
+        assert_eq!(query.iter(&mut world).count(), 2);
+        assert!(query.get(&mut world, b).is_none());
+
+        assert!(world.enable(b));
+        assert!(!world.enable(b));
+        assert_eq!(ints(&mut world), [1, 2, 3]);
+        world.despawn(c);
+        assert!(!world.disable(c) && !world.is_disabled(c));
+    }
+}
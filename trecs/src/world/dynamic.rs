@@ -0,0 +1,229 @@
+use std::alloc::Layout;
+
+use crate::{
+    bundle::{
+        BundleError, ComponentDescriptor, ComponentId, DynamicComponent, DynamicDrop, StorageType,
+    },
+    storage::{ComponentTicks, ComponentVTable, Entity},
+};
+
+use super::{migrate::Part, World};
+
+impl World {
+    /// 在运行时注册一种没有对应的Rust类型的[Component],返回它的[ComponentId]
+    ///
+    /// + name用于调试输出,以及通过[World::component_id]查找
+    /// + drop为[None]时,值被删除时只会释放内存
+    ///
+    /// 同名同布局的[Component]已经在运行时注册过时返回已有的[ComponentId],
+    /// 与已经注册的同名[Component]的内存布局不同,或者是静态的[Component]时,
+    /// 返回[BundleError::LayoutMismatch]或者[BundleError::TypeIdMismatch]
+    ///
+    /// ``` rust
+    /// use std::alloc::Layout;
+    /// use trecs::World;
+    ///
+    /// let mut world = World::new();
+    /// let speed = world
+    ///     .register_component("speed", Layout::new::<f64>(), None)
+    ///     .unwrap();
+    /// let value = 1.5f64;
+    /// let component = unsafe { world.dynamic_component(speed, &value as *const f64 as _) };
+    /// let entity = world.spawn_dynamic([component.unwrap()]);
+    /// assert!(world.entity(entity).unwrap().get_by_id(speed).is_some());
+    /// ```
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn register_component(
+        &mut self,
+        name: &'static str,
+        layout: Layout,
+        drop: Option<DynamicDrop>,
+    ) -> Result<ComponentId, BundleError> {
+        let found = ComponentDescriptor {
+            name,
+            size: layout.size(),
+            align: layout.align(),
+            storage: StorageType::Table,
+            vtable: ComponentVTable::of::<DynamicComponent>(),
+        };
+        if let Some((id, registered)) = self.descriptors.get(name) {
+            if !registered.same_layout(&found) {
+                return Err(BundleError::LayoutMismatch {
+                    registered: *registered,
+                    found,
+                });
+            }
+            return match id {
+                ComponentId::Dynamic(_) => Ok(*id),
+                ComponentId::Static(_) => Err(BundleError::TypeIdMismatch { component: name }),
+            };
+        }
+        let id = ComponentId::Dynamic(self.dynamic_components.len());
+        self.dynamic_components.push((found, drop));
+        self.descriptors.insert(name, (id, found));
+        Ok(id)
+    }
+
+    /// 创建一个在运行时注册的[Component]的值,复制value指向的值
+    ///
+    /// id不是在此[World]中通过[World::register_component]注册的时返回[None]
+    ///
+    /// # Safety
+    ///
+    /// value必须指向一个符合注册时的内存布局,并且可以被注册时的drop正确处理的值,
+    /// 之后value指向的值不能再被使用或者drop
+    ///
+    /// [Component]: crate::bundle::Component
+    pub unsafe fn dynamic_component(
+        &self,
+        id: ComponentId,
+        value: *const u8,
+    ) -> Option<DynamicComponent> {
+        let ComponentId::Dynamic(index) = id else {
+            return None;
+        };
+        let (descriptor, drop) = self.dynamic_components.get(index)?;
+        let layout = Layout::from_size_align_unchecked(descriptor.size, descriptor.align);
+        Some(DynamicComponent::new(id, layout, *drop, value))
+    }
+
+    /// 放入由在运行时注册的[Component]组成的[Entity]
+    ///
+    /// 之后可以通过[World::insert]添加静态的[Component]
+    ///
+    /// 包含重复的[Component],或者[Component]不是在此[World]中注册的时会panic
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn spawn_dynamic(
+        &mut self,
+        components: impl IntoIterator<Item = DynamicComponent>,
+    ) -> Entity {
+        let tick = self.change_tick;
+        let mut parts: Vec<Part> = vec![];
+        for component in components {
+            let id = component.id();
+            let name = self.dynamic_name(id);
+            if parts.iter().any(|part| part.0 == id) {
+                let bundle = "DynamicComponents";
+                let e = BundleError::DuplicateComponent {
+                    bundle,
+                    component: name,
+                };
+                panic!("{e}");
+            }
+            let vtable = ComponentVTable::of::<DynamicComponent>();
+            parts.push((
+                id,
+                name,
+                vtable,
+                Box::new(component),
+                ComponentTicks::new(tick),
+            ));
+        }
+        let entity = self.entities.alloc();
+        self.place(entity, parts);
+        self.record_spawn(entity);
+        entity
+    }
+
+    /// 在运行时注册的[Component]的名字
+    ///
+    /// [Component]: crate::bundle::Component
+    fn dynamic_name(&self, id: ComponentId) -> &'static str {
+        match id {
+            ComponentId::Dynamic(index) if index < self.dynamic_components.len() => {
+                self.dynamic_components[index].0.name
+            }
+            _ => panic!("{id:?} 没有在此World中注册"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        mem::ManuallyDrop,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::tools::Command;
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe fn drop_string(value: *mut u8) {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+        std::ptr::drop_in_place(value as *mut String);
+    }
+
+    fn string(world: &World, id: ComponentId, value: &str) -> DynamicComponent {
+        let value = ManuallyDrop::new(value.to_owned());
+        unsafe { world.dynamic_component(id, &*value as *const String as _) }.unwrap()
+    }
+
+    #[test]
+    fn register() {
+        let mut world = World::new();
+        let layout = Layout::new::<String>();
+        let id = world.register_component("text", layout, None).unwrap();
+        assert_eq!(world.register_component("text", layout, None), Ok(id));
+        assert!(matches!(
+            world.register_component("text", Layout::new::<u8>(), None),
+            Err(BundleError::LayoutMismatch { .. })
+        ));
+        let other = world.register_component("other", layout, None).unwrap();
+        assert_ne!(id, other);
+
+        world.register::<u32>();
+        assert_eq!(
+            world.register_component("u32", Layout::new::<u32>(), None),
+            Err(BundleError::TypeIdMismatch { component: "u32" })
+        );
+        let descriptor = world.descriptors["text"].1;
+        assert_eq!(world.component_id(&descriptor), Some(id));
+        assert_eq!(world.component_type_id(&descriptor), None);
+        assert!(unsafe { world.dynamic_component(ComponentId::of::<u32>(), &0) }.is_none());
+    }
+
+    #[test]
+    fn spawn_drop() {
+        let mut world = World::new();
+        let text = world
+            .register_component("text", Layout::new::<String>(), Some(drop_string))
+            .unwrap();
+        let unit = world
+            .register_component("unit", Layout::new::<()>(), None)
+            .unwrap();
+
+        let empty = unsafe { world.dynamic_component(unit, &() as *const () as _) };
+        let a = world.spawn_dynamic([string(&world, text, "a"), empty.unwrap()]);
+        let b = world.spawn_dynamic([string(&world, text, "b")]);
+        world.insert(b, 1u32);
+        assert_eq!(world.fetch::<&u32>(b), Some(&1));
+
+        let entity = world.entity(b).unwrap();
+        let component = entity.get_by_id(text).unwrap();
+        let component = component.downcast_ref::<DynamicComponent>().unwrap();
+        assert_eq!(unsafe { &*(component.as_ptr() as *const String) }, "b");
+        assert_eq!(entity.component_ids(), [text, ComponentId::of::<u32>()]);
+        assert!(world.entity(a).unwrap().get_by_id(unit).is_some());
+
+        world.despawn(a);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+        drop(world);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "中包含重复的Component")]
+    fn spawn_duplicate() {
+        let mut world = World::new();
+        let id = world
+            .register_component("flag", Layout::new::<bool>(), None)
+            .unwrap();
+        let a = unsafe { world.dynamic_component(id, &true as *const bool as _) };
+        let b = unsafe { world.dynamic_component(id, &true as *const bool as _) };
+        world.spawn_dynamic([a.unwrap(), b.unwrap()]);
+    }
+}
@@ -0,0 +1,314 @@
+use std::{error::Error, fmt::Display, marker::PhantomData};
+
+use crate::{
+    bundle::{ComponentId, DynamicComponent},
+    storage::{Chunk, ChunkIter, Entity},
+};
+
+use super::{Disabled, World};
+
+/// [DynamicQuery]对一个[Component]的访问
+///
+/// [Component]: crate::bundle::Component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicAccess {
+    Read(ComponentId),
+    /// 访问到的[Component]会被标记为修改过
+    ///
+    /// [Component]: crate::bundle::Component
+    Write(ComponentId),
+}
+
+impl DynamicAccess {
+    /// 被访问的[Component]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn id(&self) -> ComponentId {
+        match self {
+            DynamicAccess::Read(id) | DynamicAccess::Write(id) => *id,
+        }
+    }
+}
+
+/// 创建[DynamicQuery]失败
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicQueryError {
+    /// 同一个[Component]被同时读写,或者被写了多次
+    ///
+    /// [Component]: crate::bundle::Component
+    Conflict(ComponentId),
+}
+
+impl Display for DynamicQueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DynamicQueryError::Conflict(id) => write!(f, "{id:?}被同时读写或者写了多次"),
+        }
+    }
+}
+
+impl Error for DynamicQueryError {}
+
+/// 在运行时构造的[Query]
+///
+/// 由一组[DynamicAccess]组成,可以同时访问静态的和在运行时注册的[Component],
+/// 每个符合的[Entity]都会得到一个[DynamicItem],其中是指向[Component]的指针
+///
+/// ``` rust
+/// use std::any::TypeId;
+/// use trecs::{
+///     tools::Command,
+///     world::{DynamicAccess, DynamicQuery},
+///     World,
+/// };
+///
+/// let mut world = World::new();
+/// world.spawn_many([1i32, 2]);
+/// let query = DynamicQuery::new([DynamicAccess::Write(TypeId::of::<i32>().into())]).unwrap();
+/// for item in query.iter(&mut world) {
+///     unsafe { *(item.get_mut(0).unwrap() as *mut i32) *= 10 };
+/// }
+/// let sum: i32 = query
+///     .iter(&mut world)
+///     .map(|item| unsafe { *(item.get(0) as *const i32) })
+///     .sum();
+/// assert_eq!(sum, 30);
+/// ```
+///
+/// [Query]: crate::world::Query
+/// [Component]: crate::bundle::Component
+#[derive(Debug, Clone)]
+pub struct DynamicQuery {
+    accesses: Vec<DynamicAccess>,
+}
+
+impl DynamicQuery {
+    /// 重复的[DynamicAccess::Read]是允许的,涉及[DynamicAccess::Write]的重复会返回错误
+    pub fn new(
+        accesses: impl IntoIterator<Item = DynamicAccess>,
+    ) -> Result<Self, DynamicQueryError> {
+        let accesses = accesses.into_iter().collect::<Vec<_>>();
+        for (index, access) in accesses.iter().enumerate() {
+            let conflict = accesses[..index].iter().any(|other| {
+                other.id() == access.id()
+                    && (matches!(access, DynamicAccess::Write(_))
+                        || matches!(other, DynamicAccess::Write(_)))
+            });
+            if conflict {
+                return Err(DynamicQueryError::Conflict(access.id()));
+            }
+        }
+        Ok(Self { accesses })
+    }
+
+    pub fn accesses(&self) -> &[DynamicAccess] {
+        &self.accesses
+    }
+
+    /// 迭代所有符合的[Entity]
+    pub fn iter<'w>(&self, world: &'w mut World) -> DynamicIter<'w> {
+        let world = &*world;
+        let chunks = world
+            .metas
+            .values()
+            .filter_map(|meta| Some((self.mapping(meta.components_ids)?, &meta.chunks)))
+            .flat_map(|(mapping, chunks)| chunks.iter().map(move |&cid| (mapping.clone(), cid)))
+            .collect();
+        DynamicIter {
+            world,
+            accesses: self.accesses.clone(),
+            chunks,
+            current: None,
+        }
+    }
+
+    /// 访问一个[Entity]
+    ///
+    /// [Entity]已经失效,或者缺少被访问的[Component]时返回[None]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn get<'w>(&self, world: &'w mut World, entity: Entity) -> Option<DynamicItem<'w>> {
+        let world = &*world;
+        let location = world.entities.get(entity)?;
+        let chunk = &world.chunks[location.chunk];
+        let mapping = self.mapping(world.metas[&chunk.bundle_id()].components_ids)?;
+        Some(item(world, &self.accesses, chunk, location.row, &mapping))
+    }
+
+    /// 每个被访问的[Component]在components_ids中的位置
+    ///
+    /// 被禁用的[Entity]只在访问了[Disabled]时符合
+    ///
+    /// [Component]: crate::bundle::Component
+    fn mapping(&self, components_ids: &[ComponentId]) -> Option<Vec<usize>> {
+        let disabled = ComponentId::of::<Disabled>();
+        if components_ids.contains(&disabled) && self.accesses.iter().all(|a| a.id() != disabled) {
+            return None;
+        }
+        self.accesses
+            .iter()
+            .map(|access| components_ids.iter().position(|id| *id == access.id()))
+            .collect()
+    }
+}
+
+/// [DynamicQuery]访问到的一个[Entity]
+///
+/// 指针与[DynamicQuery::accesses]一一对应:
+/// + 静态的[Component]指向它的值
+/// + 在运行时注册的[Component]指向[DynamicComponent::as_ptr]
+///
+/// [Component]: crate::bundle::Component
+#[derive(Debug)]
+pub struct DynamicItem<'w> {
+    entity: Entity,
+    ptrs: Vec<*mut u8>,
+    writable: Vec<bool>,
+    _p: PhantomData<&'w mut World>,
+}
+
+impl DynamicItem<'_> {
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// 第index个被访问的[Component]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn get(&self, index: usize) -> *const u8 {
+        self.ptrs[index]
+    }
+
+    /// 第index个被访问的[Component]的可变指针
+    ///
+    /// 只有[DynamicAccess::Write]会返回[Some]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn get_mut(&self, index: usize) -> Option<*mut u8> {
+        self.writable[index].then_some(self.ptrs[index])
+    }
+}
+
+/// 见[DynamicQuery::iter]
+pub struct DynamicIter<'w> {
+    world: &'w World,
+    accesses: Vec<DynamicAccess>,
+    /// 剩下的区块,以及对应的mapping
+    chunks: Vec<(Vec<usize>, usize)>,
+    current: Option<(Vec<usize>, ChunkIter<'w>)>,
+}
+
+impl<'w> Iterator for DynamicIter<'w> {
+    type Item = DynamicItem<'w>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((mapping, iter)) = &mut self.current {
+                if iter.next().is_some() {
+                    let (chunk, row) = (iter.chunk, iter.index);
+                    return Some(item(self.world, &self.accesses, chunk, row, mapping));
+                }
+            }
+            let (mapping, chunk) = self.chunks.pop()?;
+            self.current = Some((mapping, self.world.chunks[chunk].iter()));
+        }
+    }
+}
+
+/// 构造row处的[Entity]的[DynamicItem],并且标记被写入的[Component]
+///
+/// 调用者需要保证对world的独占访问,并且同一行不会同时有多个[DynamicItem]
+///
+/// [Component]: crate::bundle::Component
+fn item<'w>(
+    world: &'w World,
+    accesses: &[DynamicAccess],
+    chunk: &'w Chunk,
+    row: usize,
+    mapping: &[usize],
+) -> DynamicItem<'w> {
+    let row = chunk.row(row);
+    let mut ptrs = Vec::with_capacity(mapping.len());
+    let mut writable = Vec::with_capacity(mapping.len());
+    for (access, &index) in accesses.iter().zip(mapping) {
+        let write = matches!(access, DynamicAccess::Write(_));
+        if write {
+            row.ticks(index).set_changed(world.change_tick);
+        }
+        // 通过&mut World保证独占
+        let ptr = match unsafe { row.get_mut(index) }.downcast_mut::<DynamicComponent>() {
+            Some(dynamic) => dynamic.as_mut_ptr(),
+            None => row.ptr(index),
+        };
+        ptrs.push(ptr);
+        writable.push(write);
+    }
+    DynamicItem {
+        entity: row.entity(),
+        ptrs,
+        writable,
+        _p: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{alloc::Layout, any::TypeId};
+
+    use super::*;
+    use crate::tools::Command;
+
+    #[test]
+    fn conflict() {
+        let id = ComponentId::of::<i32>();
+        assert!(DynamicQuery::new([DynamicAccess::Read(id), DynamicAccess::Read(id)]).is_ok());
+        assert_eq!(
+            DynamicQuery::new([DynamicAccess::Read(id), DynamicAccess::Write(id)]).err(),
+            Some(DynamicQueryError::Conflict(id))
+        );
+        assert!(DynamicQuery::new([DynamicAccess::Write(id), DynamicAccess::Write(id)]).is_err());
+    }
+
+    #[test]
+    fn dynamic_query() {
+        let mut world = World::new();
+        let speed = world
+            .register_component("speed", Layout::new::<u64>(), None)
+            .unwrap();
+        let entity = world
+            .spawn_dynamic([
+                unsafe { world.dynamic_component(speed, &3u64 as *const u64 as _) }.unwrap(),
+            ]);
+        world.insert(entity, 2u32);
+        world.spawn(5u32);
+
+        let query = DynamicQuery::new([
+            DynamicAccess::Read(TypeId::of::<u32>().into()),
+            DynamicAccess::Write(speed),
+        ])
+        .unwrap();
+        world.change_tick = 4;
+        let items = query.iter(&mut world).collect::<Vec<_>>();
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.entity(), entity);
+        assert!(item.get_mut(0).is_none());
+        unsafe {
+            let speed = item.get_mut(1).unwrap() as *mut u64;
+            *speed *= *(item.get(0) as *const u32) as u64;
+        }
+
+        let item = query.get(&mut world, entity).unwrap();
+        assert_eq!(unsafe { *(item.get(1) as *const u64) }, 6);
+        let location = world.entities.get(entity).unwrap();
+        let chunk = &world.chunks[location.chunk];
+        let changed = [0, 1].map(|index| chunk.ticks(index, location.row).changed());
+        assert_eq!(changed, [4, 1]);
+
+        let only_u32 = DynamicQuery::new([DynamicAccess::Read(ComponentId::of::<u32>())]).unwrap();
+        assert_eq!(only_u32.iter(&mut world).count(), 2);
+        world.despawn(entity);
+        assert!(query.get(&mut world, entity).is_none());
+        assert_eq!(query.iter(&mut world).count(), 0);
+    }
+}
@@ -0,0 +1,253 @@
+use std::any::Any;
+
+use crate::{
+    bundle::{Bundle, Component, ComponentId, StorageType},
+    storage::{ChunkRow, Entity, EntityLocation},
+    tools::Command,
+};
+
+use super::World;
+
+/// 对单个[Entity]的不可变访问,由[World::entity]返回
+///
+/// 不需要[Query],适合偶尔查看某个[Entity]
+///
+/// [Query]: crate::world::Query
+#[derive(Clone, Copy)]
+pub struct EntityRef<'w> {
+    world: &'w World,
+    entity: Entity,
+    location: EntityLocation,
+}
+
+impl<'w> EntityRef<'w> {
+    /// 被访问的[Entity]
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// [Entity]的[Component],包括[StorageType::SparseSet]的[Component]
+    ///
+    /// 没有这个[Component]时返回[None]
+    ///
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    pub fn get<T: Component>(&self) -> Option<&'w T> {
+        if T::STORAGE == StorageType::SparseSet {
+            return self.world.get_sparse(self.entity);
+        }
+        let index = self.index_of(ComponentId::of::<T>())?;
+        // 持有&World时不会有可变的访问
+        unsafe { self.row().get(index) }.downcast_ref()
+    }
+
+    /// 通过[ComponentId]访问[Entity]的[Component]
+    ///
+    /// 在运行时注册的[Component]的值是[DynamicComponent]
+    ///
+    /// [DynamicComponent]: crate::bundle::DynamicComponent
+    pub fn get_by_id(&self, id: ComponentId) -> Option<&'w dyn Any> {
+        let index = self.index_of(id)?;
+        // 持有&World时不会有可变的访问
+        Some(unsafe { self.row().get(index) })
+    }
+
+    /// [Entity]是否有这个[Component]
+    pub fn contains<T: Component>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    /// [Entity]的所有[Component]的[ComponentId]
+    pub fn component_ids(&self) -> &'static [ComponentId] {
+        let bundle_id = self.world.chunks[self.location.chunk].bundle_id();
+        self.world.metas[&bundle_id].components_ids
+    }
+
+    fn index_of(&self, id: ComponentId) -> Option<usize> {
+        self.component_ids().iter().position(|c| *c == id)
+    }
+
+    fn row(&self) -> ChunkRow<'w> {
+        self.world.chunks[self.location.chunk].row(self.location.row)
+    }
+}
+
+/// 对单个[Entity]的可变访问,由[World::entity_mut]返回
+///
+/// 可以修改,添加或者移除[Component],也可以删除[Entity]
+pub struct EntityMut<'w> {
+    world: &'w mut World,
+    entity: Entity,
+}
+
+impl EntityMut<'_> {
+    /// 被访问的[Entity]
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// 转换为[EntityRef]
+    pub fn as_ref(&self) -> EntityRef<'_> {
+        EntityRef {
+            world: self.world,
+            entity: self.entity,
+            location: self.location(),
+        }
+    }
+
+    /// 见[EntityRef::get]
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        self.as_ref().get()
+    }
+
+    /// 见[EntityRef::contains]
+    pub fn contains<T: Component>(&self) -> bool {
+        self.as_ref().contains::<T>()
+    }
+
+    /// 见[EntityRef::component_ids]
+    pub fn component_ids(&self) -> &'static [ComponentId] {
+        self.as_ref().component_ids()
+    }
+
+    /// [Entity]的[Component]的可变引用,[Component]会被标记为修改过
+    ///
+    /// 没有这个[Component]时返回[None]
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+        if T::STORAGE == StorageType::SparseSet {
+            return self.world.get_sparse_mut(self.entity);
+        }
+        let location = self.location();
+        let index = self.as_ref().index_of(ComponentId::of::<T>())?;
+        let chunk = &self.world.chunks[location.chunk];
+        chunk
+            .ticks(index, location.row)
+            .set_changed(self.world.change_tick);
+        // 持有&mut World时不会有其他的访问
+        unsafe { chunk.row(location.row).get_mut(index) }.downcast_mut()
+    }
+
+    /// 添加[Bundle]中的所有[Component],见[World::insert]
+    pub fn insert<B: Bundle>(&mut self, bundle: B) -> &mut Self {
+        self.world.insert(self.entity, bundle);
+        self
+    }
+
+    /// 移除一个[Component],见[World::remove_component]
+    pub fn remove<C: Component>(&mut self) -> Option<C> {
+        self.world.remove_component(self.entity)
+    }
+
+    /// 从[World]中删除[Entity]和它的所有[Component]
+    pub fn despawn(self) {
+        self.world.remove(self.entity);
+    }
+
+    /// 将child设为[Entity]的子级,见[World::set_parent]
+    ///
+    /// 发生错误时panic
+    #[track_caller]
+    pub fn add_child(&mut self, child: Entity) -> &mut Self {
+        self.world
+            .set_parent(child, self.entity)
+            .unwrap_or_else(|e| panic!("{e}"));
+        self
+    }
+
+    /// 删除[Entity]和它的所有后代,见[World::despawn_recursive]
+    pub fn despawn_recursive(self) {
+        self.world.despawn_recursive(self.entity);
+    }
+
+    fn location(&self) -> EntityLocation {
+        self.world
+            .entities
+            .get(self.entity)
+            .unwrap_or_else(|| unreachable!("EntityMut存在期间Entity不会被删除"))
+    }
+}
+
+impl World {
+    /// 不可变地访问一个[Entity]
+    ///
+    /// [Entity]已经失效时返回[None]
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, World};
+    ///
+    /// let mut world = World::new();
+    /// let entity = world.spawn((1i32, "a"));
+    /// let entity = world.entity(entity).unwrap();
+    /// assert_eq!(entity.get::<i32>(), Some(&1));
+    /// assert!(!entity.contains::<u32>());
+    /// ```
+    pub fn entity(&self, entity: Entity) -> Option<EntityRef<'_>> {
+        let location = self.entities.get(entity)?;
+        Some(EntityRef {
+            world: self,
+            entity,
+            location,
+        })
+    }
+
+    /// 可变地访问一个[Entity]
+    ///
+    /// [Entity]已经失效时返回[None]
+    pub fn entity_mut(&mut self, entity: Entity) -> Option<EntityMut<'_>> {
+        self.entities.get(entity)?;
+        Some(EntityMut {
+            world: self,
+            entity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entity_ref() {
+        let mut world = World::new();
+        let entity = world.spawn((1i32, "a"));
+
+        let entity_ref = world.entity(entity).unwrap();
+        assert_eq!(entity_ref.id(), entity);
+        assert_eq!(entity_ref.get::<i32>(), Some(&1));
+        assert_eq!(entity_ref.get::<u32>(), None);
+        assert!(entity_ref.contains::<&str>());
+        assert_eq!(
+            entity_ref.component_ids(),
+            [ComponentId::of::<i32>(), ComponentId::of::<&str>()]
+        );
+
+        world.despawn(entity);
+        assert!(world.entity(entity).is_none());
+        assert!(world.entity_mut(entity).is_none());
+    }
+
+    #[test]
+    fn entity_mut() {
+        let mut world = World::new();
+        let entity = world.spawn(1i32);
+        world.change_tick = 3;
+
+        let mut entity_mut = world.entity_mut(entity).unwrap();
+        *entity_mut.get_mut::<i32>().unwrap() += 1;
+        assert_eq!(entity_mut.insert(2u32).get::<u32>(), Some(&2));
+        assert_eq!(entity_mut.remove::<i32>(), Some(2));
+        assert!(!entity_mut.contains::<i32>());
+        entity_mut.despawn();
+        assert_eq!(world.alive(entity), Some(false));
+
+        let entity = world.spawn(1i32);
+        world.change_tick = 5;
+        world.entity_mut(entity).unwrap().get_mut::<i32>();
+        let location = world.entities.get(entity).unwrap();
+        assert_eq!(
+            world.chunks[location.chunk]
+                .ticks(0, location.row)
+                .changed(),
+            5
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    bundle::{Component, ComponentId},
+    storage::Entity,
+    tools::{Command, WorldFetch},
+};
+
+use super::{Commands, World};
+
+/// [Component]被添加或者移除时调用的函数,见[World::on_add]和[World::on_remove]
+///
+/// [Component]: crate::bundle::Component
+pub type ComponentHook = fn(DeferredWorld<'_>, Entity);
+
+/// 一种[Component]的所有钩子,按照添加的顺序调用
+///
+/// [Component]: crate::bundle::Component
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ComponentHooks {
+    on_add: Vec<ComponentHook>,
+    on_remove: Vec<ComponentHook>,
+}
+
+/// 钩子中对[World]的有限的访问
+///
+/// 可以访问和修改[Component]与资源,但是不能直接放入或者删除[Entity]和[Component],
+/// 这些结构性的修改需要通过[DeferredWorld::commands]推迟,
+/// 和其他[Commands]一样在[System]结束或者[World::apply_commands]时执行
+///
+/// [Component]: crate::bundle::Component
+/// [System]: crate::system::System
+pub struct DeferredWorld<'w> {
+    world: &'w mut World,
+}
+
+impl DeferredWorld<'_> {
+    /// 见[World::try_fetch]
+    pub fn fetch<F: WorldFetch>(&mut self, entity: Entity) -> Option<F::Item<'_>> {
+        self.world.fetch::<F>(entity)
+    }
+
+    /// 见[World::get_sparse]
+    pub fn get_sparse<C: Component>(&self, entity: Entity) -> Option<&C> {
+        self.world.get_sparse(entity)
+    }
+
+    /// 见[World::get_sparse_mut]
+    pub fn get_sparse_mut<C: Component>(&mut self, entity: Entity) -> Option<&mut C> {
+        self.world.get_sparse_mut(entity)
+    }
+
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        self.world.resource()
+    }
+
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.world.resource_mut()
+    }
+
+    /// 推迟结构性的修改
+    pub fn commands(&mut self) -> Commands<'_> {
+        Commands::new(self.world)
+    }
+}
+
+impl World {
+    /// 添加C被添加到[Entity]上时调用的钩子
+    ///
+    /// 钩子在C放入之后调用,包括生成[Entity]时,
+    /// 替换已经存在的C不算作添加
+    ///
+    /// ``` rust
+    /// use trecs::{
+    ///     storage::Entity,
+    ///     tools::Command,
+    ///     world::DeferredWorld,
+    ///     World,
+    /// };
+    ///
+    /// // 在Component之外维护的索引
+    /// #[derive(Default)]
+    /// struct Index(Vec<(Entity, u32)>);
+    ///
+    /// fn index(mut world: DeferredWorld, entity: Entity) {
+    ///     let id = *world.fetch::<&u32>(entity).unwrap();
+    ///     world.resource_mut::<Index>().unwrap().0.push((entity, id));
+    /// }
+    ///
+    /// fn unindex(mut world: DeferredWorld, entity: Entity) {
+    ///     let index = &mut world.resource_mut::<Index>().unwrap().0;
+    ///     index.retain(|(indexed, _)| *indexed != entity);
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world
+    ///     .init_resource::<Index>()
+    ///     .on_add::<u32>(index)
+    ///     .on_remove::<u32>(unindex);
+    /// let a = world.spawn(1u32);
+    /// let b = world.spawn("b");
+    /// world.insert(b, 2u32);
+    /// world.despawn(a);
+    /// assert_eq!(world.resource::<Index>().unwrap().0, [(b, 2)]);
+    /// ```
+    pub fn on_add<C: Component>(&mut self, hook: ComponentHook) -> &mut Self {
+        let hooks = self.component_hooks.entry(ComponentId::of::<C>());
+        hooks.or_default().on_add.push(hook);
+        self
+    }
+
+    /// 添加C从[Entity]上移除时调用的钩子
+    ///
+    /// 钩子在C移除之前调用,所以仍然可以访问C,
+    /// 包括通过[World::remove_component]移除和[Entity]被删除时
+    pub fn on_remove<C: Component>(&mut self, hook: ComponentHook) -> &mut Self {
+        let hooks = self.component_hooks.entry(ComponentId::of::<C>());
+        hooks.or_default().on_remove.push(hook);
+        self
+    }
+
+    /// 对ids中的每种[Component]调用on_add钩子
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn trigger_on_add(&mut self, entity: Entity, ids: &[ComponentId]) {
+        self.trigger(entity, ids, |hooks| &hooks.on_add)
+    }
+
+    /// 对ids中的每种[Component]调用on_remove钩子
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn trigger_on_remove(&mut self, entity: Entity, ids: &[ComponentId]) {
+        self.trigger(entity, ids, |hooks| &hooks.on_remove)
+    }
+
+    /// 对[Entity]的所有[Component]调用on_remove钩子,在[Entity]被删除之前调用
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn trigger_on_despawn(&mut self, entity: Entity) {
+        if self.component_hooks.is_empty() {
+            return;
+        }
+        let Some(location) = self.entities.get(entity) else {
+            return;
+        };
+        let bundle_id = self.chunks[location.chunk].bundle_id();
+        let mut ids = self.metas[&bundle_id].components_ids.to_vec();
+        ids.extend(
+            (self.sparse.iter())
+                .filter(|(_, set)| set.get(entity).is_some())
+                .map(|(&id, _)| id),
+        );
+        self.trigger_on_remove(entity, &ids);
+    }
+
+    fn trigger(
+        &mut self,
+        entity: Entity,
+        ids: &[ComponentId],
+        select: fn(&ComponentHooks) -> &Vec<ComponentHook>,
+    ) {
+        if self.component_hooks.is_empty() {
+            return;
+        }
+        for id in ids {
+            let Some(hooks) = self.component_hooks.get(id) else {
+                continue;
+            };
+            // 钩子可能会添加新的钩子
+            for hook in select(hooks).clone() {
+                hook(DeferredWorld { world: self }, entity);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bundle::StorageType, world::Query};
+    use std::any::TypeId;
+
+    /// 记录钩子的调用,格式为(事件,Entity,调用时的值)
+    #[derive(Default)]
+    struct Log(Vec<(&'static str, Entity, Option<i32>)>);
+
+    struct Sparse;
+    impl Component for Sparse {
+        const STORAGE: StorageType = StorageType::SparseSet;
+
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+
+    fn added(mut world: DeferredWorld, entity: Entity) {
+        let value = world.fetch::<&i32>(entity).copied();
+        world
+            .resource_mut::<Log>()
+            .unwrap()
+            .0
+            .push(("add", entity, value));
+    }
+
+    fn removed(mut world: DeferredWorld, entity: Entity) {
+        let value = world.fetch::<&i32>(entity).copied();
+        world
+            .resource_mut::<Log>()
+            .unwrap()
+            .0
+            .push(("remove", entity, value));
+        // 结构性的修改被推迟
+        world.commands().spawn("respawned");
+    }
+
+    fn sparse_removed(mut world: DeferredWorld, entity: Entity) {
+        assert!(world.get_sparse::<Sparse>(entity).is_some());
+        world
+            .resource_mut::<Log>()
+            .unwrap()
+            .0
+            .push(("sparse", entity, None));
+    }
+
+    #[test]
+    fn hooks() {
+        let mut world = World::new();
+        world
+            .init_resource::<Log>()
+            .on_add::<i32>(added)
+            .on_remove::<i32>(removed)
+            .on_remove::<Sparse>(sparse_removed);
+        let a = world.spawn(1);
+        let b = world.spawn_many([(2, "b")])[0];
+        let c = world.spawn("c");
+        // 替换不算作添加
+        world.insert(a, 10);
+        world.insert(c, (3, Sparse));
+        world.remove_component::<i32>(b);
+        world.remove_component::<i32>(b);
+        world.despawn(c);
+        assert_eq!(
+            std::mem::take(&mut world.resource_mut::<Log>().unwrap().0),
+            [
+                ("add", a, Some(1)),
+                ("add", b, Some(2)),
+                ("add", c, Some(3)),
+                ("remove", b, Some(2)),
+                ("remove", c, Some(3)),
+                ("sparse", c, None),
+            ]
+        );
+
+        assert_eq!(world.fetch::<&&str>(b), Some(&"b"));
+        let respawned = |world: &mut World| {
+            let query = Query::<&&str>::new(world);
+            query.into_iter().filter(|s| **s == "respawned").count()
+        };
+        assert_eq!(respawned(&mut world), 0);
+        world.apply_commands();
+        assert_eq!(respawned(&mut world), 2);
+    }
+}
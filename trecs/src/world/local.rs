@@ -0,0 +1,120 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    cell::UnsafeCell,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
+
+use super::UnsafeWorldCell;
+use crate::system::{state::SystemState, ParamConflict, SystemParm};
+
+/// 每个[System]各自持有的[Local]的值
+///
+/// 在创建参数时通过[UnsafeWorldCell::with_locals]传递
+///
+/// [System]: crate::system::System
+#[derive(Default)]
+pub(crate) struct Locals(UnsafeCell<HashMap<TypeId, Box<dyn Any + Send>>>);
+
+impl Locals {
+    /// 所有值,访问时需要保证没有其他对同一个[Locals]的访问
+    pub(crate) fn get(&self) -> *mut HashMap<TypeId, Box<dyn Any + Send>> {
+        self.0.get()
+    }
+}
+
+/// [System]自己的,在多次执行之间保留的值,如计数器,缓存等
+///
+/// 第一次执行时通过[Default]初始化,不同的[System]之间互不影响,
+/// 也不会被放进[World]的资源中
+///
+/// ``` rust
+/// use trecs::{world::Local, World};
+///
+/// fn count(mut count: Local<usize>) {
+///     *count += 1;
+///     println!("第{}次执行", *count);
+/// }
+///
+/// let mut world = World::new();
+/// world.add_system(count).add_system(count);
+/// world.run_once();
+/// ```
+///
+/// [System]: crate::system::System
+/// [World]: crate::World
+pub struct Local<'a, T>(&'a mut T);
+
+impl<T> Deref for Local<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<T> DerefMut for Local<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+    }
+}
+
+impl<T: Default + Send + 'static> SystemParm for Local<'_, T> {
+    type Item<'w> = Local<'w, T>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        let value = world
+            .locals()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()));
+        Local(
+            value
+                .downcast_mut()
+                .unwrap_or_else(|| unreachable!("Local的位置中存放的不是{}", type_name::<T>())),
+        )
+    }
+
+    fn init(state: &mut SystemState) {
+        if state
+            .locals
+            .insert(TypeId::of::<T>(), type_name::<T>())
+            .is_some()
+        {
+            state.conflicts.push(ParamConflict::Local(type_name::<T>()));
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        system::IntoSystem,
+        test::{self, Capture},
+        world::ResMut,
+        World,
+    };
+
+    #[test]
+    fn local() {
+        fn count(mut count: Local<usize>, capture: ResMut<Capture<usize>>) {
+            *count += 1;
+            capture.into_or_init(Capture::new).push(*count);
+        }
+
+        let mut world = World::new();
+        world
+            .add_system(count)
+            .add_system(count.run_if(|mut skip: Local<bool>| {
+                *skip = !*skip;
+                *skip
+            }));
+        for _ in 0..3 {
+            test::tick(&mut world);
+        }
+        assert_eq!(test::captured::<usize>(&mut world), [1, 1, 2, 3, 2]);
+
+        let result = world.try_add_system(|_: Local<usize>, _: Local<usize>| {});
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,533 @@
+use std::any::Any;
+
+use crate::{
+    bundle::{Bundle, BundleId, BundleMeta, Component, ComponentId, StorageType},
+    storage::{ComponentTicks, ComponentVTable, Entity, EntityLocation},
+};
+
+use super::{sparse, Name, World};
+
+/// 正在被移动的[Entity]的一个[Component],以及它在[Chunk]中的存放方式
+///
+/// [Component]: crate::bundle::Component
+/// [Chunk]: crate::storage::Chunk
+pub(crate) type Part = (
+    ComponentId,
+    &'static str,
+    &'static ComponentVTable,
+    Box<dyn Any>,
+    ComponentTicks,
+);
+
+/// 将要被放入[Entity]的一个[Component],见[Part]
+///
+/// [Component]: crate::bundle::Component
+pub(crate) type Inserted = (
+    ComponentId,
+    &'static str,
+    &'static ComponentVTable,
+    Box<dyn Any>,
+);
+
+impl World {
+    /// 为[Entity]添加[Bundle]中的所有[Component]
+    ///
+    /// + 已经存在的同类[Component]会被替换,并被标记为修改过
+    /// + [Entity]会被移动到存放新的[Component]组合的[Chunk]中,但是[Entity]本身不变
+    ///
+    /// [StorageType::SparseSet]的[Component]会被放入单独的存储中,不会移动[Entity],
+    /// 见[World::get_sparse]
+    ///
+    /// 返回[Entity]是否存在,包含重复的[Component]等的[Bundle]会panic,见[World::try_register]
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, World};
+    ///
+    /// let mut world = World::new();
+    /// let entity = world.spawn(1i32);
+    /// world.insert(entity, (2u32, "a"));
+    /// assert_eq!(world.fetch::<(&i32, &u32)>(entity), Some((&1, &2)));
+    /// ```
+    ///
+    /// [Component]: crate::bundle::Component
+    /// [Chunk]: crate::storage::Chunk
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, bundle: B) -> bool {
+        if !self.metas.contains_key(&BundleId::of::<B>()) {
+            BundleMeta::check::<B>()
+                .and_then(|_| self.register_components::<B>())
+                .unwrap_or_else(|e| panic!("{e}"));
+        }
+        if self.entities.get(entity).is_none() {
+            return false;
+        }
+        if !sparse::has_sparse::<B>() {
+            self.insert_table(entity, bundle);
+            return true;
+        }
+        let mut inserted = vec![];
+        let mut added = vec![];
+        let components = B::components_ids()
+            .iter()
+            .zip(B::components_names())
+            .zip(B::components_descriptors())
+            .zip(bundle.destory());
+        for (((&id, &name), descriptor), component) in components {
+            match descriptor.storage {
+                StorageType::Table => inserted.push((id, name, descriptor.vtable, component)),
+                StorageType::SparseSet => {
+                    if (self.insert_sparse(entity, id, descriptor.vtable, component)).is_none() {
+                        added.push(id);
+                    }
+                }
+            }
+        }
+        let alive = inserted.is_empty() || self.insert_parts(entity, inserted);
+        self.trigger_on_add(entity, &added);
+        alive
+    }
+
+    /// 为存在的[Entity]添加只有[StorageType::Table]的[Component]的[Bundle]
+    ///
+    /// 不经过[Box],其他[Component]按字节移动到新的位置,[Bundle]直接写入[Column]
+    ///
+    /// [Component]: crate::bundle::Component
+    /// [Column]: crate::storage::Column
+    fn insert_table<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        let location = self.entities.get(entity).unwrap();
+        let tick = self.change_tick;
+        let from = self.chunks[location.chunk].bundle_id();
+        let from_ids = self.metas[&from].components_ids;
+        let mut to = from;
+        let mut added = vec![];
+        let mut named = false;
+        for ((&id, &name), descriptor) in B::components_ids()
+            .iter()
+            .zip(B::components_names())
+            .zip(B::components_descriptors())
+        {
+            named |= id == ComponentId::of::<Name>();
+            match from_ids.iter().position(|component| *component == id) {
+                Some(index) => {
+                    // 被替换的Component先移出名字索引再drop
+                    let chunk = &mut self.chunks[location.chunk];
+                    let replaced = unsafe { chunk.row(location.row).get(index) };
+                    self.names.remove_component(replaced, entity);
+                    unsafe { chunk.drop_at(index, location.row) };
+                    chunk.ticks(index, location.row).set_changed(tick);
+                }
+                None => {
+                    to = self.add_edge(to, id, name, descriptor.vtable);
+                    added.push(id);
+                }
+            }
+        }
+        let to_ids = self.metas[&to].components_ids;
+        let (chunk, row) = if to == from {
+            (location.chunk, location.row)
+        } else {
+            let chunk = self.free_chunk(to);
+            let mut old = self.chunks[location.chunk].free_row(location.row);
+            let ticks = to_ids
+                .iter()
+                .map(|id| match from_ids.iter().position(|c| c == id) {
+                    Some(index) => std::mem::take(&mut old[index]),
+                    None => ComponentTicks::new(tick),
+                })
+                .collect();
+            let row = self.chunks[chunk].alloc(entity, ticks);
+            for (index, id) in from_ids.iter().enumerate() {
+                // 被替换的已经drop了,之后由bundle写入
+                if B::components_ids().contains(id) {
+                    continue;
+                }
+                let target = to_ids.iter().position(|c| c == id).unwrap();
+                let dst = self.chunks[chunk].ptr(target, row);
+                // free_row不会读取或者覆盖原来的位置,两个Chunk不同
+                unsafe { self.chunks[location.chunk].move_to(index, location.row, dst) };
+            }
+            self.entities.set(entity, EntityLocation { chunk, row });
+            (chunk, row)
+        };
+        let chunk = &self.chunks[chunk];
+        let columns = B::components_ids()
+            .iter()
+            .map(|id| to_ids.iter().position(|c| c == id).unwrap())
+            .collect::<Vec<_>>();
+        // 写入的位置要么是新分配的,要么原来的值已经被drop
+        unsafe { bundle.write_to(0, &mut |index| chunk.ptr(columns[index], row)) };
+        if named {
+            self.index_name(entity);
+        }
+        self.trigger_on_add(entity, &added);
+    }
+
+    /// 为[Entity]添加类型擦除后的[Component],见[World::insert]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn insert_parts(
+        &mut self,
+        entity: Entity,
+        inserted: impl IntoIterator<Item = Inserted>,
+    ) -> bool {
+        let Some(location) = self.entities.get(entity) else {
+            return false;
+        };
+        let tick = self.change_tick;
+        let mut bundle_id = self.chunks[location.chunk].bundle_id();
+        let mut parts = self.take_parts(location);
+        let mut named = false;
+        let mut added = vec![];
+        for (id, name, vtable, component) in inserted {
+            named |= id == ComponentId::of::<Name>();
+            match parts.iter_mut().find(|part| part.0 == id) {
+                Some(part) => {
+                    let replaced = std::mem::replace(&mut part.3, component);
+                    self.names.remove_component(&*replaced, entity);
+                    part.4.set_changed(tick);
+                }
+                None => {
+                    bundle_id = self.add_edge(bundle_id, id, name, vtable);
+                    parts.push((id, name, vtable, component, ComponentTicks::new(tick)));
+                    added.push(id);
+                }
+            }
+        }
+        self.place_in(entity, parts, bundle_id);
+        if named {
+            self.index_name(entity);
+        }
+        self.trigger_on_add(entity, &added);
+        true
+    }
+
+    /// 从[Entity]中移出一个[Component]
+    ///
+    /// [Entity]会被移动到存放剩下的[Component]组合的[Chunk]中,但是[Entity]本身不变
+    ///
+    /// [Entity]已经失效,或者没有这个[Component]时返回[None]
+    ///
+    /// [Command::remove]已经用于删除[Entity]
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    /// [Command::remove]: crate::tools::Command::remove
+    pub fn remove_component<C: Component>(&mut self, entity: Entity) -> Option<C> {
+        let component = match C::STORAGE {
+            StorageType::Table => self.remove_by_id(entity, ComponentId::of::<C>())?,
+            StorageType::SparseSet => {
+                let id = ComponentId::of::<C>();
+                self.sparse.get(&id)?.get(entity)?;
+                self.trigger_on_remove(entity, &[id]);
+                self.sparse.get_mut(&id)?.remove(entity)?
+            }
+        };
+        component.downcast().ok().map(|c| *c)
+    }
+
+    /// 从[Entity]中移出类型擦除后的[Component],见[World::remove_component]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn remove_by_id(&mut self, entity: Entity, id: ComponentId) -> Option<Box<dyn Any>> {
+        let location = self.entities.get(entity)?;
+        let bundle_id = self.chunks[location.chunk].bundle_id();
+        let index = self.metas[&bundle_id]
+            .components_ids
+            .iter()
+            .position(|component| *component == id)?;
+        // 钩子不能进行结构性的修改,所以location仍然有效
+        self.trigger_on_remove(entity, &[id]);
+        let target = self.remove_edge(bundle_id, index);
+        let mut parts = self.take_parts(location);
+        let (.., component, _) = parts.remove(index);
+        self.names.remove_component(&*component, entity);
+        self.place_in(entity, parts, target);
+        Some(component)
+    }
+
+    /// 从[Chunk]中移出[Entity]的所有[Component]
+    ///
+    /// 之后必须通过[World::place]放回去
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    /// [Component]: crate::bundle::Component
+    fn take_parts(&mut self, location: EntityLocation) -> Vec<Part> {
+        let chunk = &mut self.chunks[location.chunk];
+        let meta = &self.metas[&chunk.bundle_id()];
+        let (components, ticks) = chunk.remove(location.row);
+        meta.components_ids
+            .iter()
+            .zip(meta.components_names)
+            .zip(&meta.vtables)
+            .zip(components.into_iter().zip(ticks))
+            .map(|(((&id, &name), &vtable), (component, ticks))| {
+                (id, name, vtable, component, ticks)
+            })
+            .collect()
+    }
+
+    /// 按照[Component]的组合对应的布局排列,并放入[Chunk]中
+    ///
+    /// 组合第一次出现时会创建新的[BundleMeta]
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn place(&mut self, entity: Entity, parts: Vec<Part>) {
+        let bundle_id = self.layout_of(
+            parts.iter().map(|part| part.0).collect(),
+            parts.iter().map(|part| part.1).collect(),
+            parts.iter().map(|part| part.2).collect(),
+        );
+        self.place_in(entity, parts, bundle_id);
+        let ids = self.metas[&bundle_id].components_ids;
+        self.trigger_on_add(entity, ids);
+    }
+
+    /// [Component]的组合对应的[BundleId],组合第一次出现时会创建新的[BundleMeta]
+    ///
+    /// names和vtables与ids一一对应
+    ///
+    /// [Component]: crate::bundle::Component
+    fn layout_of(
+        &mut self,
+        ids: Vec<ComponentId>,
+        names: Vec<&'static str>,
+        vtables: Vec<&'static ComponentVTable>,
+    ) -> BundleId {
+        let mut layout = ids.clone();
+        layout.sort_unstable();
+        if let Some(bundle_id) = self.layouts.get(&layout) {
+            return *bundle_id;
+        }
+        let meta = BundleMeta::dynamic(self.metas.len(), ids, names, vtables);
+        let bundle_id = meta.bundle_id;
+        self.metas.insert(bundle_id, meta);
+        self.layouts.insert(layout, bundle_id);
+        bundle_id
+    }
+
+    /// 在from的布局中添加一种[Component]后的布局
+    ///
+    /// 第一次计算之后会记录在[BundleMeta]中,之后只需要一次查找
+    ///
+    /// [Component]: crate::bundle::Component
+    fn add_edge(
+        &mut self,
+        from: BundleId,
+        id: ComponentId,
+        name: &'static str,
+        vtable: &'static ComponentVTable,
+    ) -> BundleId {
+        let meta = &self.metas[&from];
+        if let Some(to) = meta.add_edges.get(&id) {
+            return *to;
+        }
+        let mut ids = meta.components_ids.to_vec();
+        let mut names = meta.components_names.to_vec();
+        let mut vtables = meta.vtables.clone();
+        ids.push(id);
+        names.push(name);
+        vtables.push(vtable);
+        let to = self.layout_of(ids, names, vtables);
+        self.link(from, to, id);
+        to
+    }
+
+    /// 在from的布局中移除第index个[Component]后的布局,见[World::add_edge]
+    ///
+    /// [Component]: crate::bundle::Component
+    fn remove_edge(&mut self, from: BundleId, index: usize) -> BundleId {
+        let meta = &self.metas[&from];
+        let id = meta.components_ids[index];
+        if let Some(to) = meta.remove_edges.get(&id) {
+            return *to;
+        }
+        let mut ids = meta.components_ids.to_vec();
+        let mut names = meta.components_names.to_vec();
+        let mut vtables = meta.vtables.clone();
+        ids.remove(index);
+        names.remove(index);
+        vtables.remove(index);
+        let to = self.layout_of(ids, names, vtables);
+        self.link(to, from, id);
+        to
+    }
+
+    /// 记录less添加id后成为more,more移除id后成为less
+    fn link(&mut self, less: BundleId, more: BundleId, id: ComponentId) {
+        if let Some(meta) = self.metas.get_mut(&less) {
+            meta.add_edges.insert(id, more);
+        }
+        if let Some(meta) = self.metas.get_mut(&more) {
+            meta.remove_edges.insert(id, less);
+        }
+    }
+
+    /// 按照bundle_id对应的布局排列,并放入[Chunk]中
+    ///
+    /// parts中的[Component]的组合必须与bundle_id的相同
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    /// [Component]: crate::bundle::Component
+    fn place_in(&mut self, entity: Entity, mut parts: Vec<Part>, bundle_id: BundleId) {
+        let (components, ticks) = self.metas[&bundle_id]
+            .components_ids
+            .iter()
+            .map(|id| {
+                let index = parts
+                    .iter()
+                    .position(|part| part.0 == *id)
+                    .unwrap_or_else(|| unreachable!("布局和Component的组合相同"));
+                let (.., component, ticks) = parts.swap_remove(index);
+                (component, ticks)
+            })
+            .unzip();
+        let chunk = self.free_chunk(bundle_id);
+        let row = self.chunks[chunk].push(entity, components, ticks);
+        self.entities.set(entity, EntityLocation { chunk, row });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tools::Command, world::Query};
+
+    #[test]
+    fn insert_remove() {
+        let mut world = World::new();
+        let entity = world.spawn(1i32);
+        let other = world.spawn(2i32);
+
+        assert!(world.insert(entity, (2u32, "a")));
+        assert_eq!(
+            world.fetch::<(&i32, &u32, &&str)>(entity),
+            Some((&1, &2, &"a"))
+        );
+        assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 2);
+
+        // 替换已经存在的Component
+        assert!(world.insert(entity, 3u32));
+        assert_eq!(world.fetch::<&u32>(entity), Some(&3));
+
+        assert_eq!(world.remove_component::<u32>(entity), Some(3));
+        assert_eq!(world.remove_component::<u32>(entity), None);
+        assert_eq!(world.fetch::<(&i32, &&str)>(entity), Some((&1, &"a")));
+        assert_eq!(world.fetch::<&i32>(other), Some(&2));
+
+        world.despawn(entity);
+        assert!(!world.insert(entity, 4u32));
+        assert_eq!(world.remove_component::<i32>(entity), None);
+    }
+
+    #[test]
+    fn drop_once() {
+        use std::{any::TypeId, sync::Arc};
+
+        struct Counter(#[allow(dead_code)] Arc<()>);
+
+        impl Component for Counter {
+            fn type_id_() -> TypeId {
+                TypeId::of::<Self>()
+            }
+        }
+
+        let counter = Arc::new(());
+        let mut world = World::new();
+        let entity = world.spawn((Counter(counter.clone()), 1i32));
+        // 移动到新的位置时不会drop或者复制
+        world.insert(entity, (2u32, Name::new("a")));
+        world.insert(entity, true);
+        assert_eq!(Arc::strong_count(&counter), 2);
+
+        // 替换时原来的值只会被drop一次
+        world.insert(entity, (Counter(counter.clone()), 3u8));
+        world.insert(entity, (Counter(counter.clone()), Name::new("b")));
+        assert_eq!(Arc::strong_count(&counter), 2);
+        assert_eq!(world.fetch::<(&i32, &u32, &u8)>(entity), Some((&1, &2, &3)));
+        assert_eq!(world.entity_by_name("a"), None);
+        assert_eq!(world.entity_by_name("b"), Some(entity));
+
+        world.despawn(entity);
+        assert_eq!(Arc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn reuse_layout() {
+        let mut world = World::new();
+        world.spawn((1i32, 2u32));
+        let entity = world.spawn(3i32);
+        let metas = world.metas.len();
+
+        // 与(i32,u32)的组合相同,顺序不同
+        world.insert(entity, 4u32);
+        assert_eq!(world.metas.len(), metas);
+        let location = world.entities.get(entity).unwrap();
+        let chunk = &world.chunks[location.chunk];
+        assert_eq!(chunk.bundle_id(), BundleId::of::<(i32, u32)>());
+
+        // 新的组合
+        world.insert(entity, true);
+        assert_eq!(world.metas.len(), metas + 1);
+        let query = Query::<(&i32, &u32)>::new(&mut world);
+        let mut items = query.into_iter().map(|(a, b)| (*a, *b)).collect::<Vec<_>>();
+        items.sort();
+        assert_eq!(items, [(1, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn edges() {
+        let mut world = World::new();
+        let a = world.spawn(1i32);
+        let b = world.spawn(2i32);
+        world.insert(a, 1u32);
+        let from = BundleId::of::<i32>();
+        let to = world.metas[&from].add_edges[&ComponentId::of::<u32>()];
+        assert_eq!(
+            world.metas[&to].remove_edges[&ComponentId::of::<u32>()],
+            from
+        );
+
+        // 之后沿着记录的边移动,不会创建新的布局
+        let metas = world.metas.len();
+        world.insert(b, 2u32);
+        assert_eq!(world.remove_component::<u32>(a), Some(1));
+        assert_eq!(world.metas.len(), metas);
+        let location = world.entities.get(b).unwrap();
+        assert_eq!(world.chunks[location.chunk].bundle_id(), to);
+        let location = world.entities.get(a).unwrap();
+        assert_eq!(world.chunks[location.chunk].bundle_id(), from);
+
+        // 一次添加多个Component时经过中间的布局
+        world.insert(a, (true, 3u8));
+        assert_eq!(world.fetch::<(&i32, &bool, &u8)>(a), Some((&1, &true, &3)));
+        assert!(world.metas[&from]
+            .add_edges
+            .contains_key(&ComponentId::of::<bool>()));
+    }
+
+    #[test]
+    #[should_panic(expected = "中包含重复的Component")]
+    fn insert_duplicate() {
+        let mut world = World::new();
+        let entity = world.spawn(1);
+        world.insert(entity, (2, 3));
+    }
+
+    #[test]
+    fn ticks() {
+        let mut world = World::new();
+        let entity = world.spawn(1i32);
+        world.change_tick = 5;
+        world.insert(entity, 2u32);
+
+        let location = world.entities.get(entity).unwrap();
+        let chunk = &world.chunks[location.chunk];
+        let added = [0, 1].map(|index| chunk.ticks(index, location.row).added());
+        assert_eq!(added, [1, 5]);
+
+        world.change_tick = 7;
+        world.insert(entity, 3i32);
+        let location = world.entities.get(entity).unwrap();
+        let ticks = world.chunks[location.chunk].ticks(0, location.row);
+        assert_eq!((ticks.added(), ticks.changed()), (1, 7));
+    }
+}
@@ -2,191 +2,1683 @@ use std::{
     any::{Any, TypeId},
     cell::UnsafeCell,
     collections::HashMap,
+    sync::Mutex,
 };
 
+#[cfg(feature = "system")]
+mod borrow;
+mod cell;
 mod commands;
+mod debug;
+mod disabled;
+mod dynamic;
+mod dynamic_query;
+mod entity;
+mod hooks;
+#[cfg(feature = "system")]
+mod local;
+mod migrate;
+mod name;
+#[cfg(feature = "system")]
+mod observer;
 mod query;
+mod query_state;
 mod resources;
+mod sparse;
+mod usage;
+mod validate;
 
+#[cfg(all(feature = "system", not(feature = "async")))]
+pub(crate) use self::commands::CommandQueue;
+pub(crate) use self::disabled::allows_disabled;
+#[cfg(feature = "system")]
+pub use self::local::Local;
+#[cfg(feature = "system")]
+pub(crate) use self::local::Locals;
+#[cfg(feature = "system")]
+pub use self::observer::{Despawned, Spawned, Trigger};
+pub(crate) use self::query_state::{Matched, QueryCache};
 pub use self::{
-    commands::Commands,
-    query::Query,
-    resources::{Res, Resources},
+    cell::UnsafeWorldCell,
+    commands::{Commands, EntityCommands},
+    debug::EntityDebug,
+    disabled::Disabled,
+    dynamic_query::{DynamicAccess, DynamicItem, DynamicIter, DynamicQuery, DynamicQueryError},
+    entity::{EntityMut, EntityRef},
+    hooks::{ComponentHook, DeferredWorld},
+    name::Name,
+    query::{Query, QueryEntityError, QuerySingleError},
+    query_state::QueryState,
+    resources::{NoSuchResource, NonSend, Res, ResMut, Resources},
+    usage::UnusedData,
+    validate::{DanglingEntity, ValidationReport},
 };
 
 use crate::{
-    bundle::{Bundle, BundleMeta},
-    storage::{Chunk, Entity, CHUNK_SIZE},
-    tools::{Command, ResManager},
+    bundle::{
+        Bundle, BundleError, BundleId, BundleMeta, ComponentDescriptor, ComponentId, DynamicDrop,
+    },
+    storage::{Chunk, ComponentTicks, Entities, Entity, EntityLocation, SparseSets, CHUNK_SIZE},
+    tools::{Command, FetchError, ResManager, WorldFetch},
 };
 
-/// 这里的[Any]是没有虚表的！！！
-/// 不可以进行downcast等操作！！！
-/// 否则会导致段错误！！！
-type AnRes = UnsafeCell<Option<Box<dyn Any>>>;
+/// 一种资源的位置,以及资源被插入,和最后一次被修改时的tick
+pub(crate) struct AnRes {
+    /// 这里的[Any]是没有虚表的！！！
+    /// 不可以进行downcast等操作！！！
+    /// 否则会导致段错误！！！
+    value: UnsafeCell<Option<Box<dyn Any>>>,
+    ticks: ComponentTicks,
+    /// 正在执行的[System]对资源的借用
+    ///
+    /// [System]: crate::system::System
+    #[cfg(feature = "system")]
+    borrow: borrow::BorrowFlag,
+}
+
+impl AnRes {
+    fn new() -> Self {
+        Self {
+            value: UnsafeCell::new(None),
+            ticks: ComponentTicks::new(0),
+            #[cfg(feature = "system")]
+            borrow: Default::default(),
+        }
+    }
+
+    /// 资源的值,访问时需要保证没有冲突的访问
+    pub(crate) fn get(&self) -> *mut Option<Box<dyn Any>> {
+        self.value.get()
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut Option<Box<dyn Any>> {
+        self.value.get_mut()
+    }
+
+    pub(crate) fn ticks(&self) -> &ComponentTicks {
+        &self.ticks
+    }
+}
 
 type Droper = Option<Box<dyn FnOnce(&mut AnRes)>>;
 
 #[cfg(feature = "system")]
-use crate::system::{InnerSystem, System};
+type FrameHook = (TypeId, fn(&mut World));
+
+/// [Entity]被删除时调用的函数,[TypeId]用于去重
+type DespawnHook = (TypeId, fn(&mut World, Entity));
+
+#[cfg(feature = "system")]
+use crate::system::{BoxedError, SystemError};
+
+/// 切换一种[State]的函数,返回发生panic的[System]
+///
+/// [State]: crate::state::State
+#[cfg(all(feature = "system", not(feature = "async")))]
+type StateTransition = (
+    TypeId,
+    fn(
+        &mut World,
+        &mut Vec<(&'static str, Duration)>,
+    ) -> Option<(&'static str, Box<dyn Any + Send>)>,
+);
+
+/// 异步版本的[StateTransition],返回的[Future]结束时切换完成
+///
+/// [Future]: std::future::Future
+#[cfg(all(feature = "system", feature = "async"))]
+type StateTransition = (
+    TypeId,
+    for<'a> fn(
+        &'a mut World,
+        &'a mut Vec<(&'static str, Duration)>,
+    ) -> crate::state::TransitionFuture<'a>,
+);
+
+#[cfg(feature = "system")]
+use crate::{
+    diagnostic::Diagnostics,
+    system::{
+        Ambiguity, AmbiguityReport, Executor, InnerSystem, IntoSystem, NodeKind, PanicPolicy,
+        Schedule, ScheduleError, ScheduleGraph, Stage, StartupStage, System, SystemPanic,
+        SystemParm, SystemRegistrationError, SystemSet,
+    },
+};
+#[cfg(feature = "system")]
+use std::time::{Duration, Instant};
 
 pub struct World {
     pub(crate) chunks: Vec<Chunk>,
-    pub(crate) metas: HashMap<TypeId, BundleMeta>,
+    pub(crate) metas: HashMap<BundleId, BundleMeta>,
+    /// 每种[Component]的组合对应的[BundleId],以排序后的[ComponentId]为键
+    ///
+    /// 用于在[World::insert]等移动[Entity]时找到目标布局
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) layouts: HashMap<Vec<ComponentId>, BundleId>,
+    /// 所有[Entity]以及它们的位置
+    pub(crate) entities: Entities,
+    /// [StorageType::SparseSet]的[Component]
+    ///
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    /// [Component]: crate::bundle::Component
+    pub(crate) sparse: SparseSets,
+    /// 见[World::entity_by_name]
+    pub(crate) names: name::NameIndex,
+    /// 见[UnsafeWorldCell::metas_mut]
+    pub(crate) metas_lock: Mutex<()>,
+    /// 所有注册过的[Component],以类型名为键
+    ///
+    /// 用于发现同一个类型在不同的动态库中[TypeId]不同等问题
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) descriptors: HashMap<&'static str, (ComponentId, ComponentDescriptor)>,
+    /// 在运行时注册的[Component],以[ComponentId::Dynamic]中的编号为下标
+    ///
+    /// 见[World::register_component]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) dynamic_components: Vec<(ComponentDescriptor, Option<DynamicDrop>)>,
+    /// 还没有执行过的startup system,与[StartupStage::ALL]一一对应
+    #[cfg(feature = "system")]
+    pub(crate) startup_systems: [Vec<System>; 3],
+    /// 每一帧都会执行的[System]
+    #[cfg(feature = "system")]
+    pub(crate) schedule: Schedule,
+    /// 通过[World::run_schedule]执行的schedule,以名字为键
+    #[cfg(feature = "system")]
+    pub(crate) schedules: HashMap<&'static str, Schedule>,
+    /// 已经添加过的[Plugin]的名字
+    ///
+    /// [Plugin]: crate::app::Plugin
+    #[cfg(feature = "system")]
+    pub(crate) plugins: std::collections::HashSet<&'static str>,
+    /// 每次循环结束时调用的函数,如[Replay]切换到下一帧
+    ///
+    /// [TypeId]用于去重
+    ///
+    /// [Replay]: crate::replay::Replay
     #[cfg(feature = "system")]
-    pub(crate) startup_systems: Vec<System>,
+    pub(crate) frame_end: Vec<FrameHook>,
+    /// 每次循环开始时调用的函数,如[Replay]记录或者回放外部发送的事件
+    ///
+    /// [TypeId]用于去重
+    ///
+    /// [Replay]: crate::replay::Replay
+    #[cfg(feature = "system")]
+    pub(crate) frame_start: Vec<FrameHook>,
+    /// 每次循环开始时切换[State]的函数,[TypeId]为状态的类型
+    ///
+    /// [State]: crate::state::State
+    #[cfg(feature = "system")]
+    pub(crate) state_transitions: Vec<StateTransition>,
+    /// [System]发生panic时的处理方式
+    #[cfg(feature = "system")]
+    pub(crate) panic_policy: PanicPolicy,
+    /// [System]返回错误时调用的函数,见[World::set_error_handler]
     #[cfg(feature = "system")]
-    pub(crate) systems: Vec<System>,
+    pub(crate) error_handler: fn(&mut World, SystemError),
+    /// [World::run_until]中相邻两帧开始的最小间隔,见[World::set_tick_rate]
+    #[cfg(feature = "system")]
+    pub(crate) tick_interval: Option<Duration>,
+    /// 异步运行时提供的sleep函数,见[World::set_sleep]
+    #[cfg(all(feature = "system", feature = "async"))]
+    pub(crate) sleep: Option<crate::system::AsyncSleep>,
+    /// 异步运行时提供的spawn函数,见[World::set_runtime]
+    #[cfg(all(feature = "system", feature = "async"))]
+    pub(crate) spawn: Option<crate::system::AsyncSpawn>,
+    /// 异步[System]通过[WorldAccess]请求的操作
+    ///
+    /// [WorldAccess]: crate::system::WorldAccess
+    #[cfg(all(feature = "system", feature = "async"))]
+    pub(crate) access_queue: crate::system::AccessQueue,
+    /// 执行update阶段的[System]的方式
+    #[cfg(feature = "system")]
+    pub(crate) executor: Executor,
+    /// 发生panic的[System],存在时[World]被污染
+    #[cfg(feature = "system")]
+    pub(crate) poisoned: Option<SystemPanic>,
     pub(crate) resources: HashMap<TypeId, AnRes>,
     /// 因为运行时反射 资源在最后都以[Box<dyn Any>]的状态[Drop]
     /// 而不是调用自身的[Drop::drop]和方法
     ///
-    /// 所以在创建每一个资源时都记录下一个函数用来Drop
-    pub(crate) resources_dropers: HashMap<TypeId, Droper>,
-}
+    /// 所以在创建每一个资源时都记录下一个函数用来Drop
+    pub(crate) resources_dropers: HashMap<TypeId, Droper>,
+    /// 资源的创建和读取的记录
+    pub(crate) resources_usage: usage::ResourcesUsage,
+    /// 运行时的借用检查
+    #[cfg(feature = "debug-borrows")]
+    pub(crate) borrows: crate::system::borrows::BorrowTracker,
+    /// 被[Commands]推迟的操作
+    pub(crate) command_queue: commands::CommandQueue,
+    /// [Entity]被删除时调用的函数,如从[Relations]中移除它的关系
+    ///
+    /// [Relations]: crate::relation::Relations
+    pub(crate) despawn_hooks: Vec<DespawnHook>,
+    /// 每种[Component]被添加和移除时调用的钩子,见[World::on_add]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) component_hooks: HashMap<ComponentId, hooks::ComponentHooks>,
+    /// 见[World::observe]
+    #[cfg(feature = "system")]
+    pub(crate) observers: Vec<observer::Observer>,
+    /// 每执行一个[System]就增加一次,用于变更检测
+    ///
+    /// 见[ComponentTicks]
+    ///
+    /// [ComponentTicks]: crate::storage::ComponentTicks
+    pub(crate) change_tick: usize,
+    /// 正在执行的[System]上一次执行时的tick
+    ///
+    /// 在[System]之外为0,即所有[Component]都被看作新添加的
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) last_run: usize,
+    /// 一共放入[World]的[Bundle]的数量
+    pub(crate) spawned: usize,
+    /// 一共从[World]中删除的[Bundle]的数量
+    pub(crate) despawned: usize,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            chunks: vec![],
+            metas: Default::default(),
+            layouts: Default::default(),
+            entities: Default::default(),
+            sparse: Default::default(),
+            names: Default::default(),
+            metas_lock: Default::default(),
+            descriptors: Default::default(),
+            dynamic_components: vec![],
+            #[cfg(feature = "system")]
+            startup_systems: Default::default(),
+            #[cfg(feature = "system")]
+            schedule: Default::default(),
+            #[cfg(feature = "system")]
+            schedules: Default::default(),
+            #[cfg(feature = "system")]
+            plugins: Default::default(),
+            #[cfg(feature = "system")]
+            frame_end: vec![],
+            #[cfg(feature = "system")]
+            frame_start: vec![],
+            #[cfg(feature = "system")]
+            state_transitions: vec![],
+            #[cfg(feature = "system")]
+            panic_policy: Default::default(),
+            #[cfg(feature = "system")]
+            error_handler: crate::system::panic_on_error,
+            #[cfg(feature = "system")]
+            tick_interval: None,
+            #[cfg(all(feature = "system", feature = "async"))]
+            sleep: None,
+            #[cfg(all(feature = "system", feature = "async"))]
+            spawn: None,
+            #[cfg(all(feature = "system", feature = "async"))]
+            access_queue: Default::default(),
+            #[cfg(feature = "system")]
+            executor: Default::default(),
+            #[cfg(feature = "system")]
+            poisoned: None,
+            resources: Default::default(),
+            resources_dropers: Default::default(),
+            resources_usage: Default::default(),
+            #[cfg(feature = "debug-borrows")]
+            borrows: Default::default(),
+            command_queue: Default::default(),
+            despawn_hooks: vec![],
+            component_hooks: Default::default(),
+            #[cfg(feature = "system")]
+            observers: vec![],
+            change_tick: 1,
+            last_run: 0,
+            spawned: 0,
+            despawned: 0,
+        }
+    }
+
+    /// 不通过[Res]直接读取资源
+    ///
+    /// 不会记录资源的读取
+    pub(crate) fn peek_res<T: 'static>(&self) -> Option<&T> {
+        let res = self.resources.get(&TypeId::of::<T>())?;
+        unsafe { &*res.get() }.as_ref()?.downcast_ref()
+    }
+
+    /// 不通过[ResMut]直接修改资源
+    ///
+    /// 不会记录资源的访问,也不会把资源标记为修改过
+    #[cfg(feature = "system")]
+    pub(crate) fn peek_res_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        let res = self.resources.get_mut(&TypeId::of::<T>())?;
+        res.get_mut().as_mut()?.downcast_mut()
+    }
+
+    /// 注册一种[Bundle]
+    ///
+    /// + 包含重复的[Component]时返回[BundleError::DuplicateComponent]
+    /// + 与已经注册的同名[Component]的[TypeId]或者内存布局不同时,
+    ///   返回[BundleError::TypeIdMismatch]或者[BundleError::LayoutMismatch]
+    ///
+    /// 返回错误时不会注册
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn try_register<B: Bundle>(&mut self) -> Result<(), BundleError> {
+        let bundle_id = BundleId::of::<B>();
+        if self.metas.contains_key(&bundle_id) {
+            return Ok(());
+        }
+        let meta = BundleMeta::new::<B>()?;
+        self.register_components::<B>()?;
+        self.metas.insert(bundle_id, meta);
+        let mut layout = B::components_ids().to_vec();
+        layout.sort_unstable();
+        self.layouts.entry(layout).or_insert(bundle_id);
+        Ok(())
+    }
+
+    /// 检查并记录[Bundle]中所有[Component]的[ComponentDescriptor]
+    ///
+    /// 返回的错误见[World::try_register],返回错误时不会记录
+    ///
+    /// [Component]: crate::bundle::Component
+    pub(crate) fn register_components<B: Bundle>(&mut self) -> Result<(), BundleError> {
+        let components = B::components_ids().iter().zip(B::components_descriptors());
+        for (id, found) in components.clone() {
+            let Some((registered_id, registered)) = self.descriptors.get(found.name) else {
+                continue;
+            };
+            if !registered.same_layout(found) {
+                return Err(BundleError::LayoutMismatch {
+                    registered: *registered,
+                    found: *found,
+                });
+            }
+            if registered_id != id {
+                return Err(BundleError::TypeIdMismatch {
+                    component: found.name,
+                });
+            }
+        }
+        for (id, descriptor) in components {
+            self.descriptors.insert(descriptor.name, (*id, *descriptor));
+        }
+        Ok(())
+    }
+
+    /// 通过[ComponentDescriptor]找到已经注册的[Component]的[TypeId]
+    ///
+    /// 内存布局不同时返回[None]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn component_type_id(&self, descriptor: &ComponentDescriptor) -> Option<TypeId> {
+        match self.component_id(descriptor)? {
+            ComponentId::Static(id) => Some(id),
+            ComponentId::Dynamic(_) => None,
+        }
+    }
+
+    /// 通过[ComponentDescriptor]找到已经注册的[Component]的[ComponentId],
+    /// 包括在运行时注册的[Component]
+    ///
+    /// 内存布局不同时返回[None]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn component_id(&self, descriptor: &ComponentDescriptor) -> Option<ComponentId> {
+        let (id, registered) = self.descriptors.get(descriptor.name)?;
+        registered.same_layout(descriptor).then_some(*id)
+    }
+
+    /// 访问[Entity]的[Component]
+    ///
+    /// 与[Command::fetch]不同,会返回失败的原因
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn try_fetch<F: WorldFetch>(&mut self, entity: Entity) -> Result<F::Item<'_>, FetchError> {
+        // 还是不要滥用语法糖
+        // let true = self.alive(entity).unwrap_or(false) else{
+        //     return None;
+        // };
+
+        // 脱糖
+        let Some(location) = self.entities.get(entity) else {
+            return Err(FetchError::NoSuchEntity(entity));
+        };
+        #[cfg(feature = "debug-borrows")]
+        {
+            let mut alias_map = crate::system::state::AliasMap::new();
+            F::alias_conflict(&mut alias_map);
+            self.borrows.check_access(&alias_map);
+        }
+        // Entity有效,说明chunk存在
+        let chunk = &self.chunks[location.chunk];
+        let meta = self
+            .metas
+            .get_mut(&chunk.bundle_id())
+            .unwrap_or_else(|| unreachable!("Chunk中的Bundle一定被注册过"));
+        let bundle = meta.name();
+        let row = chunk.row(location.row).with_sparse(&self.sparse);
+        let Some(mapping_table) = meta.fetch::<F>().filter(|table| F::matches(row, table)) else {
+            return Err(FetchError::Mismatch {
+                entity,
+                bundle,
+                fetch: std::any::type_name::<F>(),
+            });
+        };
+        let change_ticks = (self.last_run, self.change_tick);
+        Ok(unsafe { F::build(row, mapping_table, entity, change_ticks) })
+    }
+
+    /// 从[World]中删除[Entity]和它的所有[Component]
+    ///
+    /// 返回[Entity]是否存在,同[Command::remove]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        self.remove(entity)
+    }
+
+    /// 记录生成了一个[Entity],并通知观察者
+    ///
+    /// 在[Entity]的所有[Component]都放入之后调用
+    ///
+    /// [Component]: crate::bundle::Component
+    #[allow(unused_variables)]
+    pub(crate) fn record_spawn(&mut self, entity: Entity) {
+        self.spawned += 1;
+        #[cfg(feature = "system")]
+        self.trigger_observers(false, entity);
+    }
+
+    /// 添加一个[Entity]被删除时调用的函数
+    ///
+    /// 同一个id只会添加一次
+    pub(crate) fn on_despawn(&mut self, id: TypeId, f: fn(&mut World, Entity)) {
+        if self.despawn_hooks.iter().all(|(exist, _)| *exist != id) {
+            self.despawn_hooks.push((id, f));
+        }
+    }
+
+    /// 删除所有[Entity],返回实际被删除的数量
+    ///
+    /// 已经失效的[Entity]会被跳过
+    pub fn despawn_batch(&mut self, entities: impl IntoIterator<Item = Entity>) -> usize {
+        entities
+            .into_iter()
+            .filter(|entity| self.remove(*entity))
+            .count()
+    }
+
+    /// 一次性将大量同类型[Bundle]放入[World],返回对应的[Entity]
+    ///
+    /// 根据迭代器的[Iterator::size_hint]预先分配[Entity]和[Chunk]的空间,
+    /// 并且只查询一次[Bundle]的信息,比逐个[Command::spawn]快得多
+    ///
+    /// ``` rust
+    /// use trecs::World;
+    ///
+    /// let mut world = World::new();
+    /// let entities = world.spawn_batch((0..100_000).map(|i| (i, i as u64)));
+    /// assert_eq!(entities.len(), 100_000);
+    /// ```
+    pub fn spawn_batch<B: Bundle, I: IntoIterator<Item = B>>(&mut self, bundles: I) -> Vec<Entity> {
+        if sparse::has_sparse::<B>() {
+            return bundles.into_iter().map(|b| self.spawn(b)).collect();
+        }
+        // 注册&&准备meta
+        self.register::<B>();
+        let bundle_id = BundleId::of::<B>();
+        let len = B::components_ids().len();
+
+        let mut bundles = bundles.into_iter().peekable();
+        let reserved = bundles.size_hint().0;
+        self.entities.reserve(reserved);
+        self.reserve_chunks(bundle_id, reserved);
+
+        let mut entities = Vec::with_capacity(reserved);
+        let tick = self.change_tick;
+        let named = self.has_name(bundle_id);
+
+        // 逐个填满区块
+        while bundles.peek().is_some() {
+            let chunk = self.free_chunk(bundle_id);
+            let free = self.chunks[chunk].free();
+            for b in bundles.by_ref().take(free) {
+                let entity = self.entities.alloc();
+                let ticks = (0..len).map(|_| ComponentTicks::new(tick)).collect();
+                let row = self.chunks[chunk].push_bundle(entity, b, ticks);
+                self.entities.set(entity, EntityLocation { chunk, row });
+                if named {
+                    self.index_name(entity);
+                }
+                entities.push(entity);
+            }
+        }
+        for &entity in &entities {
+            self.trigger_on_add(entity, B::components_ids());
+            self.record_spawn(entity);
+        }
+        entities
+    }
+
+    /// 确保存放bundle_id的区块中至少有additional个空闲位置
+    fn reserve_chunks(&mut self, bundle_id: BundleId, additional: usize) {
+        let free: usize = self.metas[&bundle_id]
+            .chunks
+            .iter()
+            .map(|&cid| self.chunks[cid].free())
+            .sum();
+        let missing = additional.saturating_sub(free);
+        for _ in 0..missing.div_ceil(CHUNK_SIZE) {
+            self.new_chunk(bundle_id);
+        }
+    }
+
+    /// 创建一个新的区块,并且返回它的下标
+    ///
+    /// 防止诸如"meta和实际不一致","chunk.index不正确"等错位问题
+    pub(crate) fn new_chunk(&mut self, bundle_id: BundleId) -> usize {
+        let index = self.chunks.len();
+        let meta = self.metas.get_mut(&bundle_id).unwrap();
+        meta.chunks.push(index);
+        self.chunks
+            .push(Chunk::new(index, bundle_id, &meta.vtables));
+        index
+    }
+
+    /// 一个还有空闲空间的存放bundle_id的区块的下标,没有时创建一个新的
+    pub(crate) fn free_chunk(&mut self, bundle_id: BundleId) -> usize {
+        let meta = &self.metas[&bundle_id];
+        meta.chunks
+            .iter()
+            .copied()
+            .find(|&cid| self.chunks[cid].free() != 0)
+            .unwrap_or_else(|| self.new_chunk(bundle_id))
+    }
+}
+
+/// 独占的[System] : 参数为`&mut World`的[System]
+///
+/// 可以进行任何操作,如直接生成[Entity],加载场景等,
+/// 不能与其他访问[World]的参数共存
+///
+/// 总是在主线程中单独执行
+///
+/// ``` rust
+/// use trecs::{tools::Command, World};
+///
+/// let mut world = World::new();
+/// world.add_system(|world: &mut World| {
+///     world.spawn(1);
+/// });
+/// world.run_once();
+/// ```
+#[cfg(feature = "system")]
+impl SystemParm for &mut World {
+    type Item<'w> = &'w mut World;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        world.world_mut()
+    }
+
+    fn init(state: &mut crate::system::state::SystemState) {
+        if state.world {
+            state.conflicts.push(crate::system::ParamConflict::World);
+        }
+        state.world = true;
+        // World中可能有NonSend的资源
+        state.main_thread = true;
+    }
+}
+
+#[cfg(feature = "system")]
+impl World {
+    /// 立即执行一次[System],不会添加到[World]中
+    ///
+    /// 参数之间有冲突时在执行之前panic,见[World::try_add_system],
+    /// 返回的错误交给[World::set_error_handler]设置的函数处理
+    #[cfg(not(feature = "async"))]
+    pub fn exec<M, S: InnerSystem<M>>(&mut self, mut s: S) {
+        let mut state = s.init();
+        let conflicts = state.take_conflicts();
+        if !conflicts.is_empty() {
+            let system = s.name();
+            panic!("{}", SystemRegistrationError { system, conflicts });
+        }
+        s.prepare(self);
+        // 没有保存上一次执行的tick,所有Component都被看作新的
+        self.advance_tick(0);
+        #[cfg(feature = "debug-borrows")]
+        self.borrows.begin(s.name());
+        // 只执行一次,Local的值不会被保留
+        let locals = Locals::default();
+        let cell = unsafe { self.as_unsafe_world_cell().with_locals(&locals) };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _borrows = unsafe { cell.borrow_res(s.name(), &state.res) };
+            s.run_once(s.build_args(cell))
+        }));
+        #[cfg(feature = "debug-borrows")]
+        self.borrows.end();
+        self.last_run = 0;
+        let output = result.unwrap_or_else(|payload| {
+            self.discard_commands();
+            std::panic::resume_unwind(payload);
+        });
+        self.apply_commands();
+        if let Err(error) = output {
+            self.handle_error(s.name(), error);
+        }
+    }
+
+    /// 异步版本的[World::exec],等待返回的[Future]结束
+    ///
+    /// 参数之间有冲突时在执行之前panic,推迟的操作在等待之前执行,
+    /// 返回的错误交给[World::set_error_handler]设置的函数处理
+    #[cfg(feature = "async")]
+    pub async fn exec<M, S: InnerSystem<M>>(&mut self, mut s: S) {
+        let mut state = s.init();
+        let conflicts = state.take_conflicts();
+        if !conflicts.is_empty() {
+            let system = s.name();
+            panic!("{}", SystemRegistrationError { system, conflicts });
+        }
+        s.prepare(self);
+        // 没有保存上一次执行的tick,所有Component都被看作新的
+        self.advance_tick(0);
+        #[cfg(feature = "debug-borrows")]
+        self.borrows.begin(s.name());
+        // 只执行一次,Local的值不会被保留
+        let locals = Locals::default();
+        let cell = unsafe { self.as_unsafe_world_cell().with_locals(&locals) };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            s.run_once(s.build_args(cell))
+        }));
+        #[cfg(feature = "debug-borrows")]
+        self.borrows.end();
+        self.last_run = 0;
+        let future = result.unwrap_or_else(|payload| {
+            self.discard_commands();
+            std::panic::resume_unwind(payload);
+        });
+        self.apply_commands();
+        let Some(future) = future else {
+            return;
+        };
+        let output = self
+            .wait_system(future)
+            .await
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload));
+        if let Err(error) = output {
+            self.handle_error(s.name(), error);
+        }
+    }
+
+    /// 为即将执行的[System]增加tick,并且记录它上一次执行时的tick
+    ///
+    /// 返回这一次执行的tick,[System]需要保存它,作为下一次执行时的last_run
+    pub(crate) fn advance_tick(&mut self, last_run: usize) -> usize {
+        self.change_tick += 1;
+        self.last_run = last_run;
+        self.change_tick
+    }
+
+    /// 启用[Diagnostics]
+    ///
+    /// 之后每次循环都会记录诊断信息,可以通过[Diagnostics]资源读取
+    pub fn enable_diagnostics(&mut self) -> &mut Self {
+        self.get_res::<Diagnostics>().get_or_init(Diagnostics::new);
+        self
+    }
+
+    /// 将一次循环的开始时间,每个[System]的耗时,以及[Entity]的数量记录到[Diagnostics]
+    ///
+    /// 没有启用[Diagnostics]时什么都不做
+    fn record_diagnostics(&mut self, start: Instant, timings: Vec<(&'static str, Duration)>) {
+        let archetypes = self
+            .metas
+            .values()
+            .map(|meta| {
+                let len = meta.chunks.iter().map(|&cid| self.chunks[cid].len()).sum();
+                (meta.name(), len)
+            })
+            .collect();
+        let (spawned, despawned) = (self.spawned, self.despawned);
+
+        let Some(mut res) = self.try_get_res::<Diagnostics>() else {
+            return;
+        };
+        let Some(diagnostics) = res.get_mut() else {
+            return;
+        };
+        diagnostics.record_frame(start);
+        diagnostics.record_entities(archetypes, spawned, despawned);
+        diagnostics.record_systems(timings);
+    }
+
+    /// 当前[System]的执行顺序
+    ///
+    /// 可以通过[ScheduleGraph::to_dot]或者[ScheduleGraph::to_mermaid]导出
+    pub fn schedule_graph(&self) -> ScheduleGraph {
+        let mut graph = ScheduleGraph::new();
+        let mut order = vec![];
+        // 第一个startup阶段之前没有同步点
+        for stage in StartupStage::ALL {
+            let systems = &self.startup_systems[stage as usize];
+            if systems.is_empty() {
+                continue;
+            }
+            if !order.is_empty() {
+                order.push(graph.add_node("sync", NodeKind::SyncPoint));
+            }
+            let nodes = crate::system::sorted_or_unchanged(systems, &self.schedule.sets)
+                .into_iter()
+                .map(|sys| graph.add_node(sys.name(), NodeKind::System))
+                .collect::<Vec<_>>();
+            order.extend(&nodes);
+            graph.add_set(stage.name(), nodes);
+        }
+        // 每个阶段之前都有一个同步点,跳过空的阶段
+        for stage in Stage::ALL {
+            let systems = self.schedule.stage(stage);
+            if systems.is_empty() {
+                continue;
+            }
+            order.push(graph.add_node("sync", NodeKind::SyncPoint));
+            let nodes = systems
+                .iter()
+                .map(|sys| graph.add_node(sys.name(), NodeKind::System))
+                .collect::<Vec<_>>();
+            order.extend(&nodes);
+            graph.add_set(stage.name(), nodes);
+        }
+        graph.chain(&order);
+        graph
+    }
+
+    /// 访问冲突,并且没有确定先后顺序的[System]
+    ///
+    /// 有顺序约束(包括间接的)的[System]之间的顺序是确定的,见[IntoSystem]
+    pub fn ambiguities(&self) -> AmbiguityReport {
+        let mut ambiguities = vec![];
+        let mut stages = StartupStage::ALL
+            .map(|stage| (stage.name(), &self.startup_systems[stage as usize]))
+            .to_vec();
+        stages.extend(Stage::ALL.map(|stage| (stage.name(), self.schedule.stage(stage))));
+        for (stage, systems) in stages {
+            let ordered = self.schedule.ordered(systems);
+            for (index, first) in systems.iter().enumerate() {
+                for (other, second) in systems.iter().enumerate().skip(index + 1) {
+                    if ordered[index][other] || ordered[other][index] {
+                        continue;
+                    }
+                    let conflicts = first.state.conflicts(&second.state);
+                    if !conflicts.is_empty() {
+                        ambiguities.push(Ambiguity {
+                            stage,
+                            systems: (first.name(), second.name()),
+                            conflicts,
+                        });
+                    }
+                }
+            }
+        }
+        AmbiguityReport { ambiguities }
+    }
+
+    /// 只能在主线程运行的[System]的名字
+    ///
+    /// 访问了[NonSend]或者[Resources]的[System]都不能被并行执行
+    pub fn main_thread_systems(&self) -> Vec<&'static str> {
+        self.startup_systems
+            .iter()
+            .flatten()
+            .chain(self.schedule.systems())
+            .filter(|system| system.state.main_thread)
+            .map(System::name)
+            .collect()
+    }
+
+    /// 添加一个[System]
+    ///
+    /// 每次循环都会执行,可以通过[IntoSystem]指定顺序
+    ///
+    /// 在[World::run_once]执行期间添加时,从下一帧开始执行
+    ///
+    /// [System]的参数之间有冲突时会panic,见[World::try_add_system]
+    pub fn add_system<M, S: IntoSystem<M>>(&mut self, system: S) -> &mut Self {
+        self.try_add_system(system)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// 添加一个[System]
+    ///
+    /// [System]的参数之间有冲突时返回[SystemRegistrationError],并且不会添加
+    pub fn try_add_system<M, S: IntoSystem<M>>(
+        &mut self,
+        system: S,
+    ) -> Result<&mut Self, SystemRegistrationError> {
+        self.schedule.add(system.into_system()?);
+        Ok(self)
+    }
+
+    /// 移除带有label的所有[System]
+    ///
+    /// 在[World::run_once]执行期间移除时,这一帧结束时才生效
+    ///
+    /// ``` rust
+    /// use trecs::{system::IntoSystem, World};
+    ///
+    /// fn hot_reload(world: &mut World) {
+    ///     world.remove_system("logic").add_system(new_logic.label("logic"));
+    /// }
+    /// fn old_logic() {}
+    /// fn new_logic() {}
+    ///
+    /// let mut world = World::new();
+    /// world.add_system(old_logic.label("logic")).add_system(hot_reload);
+    /// world.run_once();
+    /// ```
+    pub fn remove_system(&mut self, label: &'static str) -> &mut Self {
+        for schedule in self.schedules.values_mut() {
+            schedule.remove(label);
+        }
+        self.schedule.remove(label);
+        self
+    }
+
+    /// 添加一个[System]到名为schedule的schedule中,它只会被[World::run_schedule]执行
+    ///
+    /// schedule不存在时会被创建,标签,[SystemSet]和[World::set_enabled]对所有schedule都有效
+    ///
+    /// [System]的参数之间有冲突时会panic,见[World::try_add_system]
+    ///
+    /// ``` rust
+    /// use trecs::{world::ResMut, World};
+    ///
+    /// fn render(frames: ResMut<usize>) {
+    ///     *frames.into_or_init(|| 0) += 1;
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world.add_system_to("render", render);
+    /// world.run_once();
+    /// world.run_schedule("render").run_schedule("render");
+    /// assert_eq!(world.resource::<usize>(), Some(&2));
+    /// ```
+    pub fn add_system_to<M, S: IntoSystem<M>>(
+        &mut self,
+        schedule: &'static str,
+        system: S,
+    ) -> &mut Self {
+        self.try_add_system_to(schedule, system)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// 同[World::add_system_to],[System]的参数之间有冲突时返回[SystemRegistrationError]
+    pub fn try_add_system_to<M, S: IntoSystem<M>>(
+        &mut self,
+        schedule: &'static str,
+        system: S,
+    ) -> Result<&mut Self, SystemRegistrationError> {
+        let system = system.into_system()?;
+        let sets = &self.schedule.sets;
+        self.schedules
+            .entry(schedule)
+            .or_insert_with(|| Schedule::with_sets(sets.clone()))
+            .add(system);
+        Ok(self)
+    }
+
+    /// 执行一次名为name的schedule中的所有[System],不存在时什么都不做
+    ///
+    /// 与[World::run_once]相同,依次执行每个[Stage],发生panic时按照[PanicPolicy]处理,
+    /// 但是不会更新时间和[Diagnostics],也不会结束这一帧
+    ///
+    /// 执行期间添加到这个schedule的[System]在执行结束时生效
+    #[cfg(not(feature = "async"))]
+    pub fn run_schedule(&mut self, name: &str) -> &mut Self {
+        self.check_poison();
+        let Some((name, mut schedule)) = self.schedules.remove_entry(name) else {
+            return self;
+        };
+        if schedule.changed() {
+            schedule.build().unwrap_or_else(|e| panic!("{e}"));
+        }
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("schedule", name).entered();
+        let mut timings = vec![];
+        let mut panicked = None;
+        schedule.set_running(true);
+        for stage in Stage::ALL {
+            if panicked.is_some() {
+                break;
+            }
+            let mut systems = std::mem::take(schedule.stage_mut(stage));
+            panicked = self.run_stage(&mut systems, &mut timings);
+            *schedule.stage_mut(stage) = systems;
+        }
+        schedule.set_running(false);
+        // 执行期间通过World::add_system_to添加的System
+        if let Some(mut added) = self.schedules.remove(name) {
+            for system in added.take_systems() {
+                schedule.add(system);
+            }
+        }
+        self.schedules.insert(name, schedule);
+        if let Some((system, payload)) = panicked {
+            self.on_panic(system, payload);
+        }
+        self
+    }
+
+    /// 为带有set的标签的所有[System]指定顺序
+    ///
+    /// 同一个标签再次配置时会覆盖之前的配置
+    pub fn configure_set(&mut self, set: SystemSet) -> &mut Self {
+        for schedule in self.schedules.values_mut() {
+            schedule.configure_set(set.clone());
+        }
+        self.schedule.configure_set(set);
+        self
+    }
+
+    /// 启用或者禁用带有label的所有[System],或者名字为label的[System]
+    ///
+    /// 被禁用的[System]会被跳过,但是不会被移除,也不需要重新排序,
+    /// [System]的名字就是它的类型名,可以通过[std::any::type_name_of_val]获得
+    pub fn set_enabled(&mut self, label: &'static str, enabled: bool) -> &mut Self {
+        self.schedule.set_enabled(label, enabled);
+        self
+    }
+
+    /// 带有label的,或者名字为label的[System]是否被启用,默认都是启用的
+    pub fn is_enabled(&self, label: &str) -> bool {
+        self.schedule.is_label_enabled(label)
+    }
+
+    /// 按照顺序约束对每个[Stage]中的[System]排序,没有约束的[System]之间保持添加的顺序
+    ///
+    /// 添加了新的[System]之后,[World::run_once]会自动调用,约束形成环时会panic
+    ///
+    /// 约束形成环时返回[ScheduleError],并且不会改变顺序
+    pub fn build_schedule(&mut self) -> Result<&mut Self, ScheduleError> {
+        self.schedule.build()?;
+        Ok(self)
+    }
+
+    /// 添加了新的[System]时重新排序
+    fn update_schedule(&mut self) {
+        if self.schedule.changed() {
+            self.build_schedule().unwrap_or_else(|e| panic!("{e}"));
+        }
+    }
+
+    /// 执行一个阶段中所有被启用,并且没有被隔离的[System]
+    ///
+    /// 返回第一个发生panic的[System]的名字,以及panic的内容
+    #[cfg(not(feature = "async"))]
+    fn run_stage(
+        &mut self,
+        systems: &mut [System],
+        timings: &mut Vec<(&'static str, Duration)>,
+    ) -> Option<(&'static str, Box<dyn Any + Send>)> {
+        let systems = systems
+            .iter_mut()
+            .filter(|sys| sys.isolated.is_none() && self.schedule.is_enabled(sys))
+            .collect::<Vec<_>>();
+        match self.executor {
+            Executor::SingleThreaded => {
+                for sys in systems {
+                    #[cfg(feature = "trace")]
+                    let _span = tracing::info_span!("system", name = sys.name()).entered();
+                    let start = Instant::now();
+                    match sys.run_once(self) {
+                        Ok(()) => {}
+                        Err(payload) if self.panic_policy == PanicPolicy::Isolate => {
+                            self.isolate(sys, payload);
+                            continue;
+                        }
+                        Err(payload) => return Some((sys.name(), payload)),
+                    }
+                    timings.push((sys.name(), start.elapsed()));
+                }
+                None
+            }
+            Executor::MultiThreaded => crate::system::run_parallel(self, systems, timings),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    async fn run_stage(
+        &mut self,
+        systems: &mut [System],
+        timings: &mut Vec<(&'static str, Duration)>,
+    ) -> Option<(&'static str, Box<dyn Any + Send>)> {
+        let mut systems = systems
+            .iter_mut()
+            .filter(|sys| sys.isolated.is_none() && self.schedule.is_enabled(sys))
+            .collect::<Vec<_>>();
+        if self.executor == Executor::Concurrent {
+            return crate::system::run_concurrent(&mut systems, self, timings).await;
+        }
+        for sys in systems {
+            let name = sys.name();
+            let start = Instant::now();
+            let run = sys.run_once(self);
+            #[cfg(feature = "trace")]
+            let run = tracing::Instrument::instrument(run, tracing::info_span!("system", name));
+            match run.await {
+                Ok(()) => {}
+                Err(payload) if self.panic_policy == PanicPolicy::Isolate => {
+                    self.isolate(sys, payload);
+                    continue;
+                }
+                Err(payload) => return Some((name, payload)),
+            }
+            timings.push((name, start.elapsed()));
+        }
+        None
+    }
+
+    /// 切换所有[State],执行对应的[OnEnter]和[OnExit]
+    ///
+    /// 返回第一个发生panic的[System]的名字,以及panic的内容
+    ///
+    /// [State]: crate::state::State
+    /// [OnEnter]: crate::state::OnEnter
+    /// [OnExit]: crate::state::OnExit
+    #[cfg(not(feature = "async"))]
+    fn apply_state_transitions(
+        &mut self,
+        timings: &mut Vec<(&'static str, Duration)>,
+    ) -> Option<(&'static str, Box<dyn Any + Send>)> {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("stage", name = "state_transition").entered();
+        for index in 0..self.state_transitions.len() {
+            let panicked = (self.state_transitions[index].1)(self, timings);
+            if panicked.is_some() {
+                return panicked;
+            }
+        }
+        None
+    }
+
+    /// 异步版本的[World::apply_state_transitions]
+    #[cfg(feature = "async")]
+    async fn apply_state_transitions(
+        &mut self,
+        timings: &mut Vec<(&'static str, Duration)>,
+    ) -> Option<(&'static str, Box<dyn Any + Send>)> {
+        for index in 0..self.state_transitions.len() {
+            let apply = (self.state_transitions[index].1)(self, timings);
+            #[cfg(feature = "trace")]
+            let apply = tracing::Instrument::instrument(
+                apply,
+                tracing::info_span!("stage", name = "state_transition"),
+            );
+            let panicked = apply.await;
+            if panicked.is_some() {
+                return panicked;
+            }
+        }
+        None
+    }
+
+    /// 这一帧中stage需要执行的次数
+    ///
+    /// [Stage::FixedUpdate]由[FixedTime]决定,其他阶段都只执行一次
+    ///
+    /// [FixedTime]: crate::time::FixedTime
+    fn stage_runs(&mut self, stage: Stage) -> usize {
+        match stage {
+            Stage::FixedUpdate => self.fixed_steps(),
+            _ => 1,
+        }
+    }
+
+    /// 添加一个[System]
+    ///
+    /// 只会在刚开始循环时执行一次,可以通过[IntoSystem::in_startup_stage]指定[StartupStage],
+    /// 同一阶段中的startup system按照[IntoSystem]指定的顺序约束执行,
+    /// 没有约束的startup system之间保持添加的顺序
+    ///
+    /// [System]的参数之间有冲突时会panic,见[World::try_add_startup_system]
+    pub fn add_startup_system<M, S: IntoSystem<M>>(&mut self, system: S) -> &mut Self {
+        self.try_add_startup_system(system)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// 添加一个只会在刚开始循环时执行一次的[System]
+    ///
+    /// [System]的参数之间有冲突时返回[SystemRegistrationError],并且不会添加
+    pub fn try_add_startup_system<M, S: IntoSystem<M>>(
+        &mut self,
+        system: S,
+    ) -> Result<&mut Self, SystemRegistrationError> {
+        let system = system.into_system()?;
+        self.startup_systems[system.startup_stage() as usize].push(system);
+        Ok(self)
+    }
+
+    /// 进入一个死循环,直到有[System]发送了[AppExit]或者线程终结
+    ///
+    /// 在执行一次所有被添加进startup_systems的[System]后
+    ///
+    /// 会进入循环,每次循环执行systems里的所有[System]
+    ///
+    /// [AppExit]: crate::app::AppExit
+    #[cfg(not(feature = "async"))]
+    pub fn run(&mut self) {
+        self.run_until(|_| false)
+    }
+    #[cfg(feature = "async")]
+    pub async fn run(&mut self) {
+        self.run_until(|_| false).await;
+    }
+
+    /// 执行还没有执行过的startup system,然后执行一帧
+    ///
+    /// 用于把[World]嵌入已有的循环或者测试中,[World::run_until]的每次循环就是一次[World::update]
+    ///
+    /// 同[World::run_until]一样,没有启用[Time]时会启用使用真实时间的[Time],
+    /// 被污染时不会执行
+    ///
+    /// ``` rust
+    /// use trecs::{world::ResMut, World};
+    ///
+    /// let mut world = World::new();
+    /// world
+    ///     .add_startup_system(|frames: ResMut<u32>| {
+    ///         frames.into_or_init(|| 10);
+    ///     })
+    ///     .add_system(|frames: ResMut<u32>| *frames.into_or_init(|| 0) += 1);
+    ///
+    /// // 由外部的循环驱动
+    /// for _ in 0..3 {
+    ///     world.update();
+    /// }
+    /// assert_eq!(world.resource::<u32>(), Some(&13));
+    /// ```
+    ///
+    /// [Time]: crate::time::Time
+    #[cfg(not(feature = "async"))]
+    pub fn update(&mut self) -> &mut Self {
+        self.init_time();
+        self.startup();
+        if self.poisoned.is_none() {
+            self.run_once();
+        }
+        self
+    }
+    #[cfg(feature = "async")]
+    pub async fn update(&mut self) -> &mut Self {
+        self.init_time();
+        self.startup().await;
+        if self.poisoned.is_none() {
+            self.run_once().await;
+        }
+        self
+    }
+
+    #[cfg(not(feature = "async"))]
+    /// 循环执行,直到until返回true
+    ///
+    /// 每次循环开始前把[World]传给until,可以根据资源等决定是否停止
+    ///
+    /// [World]被污染,或者有[System]发送了[AppExit]时也会在这一帧结束后停止,
+    /// 见[PanicPolicy::Poison]和[World::exit_requested]
+    ///
+    /// 没有启用[Time]时会启用使用真实时间的[Time]
+    ///
+    /// 默认会尽可能快地循环,可以通过[World::set_tick_rate]限制帧率
+    ///
+    /// ``` rust
+    /// use trecs::{world::ResMut, World};
+    ///
+    /// struct Score(u32);
+    ///
+    /// let mut world = World::new();
+    /// world
+    ///     .add_system(|score: ResMut<Score>| score.into_or_init(|| Score(0)).0 += 1)
+    ///     .run_until(|world| world.resource::<Score>().is_some_and(|score| score.0 == 3));
+    /// assert_eq!(world.resource::<Score>().map(|score| score.0), Some(3));
+    /// ```
+    ///
+    /// [Time]: crate::time::Time
+    /// [AppExit]: crate::app::AppExit
+    pub fn run_until<F>(&mut self, mut until: F)
+    where
+        F: FnMut(&World) -> bool,
+    {
+        self.init_time();
+        let mut next_tick = Instant::now();
+        loop {
+            if until(self) || self.poisoned.is_some() {
+                return;
+            }
+            self.update();
+            if self.exit_requested() {
+                return;
+            }
+            if let Some(wait) = self.tick_wait(&mut next_tick) {
+                std::thread::sleep(wait);
+            }
+        }
+    }
 
-impl World {
-    pub fn new() -> Self {
-        Self {
-            chunks: vec![],
-            metas: Default::default(),
-            #[cfg(feature = "system")]
-            startup_systems: vec![],
-            #[cfg(feature = "system")]
-            systems: vec![],
-            resources: Default::default(),
-            resources_dropers: Default::default(),
+    /// 按照[World::set_tick_rate]计算这一帧结束后需要等待的时间,并且更新下一帧开始的时间
+    fn tick_wait(&self, next_tick: &mut Instant) -> Option<Duration> {
+        let interval = self.tick_interval?;
+        *next_tick += interval;
+        let now = Instant::now();
+        let wait = next_tick.checked_duration_since(now);
+        if wait.is_none() {
+            // 落后时不追赶,从现在开始重新计时
+            *next_tick = now;
         }
+        wait
     }
 
-    /// 创建一个新的区块,并且返回它的可变引用
+    /// 限制[World::run_until]和[World::run]每秒最多执行ticks_per_second帧
     ///
-    /// 防止诸如"meta和实际不一致","chunk.index不正确"等错位问题
-    pub(crate) fn new_chunk<B: Bundle>(&mut self) -> &mut Chunk {
-        self.metas
-            .get_mut(&B::type_id_())
-            .unwrap()
-            .chunks
-            .push(self.chunks.len());
-        self.chunks.push(Chunk::new::<B>(self.chunks.len()));
-        self.chunks.last_mut().unwrap()
+    /// 一帧提前结束时会sleep到下一帧开始的时间,而不是空转占满一个核心;
+    /// 一帧执行得太慢时不会为了追赶而连续执行
+    ///
+    /// ticks_per_second为0时不限制,这也是默认值
+    ///
+    /// 实际每帧的间隔见[Time::delta],每帧执行所用的时间见[FrameDiagnostic::busy]
+    ///
+    /// # Panics
+    ///
+    /// ticks_per_second为负数,无穷大或者NaN时panic
+    ///
+    /// [Time::delta]: crate::time::Time::delta
+    /// [FrameDiagnostic::busy]: crate::diagnostic::FrameDiagnostic::busy
+    pub fn set_tick_rate(&mut self, ticks_per_second: f64) -> &mut Self {
+        assert!(
+            ticks_per_second.is_finite() && ticks_per_second >= 0.0,
+            "每秒的帧数必须是非负的有限数,而不是{ticks_per_second}"
+        );
+        self.tick_interval =
+            (ticks_per_second > 0.0).then(|| Duration::from_secs_f64(1.0 / ticks_per_second));
+        self
     }
-}
 
-#[cfg(feature = "system")]
-impl World {
+    /// 以每秒最多ticks_per_second帧的速度循环执行,见[World::set_tick_rate]和[World::run]
+    ///
+    /// ``` rust
+    /// use trecs::{app::AppExit, event::EventWriter, world::Local, World};
+    ///
+    /// let mut world = World::new();
+    /// world
+    ///     .add_system(|mut frames: Local<u32>, mut exit: EventWriter<AppExit>| {
+    ///         *frames += 1;
+    ///         if *frames == 5 {
+    ///             exit.send(AppExit);
+    ///         }
+    ///     })
+    ///     .run_at(100.0);
+    /// ```
     #[cfg(not(feature = "async"))]
-    pub fn exec<M, S: InnerSystem<M>>(&self, mut s: S) {
-        s.run_once(s.build_args(self));
+    pub fn run_at(&mut self, ticks_per_second: f64) {
+        self.set_tick_rate(ticks_per_second).run()
     }
-
     #[cfg(feature = "async")]
-    pub async fn exec<M, S: InnerSystem<M>>(&self, mut s: S) {
-        s.run_once(s.build_args(self)).await;
+    pub async fn run_at(&mut self, ticks_per_second: f64) {
+        self.set_tick_rate(ticks_per_second).run().await
     }
 
-    /// 添加一个[System]
-    ///
-    /// 每次循环都会执行
-    pub fn add_system<M, S: InnerSystem<M>>(&mut self, system: S) -> &mut Self {
-        self.systems.push(System::new(system));
+    /// 设置[System]发生panic时的处理方式,默认为[PanicPolicy::Propagate]
+    pub fn set_panic_policy(&mut self, policy: PanicPolicy) -> &mut Self {
+        self.panic_policy = policy;
         self
     }
 
-    /// 添加一个[System]
+    /// 设置[System]返回错误时调用的函数,默认为[panic_on_error],
+    /// 其他可选的有[log_error]和[store_error],见[SystemReturn]
     ///
-    /// 只会在刚开始循环时执行一次
-    pub fn add_startup_system<M, S: InnerSystem<M>>(&mut self, system: S) -> &mut Self {
-        self.startup_systems.push(System::new(system));
+    /// 在[System]推迟的操作执行之后调用,调用期间发生的panic算作这个[System]的
+    ///
+    /// [panic_on_error]: crate::system::panic_on_error
+    /// [log_error]: crate::system::log_error
+    /// [store_error]: crate::system::store_error
+    /// [SystemReturn]: crate::system::SystemReturn
+    pub fn set_error_handler(&mut self, handler: fn(&mut World, SystemError)) -> &mut Self {
+        self.error_handler = handler;
         self
     }
 
-    /// 进入一个死循环,直到线程终结
+    /// 把名为system的[System]返回的错误交给[World::set_error_handler]设置的函数
+    pub(crate) fn handle_error(&mut self, system: &'static str, error: BoxedError) {
+        (self.error_handler)(self, SystemError { system, error });
+    }
+
+    /// 等待异步[System]返回的[Future]结束,并且捕获期间的panic
     ///
-    /// 在执行一次所有被添加进startup_systems的[System]后
+    /// 等待期间执行[Future]通过[WorldAccess]请求的操作
     ///
-    /// 会进入循环,每次循环执行systems里的所有[System]
-    #[cfg(not(feature = "async"))]
-    pub fn run(&mut self) {
-        self.run_until(|| false)
+    /// [WorldAccess]: crate::system::WorldAccess
+    #[cfg(feature = "async")]
+    pub(crate) async fn wait_system(
+        &mut self,
+        mut future: crate::system::SystemFuture,
+    ) -> std::thread::Result<Result<(), BoxedError>> {
+        std::future::poll_fn(|cx| loop {
+            match crate::system::poll_caught(&mut future, cx) {
+                std::task::Poll::Ready(result) => return std::task::Poll::Ready(result),
+                std::task::Poll::Pending if !self.apply_access() => {
+                    return std::task::Poll::Pending
+                }
+                std::task::Poll::Pending => {}
+            }
+        })
+        .await
     }
+
+    /// 把异步[System]返回的错误交给[World::handle_error],期间发生的panic算作这个[System]的
     #[cfg(feature = "async")]
-    pub async fn run(&mut self) {
-        self.run_until(|| false).await;
+    pub(crate) fn finish_system(
+        &mut self,
+        system: &'static str,
+        output: Result<(), BoxedError>,
+    ) -> std::thread::Result<()> {
+        match output {
+            Ok(()) => Ok(()),
+            Err(error) => std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                self.handle_error(system, error)
+            })),
+        }
     }
 
-    #[cfg(not(feature = "async"))]
-    pub fn run_until<F>(&mut self, mut until: F)
-    where
-        F: FnMut() -> bool,
-    {
-        loop {
-            if until() {
-                return;
-            }
+    /// 设置执行update阶段的[System]的方式,默认为[Executor::SingleThreaded]
+    ///
+    /// startup system总是在当前线程中依次执行
+    pub fn set_executor(&mut self, executor: Executor) -> &mut Self {
+        self.executor = executor;
+        self
+    }
 
-            self.startup();
-            self.run_once();
+    /// 使[World]被污染的panic
+    ///
+    /// [World]被污染之后,[World::startup]和[World::run_once]都会panic,
+    /// 直到调用[World::clear_poison]
+    pub fn poisoned(&self) -> Option<&SystemPanic> {
+        self.poisoned.as_ref()
+    }
+
+    /// 清除污染标记,返回使[World]被污染的panic
+    ///
+    /// 被跳过的[System]会在下一帧正常执行
+    pub fn clear_poison(&mut self) -> Option<SystemPanic> {
+        self.poisoned.take()
+    }
+
+    /// 按照[PanicPolicy::Isolate]被禁用的[System],以及导致禁用的panic
+    pub fn isolated(&self) -> impl Iterator<Item = &SystemPanic> {
+        std::iter::once(&self.schedule)
+            .chain(self.schedules.values())
+            .flat_map(Schedule::systems)
+            .filter_map(|system| system.isolated.as_ref())
+    }
+
+    /// 重新启用所有被[PanicPolicy::Isolate]禁用的[System],返回导致禁用的panic
+    pub fn restore_isolated(&mut self) -> Vec<SystemPanic> {
+        std::iter::once(&mut self.schedule)
+            .chain(self.schedules.values_mut())
+            .flat_map(Schedule::systems_mut)
+            .filter_map(|system| system.isolated.take())
+            .collect()
+    }
+
+    /// 禁用发生panic的system,并且把panic记录到[Diagnostics]中
+    pub(crate) fn isolate(&mut self, system: &mut System, payload: Box<dyn Any + Send>) {
+        let panic = SystemPanic::new(system.name(), &*payload);
+        self.record_panic(panic.clone());
+        system.isolated = Some(panic);
+    }
+
+    /// 把[System]发生的panic记录到[Diagnostics]中,没有启用[Diagnostics]时什么都不做
+    fn record_panic(&mut self, panic: SystemPanic) {
+        if let Some(diagnostics) = self
+            .try_get_res::<Diagnostics>()
+            .and_then(|res| res.into_mut())
+        {
+            diagnostics.record_panic(panic);
+        }
+    }
+
+    /// [World]被污染时panic
+    fn check_poison(&self) {
+        if let Some(panic) = &self.poisoned {
+            panic!("World已经被污染: {panic}");
+        }
+    }
+
+    /// 标记[World]被污染,然后按照[PanicPolicy]处理
+    fn on_panic(&mut self, system: &'static str, payload: Box<dyn Any + Send>) {
+        self.poisoned = Some(SystemPanic::new(system, &*payload));
+        if self.panic_policy == PanicPolicy::Propagate {
+            std::panic::resume_unwind(payload);
         }
     }
 
+    /// 异步版本的[World::run_until],可以在任何异步运行时中执行
+    ///
+    /// 每帧结束后都会让出执行权,这样同一个运行时中的其他任务也可以执行;
+    /// 通过[World::set_tick_rate]限制帧率时,使用[World::set_sleep]设置的sleep函数等待,
+    /// 没有设置时使用不依赖于运行时的[sleep]
+    ///
+    /// [sleep]: crate::system::sleep
     #[cfg(feature = "async")]
     pub async fn run_until<F>(&mut self, mut until: F)
     where
-        F: FnMut() -> bool,
+        F: FnMut(&World) -> bool,
     {
+        self.init_time();
+        let mut next_tick = Instant::now();
         loop {
-            if until() {
+            if until(self) || self.poisoned.is_some() {
                 return;
             }
+            self.update().await;
+            if self.exit_requested() {
+                return;
+            }
+            match (self.tick_wait(&mut next_tick), self.sleep) {
+                (Some(wait), Some(sleep)) => sleep(wait).await,
+                (Some(wait), None) => crate::system::sleep(wait).await,
+                (None, _) => crate::system::yield_now().await,
+            }
+        }
+    }
 
-            self.startup().await;
-            self.run_once().await;
+    /// 调用所有frame_start中的函数
+    fn start_frame(&mut self) {
+        for index in 0..self.frame_start.len() {
+            (self.frame_start[index].1)(self);
+        }
+    }
+
+    /// 添加一个每次循环开始时调用的函数
+    ///
+    /// 同一个id只会添加一次
+    pub(crate) fn on_frame_start(&mut self, id: TypeId, f: fn(&mut World)) {
+        if self.frame_start.iter().all(|(exist, ..)| *exist != id) {
+            self.frame_start.push((id, f));
+        }
+    }
+
+    /// 调用所有frame_end中的函数
+    fn end_frame(&mut self) {
+        for index in 0..self.frame_end.len() {
+            (self.frame_end[index].1)(self);
+        }
+    }
+
+    /// 添加一个每次循环结束时调用的函数
+    ///
+    /// 同一个id只会添加一次
+    pub(crate) fn on_frame_end(&mut self, id: TypeId, f: fn(&mut World)) {
+        if self.frame_end.iter().all(|(exist, ..)| *exist != id) {
+            self.frame_end.push((id, f));
         }
     }
 
+    /// 执行所有还没有执行过的startup system
+    ///
+    /// 按照[StartupStage::ALL]的顺序执行各个阶段,执行期间添加的startup system也会被执行,
+    /// 总是先执行最靠前的阶段中还没有执行的startup system
+    ///
+    /// 发生panic的startup system不会再次执行,顺序约束形成环时panic
     #[cfg(not(feature = "async"))]
     pub fn startup(&mut self) -> &mut Self {
-        while let Some(mut stsys) = self.startup_systems.pop() {
-            stsys.run_once(self);
+        self.check_poison();
+        while let Some(stage) = self.next_startup_stage() {
+            #[cfg(feature = "trace")]
+            let _span = tracing::info_span!("stage", name = stage.name()).entered();
+            let mut systems = std::mem::take(&mut self.startup_systems[stage as usize]);
+            crate::system::sort_systems(&mut systems, &self.schedule.sets)
+                .unwrap_or_else(|e| panic!("{e}"));
+            let mut systems = systems.into_iter();
+            while let Some(mut stsys) = systems.next() {
+                #[cfg(feature = "trace")]
+                let _span = tracing::info_span!("system", name = stsys.name()).entered();
+                if let Err(payload) = stsys.run_once(self) {
+                    if self.panic_policy == PanicPolicy::Isolate {
+                        self.record_panic(SystemPanic::new(stsys.name(), &*payload));
+                        continue;
+                    }
+                    // 还没有执行的startup system留到下一次
+                    self.startup_systems[stage as usize].splice(0..0, systems);
+                    self.on_panic(stsys.name(), payload);
+                    break;
+                }
+            }
+            if self.poisoned.is_some() {
+                break;
+            }
         }
         self
     }
 
+    /// 第一个还有startup system没有执行的[StartupStage]
+    fn next_startup_stage(&self) -> Option<StartupStage> {
+        StartupStage::ALL
+            .into_iter()
+            .find(|&stage| !self.startup_systems[stage as usize].is_empty())
+    }
+
     #[cfg(feature = "async")]
     pub async fn startup(&mut self) -> &mut Self {
-        while let Some(mut stsys) = self.startup_systems.pop() {
-            stsys.run_once(self).await;
+        self.check_poison();
+        while let Some(stage) = self.next_startup_stage() {
+            // 不能在await期间进入span,所以只作为System的span的父级
+            #[cfg(feature = "trace")]
+            let stage_span = tracing::info_span!("stage", name = stage.name());
+            let mut systems = std::mem::take(&mut self.startup_systems[stage as usize]);
+            crate::system::sort_systems(&mut systems, &self.schedule.sets)
+                .unwrap_or_else(|e| panic!("{e}"));
+            let mut systems = systems.into_iter();
+            while let Some(mut stsys) = systems.next() {
+                let name = stsys.name();
+                let run = stsys.run_once(self);
+                #[cfg(feature = "trace")]
+                let run = tracing::Instrument::instrument(
+                    run,
+                    tracing::info_span!(parent: &stage_span, "system", name),
+                );
+                if let Err(payload) = run.await {
+                    if self.panic_policy == PanicPolicy::Isolate {
+                        self.record_panic(SystemPanic::new(name, &*payload));
+                        continue;
+                    }
+                    // 还没有执行的startup system留到下一次
+                    self.startup_systems[stage as usize].splice(0..0, systems);
+                    self.on_panic(name, payload);
+                    break;
+                }
+            }
+            if self.poisoned.is_some() {
+                break;
+            }
         }
         self
     }
 
     /// 执行一次所有system
+    ///
+    /// 有[System]发生panic时,会按照[PanicPolicy]处理
     #[cfg(not(feature = "async"))]
     pub fn run_once(&mut self) {
-        let this = unsafe {
-            // stable没下面的"cast_ref_to_mut" 所以需要下面的allow
-            #[allow(unknown_lints)]
-            // nightly版本会deny 所以这需要allow
-            #[allow(clippy::cast_ref_to_mut)]
-            &mut *(self as *const _ as *mut World)
-        };
-        for sys in &mut self.systems {
-            sys.run_once(this);
+        self.check_poison();
+        self.update_schedule();
+        let frame_start = Instant::now();
+        self.update_time();
+        self.start_frame();
+        let mut timings = vec![];
+        let mut panicked = self.apply_state_transitions(&mut timings);
+        self.schedule.set_running(true);
+        for stage in Stage::ALL {
+            for _ in 0..self.stage_runs(stage) {
+                if panicked.is_some() {
+                    break;
+                }
+                #[cfg(feature = "trace")]
+                let _span = tracing::info_span!("stage", name = stage.name()).entered();
+                // 执行期间先把systems取出来,这样System就可以独占World
+                let mut systems = std::mem::take(self.schedule.stage_mut(stage));
+                panicked = self.run_stage(&mut systems, &mut timings);
+                // 即使发生了panic也要放回去
+                *self.schedule.stage_mut(stage) = systems;
+            }
+        }
+        // 执行期间添加和移除的System在这一帧结束时生效
+        self.schedule.set_running(false);
+        if let Some((system, payload)) = panicked {
+            // 这一帧不会正常结束
+            return self.on_panic(system, payload);
         }
+        self.record_diagnostics(frame_start, timings);
+        self.end_frame();
     }
     #[cfg(feature = "async")]
     pub async fn run_once(&mut self) {
-        let this = unsafe {
-            // stable没下面的"cast_ref_to_mut" 所以需要下面的allow
-            #[allow(unknown_lints)]
-            // nightly版本会deny 所以这需要allow
-            #[allow(clippy::cast_ref_to_mut)]
-            &mut *(self as *const _ as *mut World)
-        };
-        for sys in &mut self.systems {
-            sys.run_once(this).await;
+        self.check_poison();
+        self.update_schedule();
+        let frame_start = Instant::now();
+        self.update_time();
+        self.start_frame();
+        let mut timings = vec![];
+        let mut panicked = self.apply_state_transitions(&mut timings).await;
+        self.schedule.set_running(true);
+        for stage in Stage::ALL {
+            for _ in 0..self.stage_runs(stage) {
+                if panicked.is_some() {
+                    break;
+                }
+                // 执行期间先把systems取出来,这样System就可以独占World
+                let mut systems = std::mem::take(self.schedule.stage_mut(stage));
+                let run = self.run_stage(&mut systems, &mut timings);
+                #[cfg(feature = "trace")]
+                let run = tracing::Instrument::instrument(
+                    run,
+                    tracing::info_span!("stage", name = stage.name()),
+                );
+                panicked = run.await;
+                // 即使发生了panic也要放回去
+                *self.schedule.stage_mut(stage) = systems;
+            }
+        }
+        // 执行期间添加和移除的System在这一帧结束时生效
+        self.schedule.set_running(false);
+        if let Some((system, payload)) = panicked {
+            // 这一帧不会正常结束
+            return self.on_panic(system, payload);
         }
+        self.record_diagnostics(frame_start, timings);
+        self.end_frame();
     }
 }
 
@@ -196,192 +1688,127 @@ impl Default for World {
     }
 }
 
-/// 反转[Result<T,E>]的T和E
-///
-/// 为了蹭语法糖写的垃圾
-fn rev_result<T, E>(result: Result<T, E>) -> Result<E, T> {
-    match result {
-        Ok(o) => Err(o),
-        Err(e) => Ok(e),
-    }
-}
-
 impl Command for World {
+    /// 包含重复的[Component]的[Bundle]会panic,见[World::try_register]
+    ///
+    /// [Component]: crate::bundle::Component
     fn register<B: crate::bundle::Bundle>(&mut self) {
-        let bundle_id = B::type_id_();
-        self.metas
-            .entry(bundle_id)
-            .or_insert_with(|| BundleMeta::new::<B>());
+        self.try_register::<B>().unwrap_or_else(|e| panic!("{e}"))
     }
 
+    /// [StorageType::SparseSet]的[Component]会被放入单独的[SparseSet],
+    /// 其余的[Component]放入对应布局的[Chunk]
+    ///
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    /// [Component]: crate::bundle::Component
     fn spawn<B: crate::bundle::Bundle>(&mut self, b: B) -> crate::storage::Entity {
+        if sparse::has_sparse::<B>() {
+            let entity = self.entities.alloc();
+            self.place(entity, vec![]);
+            self.insert(entity, b);
+            self.record_spawn(entity);
+            return entity;
+        }
         self.register::<B>();
-        let bundle_id = B::type_id_();
-        let mut bundle = Some(b);
-
-        let meta = self.metas.get_mut(&bundle_id).unwrap();
-
-        meta.chunks
-            .iter()
-            .try_fold((), |_, &cid| {
-                // Result<(),Entity>
-                bundle = Some(rev_result(self.chunks[cid].insert(bundle.take().unwrap()))?);
-                Ok(())
-            })
-            .err()
-            .unwrap_or_else(|| self.new_chunk::<B>().insert(bundle?).ok())
-            .unwrap()
+        let tick = self.change_tick;
+        let chunk = self.free_chunk(BundleId::of::<B>());
+        let entity = self.entities.alloc();
+        let row = self.chunks[chunk]
+            .insert(entity, b, tick)
+            .unwrap_or_else(|_| unreachable!("区块还有空闲空间"));
+        self.entities.set(entity, EntityLocation { chunk, row });
+        if self.has_name(BundleId::of::<B>()) {
+            self.index_name(entity);
+        }
+        self.trigger_on_add(entity, B::components_ids());
+        self.record_spawn(entity);
+        entity
     }
 
+    /// 见[World::spawn_batch]
     fn spawn_many<B: crate::bundle::Bundle, I: IntoIterator<Item = B>>(
         &mut self,
         i: I,
     ) -> Vec<Entity> {
-        // 注册&&准备meta
-        self.register::<B>();
-        // let meta = self.metas.get_mut(&B::type_id_()).unwrap();
-
-        // 准备迭代器和返回
-        let mut i = i.into_iter();
-        let mut chuns_iter = self
-            .metas
-            .get_mut(&B::type_id_())
-            .unwrap()
-            .chunks
-            .clone()
-            .into_iter();
-
-        let mut entities = vec![];
-
-        let mut temp_bundle: Option<B> = None;
-
-        loop {
-            // 判空
-            let Some(_temp_bundle) = temp_bundle.take().or_else(|| i.next()) else {return  entities;};
-            temp_bundle = Some(_temp_bundle);
-
-            let temp_chunk = 'get_chunk: {
-                if let Some(cid) = chuns_iter.next() {
-                    if let Some(chunk) = self.chunks.get_mut(cid) {
-                        break 'get_chunk chunk;
-                    }
-                }
-                self.new_chunk::<B>()
-            };
-
-            let eneity_iter = (0..temp_chunk.free()).filter_map(|_| {
-                let item = temp_bundle.take().or_else(|| i.next())?;
-                temp_chunk
-                    .insert(item)
-                    .map_err(|b| temp_bundle = b.into())
-                    .ok()
-            });
-
-            entities.extend(eneity_iter);
-        }
-
-        // meta.chunks
-        //     .iter()
-        //     .filter_map(|&cid| {
-        //         let chunk = self.chunks.get_mut(cid)?;
-        //         let entitiy_iter = (0..chunk.free()).filter_map(|_| {
-        //             let item = temp_bundle.take().or_else(|| i.next())?;
-        //             chunk.insert(item).map_err(|b| temp_bundle = Some(b)).ok()
-        //         });
-        //         entities.extend(entitiy_iter);
-        //         Some(())
-        //     })
-        //     .count();
-
-        // let mut temp_chunk: Option<&mut Chunk> = None;
-        // while let Some(b) = i.next().or_else(|| temp_bundle.take()) {
-        //     if temp_chunk.is_none() {
-        //         temp_chunk = self.new_chunk::<B>().into();
-        //     }
-        //     let chunk = temp_chunk.as_mut().unwrap();
-
-        //     match chunk.insert(b) {
-        //         Ok(entity) => entities.push(entity),
-        //         Err(b) => {
-        //             temp_chunk = None;
-        //             temp_bundle = b.into()
-        //         }
-        //     }
-        // }
+        self.spawn_batch(i)
     }
 
     fn alive(&self, entity: crate::storage::Entity) -> Option<bool> {
-        self.chunks.get(entity.index / CHUNK_SIZE)?.alive(entity)
+        self.entities.alive(entity)
     }
 
     fn remove(&mut self, entity: crate::storage::Entity) -> bool {
-        self.chunks
-            .get_mut(entity.index / CHUNK_SIZE)
-            .map(|chunk| chunk.remove(entity))
-            .unwrap_or(false)
+        self.trigger_on_despawn(entity);
+        #[cfg(feature = "system")]
+        self.trigger_observers(true, entity);
+        let Some(EntityLocation { chunk, row }) = self.entities.free(entity) else {
+            return false;
+        };
+        let (components, ..) = self.chunks[chunk].remove(row);
+        for component in &components {
+            self.names.remove_component(&**component, entity);
+        }
+        self.remove_sparse(entity);
+        if let Some(hierarchy) = self.get_res::<crate::hierarchy::Hierarchy>().get_mut() {
+            let parent = hierarchy.parent(entity);
+            let children = hierarchy.children(entity).to_vec();
+            hierarchy.remove_entity(entity);
+            self.sync_hierarchy(children.into_iter().chain(parent));
+        }
+        for index in 0..self.despawn_hooks.len() {
+            (self.despawn_hooks[index].1)(self, entity);
+        }
+        self.despawned += 1;
+        true
     }
 
+    /// 见[World::try_fetch]
     fn fetch<F: crate::tools::WorldFetch>(&mut self, entity: Entity) -> Option<F::Item<'_>> {
-        // 还是不要滥用语法糖
-        // let true = self.alive(entity).unwrap_or(false) else{
-        //     return None;
-        // };
-
-        // 脱糖
-        if !self.alive(entity).unwrap_or(false) {
-            return None;
-        }
-        unsafe {
-            let chunk = self.chunks.get(entity.chunk_index())?;
-            let components = chunk.get(entity.index_in_chunk());
-            let mapping_table = self.metas.get_mut(&chunk.bundle_id())?.fetch::<F>()?;
-            let item = F::build(components, mapping_table);
-            Some(item)
-        }
+        self.try_fetch::<F>(entity).ok()
     }
 }
 
 impl Drop for World {
     fn drop(&mut self) {
+        if self.resources_usage.enabled {
+            let unused = self.unused_data();
+            if !unused.is_empty() {
+                eprint!("{unused}");
+            }
+        }
+
         // Drop资源
         for (t_id, droper) in &mut self.resources_dropers {
             if let (Some(droper), Some(res)) = (droper.take(), self.resources.get_mut(t_id)) {
                 (droper)(res);
             }
         }
-
-        // Drop组件
-        for (.., meta) in &self.metas {
-            meta.chunks
-                .iter()
-                .copied()
-                .filter_map(|cid| {
-                    self.chunks.get_mut(cid)?.clear(&meta.droper);
-                    Some(())
-                })
-                .count();
-        }
+        // 组件由每个Chunk在Drop时清理
     }
 }
 
 impl ResManager for World {
-    fn get_res<T: 'static>(&mut self) -> Res<'_, T> {
+    fn get_res<T: 'static>(&mut self) -> ResMut<'_, T> {
         if !self.resources.contains_key(&TypeId::of::<T>()) {
             self.new_res::<T>();
         }
-        self.try_get_res::<T>().unwrap()
+        self.try_get_res::<T>()
+            .unwrap_or_else(|| unreachable!("资源的位置已经被创建了"))
     }
 
-    fn try_get_res<T: 'static>(&mut self) -> Option<Res<'_, T>> {
+    fn try_get_res<T: 'static>(&mut self) -> Option<ResMut<'_, T>> {
         let t_id = TypeId::of::<T>();
-        let res = self.resources.get_mut(&t_id)?.get_mut();
-        Some(Res::new(res))
+        let res = self.resources.get_mut(&t_id)?;
+        self.resources_usage.read::<T>();
+        Some(unsafe { ResMut::new(res, (self.last_run, self.change_tick)) })
     }
 
     fn new_res<T: 'static>(&mut self) {
         Resources {
             resources: &mut self.resources,
             resources_dropers: &mut self.resources_dropers,
+            usage: &mut self.resources_usage,
+            change_ticks: (self.last_run, self.change_tick),
         }
         .new_res::<T>();
     }
@@ -393,6 +1820,21 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn despawn() {
+        let mut world = World::new();
+        let entity = world.spawn(1);
+        let entities = world.spawn_many(2..10);
+
+        assert!(world.despawn(entity));
+        assert!(!world.despawn(entity));
+        assert_eq!(world.alive(entity), Some(false));
+
+        assert_eq!(world.despawn_batch(entities[..4].iter().copied()), 4);
+        assert_eq!(world.despawn_batch(entities.iter().copied()), 4);
+        assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 0);
+    }
+
     #[test]
     fn command() {
         let mut world = World::new();
@@ -401,18 +1843,16 @@ mod tests {
         let entity = world.spawn(12345);
         world.remove(entity);
 
-        // 新创建的没有立刻覆盖
-        assert_eq!(world.spawn(114514).generator, ALIVE_TAG);
-
-        // 创建CHUNK_SIZE-1个 此时刚好填满chunks[0]
-        // 最后一个entity因该出现了复用
-        let last = *world.spawn_many(1..CHUNK_SIZE as i32).last().unwrap();
+        // 新创建的复用了Entity的下标
+        let reused = world.spawn(114514);
+        assert_eq!(reused.generator, ALIVE_TAG + 1);
 
         // 具有相同的index 不同的generator
-        assert_eq!(entity.index, last.index);
-        assert_eq!(entity.generator, last.generator - 1);
+        assert_eq!(entity.index, reused.index);
+        assert_eq!(entity.generator, reused.generator - 1);
 
-        // 此时chunks.len() == 1
+        // 创建CHUNK_SIZE-1个 此时刚好填满chunks[0]
+        world.spawn_many(1..CHUNK_SIZE as i32);
         assert_eq!(world.chunks.len(), 1);
 
         // 新创建一个,因该被放置进chunks[1]
@@ -420,4 +1860,194 @@ mod tests {
         assert_eq!(world.chunks.len(), 2);
         assert_eq!(entity.index, CHUNK_SIZE);
     }
+
+    #[test]
+    fn spawn_batch() {
+        let mut world = World::new();
+        world.spawn(0usize);
+        let entities = world.spawn_batch(1..=2 * CHUNK_SIZE);
+        assert_eq!(world.chunks.len(), 3);
+        assert_eq!(entities.len(), 2 * CHUNK_SIZE);
+        assert_eq!(
+            world.fetch::<&usize>(entities[CHUNK_SIZE]),
+            Some(&(CHUNK_SIZE + 1))
+        );
+
+        // size_hint不准确时也能放入所有的Bundle
+        let entities = world.spawn_batch((0..CHUNK_SIZE).filter(|i| i % 2 == 0));
+        assert_eq!(entities.len(), CHUNK_SIZE / 2);
+        assert_eq!(world.chunks.len(), 3);
+        assert_eq!(
+            Query::<&usize>::new(&mut world).into_iter().count(),
+            CHUNK_SIZE * 5 / 2 + 1
+        );
+    }
+
+    #[test]
+    fn fetch_tuple() {
+        let mut world = World::new();
+        // Bundle中的Component顺序与WorldFetch中的顺序不同
+        let entity = world.spawn((1i32, 2usize, "3"));
+
+        let (s, a, b) = world.fetch::<(&&str, &i32, &mut usize)>(entity).unwrap();
+        assert_eq!((*s, *a, *b), ("3", 1, 2));
+
+        // 同一个Component不能被获取两次
+        assert!(world.fetch::<(&i32, &i32)>(entity).is_none());
+    }
+
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    #[test]
+    fn exclusive() {
+        fn spawn(world: &mut World) {
+            let count = world.get_res::<usize>().into_or_init(|| 0);
+            *count += 1;
+            let count = *count;
+            world.spawn(count as i32);
+        }
+        fn with_query(_: &mut World, _: Query<&i32>) {}
+        fn twice(_: &mut World, _: &mut World) {}
+
+        let mut world = World::new();
+        world
+            .set_executor(Executor::MultiThreaded)
+            .add_system(spawn)
+            .add_system(spawn);
+        world.run_once();
+        assert_eq!(world.main_thread_systems().len(), 2);
+        let mut spawned = Query::<&i32>::new(&mut world)
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        spawned.sort();
+        assert_eq!(spawned, [1, 2]);
+
+        for result in [
+            world.try_add_system(with_query).map(|_| ()),
+            world.try_add_system(twice).map(|_| ()),
+        ] {
+            let error = result.unwrap_err();
+            assert_eq!(error.conflicts, [crate::system::ParamConflict::World]);
+        }
+    }
+
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    #[test]
+    #[should_panic(expected = "无法添加System")]
+    fn exec_conflict() {
+        let mut world = World::new();
+        world.spawn(1);
+        // 在构造参数之前就会panic,不需要debug-borrows
+        world.exec(|a: Query<&mut i32>, b: Query<(Entity, &i32)>| {
+            a.into_iter().zip(b).for_each(|(mut a, (_, b))| *a += *b);
+        });
+    }
+
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    #[test]
+    fn tick_rate() {
+        let mut world = World::new();
+        let start = Instant::now();
+        let mut frames = 0;
+        world.set_tick_rate(100.0).run_until(|_| {
+            frames += 1;
+            frames > 5
+        });
+        // 5帧之间至少间隔4次10ms
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        // 为0时不限制
+        world.set_tick_rate(0.0);
+        assert_eq!(world.tick_interval, None);
+    }
+
+    #[cfg(all(feature = "trace", feature = "system", not(feature = "async")))]
+    #[test]
+    fn trace_spans() {
+        use std::sync::{Arc, Mutex};
+        use tracing::{field::Visit, span, Event, Metadata, Subscriber};
+
+        /// 每个span的名字,name字段和父级的下标
+        type Spans = Vec<(&'static str, String, Option<usize>)>;
+
+        #[derive(Clone, Default)]
+        struct Recorder {
+            spans: Arc<Mutex<Spans>>,
+            stack: Arc<Mutex<Vec<usize>>>,
+        }
+
+        struct NameField(String);
+
+        impl Visit for NameField {
+            fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+                if field.name() == "name" {
+                    self.0 = value.to_owned();
+                }
+            }
+
+            fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+                let mut name = NameField(String::new());
+                attrs.record(&mut name);
+                let parent = match attrs.parent() {
+                    Some(id) => Some(id.into_u64() as usize - 1),
+                    None => self.stack.lock().unwrap().last().copied(),
+                };
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((attrs.metadata().name(), name.0, parent));
+                span::Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+            fn event(&self, _: &Event<'_>) {}
+
+            fn enter(&self, span: &span::Id) {
+                self.stack
+                    .lock()
+                    .unwrap()
+                    .push(span.into_u64() as usize - 1);
+            }
+
+            fn exit(&self, _: &span::Id) {
+                self.stack.lock().unwrap().pop();
+            }
+        }
+
+        fn traced_system(mut commands: Commands) {
+            commands.spawn(1);
+        }
+
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            World::new().add_system(traced_system).run_once();
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        let system = spans
+            .iter()
+            .find(|(kind, name, _)| {
+                *kind == "system" && name == std::any::type_name_of_val(&traced_system)
+            })
+            .unwrap();
+        let (kind, name, _) = &spans[system.2.unwrap()];
+        assert_eq!((*kind, name.as_str()), ("stage", "update"));
+        assert!(spans.iter().any(|(kind, ..)| *kind == "apply_commands"));
+    }
+
+    #[cfg(all(feature = "system", not(feature = "async")))]
+    #[test]
+    #[should_panic(expected = "每秒的帧数必须是非负的有限数")]
+    fn negative_tick_rate() {
+        World::new().set_tick_rate(-1.0);
+    }
 }
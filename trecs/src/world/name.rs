@@ -0,0 +1,197 @@
+use std::{
+    any::{Any, TypeId},
+    borrow::{Borrow, Cow},
+    collections::HashMap,
+    fmt::{Debug, Display},
+    ops::Deref,
+};
+
+use crate::{
+    bundle::{BundleId, Component, ComponentId},
+    storage::Entity,
+};
+
+use super::World;
+
+/// [Entity]的名字,可以通过[World::entity_by_name]找到对应的[Entity]
+///
+/// ``` rust
+/// use trecs::{tools::Command, world::Name, World};
+///
+/// let mut world = World::new();
+/// let player = world.spawn((Name::new("player"), 100u32));
+/// assert_eq!(world.entity_by_name("player"), Some(player));
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Name(Cow<'static, str>);
+
+impl Name {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Component for Name {
+    fn type_id_() -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Debug for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl From<&'static str> for Name {
+    fn from(value: &'static str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for Name {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+/// 名字到[Entity]的索引
+///
+/// 在放入,删除[Entity],以及添加,移除[Name]时更新
+///
+/// 通过`&mut Name`修改名字不会更新索引,所以查找时需要验证
+#[derive(Debug, Default)]
+pub(crate) struct NameIndex {
+    inner: HashMap<Name, Vec<Entity>>,
+}
+
+impl NameIndex {
+    pub fn add(&mut self, name: &Name, entity: Entity) {
+        self.inner.entry(name.clone()).or_default().push(entity);
+    }
+
+    pub fn remove(&mut self, name: &Name, entity: Entity) {
+        let Some(entities) = self.inner.get_mut(name) else {
+            return;
+        };
+        entities.retain(|e| *e != entity);
+        if entities.is_empty() {
+            self.inner.remove(name);
+        }
+    }
+
+    /// 被移出的[Component]是[Name]时,从索引中移除
+    pub fn remove_component(&mut self, component: &dyn Any, entity: Entity) {
+        if let Some(name) = component.downcast_ref::<Name>() {
+            self.remove(name, entity);
+        }
+    }
+}
+
+impl World {
+    /// 名字为name的[Entity]
+    ///
+    /// 有多个同名的[Entity]时返回最早得到这个名字的
+    ///
+    /// 通过`&mut Name`修改的名字不在索引中,会遍历所有[Name]来查找
+    pub fn entity_by_name(&self, name: &str) -> Option<Entity> {
+        let matches = |entity: Entity| {
+            self.entity(entity)
+                .and_then(|entity| entity.get::<Name>())
+                .is_some_and(|n| n.as_str() == name)
+        };
+        let indexed = self.names.inner.get(name);
+        if let Some(entity) = indexed.into_iter().flatten().copied().find(|e| matches(*e)) {
+            return Some(entity);
+        }
+        self.metas
+            .values()
+            .filter(|meta| meta.components_ids.contains(&ComponentId::of::<Name>()))
+            .flat_map(|meta| meta.chunks.iter())
+            .flat_map(|&cid| {
+                let chunk = &self.chunks[cid];
+                let mut iter = chunk.iter();
+                std::iter::from_fn(move || iter.next().map(|_| chunk.entity(iter.index)))
+            })
+            .find(|e| matches(*e))
+    }
+
+    /// 把[Entity]的[Name]加入索引
+    pub(crate) fn index_name(&mut self, entity: Entity) {
+        if let Some(name) = self.entity(entity).and_then(|e| e.get::<Name>()) {
+            self.names.add(&name.clone(), entity);
+        }
+    }
+
+    /// bundle_id对应的布局中是否有[Name]
+    pub(crate) fn has_name(&self, bundle_id: BundleId) -> bool {
+        self.metas[&bundle_id]
+            .components_ids
+            .contains(&ComponentId::of::<Name>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tools::Command, world::Query};
+
+    #[test]
+    fn entity_by_name() {
+        let mut world = World::new();
+        let a = world.spawn((Name::new("a"), 1));
+        let b = world.spawn_batch([Name::new("b"), Name::new("b")]);
+        let c = world.spawn(2);
+        assert_eq!(world.entity_by_name("a"), Some(a));
+        assert_eq!(world.entity_by_name("b"), Some(b[0]));
+        assert_eq!(world.entity_by_name("c"), None);
+
+        world.despawn(b[0]);
+        assert_eq!(world.entity_by_name("b"), Some(b[1]));
+
+        world.insert(c, Name::from(String::from("c")));
+        assert_eq!(world.entity_by_name("c"), Some(c));
+        world.insert(c, Name::new("d"));
+        world.insert(c, 3u32);
+        assert_eq!(world.names.inner["d"], [c]);
+        assert_eq!(world.entity_by_name("c"), None);
+        assert_eq!(world.entity_by_name("d"), Some(c));
+        world.remove_component::<Name>(c);
+        assert_eq!(world.entity_by_name("d"), None);
+        assert!(!world.names.inner.contains_key("d"));
+
+        // 直接修改的名字不在索引中
+        for mut name in Query::<&mut Name>::new(&mut world) {
+            if name.as_str() == "a" {
+                *name = Name::new("e");
+            }
+        }
+        assert_eq!(world.entity_by_name("a"), None);
+        assert_eq!(world.entity_by_name("e"), Some(a));
+    }
+}
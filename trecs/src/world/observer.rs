@@ -0,0 +1,258 @@
+use std::marker::PhantomData;
+
+use crate::{
+    storage::Entity,
+    system::{PipeSystem, SystemRegistrationError, SystemState},
+    tools::{MappingTable, WorldFilter},
+};
+
+use super::{allows_disabled, Locals, World};
+
+/// 执行观察者的函数,传入触发事件的[Entity]
+type RunObserver = Box<dyn FnMut(&mut World, Entity) + Send>;
+
+/// 见[World::observe]
+pub(crate) struct Observer {
+    /// 是否在[Entity]被删除时触发,否则在生成时触发
+    despawn: bool,
+    /// [Entity]是否符合观察者的过滤条件
+    filter: fn(&mut World, Entity) -> bool,
+    run: RunObserver,
+}
+
+/// 可以被观察的事件,作为观察者的[In]参数,见[World::observe]
+///
+/// [In]: crate::system::In
+pub trait Trigger: Sized + 'static {
+    /// 用于过滤[Entity]的[Component]
+    ///
+    /// [Component]: crate::bundle::Component
+    type Filter: WorldFilter;
+
+    /// 是否在[Entity]被删除时触发,否则在生成时触发
+    const DESPAWN: bool;
+
+    fn new(entity: Entity) -> Self;
+}
+
+/// [Entity]被生成之后触发,只有[Component]符合Q的[Entity]才会触发
+///
+/// [Component]: crate::bundle::Component
+pub struct Spawned<Q: WorldFilter = ()> {
+    entity: Entity,
+    _q: PhantomData<fn() -> Q>,
+}
+
+/// [Entity]被删除之前触发,此时仍然可以访问它的[Component],
+/// 只有[Component]符合Q的[Entity]才会触发
+///
+/// [Component]: crate::bundle::Component
+pub struct Despawned<Q: WorldFilter = ()> {
+    entity: Entity,
+    _q: PhantomData<fn() -> Q>,
+}
+
+macro_rules! impl_trigger {
+    ($t:ident, $despawn:literal) => {
+        impl<Q: WorldFilter> $t<Q> {
+            /// 触发事件的[Entity]
+            pub fn entity(&self) -> Entity {
+                self.entity
+            }
+        }
+
+        impl<Q: WorldFilter> Trigger for $t<Q> {
+            type Filter = Q;
+
+            const DESPAWN: bool = $despawn;
+
+            fn new(entity: Entity) -> Self {
+                Self {
+                    entity,
+                    _q: PhantomData,
+                }
+            }
+        }
+    };
+}
+impl_trigger!(Spawned, false);
+impl_trigger!(Despawned, true);
+
+/// [Entity]是否符合Q,同[Query]一样默认不包括被禁用的[Entity]
+///
+/// [Query]: crate::world::Query
+fn matches<Q: WorldFilter>(world: &mut World, entity: Entity) -> bool {
+    let Some(location) = world.entities.get(entity) else {
+        return false;
+    };
+    let chunk = &world.chunks[location.chunk];
+    let meta = world
+        .metas
+        .get_mut(&chunk.bundle_id())
+        .unwrap_or_else(|| unreachable!("Chunk中的Bundle一定被注册过"));
+    if !Q::filter_by_meta(meta)
+        || !allows_disabled::<Q>(meta.components_ids, &MappingTable::Missing)
+    {
+        return false;
+    }
+    let row = chunk.row(location.row).with_sparse(&world.sparse);
+    !Q::PER_ENTITY || Q::filter_entity(meta.components_ids, row, world.last_run)
+}
+
+impl World {
+    /// 添加观察者,它会在[Entity]被生成或者删除时立即执行,而不需要等到下一帧通过[Added]轮询
+    ///
+    /// 观察者是第一个参数为[In]的[System],[In]中是[Spawned]或者[Despawned],
+    /// 它们的类型参数用于过滤[Entity]的[Component],如`Spawned<AnyOf<Player>>`
+    ///
+    /// 观察者可以有除了`&mut World`之外的任何参数,它通过[Commands]推迟的操作
+    /// 和其他[Commands]一样在[System]结束或者[World::apply_commands]时执行,
+    /// 所以观察者不会在其他观察者执行期间被触发
+    ///
+    /// 参数之间有冲突时panic
+    ///
+    /// ``` rust
+    /// use trecs::{
+    ///     storage::Entity,
+    ///     system::In,
+    ///     tools::{AnyOf, Command},
+    ///     world::{Despawned, ResMut, Spawned},
+    ///     World,
+    /// };
+    ///
+    /// fn count(_: In<Spawned<AnyOf<u32>>>, mut count: ResMut<usize>) {
+    ///     *count.get_mut().unwrap() += 1;
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world
+    ///     .insert_resource(0usize)
+    ///     .observe(count)
+    ///     .observe(|despawned: In<Despawned>, log: ResMut<Vec<Entity>>| {
+    ///         log.into_or_init(Vec::new).push(despawned.0.entity());
+    ///     });
+    /// let entity = world.spawn(1u32);
+    /// world.spawn("not counted");
+    /// assert_eq!(world.resource::<usize>(), Some(&1));
+    /// world.despawn(entity);
+    /// assert_eq!(world.resource::<Vec<Entity>>().unwrap(), &[entity]);
+    /// ```
+    ///
+    /// [Added]: crate::tools::Added
+    /// [In]: crate::system::In
+    /// [System]: crate::system::System
+    /// [Commands]: crate::world::Commands
+    pub fn observe<T, M, S>(&mut self, mut observer: S) -> &mut Self
+    where
+        T: Trigger,
+        S: PipeSystem<M, In = T, Out = ()> + Send + 'static,
+    {
+        let mut state = SystemState::new();
+        observer.init(&mut state);
+        let system = observer.name();
+        let conflicts = state.take_conflicts();
+        if !conflicts.is_empty() {
+            panic!("{}", SystemRegistrationError { system, conflicts });
+        }
+        if state.world {
+            panic!("观察者{system}不能访问&mut World,结构性的修改需要通过Commands推迟");
+        }
+        let locals = Locals::default();
+        let run = move |world: &mut World, entity| {
+            observer.prepare(world);
+            let cell = unsafe { world.as_unsafe_world_cell().with_locals(&locals) };
+            // 观察者执行期间没有其他System,借用只用于检查参数本身
+            let _borrows = unsafe { cell.borrow_res(system, &state.res) };
+            let args = observer.build_args(cell);
+            observer.run(T::new(entity), args);
+        };
+        self.observers.push(Observer {
+            despawn: T::DESPAWN,
+            filter: matches::<T::Filter>,
+            run: Box::new(run),
+        });
+        self
+    }
+
+    /// 执行所有符合的观察者
+    pub(crate) fn trigger_observers(&mut self, despawn: bool, entity: Entity) {
+        if self.observers.is_empty() {
+            return;
+        }
+        // 观察者无法访问&mut World,执行期间不会添加新的观察者
+        let mut observers = std::mem::take(&mut self.observers);
+        for observer in observers.iter_mut().filter(|o| o.despawn == despawn) {
+            if (observer.filter)(self, entity) {
+                (observer.run)(self, entity);
+            }
+        }
+        self.observers = observers;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        system::In,
+        tools::{AnyOf, Command, Not, ResManager},
+        world::{Commands, Disabled, Local, Query, ResMut},
+    };
+
+    #[derive(Default)]
+    struct Log(Vec<(&'static str, Entity)>);
+
+    fn log(world: &mut World) -> Vec<(&'static str, Entity)> {
+        std::mem::take(&mut world.get_res::<Log>().into_or_init(Log::default).0)
+    }
+
+    #[test]
+    fn observers() {
+        let mut world = World::new();
+        world
+            .observe(|s: In<Spawned<AnyOf<u8>>>, log: ResMut<Log>| {
+                log.into_or_init(Log::default).0.push(("u8", s.0.entity()));
+            })
+            .observe(
+                |d: In<Despawned<Not<AnyOf<u8>>>>, query: Query<&i32>, log: ResMut<Log>| {
+                    // 删除之前仍然可以访问Component
+                    assert!(query.get(d.0.entity()).is_some());
+                    log.into_or_init(Log::default)
+                        .0
+                        .push(("despawn", d.0.entity()));
+                },
+            )
+            .observe(
+                |_: In<Spawned>, mut count: Local<usize>, mut commands: Commands| {
+                    // 推迟的操作不会立即触发观察者
+                    *count += 1;
+                    if *count == 1 {
+                        commands.spawn(0u8);
+                    }
+                },
+            );
+
+        let a = world.spawn((1i32, 1u8));
+        let b = world.spawn_many([2i32, 3])[0];
+        assert_eq!(log(&mut world), [("u8", a)]);
+        world.apply_commands();
+        let spawned = Query::<Entity, AnyOf<u8>>::new(&mut world)
+            .into_iter()
+            .find(|&entity| entity != a)
+            .unwrap();
+        assert_eq!(log(&mut world), [("u8", spawned)]);
+
+        world.despawn(a);
+        world.despawn(b);
+        // 被禁用的Entity同Query一样默认被跳过
+        let c = world.spawn((4i32, Disabled));
+        world.despawn(c);
+        assert_eq!(log(&mut world), [("despawn", b)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "不能访问&mut World")]
+    fn exclusive() {
+        World::new().observe(|_: In<Spawned>, _: &mut World| {});
+    }
+}
@@ -1,42 +1,563 @@
-use std::marker::PhantomData;
+use std::{any::type_name, error::Error, fmt::Display, marker::PhantomData, sync::Arc};
 
-#[cfg(feature = "system")]
-use crate::system::SystemParm;
 use crate::{
-    iter::{EIter, Iter},
-    tools::{WorldFetch, WorldFilter},
-    world::World,
+    hierarchy::Hierarchy,
+    iter::{matching_entities, Combinations, EBundle, EIter, Iter, Select},
+    relation::Relations,
+    storage::Entity,
+    tools::{ReadOnlyFetch, WorldFetch, WorldFilter},
+    world::{Matched, QueryState, UnsafeWorldCell, World},
 };
+#[cfg(feature = "system")]
+use crate::{system::SystemParm, world::QueryCache};
+#[cfg(feature = "system")]
+use std::any::TypeId;
 
 #[allow(unused_imports)]
 use crate::bundle::Components;
+#[cfg(feature = "rayon")]
+use crate::iter::{par_chunks, SendIter};
+
+/// 通过[Query]访问指定的[Entity]失败
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryEntityError {
+    /// [Entity]不存在或者已经失效
+    NoSuchEntity(Entity),
+    /// [Entity]不符合[Query]的[WorldFetch]或者[WorldFilter]
+    Mismatch {
+        entity: Entity,
+        /// [Query]的类型名
+        query: &'static str,
+    },
+    /// 同一个[Entity]被访问了多次
+    Aliased(Entity),
+}
+
+impl Display for QueryEntityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryEntityError::NoSuchEntity(entity) => write!(f, "{entity:?}不存在或者已经失效"),
+            QueryEntityError::Mismatch { entity, query } => {
+                write!(f, "{entity:?}不符合{query}")
+            }
+            QueryEntityError::Aliased(entity) => write!(f, "{entity:?}被访问了多次"),
+        }
+    }
+}
+
+impl Error for QueryEntityError {}
+
+/// [Query]没有恰好符合一个[Entity]
+///
+/// 见[Query::get_single]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuerySingleError {
+    /// 没有符合的[Entity],值为[Query]的类型名
+    NoEntities(&'static str),
+    /// 有多个符合的[Entity],值为[Query]的类型名
+    MultipleEntities(&'static str),
+}
+
+impl Display for QuerySingleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuerySingleError::NoEntities(query) => write!(f, "没有符合{query}的Entity"),
+            QuerySingleError::MultipleEntities(query) => {
+                write!(f, "有多个符合{query}的Entity")
+            }
+        }
+    }
+}
+
+impl Error for QuerySingleError {}
 /// [FnSystem]的参数之一
 ///
 /// 用来操作从world中选定的部分[Components]
 ///
-/// 有可能会出现别名冲突导致[FnSystem]第一次运行时painc
+/// 有可能会出现别名冲突,此时添加[FnSystem]会失败,见[World::try_add_system]
 ///
 /// [FnSystem]: system::fnsys::FnSystem
-#[derive(Clone)]
+/// [World::try_add_system]: crate::World::try_add_system
+///
+/// [Query]不能被复制,[Query]迭代出的引用的生命周期也不会超过[Query]本身
+///
+/// ``` rust,compile_fail
+/// use trecs::{world::Query, World};
+///
+/// let mut world = World::new();
+/// // 引用无法离开System
+/// world.add_system(|query: Query<'static, &'static mut i32>| {
+///     let _: Vec<&'static mut i32> = query.into_iter().collect();
+/// });
+/// ```
+///
+/// ``` rust,compile_fail
+/// use trecs::world::Query;
+///
+/// fn system(query: Query<&mut i32>) {
+///     let _ = query.clone();
+/// }
+/// ```
 pub struct Query<'a, F: WorldFetch, Q: WorldFilter = ()> {
-    world: &'a World,
+    world: UnsafeWorldCell<'a>,
+    /// 符合的[Bundle],由[QueryState]记录
+    ///
+    /// [Bundle]: crate::bundle::Bundle
+    matched: Arc<Vec<Matched>>,
     _p: PhantomData<(F, Q)>,
 }
 
 impl<'a, F: WorldFetch, Q: WorldFilter> Query<'a, F, Q> {
+    /// 每次都会检查所有的[Bundle],需要多次使用时见[QueryState]
+    ///
+    /// [Bundle]: crate::bundle::Bundle
     pub fn new(world: &mut World) -> Query<'_, F, Q> {
+        QueryState::new().query(world)
+    }
+
+    /// # Safety
+    ///
+    /// 同[SystemParm::build]
+    pub(crate) unsafe fn from_matched(
+        world: UnsafeWorldCell<'a>,
+        matched: Arc<Vec<Matched>>,
+    ) -> Self {
         Query {
             world,
+            matched,
             _p: PhantomData,
         }
     }
 
+    /// 所有符合的[Chunk]
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    fn select(&self) -> Select<'a> {
+        // Query存在期间不会增加或者删除Chunk
+        unsafe { Select::from_matched(self.world, &self.matched) }
+    }
+
     pub fn into_eiter(self) -> EIter<'a, F> {
-        unsafe {
-            #[allow(mutable_transmutes)]
-            EIter::new::<Q>(std::mem::transmute(self.world))
+        // 安全性由SystemState保证
+        unsafe { EIter::from_select::<Q>(self.world, self.select()) }
+    }
+
+    /// 只读地访问同样的[Entity],可以传给接受`Query<&T>`等只读[Query]的函数
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, world::Query, World};
+    ///
+    /// fn sum(query: Query<&i32>) -> i32 {
+    ///     query.into_iter().sum()
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world.spawn_many([1, 2, 3]);
+    /// let query = Query::<&mut i32>::new(&mut world);
+    /// assert_eq!(sum(query.as_readonly()), 6);
+    /// ```
+    pub fn as_readonly(&self) -> Query<'_, F::ReadOnly, Q> {
+        // 只读的Item之间不会冲突,通过&self保证同时没有可变的Item
+        // F::ReadOnly与F的MappingTable相同
+        unsafe { Query::from_matched(self.world, self.matched.clone()) }
+    }
+
+    /// 把[Query]看作访问更少[Component]的NF,如把`Query<(&mut A, &B)>`看作`Query<&A>`
+    ///
+    /// 访问的[Entity]不变,仍然由F和Q决定,
+    /// 可以把不同的[Query]传给同一个接受NF的函数;
+    /// 例外是F中[StorageType::SparseSet]的[Component]在NF中没有被访问时,不再逐个[Entity]检查
+    ///
+    /// NF访问了F没有访问的[Component],或者修改了F只读取的[Component]时panic
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, world::Query, World};
+    ///
+    /// fn double(mut query: Query<&mut i32>) {
+    ///     query.into_iter().for_each(|mut i| *i *= 2);
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world.spawn_many([(1, "a"), (2, "b")]);
+    /// let mut query = Query::<(&mut i32, &&str)>::new(&mut world);
+    /// double(query.transmute_lens());
+    /// let ints = query.transmute_lens::<&i32>().into_iter().copied();
+    /// assert_eq!(ints.sum::<i32>(), 6);
+    /// ```
+    ///
+    /// [Component]: crate::bundle::Component
+    /// [StorageType::SparseSet]: crate::bundle::StorageType::SparseSet
+    pub fn transmute_lens<NF: WorldFetch>(&mut self) -> Query<'_, NF, Q> {
+        let matched = (self.matched.iter())
+            .map(|matched| {
+                let mapping_table = NF::contain(&mut matched.components_ids.to_vec())
+                    .filter(|lens| {
+                        let table = &matched.mapping_table;
+                        let (mappings, sparse) = (table.mappings(), table.sparse());
+                        let mutable = F::mutable_mappings(table);
+                        let mutable_sparse = F::mutable_sparse(table);
+                        lens.mappings().iter().all(|m| mappings.contains(m))
+                            && NF::mutable_mappings(lens)
+                                .iter()
+                                .all(|m| mutable.contains(m))
+                            && lens.sparse().iter().all(|id| sparse.contains(id))
+                            && NF::mutable_sparse(lens)
+                                .iter()
+                                .all(|id| mutable_sparse.contains(id))
+                    })
+                    .unwrap_or_else(|| panic!("{}无法看作{}", type_name::<F>(), type_name::<NF>()));
+                Matched {
+                    mapping_table,
+                    components_ids: matched.components_ids,
+                    chunks: matched.chunks.clone(),
+                }
+            })
+            .collect();
+        // 通过&mut self保证同时只有NF的Item,NF访问的Component都可以被F访问
+        unsafe { Query::from_matched(self.world, Arc::new(matched)) }
+    }
+
+    /// 访问一个[Entity]
+    ///
+    /// [Entity]已经失效,或者不符合F和Q时返回[None]
+    pub fn get_mut(&mut self, entity: Entity) -> Option<F::Item<'_>> {
+        // 通过&mut self保证同时只有一个Item
+        unsafe { self.get_unchecked(entity) }.ok()
+    }
+
+    /// 同时访问多个[Entity]
+    ///
+    /// + 有[Entity]重复时返回[QueryEntityError::Aliased]
+    /// + 有[Entity]已经失效,或者不符合F和Q时也会返回错误
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, world::Query, World};
+    ///
+    /// let mut world = World::new();
+    /// let entities = world.spawn_many([1, 2]);
+    /// let mut query = Query::<&mut i32>::new(&mut world);
+    /// let [mut a, mut b] = query.get_many_mut([entities[0], entities[1]]).unwrap();
+    /// std::mem::swap(&mut *a, &mut *b);
+    /// assert!(query.get_many_mut([entities[0], entities[0]]).is_err());
+    /// ```
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> Result<[F::Item<'_>; N], QueryEntityError> {
+        for (index, entity) in entities.iter().enumerate() {
+            if entities[..index].contains(entity) {
+                return Err(QueryEntityError::Aliased(*entity));
+            }
+        }
+        // Entity互不相同,所以Item之间不会冲突
+        let items = entities
+            .into_iter()
+            .map(|entity| unsafe { self.get_unchecked(entity) })
+            .collect::<Result<Vec<_>, _>>()?;
+        let Ok(items) = items.try_into() else {
+            unreachable!("每个Entity都有一个Item")
+        };
+        Ok(items)
+    }
+
+    /// 唯一符合的[Entity]
+    ///
+    /// 没有或者有多个符合的[Entity]时返回[QuerySingleError]
+    pub fn get_single_mut(&mut self) -> Result<F::Item<'_>, QuerySingleError> {
+        // 通过&mut self保证同时只有一个Item
+        unsafe { self.single_unchecked() }
+    }
+
+    /// 唯一符合的[Entity]
+    ///
+    /// 没有或者有多个符合的[Entity]时panic,见[Query::get_single_mut]
+    #[track_caller]
+    pub fn single_mut(&mut self) -> F::Item<'_> {
+        self.get_single_mut().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// 符合的[Entity]的所有组合,每个组合中有K个互不相同的[Entity]
+    ///
+    /// 通过[Combinations::fetch_next]访问
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, world::Query, World};
+    ///
+    /// let mut world = World::new();
+    /// world.spawn_many([1, 2, 3]);
+    /// let mut query = Query::<&mut i32>::new(&mut world);
+    /// let mut combinations = query.iter_combinations_mut::<2>();
+    /// while let Some([mut a, mut b]) = combinations.fetch_next() {
+    ///     std::mem::swap(&mut *a, &mut *b);
+    /// }
+    /// ```
+    pub fn iter_combinations_mut<const K: usize>(&mut self) -> Combinations<'_, 'a, F, Q, K> {
+        let entities = unsafe { matching_entities::<F, Q>(self.world, self.select()) };
+        Combinations::new(self, entities)
+    }
+
+    /// 在多个线程中对每个符合的[Entity]执行f
+    ///
+    /// 按照[Chunk]分配给[rayon]的线程池
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    #[cfg(feature = "rayon")]
+    pub fn par_for_each_mut<Func>(&mut self, f: Func)
+    where
+        Func: Fn(F::Item<'_>) + Send + Sync,
+    {
+        use rayon::prelude::*;
+        // 通过&mut self保证同时只有这些Item
+        let iters = unsafe { par_chunks::<F, Q>(self.world, self.select()) };
+        iters
+            .into_par_iter()
+            .for_each(|SendIter(iter)| iter.for_each(&f));
+    }
+
+    /// # Safety
+    ///
+    /// 返回的Item不能和其他Item冲突
+    unsafe fn single_unchecked(&self) -> Result<F::Item<'_>, QuerySingleError> {
+        let mut iter = Iter::<F>::from_select::<Q>(self.world, self.select());
+        let item = iter
+            .next()
+            .ok_or(QuerySingleError::NoEntities(type_name::<Self>()))?;
+        match iter.next() {
+            Some(_) => Err(QuerySingleError::MultipleEntities(type_name::<Self>())),
+            None => Ok(item),
         }
     }
+
+    /// # Safety
+    ///
+    /// 返回的Item不能和其他Item冲突
+    pub(crate) unsafe fn get_unchecked(
+        &self,
+        entity: Entity,
+    ) -> Result<F::Item<'_>, QueryEntityError> {
+        let location = self
+            .world
+            .entities()
+            .get(entity)
+            .ok_or(QueryEntityError::NoSuchEntity(entity))?;
+        let mismatch = QueryEntityError::Mismatch {
+            entity,
+            query: type_name::<Self>(),
+        };
+        // 创建Query时已经检查过Bundle是否符合F和Q
+        let matched = (self.matched.iter())
+            .find(|matched| matched.chunks.contains(&location.chunk))
+            .ok_or(mismatch)?;
+        let chunk = &self.world.chunks()[location.chunk];
+        let (last_run, change_tick) = self.world.change_ticks();
+        let row = chunk.row(location.row).with_sparse(self.world.sparse());
+        if Q::PER_ENTITY && !Q::filter_entity(matched.components_ids, row, last_run)
+            || !F::matches(row, &matched.mapping_table)
+        {
+            return Err(mismatch);
+        }
+        Ok(F::build(
+            row,
+            &matched.mapping_table,
+            entity,
+            (last_run, change_tick),
+        ))
+    }
+}
+
+impl<'a, F: ReadOnlyFetch, Q: WorldFilter> Query<'a, F, Q> {
+    /// 不可变地访问一个[Entity]
+    ///
+    /// [Entity]已经失效,或者不符合F和Q时返回[None]
+    ///
+    /// 只有[ReadOnlyFetch]可以使用,否则请使用[Query::get_mut]
+    ///
+    /// ``` rust,compile_fail
+    /// use trecs::{storage::Entity, world::Query};
+    ///
+    /// fn system(query: Query<&mut i32>, entity: Entity) {
+    ///     let (a, b) = (query.get(entity), query.get(entity));
+    /// }
+    /// ```
+    pub fn get(&self, entity: Entity) -> Option<F::Item<'_>> {
+        // ReadOnlyFetch的Item之间不会冲突
+        unsafe { self.get_unchecked(entity) }.ok()
+    }
+
+    /// entity的所有后代中符合F和Q的[Entity],按照[Hierarchy::descendants]的顺序
+    pub fn iter_descendants<'s>(
+        &'s self,
+        hierarchy: &'s Hierarchy,
+        entity: Entity,
+    ) -> impl Iterator<Item = F::Item<'s>> + 's {
+        hierarchy
+            .descendants(entity)
+            .filter_map(|descendant| self.get(descendant))
+    }
+
+    /// 所有通过类型为R的关系指向target,并且符合F和Q的[Entity],见[Relations::sources]
+    pub fn iter_related<'s, R>(
+        &'s self,
+        relations: &'s Relations<R>,
+        target: Entity,
+    ) -> impl Iterator<Item = F::Item<'s>> + 's {
+        (relations.sources(target).iter()).filter_map(|&source| self.get(source))
+    }
+
+    /// source通过类型为R的关系指向的,并且符合F和Q的[Entity],见[Relations::targets]
+    pub fn iter_targets<'s, R>(
+        &'s self,
+        relations: &'s Relations<R>,
+        source: Entity,
+    ) -> impl Iterator<Item = F::Item<'s>> + 's {
+        (relations.targets(source).iter()).filter_map(|&target| self.get(target))
+    }
+
+    /// 符合的[Entity]的所有组合,每个组合中有K个互不相同的[Entity]
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, world::Query, World};
+    ///
+    /// let mut world = World::new();
+    /// world.spawn_many([1, 2, 3]);
+    /// let query = Query::<&i32>::new(&mut world);
+    /// assert_eq!(query.iter_combinations::<2>().count(), 3);
+    /// ```
+    pub fn iter_combinations<const K: usize>(&self) -> Combinations<'_, 'a, F, Q, K> {
+        let entities = unsafe { matching_entities::<F, Q>(self.world, self.select()) };
+        Combinations::new(self, entities)
+    }
+
+    /// 在多个线程中不可变地迭代所有符合的[Entity]
+    ///
+    /// 按照[Chunk]分配给[rayon]的线程池
+    ///
+    /// ``` rust
+    /// use rayon::prelude::*;
+    /// use trecs::{tools::Command, world::Query, World};
+    ///
+    /// let mut world = World::new();
+    /// world.spawn_many(0..10000);
+    /// let query = Query::<&i32>::new(&mut world);
+    /// assert_eq!(query.par_iter().map(|i| *i as i64).sum::<i64>(), 49995000);
+    /// ```
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    #[cfg(feature = "rayon")]
+    pub fn par_iter(&self) -> impl rayon::iter::ParallelIterator<Item = F::Item<'_>> + '_
+    where
+        for<'x> F::Item<'x>: Send,
+    {
+        use rayon::prelude::*;
+        // ReadOnlyFetch的Item之间不会冲突
+        let iters = unsafe { par_chunks::<F, Q>(self.world, self.select()) };
+        iters.into_par_iter().flat_map_iter(|SendIter(iter)| iter)
+    }
+
+    /// 在多个线程中对每个符合的[Entity]不可变地执行f
+    ///
+    /// 见[Query::par_iter]
+    #[cfg(feature = "rayon")]
+    pub fn par_for_each<Func>(&self, f: Func)
+    where
+        Func: Fn(F::Item<'_>) + Send + Sync,
+    {
+        use rayon::prelude::*;
+        // ReadOnlyFetch的Item之间不会冲突
+        let iters = unsafe { par_chunks::<F, Q>(self.world, self.select()) };
+        iters
+            .into_par_iter()
+            .for_each(|SendIter(iter)| iter.for_each(&f));
+    }
+
+    /// 不可变地访问唯一符合的[Entity]
+    ///
+    /// 没有或者有多个符合的[Entity]时返回[QuerySingleError]
+    ///
+    /// ``` rust
+    /// use trecs::{tools::Command, world::{Query, QuerySingleError}, World};
+    ///
+    /// let mut world = World::new();
+    /// world.spawn((1, "player"));
+    /// world.spawn_many([2, 3]);
+    /// assert_eq!(Query::<&&str>::new(&mut world).single(), &"player");
+    /// assert!(matches!(
+    ///     Query::<&i32>::new(&mut world).get_single(),
+    ///     Err(QuerySingleError::MultipleEntities(_))
+    /// ));
+    /// ```
+    pub fn get_single(&self) -> Result<F::Item<'_>, QuerySingleError> {
+        // ReadOnlyFetch的Item之间不会冲突
+        unsafe { self.single_unchecked() }
+    }
+
+    /// 不可变地访问唯一符合的[Entity]
+    ///
+    /// 没有或者有多个符合的[Entity]时panic,见[Query::get_single]
+    #[track_caller]
+    pub fn single(&self) -> F::Item<'_> {
+        self.get_single().unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl<'a, F: WorldFetch + 'a, Q: WorldFilter> Query<'a, F, Q> {
+    /// 只迭代没有父级的[Entity]
+    ///
+    /// 不在[Hierarchy]中的[Entity]也会被看作根节点
+    pub fn into_roots(self, hierarchy: &'a Hierarchy) -> impl Iterator<Item = EBundle<'a, F>> {
+        self.into_eiter()
+            .filter(|eb| hierarchy.is_root(eb.entity()))
+    }
+
+    /// 只迭代没有子级的[Entity]
+    ///
+    /// 不在[Hierarchy]中的[Entity]也会被看作叶节点
+    pub fn into_leaves(self, hierarchy: &'a Hierarchy) -> impl Iterator<Item = EBundle<'a, F>> {
+        self.into_eiter()
+            .filter(|eb| hierarchy.is_leaf(eb.entity()))
+    }
+
+    /// 按照f返回的键从小到大迭代,键相同的保持原来的顺序
+    ///
+    /// 会先收集所有符合的[Entity],需要[Entity]时使用`Query<(Entity, ..)>`
+    ///
+    /// ``` rust
+    /// use trecs::{bundle::Component, tools::Command, world::Query, World};
+    ///
+    /// #[derive(Component)]
+    /// struct Sprite {
+    ///     name: &'static str,
+    ///     layer: i32,
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world.spawn(Sprite { name: "ui", layer: 2 });
+    /// world.spawn(Sprite { name: "ground", layer: 0 });
+    /// world.spawn(Sprite { name: "player", layer: 1 });
+    /// let order = Query::<&Sprite>::new(&mut world)
+    ///     .iter_sorted_by_key(|sprite| sprite.layer)
+    ///     .map(|sprite| sprite.name)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(order, ["ground", "player", "ui"]);
+    /// ```
+    pub fn iter_sorted_by_key<K: Ord>(
+        self,
+        f: impl FnMut(&F::Item<'a>) -> K,
+    ) -> std::vec::IntoIter<F::Item<'a>> {
+        let mut items = self.into_iter().collect::<Vec<_>>();
+        items.sort_by_key(f);
+        items.into_iter()
+    }
+
+    /// 同[Query::iter_sorted_by_key],按照compare的结果排序,可以用于[f32]等没有实现[Ord]的键
+    pub fn iter_sorted_by(
+        self,
+        compare: impl FnMut(&F::Item<'a>, &F::Item<'a>) -> std::cmp::Ordering,
+    ) -> std::vec::IntoIter<F::Item<'a>> {
+        let mut items = self.into_iter().collect::<Vec<_>>();
+        items.sort_by(compare);
+        items.into_iter()
+    }
 }
 
 impl<'a, F: WorldFetch + 'a, Q: WorldFilter> IntoIterator for Query<'a, F, Q> {
@@ -45,22 +566,264 @@ impl<'a, F: WorldFetch + 'a, Q: WorldFilter> IntoIterator for Query<'a, F, Q> {
     type IntoIter = Iter<'a, F>;
 
     fn into_iter(self) -> Self::IntoIter {
-        unsafe {
-            #[allow(mutable_transmutes)]
-            Iter::new::<Q>(std::mem::transmute(self.world))
-        }
+        // 安全性由SystemState保证
+        unsafe { Iter::from_select::<Q>(self.world, self.select()) }
     }
 }
 
+/// 作为[System]参数的[Query]的[QueryCache]在[Local]中的键
+///
+/// B为[WorldFetch::Bundle],不依赖于F的生命周期
+///
+/// [System]: crate::system::System
+/// [Local]: crate::world::Local
+#[cfg(feature = "system")]
+struct QueryKey<B, Q>(PhantomData<(B, Q)>);
+
 #[cfg(feature = "system")]
 impl<F: WorldFetch, Q: WorldFilter> SystemParm for Query<'_, F, Q> {
-    unsafe fn build(world: &World) -> Self {
-        #[allow(mutable_transmutes)]
-        let world: &mut World = std::mem::transmute(world);
-        Query::<'_, F, Q>::new(world)
+    type Item<'w> = Query<'w, F, Q>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        #[cfg(feature = "debug-borrows")]
+        {
+            let mut alias_map = crate::system::state::AliasMap::new();
+            F::alias_conflict(&mut alias_map);
+            world.borrows().acquire(&alias_map);
+        }
+        // 不是作为System的参数时没有Local,每次都重新检查
+        let mut fresh = QueryCache::default();
+        let cache = match world.try_locals() {
+            Some(locals) => locals
+                .entry(TypeId::of::<QueryKey<F::Bundle, Q>>())
+                .or_insert_with(|| Box::<QueryCache>::default())
+                .downcast_mut::<QueryCache>()
+                .unwrap_or_else(|| unreachable!("Query的位置中存放的不是QueryCache")),
+            None => &mut fresh,
+        };
+        cache.update::<F, Q>(world);
+        Query::from_matched(world, cache.matched())
     }
 
     fn init(state: &mut crate::system::state::SystemState) {
         F::alias_conflict(&mut state.alias_map);
     }
 }
+
+#[cfg(all(test, feature = "system", not(feature = "async")))]
+mod tests {
+    use crate::{
+        storage::Entity,
+        test::{self, Capture},
+        tools::{AnyOf, Changed, Command, Not},
+        world::{Query, QueryEntityError, QuerySingleError, ResMut, World},
+    };
+
+    #[test]
+    fn get() {
+        let mut world = World::new();
+        let a = world.spawn((1, "a"));
+        let b = world.spawn(2);
+        let dead = world.spawn(3);
+        world.remove(dead);
+
+        let mut query = Query::<&mut i32, Not<AnyOf<&str>>>::new(&mut world);
+        *query.get_mut(b).unwrap() += 10;
+        // 不符合Q
+        assert!(query.get_mut(a).is_none());
+        assert!(query.get_mut(dead).is_none());
+
+        let query = Query::<(&i32, Option<&&str>)>::new(&mut world);
+        assert_eq!(query.get(a), Some((&1, Some(&"a"))));
+        assert_eq!(query.get(b), Some((&12, None)));
+    }
+
+    #[test]
+    fn lens() {
+        let mut world = World::new();
+        let [a, b] = [(1, "a"), (2, "b")].map(|item| world.spawn(item));
+        let alone = world.spawn(3);
+
+        let mut query = Query::<(&mut i32, &&str)>::new(&mut world);
+        let readonly = query.as_readonly();
+        assert_eq!(readonly.get(a), Some((&1, &"a")));
+        assert!(readonly.get(alone).is_none());
+
+        // 仍然只访问原来的Query符合的Entity
+        let lens = query.transmute_lens::<&mut i32>();
+        lens.into_iter().for_each(|mut i| *i *= 10);
+        let lens = query.transmute_lens::<(Entity, &&str)>();
+        assert_eq!(lens.get(b), Some((b, &"b")));
+        assert!(query.transmute_lens::<&i32>().get(alone).is_none());
+        let mut ints = Query::<&i32>::new(&mut world)
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        ints.sort();
+        assert_eq!(ints, [3, 10, 20]);
+    }
+
+    #[test]
+    #[should_panic(expected = "无法看作")]
+    fn invalid_lens() {
+        let mut world = World::new();
+        world.spawn((1, "a"));
+        Query::<(&i32, &&str)>::new(&mut world).transmute_lens::<&mut i32>();
+    }
+
+    #[test]
+    fn get_many_mut() {
+        let mut world = World::new();
+        let [a, b, c] = [1, 2, 3].map(|i| world.spawn(i));
+        let text = world.spawn("a");
+        world.remove(c);
+
+        let mut query = Query::<&mut i32>::new(&mut world);
+        let [mut x, y] = query.get_many_mut([a, b]).unwrap();
+        *x += *y;
+        assert_eq!(query.get_mut(a).map(|i| *i), Some(3));
+        assert_eq!(
+            query.get_many_mut([a, b, a]).err(),
+            Some(QueryEntityError::Aliased(a))
+        );
+        assert_eq!(
+            query.get_many_mut([b, c]).err(),
+            Some(QueryEntityError::NoSuchEntity(c))
+        );
+        assert!(matches!(
+            query.get_many_mut([text]),
+            Err(QueryEntityError::Mismatch { entity, .. }) if entity == text
+        ));
+    }
+
+    #[test]
+    fn single() {
+        let mut world = World::new();
+        let mut query = Query::<&mut usize>::new(&mut world);
+        assert!(matches!(
+            query.get_single_mut(),
+            Err(QuerySingleError::NoEntities(_))
+        ));
+
+        world.spawn_many([(1, 1usize)]);
+        world.spawn(2);
+        *Query::<&mut usize>::new(&mut world).single_mut() += 1;
+        assert_eq!(Query::<(&i32, &usize)>::new(&mut world).single(), (&1, &2));
+    }
+
+    #[test]
+    #[should_panic(expected = "有多个符合")]
+    fn single_multiple() {
+        let mut world = World::new();
+        world.spawn_many([1, 2]);
+        Query::<&i32>::new(&mut world).single();
+    }
+
+    #[test]
+    fn combinations() {
+        let mut world = World::new();
+        world.spawn_many([1, 2, 3, 4]);
+        world.spawn((5, "a"));
+
+        let query = Query::<&i32, Not<AnyOf<&str>>>::new(&mut world);
+        let mut triples = query
+            .iter_combinations::<3>()
+            .map(|items| {
+                let mut items = items.map(|i| *i);
+                items.sort();
+                items
+            })
+            .collect::<Vec<_>>();
+        triples.sort();
+        assert_eq!(triples, [[1, 2, 3], [1, 2, 4], [1, 3, 4], [2, 3, 4]]);
+        assert_eq!(query.iter_combinations::<5>().count(), 0);
+        assert_eq!(query.iter_combinations::<0>().count(), 0);
+
+        // 每一对都互相加一次
+        let mut query = Query::<&mut i32, Not<AnyOf<&str>>>::new(&mut world);
+        let mut combinations = query.iter_combinations_mut::<2>();
+        while let Some([mut a, mut b]) = combinations.fetch_next() {
+            *a += 1;
+            *b += 1;
+        }
+        let mut ints = query.into_iter().map(|i| *i).collect::<Vec<_>>();
+        ints.sort();
+        assert_eq!(ints, [4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn get_changed() {
+        let mut world = World::new();
+        let [a, b] = [1, 2].map(|i| world.spawn(i));
+        let check = move |query: Query<&i32, Changed<i32>>,
+                          capture: ResMut<Capture<(bool, bool)>>| {
+            let changed = (query.get(a).is_some(), query.get(b).is_some());
+            capture.into_or_init(Capture::new).push(changed);
+        };
+        let write = move |mut query: Query<&mut i32>| {
+            *query.get_mut(b).unwrap() += 1;
+        };
+        world.add_system(check).add_system(write);
+        test::tick(&mut world);
+        test::tick(&mut world);
+        assert_eq!(
+            test::captured::<(bool, bool)>(&mut world),
+            [(true, true), (false, true)]
+        );
+    }
+
+    struct Depth(f32);
+
+    impl crate::bundle::Component for Depth {
+        fn type_id_() -> std::any::TypeId {
+            std::any::TypeId::of::<Self>()
+        }
+    }
+
+    #[test]
+    fn sorted() {
+        let mut world = World::new();
+        let entities =
+            world.spawn_many([(3, 0.5), (1, 2.0), (2, -1.0), (1, 0.0)].map(|(i, d)| (i, Depth(d))));
+        world.spawn((0, Depth(0.0), "skipped"));
+
+        let query = Query::<(Entity, &i32), Not<AnyOf<&str>>>::new(&mut world);
+        let by_key = query
+            .iter_sorted_by_key(|(_, i)| **i)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        // 键相同时保持原来的顺序
+        assert_eq!(by_key, [entities[1], entities[3], entities[2], entities[0]]);
+
+        let query = Query::<(&i32, &mut Depth)>::new(&mut world);
+        let by_float = query
+            .iter_sorted_by(|(_, a), (_, b)| b.0.total_cmp(&a.0))
+            .map(|(i, mut depth)| {
+                depth.0 += 1.0;
+                *i
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(by_float, [1, 3, 0, 1, 2]);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_for_each() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut world = World::new();
+        // 跨越多个Chunk
+        world.spawn_many(0..5000);
+        world.spawn_many((0..5000).map(|i| (i, "b")));
+
+        let mut query = Query::<&mut i32>::new(&mut world);
+        query.par_for_each_mut(|mut i| *i += 1);
+        let count = AtomicUsize::new(0);
+        let query = Query::<&i32>::new(&mut world);
+        query.par_for_each(|i| {
+            assert!((1..=5000).contains(i));
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(count.into_inner(), 10000);
+    }
+}
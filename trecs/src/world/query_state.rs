@@ -0,0 +1,189 @@
+use std::{collections::HashMap, marker::PhantomData, sync::Arc};
+
+use crate::{
+    bundle::{BundleId, ComponentId},
+    tools::{MappingTable, WorldFetch, WorldFilter},
+};
+
+use super::{allows_disabled, Query, UnsafeWorldCell, World};
+
+/// 一种符合[Query]的[Bundle],以及存放它的[Chunk]
+///
+/// [Bundle]: crate::bundle::Bundle
+/// [Chunk]: crate::storage::Chunk
+#[derive(Debug, Clone)]
+pub(crate) struct Matched {
+    pub(crate) mapping_table: MappingTable,
+    pub(crate) components_ids: &'static [ComponentId],
+    /// [World]中存放此类[Bundle]的[Chunk]的下标
+    ///
+    /// [Bundle]: crate::bundle::Bundle
+    /// [Chunk]: crate::storage::Chunk
+    pub(crate) chunks: Vec<usize>,
+}
+
+/// [QueryState]中不依赖于F和Q的部分
+///
+/// [System]的[Query]参数把它作为自己的[Local]保存
+///
+/// [System]: crate::system::System
+/// [Local]: crate::world::Local
+#[derive(Debug, Default)]
+pub(crate) struct QueryCache {
+    /// 已经检查过的[Chunk]的数量
+    ///
+    /// [Chunk]只会被添加,不会被删除,所以之后的[Chunk]都是新的
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    checked: usize,
+    /// 检查过的[Bundle],符合时为在matched中的下标
+    ///
+    /// [Bundle]: crate::bundle::Bundle
+    bundles: HashMap<BundleId, Option<usize>>,
+    /// 被[Query]共享,没有[Query]存在时修改不需要复制
+    matched: Arc<Vec<Matched>>,
+}
+
+impl QueryCache {
+    /// 只检查上一次之后新出现的[Chunk],每种新的[Bundle]只会计算一次[MappingTable]
+    ///
+    /// # Safety
+    ///
+    /// 见[UnsafeWorldCell::metas_mut]和[UnsafeWorldCell::chunks]
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    /// [Bundle]: crate::bundle::Bundle
+    pub(crate) unsafe fn update<F: WorldFetch, Q: WorldFilter>(
+        &mut self,
+        world: UnsafeWorldCell<'_>,
+    ) {
+        let chunks = world.chunks();
+        if self.checked == chunks.len() {
+            return;
+        }
+        let matched = Arc::make_mut(&mut self.matched);
+        // 只在出现新的Bundle时加锁
+        let mut metas = None;
+        for (cid, chunk) in chunks.iter().enumerate().skip(self.checked) {
+            let bundle_id = chunk.bundle_id();
+            let index = *self.bundles.entry(bundle_id).or_insert_with(|| {
+                let metas = metas.get_or_insert_with(|| world.metas_mut());
+                let meta = metas
+                    .get_mut(&bundle_id)
+                    .unwrap_or_else(|| unreachable!("Chunk中的Bundle一定被注册过"));
+                if !meta.filter::<Q>() {
+                    return None;
+                }
+                let mapping_table = meta.fetch::<F>()?.clone();
+                if !allows_disabled::<Q>(meta.components_ids, &mapping_table) {
+                    return None;
+                }
+                matched.push(Matched {
+                    mapping_table,
+                    components_ids: meta.components_ids,
+                    chunks: vec![],
+                });
+                Some(matched.len() - 1)
+            });
+            if let Some(index) = index {
+                matched[index].chunks.push(cid);
+            }
+        }
+        self.checked = chunks.len();
+    }
+
+    pub(crate) fn matched(&self) -> Arc<Vec<Matched>> {
+        self.matched.clone()
+    }
+}
+
+/// [Query]在多次使用之间保留的状态
+///
+/// 记录每种符合的[Bundle]的[MappingTable],之后只需要检查新出现的[Bundle],
+/// 而[Query::new]每次都会检查所有的[Bundle]
+///
+/// 作为[System]的参数的[Query]会自动保留状态
+///
+/// 一个[QueryState]只能用于同一个[World]
+///
+/// ``` rust
+/// use trecs::{tools::Command, world::QueryState, World};
+///
+/// let mut world = World::new();
+/// let mut state = QueryState::<&i32>::new();
+/// world.spawn(1);
+/// assert_eq!(state.query(&mut world).into_iter().count(), 1);
+/// world.spawn((2, "a"));
+/// assert_eq!(state.query(&mut world).into_iter().count(), 2);
+/// ```
+///
+/// [Bundle]: crate::bundle::Bundle
+/// [System]: crate::system::System
+pub struct QueryState<F: WorldFetch, Q: WorldFilter = ()> {
+    cache: QueryCache,
+    _p: PhantomData<fn() -> (F, Q)>,
+}
+
+impl<F: WorldFetch, Q: WorldFilter> QueryState<F, Q> {
+    pub fn new() -> Self {
+        Self {
+            cache: Default::default(),
+            _p: PhantomData,
+        }
+    }
+
+    /// 更新状态,并创建[Query]
+    pub fn query<'w>(&mut self, world: &'w mut World) -> Query<'w, F, Q> {
+        let world = world.as_unsafe_world_cell();
+        // 通过&mut World保证独占
+        unsafe {
+            self.cache.update::<F, Q>(world);
+            Query::from_matched(world, self.cache.matched())
+        }
+    }
+}
+
+impl<F: WorldFetch, Q: WorldFilter> Default for QueryState<F, Q> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Command;
+
+    #[test]
+    fn incremental() {
+        let mut world = World::new();
+        world.spawn_many([1, 2]);
+        world.spawn((1u32, "a"));
+        let mut state = QueryState::<&i32>::new();
+        assert_eq!(state.query(&mut world).into_iter().count(), 2);
+        assert_eq!((state.cache.checked, state.cache.bundles.len()), (2, 2));
+        assert_eq!(state.cache.matched.len(), 1);
+
+        // 已有的Chunk中新增的Entity不需要重新检查
+        world.spawn(3);
+        state.query(&mut world);
+        assert_eq!(state.cache.checked, 2);
+
+        // 新的Bundle和新的Chunk
+        world.spawn((4, "b"));
+        world.spawn_batch(0..2000);
+        let mut ints = state
+            .query(&mut world)
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(ints.len(), 2004);
+        ints.sort();
+        assert_eq!(ints[..4], [0, 1, 1, 2]);
+        assert_eq!(state.cache.checked, world.chunks.len());
+        assert_eq!(state.cache.bundles.len(), 3);
+        assert_eq!(state.cache.matched.len(), 2);
+        let chunks = &world.metas[&BundleId::of::<i32>()].chunks;
+        assert_eq!(state.cache.matched[0].chunks, *chunks);
+    }
+}
@@ -1,32 +1,158 @@
 use std::{
-    any::{Any, TypeId},
-    cell::UnsafeCell,
+    any::{type_name, Any, TypeId},
     collections::HashMap,
+    error::Error,
+    fmt::Display,
     marker::PhantomData,
+    ops::{Deref, DerefMut},
 };
 
-use crate::tools::ResManager;
+use super::AnRes;
+use crate::{storage::ComponentTicks, tools::ResManager};
 
+/// 资源不存在
+///
+/// 见[Res::try_get]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoSuchResource {
+    /// 资源的类型名
+    pub resource: &'static str,
+}
+
+impl Display for NoSuchResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "资源{}不存在", self.resource)
+    }
+}
+
+impl Error for NoSuchResource {}
+
+/// 资源的位置中存放的不是T
+///
+/// 位置总是通过[TypeId]找到的,不会发生
+fn mismatch<T>() -> ! {
+    unreachable!("资源的位置中存放的不是{}", type_name::<T>())
+}
+
+/// 只读的资源
+///
+/// 作为[System]的参数时,多个[System]可以同时读取同一种资源,
+/// 需要修改或者初始化资源时请使用[ResMut]
+///
+/// [System]执行期间会在运行时借用资源,与正在进行的可变借用冲突时panic,
+/// 而不是产生别名冲突
+///
+/// [System]: crate::system::System
 pub struct Res<'a, T: 'static> {
-    handle: &'a mut Option<Box<dyn Any>>,
+    handle: &'a Option<Box<dyn Any>>,
+    ticks: &'a ComponentTicks,
+    /// 见[UnsafeWorldCell::change_ticks]
+    last_run: usize,
     _m: PhantomData<T>,
 }
 
 impl<'a, T: 'static> Res<'a, T> {
-    pub(crate) fn new(res: &mut Option<Box<dyn Any>>) -> Res<'_, T> {
+    /// # Safety
+    ///
+    /// 返回的[Res]存在期间,不能有对资源的可变访问
+    #[cfg(feature = "system")]
+    pub(crate) unsafe fn new(res: &'a AnRes, last_run: usize) -> Self {
+        Res {
+            handle: &*res.get(),
+            ticks: res.ticks(),
+            last_run,
+            _m: PhantomData,
+        }
+    }
+
+    /// 获取资源的不可变引用
+    pub fn get(&self) -> Option<&T> {
+        self.try_get().ok()
+    }
+
+    /// 获取资源的不可变引用,资源不存在时返回[NoSuchResource]
+    pub fn try_get(&self) -> Result<&T, NoSuchResource> {
+        match self.handle.as_ref() {
+            Some(box_) => Ok(box_.downcast_ref().unwrap_or_else(|| mismatch::<T>())),
+            None => Err(NoSuchResource {
+                resource: type_name::<T>(),
+            }),
+        }
+    }
+
+    /// 获取资源的不可变引用
+    ///
+    /// 与[Res::get]不同,返回的引用的生命周期与[Res]本身无关
+    pub fn into_ref(self) -> Option<&'a T> {
+        let box_ = self.handle.as_ref()?;
+        Some(box_.downcast_ref().unwrap_or_else(|| mismatch::<T>()))
+    }
+
+    /// 资源是否存在
+    pub fn exists(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    /// 资源是否在[System]上一次执行之后被插入
+    ///
+    /// [System]: crate::system::System
+    pub fn is_added(&self) -> bool {
+        self.exists() && self.ticks.is_added(self.last_run)
+    }
+
+    /// 资源是否在[System]上一次执行之后被插入或者修改
+    ///
+    /// 通过[ResMut]获取资源的可变引用就会被看作修改过
+    ///
+    /// [System]: crate::system::System
+    pub fn is_changed(&self) -> bool {
+        self.exists() && self.ticks.is_changed(self.last_run)
+    }
+}
+
+/// 可以修改的资源
+///
+/// 可以初始化,修改,取出和删除资源
+///
+/// 作为[System]的参数时,与所有访问同一种资源的[System]冲突,
+/// 执行期间会在运行时借用资源,见[Res]
+///
+/// [System]: crate::system::System
+pub struct ResMut<'a, T: 'static> {
+    handle: &'a mut Option<Box<dyn Any>>,
+    ticks: &'a ComponentTicks,
+    /// 见[UnsafeWorldCell::change_ticks]
+    change_ticks: (usize, usize),
+    _m: PhantomData<T>,
+}
+
+impl<'a, T: 'static> ResMut<'a, T> {
+    /// # Safety
+    ///
+    /// 返回的[ResMut]存在期间,不能有其他对资源的访问
+    pub(crate) unsafe fn new(res: &'a AnRes, change_ticks: (usize, usize)) -> Self {
         // 在transmute之前 res可能是None
         // 意味着内部的Box<dyn Any>实际上是没有虚表的
         // let handle : &mut Option<Box<T>> = unsafe { std::mem::transmute(res) };
         // 在downcast时就会造成ub
         // 因此变更设计,使用downcast在每个函数转换，而不是创建时直接转换
 
-        let handle = unsafe { std::mem::transmute(res) };
-        Res {
-            handle,
+        ResMut {
+            handle: &mut *res.get(),
+            ticks: res.ticks(),
+            change_ticks,
             _m: PhantomData,
         }
     }
 
+    fn set_added(&self) {
+        self.ticks.set_added(self.change_ticks.1);
+    }
+
+    fn set_changed(&self) {
+        self.ticks.set_changed(self.change_ticks.1);
+    }
+
     /// 获取资源的不可变引用,或者初始化资源
     ///
     /// + 如果原来有资源,会返回资源的不可变引用
@@ -38,18 +164,68 @@ impl<'a, T: 'static> Res<'a, T> {
     {
         if self.handle.is_none() {
             *self.handle = Some(Box::new(init()));
+            self.set_added();
         }
         self.get().unwrap()
     }
 
+    /// 获取资源的可变引用,或者初始化资源
+    ///
+    /// 与[ResMut::get_or_init]不同,返回的引用的生命周期与[ResMut]本身无关
+    pub fn into_or_init<F>(self, init: F) -> &'a mut T
+    where
+        F: FnOnce() -> T,
+    {
+        match self.handle.is_some() {
+            true => self.set_changed(),
+            false => self.set_added(),
+        }
+        self.handle
+            .get_or_insert_with(|| Box::new(init()))
+            .downcast_mut()
+            .unwrap_or_else(|| mismatch::<T>())
+    }
+
     /// 获取资源的不可变引用
     pub fn get(&self) -> Option<&T> {
-        self.handle.as_ref().and_then(|box_| box_.downcast_ref())
+        self.try_get().ok()
     }
 
     /// 获取资源的可变引用
     pub fn get_mut(&mut self) -> Option<&mut T> {
-        self.handle.as_mut().and_then(|box_| box_.downcast_mut())
+        self.try_get_mut().ok()
+    }
+
+    /// 获取资源的可变引用
+    ///
+    /// 与[ResMut::get_mut]不同,返回的引用的生命周期与[ResMut]本身无关
+    pub fn into_mut(self) -> Option<&'a mut T> {
+        let box_ = self.handle.as_mut()?;
+        self.ticks.set_changed(self.change_ticks.1);
+        Some(box_.downcast_mut().unwrap_or_else(|| mismatch::<T>()))
+    }
+
+    /// 获取资源的不可变引用,资源不存在时返回[NoSuchResource]
+    pub fn try_get(&self) -> Result<&T, NoSuchResource> {
+        match self.handle.as_ref() {
+            Some(box_) => Ok(box_.downcast_ref().unwrap_or_else(|| mismatch::<T>())),
+            None => Err(NoSuchResource {
+                resource: type_name::<T>(),
+            }),
+        }
+    }
+
+    /// 获取资源的可变引用,资源不存在时返回[NoSuchResource]
+    pub fn try_get_mut(&mut self) -> Result<&mut T, NoSuchResource> {
+        match self.handle.as_mut() {
+            Some(box_) => {
+                self.ticks.set_changed(self.change_ticks.1);
+                Ok(box_.downcast_mut().unwrap_or_else(|| mismatch::<T>()))
+            }
+            None => Err(NoSuchResource {
+                resource: type_name::<T>(),
+            }),
+        }
     }
 
     /// 取得资源
@@ -58,7 +234,23 @@ impl<'a, T: 'static> Res<'a, T> {
     ///
     /// + 如果原来没有资源，返回[None]
     pub fn take(&mut self) -> Option<Box<T>> {
-        self.handle.take()?.downcast().ok()
+        let box_ = self.handle.take()?;
+        Some(box_.downcast().unwrap_or_else(|_| mismatch::<T>()))
+    }
+
+    /// 资源是否存在
+    pub fn exists(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    /// 见[Res::is_added]
+    pub fn is_added(&self) -> bool {
+        self.exists() && self.ticks.is_added(self.change_ticks.0)
+    }
+
+    /// 见[Res::is_changed]
+    pub fn is_changed(&self) -> bool {
+        self.exists() && self.ticks.is_changed(self.change_ticks.0)
     }
 
     /// 删除资源
@@ -71,28 +263,288 @@ impl<'a, T: 'static> Res<'a, T> {
     }
 }
 
+impl crate::World {
+    /// 插入资源,替换原来的资源
+    pub fn insert_resource<T: 'static>(&mut self, value: T) -> &mut Self {
+        let mut res = self.get_res::<T>();
+        res.remove();
+        res.get_or_init(|| value);
+        self
+    }
+
+    /// 资源不存在时,插入[Default::default]
+    pub fn init_resource<T: Default + 'static>(&mut self) -> &mut Self {
+        self.get_res::<T>().get_or_init(T::default);
+        self
+    }
+
+    /// 获取资源的不可变引用
+    ///
+    /// 不会记录资源的读取,见[World::lint_unused_data]
+    ///
+    /// [World::lint_unused_data]: crate::World::lint_unused_data
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        self.peek_res()
+    }
+
+    /// 获取资源的可变引用
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.try_get_res::<T>()?.into_mut()
+    }
+
+    /// 移除资源,返回被移除的值
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        self.try_get_res::<T>()?.take().map(|box_| *box_)
+    }
+
+    /// 资源是否存在
+    pub fn contains_resource<T: 'static>(&self) -> bool {
+        self.peek_res::<T>().is_some()
+    }
+}
+
+#[cfg(feature = "system")]
+use crate::{
+    system::{state::Alias, ParamConflict, SystemParm},
+    world::{UnsafeWorldCell, World},
+};
+
+/// 作为[System]的参数时,T必须是[Send]+[Sync]的
+///
+/// 否则请使用[NonSend]
+///
+/// [System]: crate::system::System
+#[cfg(feature = "system")]
+impl<T: Send + Sync + 'static> SystemParm for Res<'_, T> {
+    type Item<'w> = Res<'w, T>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        Res::new(build_res::<T>(world), world.change_ticks().0)
+    }
+
+    fn prepare(world: &mut World) {
+        world.new_res::<T>();
+    }
+
+    fn init(state: &mut crate::system::state::SystemState) {
+        init_res::<T>(state, Alias::Imut);
+    }
+}
+
+/// 见[Res]的[SystemParm]实现
+///
+/// [System]: crate::system::System
+#[cfg(feature = "system")]
+impl<T: Send + Sync + 'static> SystemParm for ResMut<'_, T> {
+    type Item<'w> = ResMut<'w, T>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        ResMut::new(build_res::<T>(world), world.change_ticks())
+    }
+
+    fn prepare(world: &mut World) {
+        world.new_res::<T>();
+    }
+
+    fn init(state: &mut crate::system::state::SystemState) {
+        init_res::<T>(state, Alias::Mut);
+    }
+}
+
+/// 可选的资源,资源不存在时为[None],存在时与[Res]相同
+///
+/// ``` rust
+/// use trecs::{world::Res, World};
+///
+/// struct Config(u32);
+///
+/// fn configure(config: Option<Res<Config>>) {
+///     let Some(config) = config else {
+///         return;
+///     };
+///     println!("{}", config.get().unwrap().0);
+/// }
+///
+/// let mut world = World::new();
+/// world.add_system(configure);
+/// world.run_once();
+/// ```
 #[cfg(feature = "system")]
-use crate::{system::SystemParm, world::World};
+impl<T: Send + Sync + 'static> SystemParm for Option<Res<'_, T>> {
+    type Item<'w> = Option<Res<'w, T>>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        let res = Res::<T>::build(world);
+        res.exists().then_some(res)
+    }
+
+    fn prepare(world: &mut World) {
+        Res::<T>::prepare(world);
+    }
+
+    fn init(state: &mut crate::system::state::SystemState) {
+        Res::<T>::init(state);
+    }
+}
 
+/// 可选的资源,资源不存在时为[None],存在时与[ResMut]相同
 #[cfg(feature = "system")]
-impl<'a, T: 'static> SystemParm for Res<'a, T> {
-    unsafe fn build(world: &World) -> Self {
-        #[allow(mutable_transmutes)]
-        let world: &mut World = std::mem::transmute(world);
-        std::mem::transmute(world.get_res::<T>())
+impl<T: Send + Sync + 'static> SystemParm for Option<ResMut<'_, T>> {
+    type Item<'w> = Option<ResMut<'w, T>>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        let res = ResMut::<T>::build(world);
+        res.exists().then_some(res)
+    }
+
+    fn prepare(world: &mut World) {
+        ResMut::<T>::prepare(world);
     }
 
     fn init(state: &mut crate::system::state::SystemState) {
-        if state.resources || state.res.contains(&TypeId::of::<T>()) {
-            panic!("Res不可和Resources或者重复的Res共存")
+        ResMut::<T>::init(state);
+    }
+}
+
+/// [Res],[ResMut]和[NonSend]共用的创建过程,返回资源的位置
+#[cfg(feature = "system")]
+unsafe fn build_res<T: 'static>(world: UnsafeWorldCell<'_>) -> &AnRes {
+    let res = world
+        .resources()
+        .get(&TypeId::of::<T>())
+        .unwrap_or_else(|| unreachable!("资源{}的位置已经在prepare中创建了", type_name::<T>()));
+    world.resources_usage().read::<T>();
+    res
+}
+
+/// [Res],[ResMut]和[NonSend]共用的冲突检查
+///
+/// 同一种资源可以被多次读取,但是不能在修改的同时被访问
+#[cfg(feature = "system")]
+fn init_res<T: 'static>(state: &mut crate::system::state::SystemState, alias: Alias) {
+    let exist = state.res.get(&TypeId::of::<T>());
+    if state.resources || exist.is_some_and(|(_, exist)| exist.is_mut() || alias.is_mut()) {
+        let conflict = ParamConflict::Resource(type_name::<T>());
+        state.conflicts.push(conflict);
+    }
+    let alias = match exist {
+        Some((_, Alias::Mut)) => Alias::Mut,
+        _ => alias,
+    };
+    state
+        .res
+        .insert(TypeId::of::<T>(), (type_name::<T>(), alias));
+}
+
+/// 不要求[Send]+[Sync]的资源,如窗口句柄等
+///
+/// 用法与[ResMut]相同,和[Res]以及[ResMut]共用同一份资源
+///
+/// 访问了[NonSend]的[System]只会在主线程,即[World]所在的线程运行
+///
+/// ``` rust
+/// use std::rc::Rc;
+/// use trecs::{world::NonSend, World};
+///
+/// let mut world = World::new();
+/// world.add_system(|handle: NonSend<Rc<u32>>| {
+///     handle.into_or_init(|| Rc::new(1));
+/// });
+/// ```
+///
+/// ``` rust,compile_fail
+/// use std::rc::Rc;
+/// use trecs::{world::Res, World};
+///
+/// let mut world = World::new();
+/// world.add_system(|_: Res<Rc<u32>>| {});
+/// ```
+///
+/// [World]可能持有这样的资源,所以[World]本身也不能被发送到其他线程
+///
+/// ``` rust,compile_fail
+/// let world = trecs::World::new();
+/// std::thread::spawn(move || drop(world));
+/// ```
+///
+/// [System]: crate::system::System
+/// [World]: crate::World
+pub struct NonSend<'a, T: 'static> {
+    res: ResMut<'a, T>,
+    /// 使[NonSend]不能被发送到其他线程
+    _m: PhantomData<*const ()>,
+}
+
+impl<'a, T: 'static> Deref for NonSend<'a, T> {
+    type Target = ResMut<'a, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.res
+    }
+}
+
+impl<T: 'static> DerefMut for NonSend<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.res
+    }
+}
+
+impl<'a, T: 'static> NonSend<'a, T> {
+    /// 见[ResMut::into_or_init]
+    pub fn into_or_init<F>(self, init: F) -> &'a mut T
+    where
+        F: FnOnce() -> T,
+    {
+        self.res.into_or_init(init)
+    }
+}
+
+#[cfg(feature = "system")]
+impl<T: 'static> SystemParm for NonSend<'_, T> {
+    type Item<'w> = NonSend<'w, T>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        NonSend {
+            res: ResMut::new(build_res::<T>(world), world.change_ticks()),
+            _m: PhantomData,
         }
-        state.res.insert(TypeId::of::<T>());
+    }
+
+    fn prepare(world: &mut World) {
+        world.new_res::<T>();
+    }
+
+    fn init(state: &mut crate::system::state::SystemState) {
+        init_res::<T>(state, Alias::Mut);
+        state.main_thread = true;
+    }
+}
+
+/// 可选的[NonSend],资源不存在时为[None]
+#[cfg(feature = "system")]
+impl<T: 'static> SystemParm for Option<NonSend<'_, T>> {
+    type Item<'w> = Option<NonSend<'w, T>>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        let res = NonSend::<T>::build(world);
+        res.exists().then_some(res)
+    }
+
+    fn prepare(world: &mut World) {
+        NonSend::<T>::prepare(world);
+    }
+
+    fn init(state: &mut crate::system::state::SystemState) {
+        NonSend::<T>::init(state);
     }
 }
 
 pub struct Resources<'a> {
-    pub(crate) resources: &'a mut HashMap<TypeId, UnsafeCell<Option<Box<dyn Any>>>>,
+    pub(crate) resources: &'a mut HashMap<TypeId, AnRes>,
     pub(crate) resources_dropers: &'a mut HashMap<TypeId, super::Droper>,
+    pub(crate) usage: &'a mut super::usage::ResourcesUsage,
+    /// 见[UnsafeWorldCell::change_ticks]
+    pub(crate) change_ticks: (usize, usize),
 }
 
 impl Resources<'_> {
@@ -108,36 +560,40 @@ impl Resources<'_> {
 }
 
 impl<'a> ResManager for Resources<'a> {
-    fn get_res<T: 'static>(&mut self) -> Res<'_, T> {
+    fn get_res<T: 'static>(&mut self) -> ResMut<'_, T> {
         if !self.resources.contains_key(&TypeId::of::<T>()) {
             self.new_res::<T>();
         }
-        self.try_get_res::<T>().unwrap()
+        self.try_get_res::<T>()
+            .unwrap_or_else(|| unreachable!("资源的位置已经被创建了"))
     }
 
-    fn try_get_res<T: 'static>(&mut self) -> Option<Res<'_, T>> {
+    fn try_get_res<T: 'static>(&mut self) -> Option<ResMut<'_, T>> {
         let t_id = TypeId::of::<T>();
-        let res = self.resources.get_mut(&t_id)?.get_mut();
-        Some(Res::new(res))
+        let res = self.resources.get_mut(&t_id)?;
+        self.usage.read::<T>();
+        Some(unsafe { ResMut::new(res, self.change_ticks) })
     }
 
     fn new_res<T: 'static>(&mut self) {
         let t_id = TypeId::of::<T>();
         self.drop_tag::<T>();
-        self.resources
-            .entry(t_id)
-            .or_insert_with(|| UnsafeCell::new(None));
+        self.usage.created::<T>();
+        self.resources.entry(t_id).or_insert_with(AnRes::new);
     }
 }
 
 #[cfg(feature = "system")]
 impl SystemParm for Resources<'_> {
-    unsafe fn build(world: &World) -> Self {
-        #[allow(mutable_transmutes)]
-        let world: &mut World = std::mem::transmute(world);
-        Self {
-            resources: &mut world.resources,
-            resources_dropers: &mut world.resources_dropers,
+    type Item<'w> = Resources<'w>;
+
+    unsafe fn build<'w>(world: UnsafeWorldCell<'w>) -> Self::Item<'w> {
+        let (resources, resources_dropers, usage) = world.resources_mut();
+        Resources {
+            resources,
+            resources_dropers,
+            usage,
+            change_ticks: world.change_ticks(),
         }
     }
 
@@ -145,8 +601,116 @@ impl SystemParm for Resources<'_> {
         // 理论上因为UnsafeCell会自己在运行时painc
         // 但是还是提前制止吧?
         if state.resources || !state.res.is_empty() {
-            panic!("Resources不可和其他Resources或者任何Res共存")
+            state.conflicts.push(ParamConflict::Resource("Resources"));
         }
         state.resources = true;
+        // 可以访问任何资源,包括NonSend的
+        state.main_thread = true;
+    }
+}
+
+#[cfg(all(test, feature = "system", not(feature = "async")))]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn non_send() {
+        fn window(handle: NonSend<Rc<String>>) {
+            handle.into_or_init(|| Rc::new("window".to_string()));
+        }
+        fn count(count: ResMut<u32>) {
+            *count.into_or_init(|| 0) += 1;
+        }
+
+        let mut world = World::new();
+        world.add_system(window).add_system(count);
+        world.run_once();
+        assert_eq!(
+            world.main_thread_systems(),
+            [std::any::type_name_of_val(&window)]
+        );
+        assert_eq!(**world.get_res::<Rc<String>>().get().unwrap(), "window");
+
+        world.add_system(|handle: Option<NonSend<Rc<String>>>| assert!(handle.is_some()));
+        world.add_system(|handle: Option<NonSend<Rc<u8>>>| assert!(handle.is_none()));
+        world.run_once();
+        assert_eq!(world.main_thread_systems().len(), 3);
+
+        let mut missing = world.get_res::<i64>();
+        assert_eq!(
+            missing.try_get_mut(),
+            Err(NoSuchResource { resource: "i64" })
+        );
+
+        // NonSend与Res共用同一份资源
+        let error = world.try_add_system(|_: NonSend<u32>, _: Res<u32>| {});
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn read_only() {
+        fn read(res: Res<u32>) {
+            assert_eq!(res.get(), Some(&1));
+            assert!(res.exists());
+        }
+
+        let mut world = World::new();
+        world.get_res::<u32>().get_or_init(|| 1);
+        world.add_system(read).add_system(read);
+        world.run_once();
+
+        let error = world.try_add_system(|_: Res<u32>, _: ResMut<u32>| {});
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn world_api() {
+        let mut world = World::new();
+        world
+            .init_resource::<u32>()
+            .insert_resource(String::from("a"));
+        assert_eq!(world.resource::<u32>(), Some(&0));
+        assert!(world.contains_resource::<String>());
+
+        world.insert_resource(1u32).init_resource::<u32>();
+        *world.resource_mut::<u32>().unwrap() += 1;
+        assert_eq!(world.resource::<u32>(), Some(&2));
+
+        world.add_system(|count: Res<u32>, name: Res<String>| {
+            assert_eq!(count.get(), Some(&2));
+            assert_eq!(name.get().unwrap(), "a");
+        });
+        world.run_once();
+
+        assert_eq!(world.remove_resource::<String>().as_deref(), Some("a"));
+        assert_eq!(world.remove_resource::<String>(), None);
+        assert_eq!(world.remove_resource::<i64>(), None);
+        assert!(world.resource_mut::<i64>().is_none());
+        assert!(!world.contains_resource::<String>());
+    }
+
+    #[test]
+    fn optional() {
+        fn count(count: Option<ResMut<u32>>, read: Option<Res<u64>>) {
+            assert!(read.is_none());
+            if let Some(count) = count {
+                *count.into_or_init(|| 0) += 1;
+            }
+        }
+
+        let mut world = World::new();
+        world.add_system(count);
+        world.run_once();
+        assert!(world.get_res::<u32>().get().is_none());
+
+        world.get_res::<u32>().get_or_init(|| 0);
+        world.run_once();
+        world.run_once();
+        assert_eq!(world.get_res::<u32>().get(), Some(&2));
+
+        let error = world.try_add_system(|_: Option<Res<u32>>, _: ResMut<u32>| {});
+        assert!(error.is_err());
     }
 }
@@ -0,0 +1,191 @@
+use std::any::Any;
+
+use crate::{
+    bundle::{Bundle, Component, ComponentId, StorageType},
+    storage::{ComponentVTable, Entity, SparseSet},
+};
+
+use super::World;
+
+impl World {
+    /// 访问[Entity]的[StorageType::SparseSet]的[Component]
+    ///
+    /// 这类[Component]不在[Chunk]中,也可以通过[Query]和[World::try_fetch]访问
+    ///
+    /// ``` rust
+    /// use trecs::{bundle::Component, tools::Command, World};
+    ///
+    /// #[derive(Component)]
+    /// #[component(storage = "sparse")]
+    /// struct Poison(u32);
+    ///
+    /// let mut world = World::new();
+    /// let entity = world.spawn(1i32);
+    /// world.insert(entity, Poison(3));
+    /// assert_eq!(world.get_sparse::<Poison>(entity).map(|p| p.0), Some(3));
+    /// assert_eq!(world.remove_component::<Poison>(entity).map(|p| p.0), Some(3));
+    /// assert!(world.get_sparse::<Poison>(entity).is_none());
+    /// ```
+    ///
+    /// [Chunk]: crate::storage::Chunk
+    /// [Query]: crate::world::Query
+    pub fn get_sparse<C: Component>(&self, entity: Entity) -> Option<&C> {
+        let (component, _) = self.sparse.get(&ComponentId::of::<C>())?.get(entity)?;
+        // 通过&self保证没有冲突的访问
+        unsafe { &*component }.downcast_ref()
+    }
+
+    /// 同[World::get_sparse],会把[Component]标记为修改过
+    pub fn get_sparse_mut<C: Component>(&mut self, entity: Entity) -> Option<&mut C> {
+        let (component, ticks) = self.sparse.get(&ComponentId::of::<C>())?.get(entity)?;
+        ticks.set_changed(self.change_tick);
+        // 通过&mut self保证独占
+        unsafe { &mut *component }.downcast_mut()
+    }
+
+    /// 所有带有C的[Entity],以及它们的C,C需要是[StorageType::SparseSet]的
+    ///
+    /// 顺序不固定
+    pub fn sparse_iter<C: Component>(&self) -> impl Iterator<Item = (Entity, &C)> + '_ {
+        self.sparse
+            .get(&ComponentId::of::<C>())
+            .into_iter()
+            .flat_map(SparseSet::iter)
+            .filter_map(|(entity, component, _)| {
+                Some((entity, unsafe { &*component }.downcast_ref()?))
+            })
+    }
+
+    /// 把[StorageType::SparseSet]的[Component]放入对应的[SparseSet],返回被替换的旧值
+    pub(crate) fn insert_sparse(
+        &mut self,
+        entity: Entity,
+        id: ComponentId,
+        vtable: &'static ComponentVTable,
+        component: Box<dyn Any>,
+    ) -> Option<Box<dyn Any>> {
+        let tick = self.change_tick;
+        self.sparse
+            .entry(id)
+            .or_insert_with(|| SparseSet::new(vtable))
+            .insert(entity, component, tick)
+    }
+
+    /// 从所有的[SparseSet]中移除[Entity],在[Entity]被删除时调用
+    pub(crate) fn remove_sparse(&mut self, entity: Entity) {
+        for set in self.sparse.values_mut() {
+            set.remove(entity);
+        }
+    }
+}
+
+/// B中是否有[StorageType::SparseSet]的[Component]
+pub(crate) fn has_sparse<B: Bundle>() -> bool {
+    B::components_descriptors()
+        .iter()
+        .any(|descriptor| descriptor.storage == StorageType::SparseSet)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use super::*;
+    use crate::{
+        tools::{Added, Changed, Command, Not},
+        world::Query,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Marker(u32);
+    impl Component for Marker {
+        const STORAGE: StorageType = StorageType::SparseSet;
+
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+
+    #[test]
+    fn sparse() {
+        let mut world = World::new();
+        let a = world.spawn(1i32);
+        let b = world.spawn((2i32, Marker(2)));
+        let location = world.entities.get(a).unwrap();
+        let metas = world.metas.len();
+
+        // 不会移动Entity
+        world.insert(a, Marker(1));
+        assert_eq!(world.entities.get(a), Some(location));
+        assert_eq!(world.metas.len(), metas);
+        assert_eq!(world.get_sparse::<Marker>(a), Some(&Marker(1)));
+        assert_eq!(world.fetch::<&i32>(b), Some(&2));
+        assert_eq!(Query::<&i32>::new(&mut world).into_iter().count(), 2);
+
+        world.change_tick = 4;
+        world.get_sparse_mut::<Marker>(b).unwrap().0 = 5;
+        let set = &world.sparse[&ComponentId::of::<Marker>()];
+        assert_eq!(set.get(b).unwrap().1.changed(), 4);
+        let mut all = world
+            .sparse_iter::<Marker>()
+            .map(|(e, m)| (e, m.0))
+            .collect::<Vec<_>>();
+        all.sort();
+        assert_eq!(all, [(a, 1), (b, 5)]);
+
+        assert_eq!(world.remove_component::<Marker>(a), Some(Marker(1)));
+        assert_eq!(world.entities.get(a), Some(location));
+        world.despawn(b);
+        assert_eq!(world.sparse_iter::<Marker>().count(), 0);
+
+        let batch = world.spawn_batch([(3i32, Marker(3)), (4i32, Marker(4))]);
+        assert_eq!(world.get_sparse::<Marker>(batch[1]), Some(&Marker(4)));
+        assert_eq!(world.fetch::<&i32>(batch[1]), Some(&4));
+    }
+
+    #[test]
+    fn query() {
+        let mut world = World::new();
+        let [a, b, c] = [1, 2, 3].map(|i| world.spawn(i));
+        world.insert(a, Marker(10));
+        world.insert(c, Marker(30));
+
+        // 只有带有Marker的Entity
+        let mut query = Query::<(&i32, &mut Marker)>::new(&mut world);
+        assert!(query.get_mut(b).is_none());
+        let iter = query.into_iter();
+        assert_eq!(iter.len(), 2);
+        for (i, mut marker) in iter {
+            marker.0 += *i as u32;
+        }
+        let mut pairs = Query::<(&i32, Option<&Marker>)>::new(&mut world)
+            .into_iter()
+            .map(|(i, marker)| (*i, marker.map(|m| m.0)))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        assert_eq!(pairs, [(1, Some(11)), (2, None), (3, Some(33))]);
+        assert_eq!(world.fetch::<&Marker>(c), Some(&Marker(33)));
+        assert!(world.try_fetch::<&Marker>(b).is_err());
+        // 同一个Component仍然不能被获取两次
+        assert!(world
+            .fetch::<(&Marker, Option<&Marker>)>(a)
+            .is_some_and(|(_, marker)| marker.is_none()));
+
+        // 模拟在之后的System中访问
+        world.last_run = world.change_tick;
+        world.change_tick += 1;
+        world.insert(b, Marker(20));
+        *world.fetch::<&mut Marker>(c).unwrap() = Marker(0);
+        let added = Query::<Entity, Added<Marker>>::new(&mut world)
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(added, [b]);
+        let mut changed = Query::<Entity, Changed<Marker>>::new(&mut world)
+            .into_iter()
+            .collect::<Vec<_>>();
+        changed.sort();
+        assert_eq!(changed, [b, c]);
+        let unchanged = Query::<&i32, Not<Changed<Marker>>>::new(&mut world);
+        assert_eq!(unchanged.into_iter().copied().collect::<Vec<_>>(), [1]);
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    any::{type_name, TypeId},
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use super::World;
+
+/// 记录资源的创建和读取
+#[derive(Debug, Default)]
+pub(crate) struct ResourcesUsage {
+    /// 是否记录资源的读取
+    pub(crate) enabled: bool,
+    names: HashMap<TypeId, &'static str>,
+    read: HashSet<TypeId>,
+}
+
+impl ResourcesUsage {
+    pub(crate) fn created<T: 'static>(&mut self) {
+        self.names.insert(TypeId::of::<T>(), type_name::<T>());
+    }
+
+    pub(crate) fn read<T: 'static>(&mut self) {
+        if self.enabled {
+            self.read.insert(TypeId::of::<T>());
+        }
+    }
+}
+
+/// 从来没有被使用过的数据
+///
+/// 通过[World::unused_data]获得
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnusedData {
+    /// 从来没有被读取过的资源的类型名
+    pub resources: Vec<&'static str>,
+    /// 从来没有被查询过的[Component]的类型名
+    ///
+    /// [Component]: crate::bundle::Component
+    pub components: Vec<&'static str>,
+}
+
+impl UnusedData {
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty() && self.components.is_empty()
+    }
+}
+
+impl Display for UnusedData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for resource in &self.resources {
+            writeln!(f, "资源 {resource} 从来没有被读取过")?;
+        }
+        for component in &self.components {
+            writeln!(f, "组件 {component} 从来没有被查询过")?;
+        }
+        Ok(())
+    }
+}
+
+impl World {
+    /// 开始记录资源的读取,并在[World]被[Drop]时输出[World::unused_data]
+    ///
+    /// 应该在读取任何资源之前调用
+    pub fn lint_unused_data(&mut self) -> &mut Self {
+        self.resources_usage.enabled = true;
+        self
+    }
+
+    /// 从来没有被读取过的资源,和从来没有被查询过的[Component]
+    ///
+    /// 一种[Component]只要在任意一种[Bundle]中被查询过就不算未使用
+    ///
+    /// 没有调用[World::lint_unused_data]时不会记录资源的读取
+    ///
+    /// [Component]: crate::bundle::Component
+    /// [Bundle]: crate::bundle::Bundle
+    pub fn unused_data(&self) -> UnusedData {
+        let usage = &self.resources_usage;
+        let mut resources: Vec<_> = usage
+            .names
+            .iter()
+            .filter(|(id, ..)| !usage.read.contains(id))
+            .map(|(.., name)| *name)
+            .collect();
+
+        let mut components = HashMap::new();
+        for meta in self.metas.values() {
+            for (index, id) in meta.components_ids.iter().enumerate() {
+                let queried = components
+                    .entry(*id)
+                    .or_insert((meta.components_names[index], false));
+                queried.1 |= meta.queried[index];
+            }
+        }
+        let mut components: Vec<_> = components
+            .into_values()
+            .filter(|(.., queried)| !queried)
+            .map(|(name, ..)| name)
+            .collect();
+
+        resources.sort_unstable();
+        components.sort_unstable();
+        UnusedData {
+            resources,
+            components,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Command, ResManager};
+
+    #[test]
+    fn unused_data() {
+        let mut world = World::new();
+        world.lint_unused_data();
+        world.new_res::<String>();
+        world.new_res::<u32>();
+        world.spawn((1i32, 2usize, "a"));
+        let entity = world.spawn(3usize);
+
+        world.get_res::<u32>();
+        world.fetch::<&usize>(entity);
+
+        assert_eq!(
+            world.unused_data(),
+            UnusedData {
+                resources: vec!["alloc::string::String"],
+                components: vec!["&str", "i32"],
+            }
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::{any::type_name, fmt::Display};
+
+use crate::{
+    hierarchy::Hierarchy,
+    reflect::{MapEntities, TypeRegistry},
+    storage::Entity,
+    tools::{Command, ResManager},
+};
+
+use super::World;
+
+/// 指向已经失效的[Entity]的引用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DanglingEntity {
+    /// 保存着引用的[Entity]
+    pub entity: Entity,
+    /// 保存着引用的[Component]或者资源的类型名
+    pub source: &'static str,
+    /// 已经失效的[Entity]
+    pub target: Entity,
+}
+
+impl Display for DanglingEntity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}的{}引用了已经失效的{:?}",
+            self.entity, self.source, self.target
+        )
+    }
+}
+
+/// [World::validate]的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub dangling: Vec<DanglingEntity>,
+}
+
+impl ValidationReport {
+    pub fn is_empty(&self) -> bool {
+        self.dangling.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for dangling in &self.dangling {
+            writeln!(f, "{dangling}")?;
+        }
+        Ok(())
+    }
+}
+
+impl World {
+    /// 在[TypeRegistry]中注册一种实现了[MapEntities]的[Component]
+    ///
+    /// 以便[World::validate]检查其中的[Entity]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn register_entities<T: MapEntities>(&mut self) -> &mut Self {
+        self.get_res::<TypeRegistry>()
+            .into_or_init(TypeRegistry::new)
+            .register_entities::<T>();
+        self
+    }
+
+    /// 找出所有指向已经失效的[Entity]的引用
+    ///
+    /// 会检查所有在[TypeRegistry]中注册了[MapEntities]的[Component],以及[Hierarchy]
+    ///
+    /// [Component]: crate::bundle::Component
+    pub fn validate(&self) -> ValidationReport {
+        let alive = |entity| self.alive(entity).unwrap_or(false);
+        let mut dangling = vec![];
+
+        if let Some(registry) = self.peek_res::<TypeRegistry>() {
+            for chunk in &self.chunks {
+                for row in chunk.iter() {
+                    let entity = row.entity();
+                    // 持有&World时不会有可变的访问
+                    for component in unsafe { row.components() } {
+                        let Some(registration) = registry.get(component.type_id()) else {
+                            continue;
+                        };
+                        registration.visit_entities(component, &mut |target| {
+                            if !alive(target) {
+                                dangling.push(DanglingEntity {
+                                    entity,
+                                    source: registration.name,
+                                    target,
+                                });
+                            }
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(hierarchy) = self.peek_res::<Hierarchy>() {
+            let source = type_name::<Hierarchy>();
+            for (child, parent) in hierarchy.relations() {
+                for (entity, target) in [(child, parent), (parent, child)] {
+                    if !alive(target) {
+                        dangling.push(DanglingEntity {
+                            entity,
+                            source,
+                            target,
+                        });
+                    }
+                }
+            }
+        }
+
+        ValidationReport { dangling }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use super::*;
+    use crate::bundle::Component;
+
+    struct Target(Entity);
+    impl Component for Target {
+        fn type_id_() -> TypeId {
+            TypeId::of::<Self>()
+        }
+    }
+    impl MapEntities for Target {
+        fn visit_entities(&self, visit: &mut dyn FnMut(Entity)) {
+            visit(self.0)
+        }
+
+        fn map_entities(&mut self, map: &mut dyn FnMut(Entity) -> Entity) {
+            self.0 = map(self.0)
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let mut world = World::new();
+        world.register_entities::<Target>();
+        let a = world.spawn(1);
+        let b = world.spawn(2);
+        let holder = world.spawn((Target(a), 0usize));
+        world.spawn(Target(b));
+        let set_parent = |world: &mut World, child| {
+            world
+                .get_res::<Hierarchy>()
+                .into_or_init(Hierarchy::new)
+                .set_parent(child, holder)
+                .unwrap();
+        };
+        set_parent(&mut world, b);
+        assert!(world.validate().is_empty());
+
+        world.remove(a);
+        world.remove(b);
+        // 删除Entity时会移除它在Hierarchy中的关系,直接修改Hierarchy才会留下失效的关系
+        assert_eq!(world.validate().dangling.len(), 2);
+        set_parent(&mut world, b);
+        let report = world.validate();
+        assert_eq!(report.dangling.len(), 3);
+        assert!(report.dangling.contains(&DanglingEntity {
+            entity: holder,
+            source: type_name::<Target>(),
+            target: a,
+        }));
+        assert!(report.dangling.contains(&DanglingEntity {
+            entity: holder,
+            source: type_name::<Hierarchy>(),
+            target: b,
+        }));
+    }
+}